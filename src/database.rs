@@ -1,18 +1,39 @@
 use std::fs::create_dir_all;
 
+use aes_gcm::{Aes256Gcm, KeyInit};
+use chrono::{DateTime, Local};
 use serde::{de::DeserializeOwned, Serialize};
 
 pub trait KeyValueStore {
-    fn insert<T: Serialize>(&self, key: &str, data: &T) -> Result<(), anyhow::Error>;
+    /// entries with expires_at are ignored and pruned after the time specified
+    fn insert<T: Serialize>(
+        &self,
+        key: &str,
+        data: &T,
+        expires_at: Option<DateTime<Local>>,
+    ) -> Result<(), anyhow::Error>;
     fn get<T: DeserializeOwned>(&self, key: &str) -> Option<T>;
     fn remove(&self, key: &str) -> bool;
+    /// remove expired entries and return the number of entries removed
+    fn prune(&self) -> usize;
 }
 
-pub fn init(uri: Option<&str>) -> impl KeyValueStore {
+pub fn is_redis(uri: &str) -> bool {
+    uri.starts_with("redis://") || uri.starts_with("redis+unix://")
+}
+
+pub fn init(uri: Option<&str>, encryption_key: Option<[u8; 32]>) -> impl KeyValueStore {
+    #[cfg(feature = "redis")]
+    if let Some(u) = uri.filter(|u| is_redis(u)) {
+        let client = crate::pools::redis::RedisClient::new(u)
+            .unwrap_or_else(|e| panic!("Invalid redis uri {u} {e}"));
+        return Store::Redis(redis::Redis { client });
+    }
     if let Some(u) = uri {
         create_dir_all(u).unwrap_or_else(|e| panic!("Unable to create directory {u} {e}"));
         return Store::Dir(filesystem::FileSystem {
             directory: u.to_string(),
+            cipher: encryption_key.map(|k| Aes256Gcm::new(&k.into()).into()),
         });
     }
     Store::Null
@@ -20,13 +41,22 @@ pub fn init(uri: Option<&str>) -> impl KeyValueStore {
 
 pub enum Store {
     Dir(filesystem::FileSystem),
+    #[cfg(feature = "redis")]
+    Redis(redis::Redis),
     Null,
 }
 
 impl KeyValueStore for Store {
-    fn insert<T: Serialize>(&self, key: &str, data: &T) -> Result<(), anyhow::Error> {
+    fn insert<T: Serialize>(
+        &self,
+        key: &str,
+        data: &T,
+        expires_at: Option<DateTime<Local>>,
+    ) -> Result<(), anyhow::Error> {
         match self {
-            Store::Dir(f) => f.insert(key, data),
+            Store::Dir(f) => f.insert(key, data, expires_at),
+            #[cfg(feature = "redis")]
+            Store::Redis(r) => r.insert(key, data, expires_at),
             Store::Null => Ok(()),
         }
     }
@@ -34,6 +64,8 @@ impl KeyValueStore for Store {
     fn get<T: DeserializeOwned>(&self, key: &str) -> Option<T> {
         match self {
             Store::Dir(f) => f.get(key),
+            #[cfg(feature = "redis")]
+            Store::Redis(r) => r.get(key),
             Store::Null => None,
         }
     }
@@ -41,48 +73,322 @@ impl KeyValueStore for Store {
     fn remove(&self, key: &str) -> bool {
         match self {
             Store::Dir(f) => f.remove(key),
+            #[cfg(feature = "redis")]
+            Store::Redis(r) => r.remove(key),
             Store::Null => false,
         }
     }
+
+    fn prune(&self) -> usize {
+        match self {
+            Store::Dir(f) => f.prune(),
+            #[cfg(feature = "redis")]
+            Store::Redis(r) => r.prune(),
+            Store::Null => 0,
+        }
+    }
 }
 
 mod filesystem {
     use std::{
-        fs::{remove_file, File},
+        fs::{read, read_dir, remove_file, write},
         path::Path,
     };
 
-    use anyhow::Context;
-    use serde::{de::DeserializeOwned, Serialize};
+    use aes_gcm::{
+        aead::{Aead, OsRng},
+        AeadCore, Aes256Gcm, Nonce,
+    };
+    use anyhow::{anyhow, Context};
+    use chrono::{DateTime, Local};
+    use log::{debug, warn};
+    use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+    use crate::config::now;
 
     use super::KeyValueStore;
 
+    const NONCE_SIZE: usize = 12;
+
     pub struct FileSystem {
         pub directory: String,
+        /// encrypt file contents when defined
+        pub cipher: Option<Box<Aes256Gcm>>,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    struct Entry<T> {
+        value: T,
+        expires_at: Option<DateTime<Local>>,
+    }
+
+    #[derive(Deserialize)]
+    struct Expiry {
+        expires_at: Option<DateTime<Local>>,
+    }
+
+    impl FileSystem {
+        fn read(&self, path: &Path) -> Option<Vec<u8>> {
+            let content = read(path).ok()?;
+            let Some(cipher) = &self.cipher else {
+                return content.into();
+            };
+            if content.len() < NONCE_SIZE {
+                warn!("Invalid encrypted file {}", path.to_string_lossy());
+                return None;
+            }
+            let (nonce, encrypted) = content.split_at(NONCE_SIZE);
+            cipher
+                .decrypt(Nonce::from_slice(nonce), encrypted)
+                .map_err(|_| warn!("Unable to decrypt file {}", path.to_string_lossy()))
+                .ok()
+        }
+
+        fn write(&self, path: &Path, content: Vec<u8>) -> Result<(), anyhow::Error> {
+            let content = if let Some(cipher) = &self.cipher {
+                let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+                let encrypted = cipher
+                    .encrypt(&nonce, content.as_slice())
+                    .map_err(|_| anyhow!("Unable to encrypt {}", path.to_string_lossy()))?;
+                let mut content = nonce.to_vec();
+                content.extend(encrypted);
+                content
+            } else {
+                content
+            };
+            write(path, content)
+                .with_context(|| format!("Can not write file {}", path.to_string_lossy()))
+        }
     }
 
     impl KeyValueStore for FileSystem {
-        fn insert<T: Serialize>(&self, key: &str, data: &T) -> Result<(), anyhow::Error> {
+        fn insert<T: Serialize>(
+            &self,
+            key: &str,
+            data: &T,
+            expires_at: Option<DateTime<Local>>,
+        ) -> Result<(), anyhow::Error> {
             let path = Path::new(&self.directory).join(key);
-            let file = File::options()
-                .create(true)
-                .truncate(true)
-                .write(true)
-                .open(&path)
-                .with_context(|| "Can not write file {path}")?;
-            serde_json::to_writer(file, data)?;
-            Ok(())
+            let content = serde_json::to_vec(&Entry {
+                value: data,
+                expires_at,
+            })?;
+            self.write(&path, content)
         }
 
         fn get<T: DeserializeOwned>(&self, key: &str) -> Option<T> {
             let path = Path::new(&self.directory).join(key);
-            let file = File::options().read(true).open(&path).ok()?;
-            serde_json::from_reader(file).ok()
+            let content = self.read(&path)?;
+            // entries written before expiry was introduced contain only the value
+            let Ok(entry) = serde_json::from_slice::<Entry<T>>(&content) else {
+                return serde_json::from_slice(&content).ok();
+            };
+            if entry.expires_at.map(|e| e <= now()).unwrap_or_default() {
+                debug!("Stored entry {key} expired");
+                remove_file(path).ok();
+                return None;
+            }
+            Some(entry.value)
         }
 
         fn remove(&self, key: &str) -> bool {
             let path = Path::new(&self.directory).join(key);
             remove_file(path).is_ok()
         }
+
+        fn prune(&self) -> usize {
+            let Ok(entries) = read_dir(&self.directory) else {
+                return 0;
+            };
+            let now = now();
+            entries
+                .filter_map(|e| e.ok())
+                .filter(|e| {
+                    self.read(&e.path())
+                        .and_then(|c| serde_json::from_slice::<Expiry>(&c).ok())
+                        .and_then(|e| e.expires_at)
+                        .map(|e| e <= now)
+                        .unwrap_or_default()
+                })
+                .filter(|e| remove_file(e.path()).is_ok())
+                .count()
+        }
+    }
+}
+
+#[cfg(feature = "redis")]
+mod redis {
+    use chrono::{DateTime, Local};
+    use log::warn;
+    use redis::{pipe, Commands};
+    use serde::{de::DeserializeOwned, Serialize};
+
+    use crate::{config::now, pools::redis::RedisClient};
+
+    use super::KeyValueStore;
+
+    /// sorted set of keys with an expiry scored by the expiry timestamp
+    const EXPIRY_KEY: &str = "hvents:expiry";
+
+    pub struct Redis {
+        pub client: RedisClient,
+    }
+
+    impl KeyValueStore for Redis {
+        fn insert<T: Serialize>(
+            &self,
+            key: &str,
+            data: &T,
+            expires_at: Option<DateTime<Local>>,
+        ) -> Result<(), anyhow::Error> {
+            let value = serde_json::to_string(data)?;
+            // redis expires keys by itself, prune removes them from the expiry set
+            let mut pipe = pipe();
+            pipe.atomic();
+            match expires_at.map(|e| (e, (e - now()).num_seconds())) {
+                Some((e, ttl)) if ttl > 0 => {
+                    pipe.set_ex(key, value, ttl as u64)
+                        .zadd(EXPIRY_KEY, key, e.timestamp())
+                }
+                Some(_) => pipe.del(key).zrem(EXPIRY_KEY, key),
+                None => pipe.set(key, value).zrem(EXPIRY_KEY, key),
+            };
+            self.client
+                .with_connection(true, |c| Ok(pipe.query::<()>(c)?))
+                .map_err(|e| e.context(format!("Can not write {key} to redis")))
+        }
+
+        fn get<T: DeserializeOwned>(&self, key: &str) -> Option<T> {
+            let value: Option<String> = self
+                .client
+                .with_connection(true, |c| Ok(c.get(key)?))
+                .map_err(|e| warn!("Can not read {key} from redis {e:#}"))
+                .ok()?;
+            serde_json::from_str(&value?)
+                .map_err(|e| warn!("Invalid value stored in redis for {key} {e}"))
+                .ok()
+        }
+
+        fn remove(&self, key: &str) -> bool {
+            let mut pipe = pipe();
+            pipe.atomic().del(key).zrem(EXPIRY_KEY, key).ignore();
+            self.client
+                .with_connection(true, |c| Ok(pipe.query::<(u64,)>(c)?))
+                .map(|(removed,)| removed > 0)
+                .map_err(|e| warn!("Can not remove {key} from redis {e:#}"))
+                .unwrap_or_default()
+        }
+
+        fn prune(&self) -> usize {
+            let now = now().timestamp();
+            let result = self.client.with_connection(true, |c| {
+                let expired: Vec<String> = c.zrangebyscore(EXPIRY_KEY, "-inf", now)?;
+                if expired.is_empty() {
+                    return Ok(0);
+                }
+                // keys expired by redis are already gone, delete in case the clock differs
+                let mut pipe = pipe();
+                pipe.atomic()
+                    .del(&expired)
+                    .ignore()
+                    .zrem(EXPIRY_KEY, &expired);
+                Ok(pipe.query::<(usize,)>(c)?.0)
+            });
+            result
+                .map_err(|e| warn!("Can not prune expired entries from redis {e:#}"))
+                .unwrap_or_default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::remove_dir_all;
+
+    use chrono::Duration;
+
+    use crate::config::now;
+
+    use super::*;
+
+    #[test]
+    fn test_filesystem_expiry() {
+        let directory = "/tmp/_test_store_expiry";
+        remove_dir_all(directory).ok();
+        let store = init(Some(directory), None);
+
+        store.insert("no_expiry", &"a", None).unwrap();
+        store
+            .insert("expires_later", &"b", Some(now() + Duration::days(1)))
+            .unwrap();
+        store
+            .insert("expired", &"c", Some(now() - Duration::days(1)))
+            .unwrap();
+        store
+            .insert("expired_pruned", &"d", Some(now() - Duration::seconds(1)))
+            .unwrap();
+
+        assert_eq!(store.get::<String>("no_expiry").unwrap(), "a");
+        assert_eq!(store.get::<String>("expires_later").unwrap(), "b");
+        assert!(store.get::<String>("expired").is_none());
+
+        assert_eq!(store.prune(), 1);
+        assert!(store.get::<String>("expired_pruned").is_none());
+        assert_eq!(store.get::<String>("no_expiry").unwrap(), "a");
+    }
+
+    #[test]
+    fn test_filesystem_encryption() {
+        let directory = "/tmp/_test_store_encryption";
+        remove_dir_all(directory).ok();
+        let store = init(Some(directory), Some([7; 32]));
+
+        store.insert("secret", &"token", None).unwrap();
+        assert_eq!(store.get::<String>("secret").unwrap(), "token");
+
+        let content = std::fs::read(format!("{directory}/secret")).unwrap();
+        assert!(!String::from_utf8_lossy(&content).contains("token"));
+
+        let store = init(Some(directory), Some([8; 32]));
+        assert!(store.get::<String>("secret").is_none());
+
+        let store = init(Some(directory), None);
+        assert!(store.get::<String>("secret").is_none());
+    }
+
+    #[cfg(feature = "redis")]
+    #[test]
+    #[ignore = "requires a redis server on localhost:6379"]
+    fn test_redis_expiry() {
+        let store = init(Some("redis://127.0.0.1:6379/15"), None);
+        for key in ["no_expiry", "expires_later", "expires_soon"] {
+            store.remove(key);
+        }
+
+        store.insert("no_expiry", &"a", None).unwrap();
+        store
+            .insert("expires_later", &"b", Some(now() + Duration::days(1)))
+            .unwrap();
+        store
+            .insert("expired", &"c", Some(now() - Duration::days(1)))
+            .unwrap();
+        store
+            .insert("expires_soon", &"d", Some(now() + Duration::seconds(2)))
+            .unwrap();
+
+        assert_eq!(store.get::<String>("no_expiry").unwrap(), "a");
+        assert_eq!(store.get::<String>("expires_later").unwrap(), "b");
+        assert_eq!(store.get::<String>("expires_soon").unwrap(), "d");
+        assert!(store.get::<String>("expired").is_none());
+        assert_eq!(store.prune(), 0);
+
+        std::thread::sleep(std::time::Duration::from_secs(3));
+        assert!(store.get::<String>("expires_soon").is_none());
+        assert_eq!(store.prune(), 1);
+
+        assert!(store.remove("expires_later"));
+        assert!(!store.remove("expires_later"));
+        assert_eq!(store.get::<String>("no_expiry").unwrap(), "a");
+        assert!(store.remove("no_expiry"));
     }
 }
@@ -0,0 +1,80 @@
+use std::{
+    io::{self, BufRead, BufReader, ErrorKind, Read, Write},
+    os::unix::net::UnixStream,
+    path::Path,
+};
+
+/// docker engine api request over a unix socket returning the status code and the body reader
+///
+/// http/1.0 is used so that streamed responses are not chunked
+pub fn request(
+    socket: &Path,
+    method: &str,
+    path: &str,
+) -> io::Result<(u16, BufReader<UnixStream>)> {
+    let mut stream = UnixStream::connect(socket)?;
+    write!(
+        stream,
+        "{method} {path} HTTP/1.0\r\nHost: docker\r\nContent-Length: 0\r\n\r\n"
+    )?;
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    let status = line
+        .split(' ')
+        .nth(1)
+        .and_then(|s| s.parse().ok())
+        .ok_or_else(|| io::Error::new(ErrorKind::InvalidData, format!("Invalid status {line}")))?;
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 || line.trim_end().is_empty() {
+            break;
+        }
+    }
+    Ok((status, reader))
+}
+
+/// error message from a docker engine api response body
+pub fn error_message(reader: &mut impl Read) -> String {
+    let mut body = String::new();
+    if let Err(e) = reader.read_to_string(&mut body) {
+        return e.to_string();
+    }
+    serde_json::from_str::<serde_json::Value>(&body)
+        .ok()
+        .and_then(|v| v["message"].as_str().map(str::to_string))
+        .unwrap_or(body)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{os::unix::net::UnixListener, thread::spawn};
+
+    use super::*;
+
+    #[test]
+    fn test_request() {
+        let socket =
+            std::env::temp_dir().join(format!("hvents_docker_{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&socket);
+        let listener = UnixListener::bind(&socket).unwrap();
+        let handle = spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = String::new();
+            BufReader::new(&stream).read_line(&mut request).unwrap();
+            stream
+                .write_all(b"HTTP/1.0 404 Not Found\r\nContent-Type: application/json\r\n\r\n{\"message\":\"No such container: tv\"}")
+                .unwrap();
+            request
+        });
+
+        let (status, mut reader) = request(&socket, "POST", "/containers/tv/start").unwrap();
+        assert_eq!(status, 404);
+        assert_eq!(error_message(&mut reader), "No such container: tv");
+        assert_eq!(
+            handle.join().unwrap(),
+            "POST /containers/tv/start HTTP/1.0\r\n"
+        );
+        std::fs::remove_file(&socket).unwrap();
+    }
+}
@@ -0,0 +1,18 @@
+use serde::{Deserialize, Serialize};
+
+use crate::config::PoolId;
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct AmqpAckEvent {
+    /// template e.g. "{{metadata.queue_event.delivery_id}}"
+    pub delivery_id: String,
+    /// reject the message instead of acknowledging it
+    #[serde(default)]
+    pub reject: bool,
+    /// requeue rejected messages, otherwise they are dropped or dead lettered
+    #[serde(default)]
+    pub requeue: bool,
+    /// the first amqp broker is used if empty
+    #[serde(default)]
+    pub pool_id: PoolId,
+}
@@ -0,0 +1,34 @@
+use serde::{Deserialize, Serialize};
+
+use crate::config::PoolId;
+
+use super::mqtt_subscribe::MqttBodyMatch;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum AmqpAckMode {
+    /// acknowledge once the message is passed to the next event
+    #[default]
+    Receive,
+    /// acknowledge with an amqp_ack event after the message was processed
+    Manual,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct AmqpConsumeEvent {
+    /// existing queue to consume messages from
+    pub queue: String,
+    #[serde(default)]
+    pub ack: AmqpAckMode,
+    #[serde(flatten)]
+    pub body: Option<MqttBodyMatch>,
+    /// the first amqp broker is used if empty
+    #[serde(default)]
+    pub pool_id: PoolId,
+}
+
+impl AmqpConsumeEvent {
+    pub fn matches(&self, queue: &str, body: &[u8]) -> bool {
+        self.queue == queue && self.body.as_ref().map(|b| b.matches(body)).unwrap_or(true)
+    }
+}
@@ -0,0 +1,20 @@
+use serde::{Deserialize, Serialize};
+
+use crate::config::PoolId;
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct AmqpPublishEvent {
+    /// the default exchange routes messages to the queue named by the routing key
+    #[serde(default)]
+    pub exchange: String,
+    /// template
+    pub routing_key: String,
+    /// template, event data is published if not defined
+    pub body: Option<String>,
+    /// messages survive a broker restart
+    #[serde(default)]
+    pub persistent: bool,
+    /// the first amqp broker is used if empty
+    #[serde(default)]
+    pub pool_id: PoolId,
+}
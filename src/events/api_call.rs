@@ -1,25 +1,51 @@
-use core::fmt::Display;
-use std::collections::HashMap;
+use core::{fmt::Display, time::Duration};
+use std::{
+    collections::HashMap,
+    fs::{read, remove_file, rename, File},
+    path::Path,
+    thread::sleep,
+    time::Instant,
+};
 
-use anyhow::anyhow;
+use anyhow::{anyhow, bail, Context};
 use indexmap::IndexMap;
-use log::debug;
+use log::{debug, warn};
 use reqwest::{
-    blocking::Client,
-    header::{HeaderMap, HeaderValue, CONTENT_TYPE},
+    blocking::{
+        multipart::{Form, Part},
+        Client, Response,
+    },
+    header::{HeaderMap, HeaderValue, CONTENT_TYPE, LINK},
+    Url,
 };
-use serde::{Deserialize, Serialize};
-use serde_json::json;
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::{json, Map, Value};
 
-use crate::{config::PoolId, events::data::Metadata};
+use crate::{
+    config::{deserialize_redirect, PoolId},
+    events::data::Metadata,
+};
 
-use super::data::Data;
+use super::{
+    data::{CsvOptions, Data, PayloadEncoding},
+    EventName,
+};
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct ApiCallEvent {
     pub url: String,
     #[serde(default)]
     pub headers: HashMap<String, String>,
+    /// query parameters appended to the url, values are templates
+    #[serde(default)]
+    pub query: IndexMap<String, String>,
+    /// path template to save the response to with file response content
+    pub response_file: Option<String>,
+    /// files uploaded with multipart request content by field name, paths are templates
+    #[serde(default)]
+    pub files: IndexMap<String, String>,
+    /// request body template, event data is sent if not defined
+    pub body: Option<String>,
     #[serde(default)]
     pub method: RequestMethod,
     #[serde(default)]
@@ -28,6 +54,76 @@ pub struct ApiCallEvent {
     pub response_content: ResponseContent,
     #[serde(default)]
     pub pool_id: PoolId,
+    /// seconds to complete the request, overrides the client timeout
+    pub timeout: Option<u64>,
+    /// retry failed requests and responses with the status codes
+    pub retry: Option<ApiCallRetry>,
+    /// post a graphql query, the response data is used as event data
+    pub graphql: Option<GraphQl>,
+    /// request further pages and collect their items into a json array
+    pub pagination: Option<ApiCallPagination>,
+    /// keep only the json response values by key, values are json pointers e.g. /main/temp
+    #[serde(default)]
+    pub extract: IndexMap<String, String>,
+    /// next event by response status code e.g. 404 or status class e.g. 4xx
+    #[serde(default, deserialize_with = "deserialize_on_status")]
+    pub on_status: IndexMap<String, EventName>,
+    /// maximum number of redirects to follow, overrides the client redirect limit
+    #[serde(default, deserialize_with = "deserialize_redirect")]
+    pub redirect: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiCallRetry {
+    /// total number of requests to make
+    #[serde(default = "default_retry_attempts")]
+    pub attempts: u32,
+    /// seconds to wait before retrying, doubled on each attempt
+    #[serde(default = "default_retry_delay")]
+    pub delay: u64,
+    /// response status codes to retry
+    #[serde(default = "default_retry_status")]
+    pub status: Vec<u16>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GraphQl {
+    pub query: String,
+    /// json event data is used if not provided
+    pub variables: Option<Value>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiCallPagination {
+    /// json pointer to the next page cursor e.g. /meta/next_cursor,
+    /// the link header with rel="next" is followed if not provided
+    pub cursor: Option<String>,
+    /// query parameter to send the cursor with
+    #[serde(default = "default_cursor_param")]
+    pub param: String,
+    /// json pointer to the items e.g. /data, the whole response is used if not provided
+    pub items: Option<String>,
+    #[serde(default = "default_max_pages")]
+    pub max_pages: u32,
+}
+
+impl Default for ApiCallRetry {
+    fn default() -> Self {
+        Self {
+            attempts: default_retry_attempts(),
+            delay: default_retry_delay(),
+            status: default_retry_status(),
+        }
+    }
+}
+
+impl ApiCallRetry {
+    fn delay(&self, attempt: u32) -> Duration {
+        Duration::from_secs(
+            self.delay
+                .saturating_mul(1 << attempt.saturating_sub(1).min(16)),
+        )
+    }
 }
 
 impl ApiCallEvent {
@@ -36,39 +132,433 @@ impl ApiCallEvent {
         client: &Client,
         data: &Data,
         name: &str,
-    ) -> Result<(Data, Metadata), anyhow::Error> {
+    ) -> Result<(Data, Metadata, u16), anyhow::Error> {
         let mut headers: HeaderMap = (&self.headers)
             .try_into()
             .map_err(|e| anyhow!("Invalid header specified: {e}"))?;
-        if let RequestContent::Json = &self.request_content {
-            headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+        match &self.request_content {
+            _ if self.graphql.is_some() => {
+                headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+            }
+            RequestContent::Json => {
+                headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+            }
+            RequestContent::Form => {
+                headers.insert(
+                    CONTENT_TYPE,
+                    HeaderValue::from_static("application/x-www-form-urlencoded"),
+                );
+            }
+            RequestContent::Msgpack | RequestContent::Cbor => {
+                if let Some(encoding) = self.request_content.encoding() {
+                    headers.insert(
+                        CONTENT_TYPE,
+                        HeaderValue::from_static(encoding.content_type()),
+                    );
+                }
+            }
+            // content type with the boundary is set by the multipart form
+            RequestContent::Multipart | RequestContent::Text | RequestContent::Bytes => (),
         };
 
-        debug!("Request to {} body {data:?} headers {headers:?}", self.url);
-        let response = match &self.method {
-            RequestMethod::Delete => client.delete(&self.url).headers(headers).send()?,
-            RequestMethod::Put => client
-                .put(&self.url)
-                .body(data.to_bytes()?)
-                .headers(headers)
-                .send()?,
-            RequestMethod::Post => client
-                .post(&self.url)
-                .body(data.to_bytes()?)
-                .headers(headers)
-                .send()?,
-            RequestMethod::Get => client.get(&self.url).headers(headers).send()?,
+        let url = self.url();
+        // graphql queries are always posted
+        let method = match &self.graphql {
+            Some(_) => &RequestMethod::Post,
+            None => &self.method,
+        };
+        let has_body = matches!(
+            method,
+            RequestMethod::Put | RequestMethod::Post | RequestMethod::Patch | RequestMethod::Notify
+        );
+        let (body, files) = match &self.request_content {
+            _ if self.graphql.is_some() => (self.body(data)?, Vec::new()),
+            RequestContent::Multipart if has_body => (Vec::new(), self.read_files()?),
+            _ if has_body => (self.body(data)?, Vec::new()),
+            _ => (Vec::new(), Vec::new()),
+        };
+        let send = |url: &str| {
+            debug!("Request to {url} body {data:?} headers {headers:?}");
+            let request = match method {
+                RequestMethod::Delete => client.delete(url),
+                RequestMethod::Put => client.put(url),
+                RequestMethod::Post => client.post(url),
+                RequestMethod::Get => client.get(url),
+                RequestMethod::Patch => client.patch(url),
+                RequestMethod::Head => client.head(url),
+                RequestMethod::Options => client.request(reqwest::Method::OPTIONS, url),
+                RequestMethod::Subscribe | RequestMethod::Unsubscribe | RequestMethod::Notify => {
+                    let method = reqwest::Method::from_bytes(method.to_string().as_bytes())
+                        .expect("valid method");
+                    client.request(method, url)
+                }
+            }
+            .headers(headers.clone());
+            let request = match &self.request_content {
+                _ if !has_body => request,
+                _ if self.graphql.is_some() => request.body(body.clone()),
+                RequestContent::Multipart => request.multipart(multipart_form(data, &files)),
+                _ => request.body(body.clone()),
+            };
+            match self.timeout {
+                Some(timeout) => request.timeout(Duration::from_secs(timeout)),
+                None => request,
+            }
+            .send()
+        };
+        let send_with_retry = |url: &str| -> Result<Response, anyhow::Error> {
+            let mut attempt = 1;
+            loop {
+                let result = send(url);
+                let Some(retry) = &self.retry else {
+                    return Ok(result?);
+                };
+                let failed = match &result {
+                    Ok(r) => retry.status.contains(&r.status().as_u16()),
+                    Err(_) => true,
+                };
+                if !failed || attempt >= retry.attempts {
+                    return Ok(result?);
+                }
+                let delay = retry.delay(attempt);
+                warn!("Request to {url} failed, retrying in {delay:?} attempt {attempt}");
+                sleep(delay);
+                attempt += 1;
+            }
+        };
+        let started = Instant::now();
+        let mut response = send_with_retry(&url)?;
+        // time until the response headers are received including retries
+        let response_time = started.elapsed().as_millis() as u64;
+        debug!("Response from {url} in {response_time}ms {response:?}");
+        let status = response.status().as_u16();
+        let success = response.status().is_success();
+        // url after following redirects
+        let final_url = response.url().to_string();
+        let response_headers: IndexMap<String, String> = response
+            .headers()
+            .into_iter()
+            .filter_map(|(k, v)| Some((k.to_string(), v.to_str().ok()?.to_string())))
+            .collect();
+        let mut file = None;
+        let data = match (&self.pagination, &self.response_content) {
+            // error responses are kept as text if they can not be decoded so that on_status can route them
+            (_, content) if !success => error_data(content, &response.bytes()?),
+            (Some(pagination), _) => self.paginate(pagination, &url, response, send_with_retry)?,
+            (None, _) if self.graphql.is_some() => {
+                graphql_data(serde_json::from_slice(&response.bytes()?)?)?
+            }
+            (None, ResponseContent::File) => {
+                let path = self.response_file.as_deref().ok_or_else(|| {
+                    anyhow!("response_file must be provided for file response content")
+                })?;
+                // the previous file is replaced once the response is complete
+                let partial = format!("{path}.part");
+                let mut f = File::create(&partial)
+                    .with_context(|| format!("Unable to create response file {partial}"))?;
+                if let Err(e) = response.copy_to(&mut f) {
+                    let _ = remove_file(&partial);
+                    return Err(e.into());
+                }
+                drop(f);
+                rename(&partial, path)
+                    .with_context(|| format!("Unable to create response file {path}"))?;
+                file = Some(path);
+                Data::Empty
+            }
+            (None, content) => {
+                let bytes = response.bytes()?;
+                match content {
+                    ResponseContent::Json => Data::Json(serde_json::from_slice(&bytes)?),
+                    ResponseContent::Text => {
+                        Data::String(String::from_utf8_lossy(&bytes).to_string())
+                    }
+                    ResponseContent::Csv => Data::Json(CsvOptions::default().parse(&*bytes)?),
+                    ResponseContent::Msgpack | ResponseContent::Cbor => {
+                        let encoding = content.encoding().expect("binary response content");
+                        Data::Json(encoding.decode(&bytes)?)
+                    }
+                    ResponseContent::Bytes | ResponseContent::File => Data::Bytes(bytes.to_vec()),
+                }
+            }
         };
-        debug!("Response from {} {response:?}", self.url);
-        let meta = json!({ name: {"headers": response.headers().into_iter().filter_map(|(k, v)| Some((k.as_str(), v.to_str().ok()?))).collect::<IndexMap<&str, &str>>()}}).into();
-        let bytes = response.bytes()?;
-        let data = match &self.response_content {
-            ResponseContent::Json => Data::Json(serde_json::from_slice(&bytes)?),
-            ResponseContent::Text => Data::String(String::from_utf8_lossy(&bytes).to_string()),
-            ResponseContent::Bytes => Data::Bytes(bytes.to_vec()),
+        let data = match data {
+            Data::Json(value) if !self.extract.is_empty() => Data::Json(self.extract(&value)),
+            data => data,
         };
-        Ok((data, meta))
+        let meta = json!({ name: {
+            "status": status,
+            "headers": response_headers,
+            "file": file,
+            "url": final_url,
+            "response_time": response_time,
+        }})
+        .into();
+        Ok((data, meta, status))
+    }
+
+    fn paginate(
+        &self,
+        pagination: &ApiCallPagination,
+        url: &str,
+        mut response: Response,
+        send: impl Fn(&str) -> Result<Response, anyhow::Error>,
+    ) -> Result<Data, anyhow::Error> {
+        let mut items = Vec::new();
+        let mut page = 1;
+        loop {
+            let next_link = next_link(&response);
+            let value: Value = serde_json::from_slice(&response.bytes()?)?;
+            let next_url = match &pagination.cursor {
+                Some(pointer) => match value.pointer(pointer) {
+                    Some(Value::String(c)) if !c.is_empty() => {
+                        Some(set_query(url, &pagination.param, c)?)
+                    }
+                    Some(Value::Number(c)) => {
+                        Some(set_query(url, &pagination.param, &c.to_string())?)
+                    }
+                    _ => None,
+                },
+                None => next_link,
+            };
+            let page_items = match &pagination.items {
+                Some(pointer) => value.pointer(pointer).cloned().unwrap_or_default(),
+                None => value,
+            };
+            match page_items {
+                Value::Array(a) => items.extend(a),
+                Value::Null => (),
+                v => items.push(v),
+            }
+            let Some(next_url) = next_url else {
+                break;
+            };
+            if page >= pagination.max_pages {
+                warn!("Request to {url} reached max_pages {page}");
+                break;
+            }
+            response = send(&next_url)?;
+            page += 1;
+        }
+        Ok(Data::Json(Value::Array(items)))
+    }
+
+    fn extract(&self, value: &Value) -> Value {
+        self.extract
+            .iter()
+            .filter_map(|(key, pointer)| {
+                let v = value.pointer(pointer);
+                if v.is_none() {
+                    debug!("No value found for {key} at {pointer}");
+                }
+                Some((key.clone(), v?.clone()))
+            })
+            .collect::<Map<String, Value>>()
+            .into()
+    }
+
+    /// event to execute next for the response status, exact codes take precedence
+    pub fn status_event(&self, status: u16) -> Option<&EventName> {
+        self.on_status
+            .get(&status.to_string())
+            .or_else(|| self.on_status.get(&format!("{}xx", status / 100)))
+    }
+
+    fn url(&self) -> String {
+        if self.query.is_empty() {
+            return self.url.clone();
+        }
+        let query = form_urlencoded::Serializer::new(String::new())
+            .extend_pairs(&self.query)
+            .finish();
+        let separator = if self.url.contains('?') { '&' } else { '?' };
+        format!("{}{separator}{query}", self.url)
+    }
+
+    fn read_files(&self) -> Result<Vec<UploadFile<'_>>, anyhow::Error> {
+        self.files
+            .iter()
+            .map(|(field, path)| {
+                let content = read(path).with_context(|| format!("Unable to read file {path}"))?;
+                let file_name = Path::new(path)
+                    .file_name()
+                    .map(|n| n.to_string_lossy().to_string())
+                    .unwrap_or_default();
+                Ok(UploadFile {
+                    field,
+                    file_name,
+                    content,
+                })
+            })
+            .collect()
     }
+
+    fn body(&self, data: &Data) -> Result<Vec<u8>, anyhow::Error> {
+        if let Some(body) = &self.body {
+            return match self.request_content.encoding() {
+                Some(encoding) => encoding.encode(&Data::from_bytes(body.as_bytes())),
+                None => Ok(body.as_bytes().to_vec()),
+            };
+        }
+        if let Some(graphql) = &self.graphql {
+            let variables = match (&graphql.variables, data) {
+                (Some(v), _) => v,
+                (None, Data::Json(v)) => v,
+                (None, _) => &Value::Null,
+            };
+            return Ok(serde_json::to_vec(
+                &json!({"query": graphql.query, "variables": variables}),
+            )?);
+        }
+        match (&self.request_content, data) {
+            (RequestContent::Form, Data::Json(Value::Object(fields))) => {
+                Ok(form_urlencoded::Serializer::new(String::new())
+                    .extend_pairs(fields.iter().map(|(k, v)| (k, field_value(v))))
+                    .finish()
+                    .into_bytes())
+            }
+            (RequestContent::Msgpack | RequestContent::Cbor, data) => self
+                .request_content
+                .encoding()
+                .expect("binary request content")
+                .encode(data),
+            _ => Ok(data.to_bytes()?),
+        }
+    }
+}
+
+struct UploadFile<'a> {
+    field: &'a str,
+    file_name: String,
+    content: Vec<u8>,
+}
+
+fn graphql_data(mut response: Value) -> Result<Data, anyhow::Error> {
+    match response.get("errors") {
+        Some(Value::Array(errors)) if !errors.is_empty() => {
+            bail!("GraphQL errors {}", Value::Array(errors.clone()))
+        }
+        _ => Ok(Data::Json(response["data"].take())),
+    }
+}
+
+/// decoded error response or the response as is
+fn error_data(content: &ResponseContent, bytes: &[u8]) -> Data {
+    let decoded = match content {
+        ResponseContent::Json => serde_json::from_slice(bytes).ok(),
+        ResponseContent::Csv => CsvOptions::default().parse(bytes).ok(),
+        ResponseContent::Msgpack | ResponseContent::Cbor => content
+            .encoding()
+            .and_then(|encoding| encoding.decode(bytes).ok()),
+        ResponseContent::Bytes => return Data::Bytes(bytes.to_vec()),
+        ResponseContent::Text | ResponseContent::File => None,
+    };
+    match decoded {
+        Some(value) => Data::Json(value),
+        None if bytes.is_empty() => Data::Empty,
+        None => Data::String(String::from_utf8_lossy(bytes).to_string()),
+    }
+}
+
+/// url from the link header with rel="next"
+fn next_link(response: &Response) -> Option<String> {
+    response
+        .headers()
+        .get_all(LINK)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .find_map(|link| {
+            let (target, parameters) = link.split_once(';')?;
+            parameters
+                .split(';')
+                .any(|p| matches!(p.trim(), "rel=\"next\"" | "rel=next"))
+                .then(|| {
+                    let target = target.trim().trim_start_matches('<').trim_end_matches('>');
+                    response.url().join(target).ok().map(String::from)
+                })
+                .flatten()
+        })
+}
+
+/// replace the query parameter in the url
+fn set_query(url: &str, key: &str, value: &str) -> Result<String, anyhow::Error> {
+    let mut url = Url::parse(url)?;
+    let pairs: Vec<(String, String)> = url
+        .query_pairs()
+        .into_owned()
+        .filter(|(k, _)| k != key)
+        .collect();
+    url.query_pairs_mut()
+        .clear()
+        .extend_pairs(pairs)
+        .append_pair(key, value);
+    Ok(url.into())
+}
+
+/// json object fields are sent as text parts
+fn multipart_form(data: &Data, files: &[UploadFile]) -> Form {
+    let mut form = Form::new();
+    if let Data::Json(Value::Object(fields)) = data {
+        for (key, value) in fields {
+            form = form.text(key.clone(), field_value(value));
+        }
+    }
+    for file in files {
+        form = form.part(
+            file.field.to_string(),
+            Part::bytes(file.content.clone()).file_name(file.file_name.clone()),
+        );
+    }
+    form
+}
+
+fn field_value(value: &Value) -> String {
+    value
+        .as_str()
+        .map(ToString::to_string)
+        .unwrap_or(value.to_string())
+}
+
+fn deserialize_on_status<'de, D>(deserializer: D) -> Result<IndexMap<String, EventName>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    // yaml keys such as 404 are numbers
+    #[derive(Deserialize, PartialEq, Eq, Hash)]
+    #[serde(untagged)]
+    enum StatusKey {
+        Code(u16),
+        Class(String),
+    }
+    let map: IndexMap<StatusKey, EventName> = Deserialize::deserialize(deserializer)?;
+    Ok(map
+        .into_iter()
+        .map(|(key, event)| match key {
+            StatusKey::Code(c) => (c.to_string(), event),
+            StatusKey::Class(c) => (c.to_lowercase(), event),
+        })
+        .collect())
+}
+
+fn default_cursor_param() -> String {
+    "cursor".to_string()
+}
+
+fn default_max_pages() -> u32 {
+    100
+}
+
+fn default_retry_attempts() -> u32 {
+    3
+}
+
+fn default_retry_delay() -> u64 {
+    1
+}
+
+fn default_retry_status() -> Vec<u16> {
+    vec![429, 500, 502, 503, 504]
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -79,6 +569,14 @@ pub enum RequestMethod {
     #[default]
     Get,
     Delete,
+    Patch,
+    Head,
+    Options,
+    /// upnp event subscription
+    Subscribe,
+    Unsubscribe,
+    /// upnp event notification
+    Notify,
 }
 
 impl Display for RequestMethod {
@@ -88,6 +586,12 @@ impl Display for RequestMethod {
             RequestMethod::Post => write!(f, "POST"),
             RequestMethod::Get => write!(f, "GET"),
             RequestMethod::Delete => write!(f, "DELETE"),
+            RequestMethod::Patch => write!(f, "PATCH"),
+            RequestMethod::Head => write!(f, "HEAD"),
+            RequestMethod::Options => write!(f, "OPTIONS"),
+            RequestMethod::Subscribe => write!(f, "SUBSCRIBE"),
+            RequestMethod::Unsubscribe => write!(f, "UNSUBSCRIBE"),
+            RequestMethod::Notify => write!(f, "NOTIFY"),
         }
     }
 }
@@ -99,6 +603,24 @@ pub enum RequestContent {
     Text,
     #[default]
     Bytes,
+    /// application/x-www-form-urlencoded
+    Form,
+    /// multipart/form-data
+    Multipart,
+    /// application/msgpack
+    Msgpack,
+    /// application/cbor
+    Cbor,
+}
+
+impl RequestContent {
+    pub fn encoding(&self) -> Option<PayloadEncoding> {
+        match self {
+            RequestContent::Msgpack => Some(PayloadEncoding::Msgpack),
+            RequestContent::Cbor => Some(PayloadEncoding::Cbor),
+            _ => None,
+        }
+    }
 }
 
 impl Display for RequestContent {
@@ -107,6 +629,10 @@ impl Display for RequestContent {
             RequestContent::Json => write!(f, "json"),
             RequestContent::Text => write!(f, "text"),
             RequestContent::Bytes => write!(f, "bytes"),
+            RequestContent::Form => write!(f, "form"),
+            RequestContent::Multipart => write!(f, "multipart"),
+            RequestContent::Msgpack => write!(f, "msgpack"),
+            RequestContent::Cbor => write!(f, "cbor"),
         }
     }
 }
@@ -118,6 +644,22 @@ pub enum ResponseContent {
     #[default]
     Text,
     Bytes,
+    /// json array of objects by the header row, api_call only
+    Csv,
+    Msgpack,
+    Cbor,
+    /// stream the response to response_file, api_call only
+    File,
+}
+
+impl ResponseContent {
+    pub fn encoding(&self) -> Option<PayloadEncoding> {
+        match self {
+            ResponseContent::Msgpack => Some(PayloadEncoding::Msgpack),
+            ResponseContent::Cbor => Some(PayloadEncoding::Cbor),
+            _ => None,
+        }
+    }
 }
 
 impl Display for ResponseContent {
@@ -126,6 +668,366 @@ impl Display for ResponseContent {
             ResponseContent::Json => write!(f, "json"),
             ResponseContent::Text => write!(f, "text"),
             ResponseContent::Bytes => write!(f, "bytes"),
+            ResponseContent::Csv => write!(f, "csv"),
+            ResponseContent::Msgpack => write!(f, "msgpack"),
+            ResponseContent::Cbor => write!(f, "cbor"),
+            ResponseContent::File => write!(f, "file"),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{net::TcpListener, sync::mpsc::channel, thread::spawn, time::Instant};
+
+    use crate::multipart;
+
+    use super::*;
+
+    #[test]
+    fn test_timeout() {
+        let listener = TcpListener::bind("127.0.0.1:13339").unwrap();
+        // accept connections without responding
+        spawn(move || {
+            let _connections: Vec<_> = listener.incoming().collect();
+        });
+
+        let event = ApiCallEvent {
+            url: "http://127.0.0.1:13339".to_string(),
+            timeout: Some(1),
+            ..Default::default()
+        };
+        let started = Instant::now();
+        assert!(event
+            .call_api(&Client::new(), &Data::default(), "call")
+            .is_err());
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_retry() {
+        let server = tiny_http::Server::http("127.0.0.1:13340").unwrap();
+        spawn(move || {
+            for (status, request) in [503, 200].into_iter().zip(server.incoming_requests()) {
+                request
+                    .respond(tiny_http::Response::from_string("done").with_status_code(status))
+                    .unwrap();
+            }
+        });
+
+        let event = ApiCallEvent {
+            url: "http://127.0.0.1:13340".to_string(),
+            retry: Some(ApiCallRetry {
+                attempts: 2,
+                delay: 0,
+                status: default_retry_status(),
+            }),
+            ..Default::default()
+        };
+        let (data, _, _) = event
+            .call_api(&Client::new(), &Data::default(), "call")
+            .unwrap();
+        assert_eq!(data, Data::String("done".to_string()));
+    }
+
+    #[test]
+    fn test_status_event() {
+        let event: ApiCallEvent = serde_yaml::from_str(
+            "url: http://localhost\non_status:\n  404: missing\n  4XX: invalid\n  2xx: ok",
+        )
+        .unwrap();
+        assert_eq!(event.status_event(200).unwrap(), "ok");
+        assert_eq!(event.status_event(404).unwrap(), "missing");
+        assert_eq!(event.status_event(400).unwrap(), "invalid");
+        assert_eq!(event.status_event(500), None);
+    }
+
+    #[test]
+    fn test_error_response_status() {
+        let server = tiny_http::Server::http("127.0.0.1:13354").unwrap();
+        spawn(move || {
+            for request in server.incoming_requests() {
+                let response = match request.url() {
+                    "/error" => tiny_http::Response::from_string("<html>Internal error</html>")
+                        .with_status_code(500),
+                    _ => tiny_http::Response::from_string(r#"{"error":"missing"}"#)
+                        .with_status_code(404),
+                };
+                request.respond(response).unwrap();
+            }
+        });
+
+        let event: ApiCallEvent = serde_yaml::from_str(
+            "url: http://127.0.0.1:13354/error\nresponse_content: json\non_status:\n  5xx: failed\n  404: missing",
+        )
+        .unwrap();
+        let (data, _, status) = event
+            .call_api(&Client::new(), &Data::default(), "call")
+            .unwrap();
+        assert_eq!(status, 500);
+        assert_eq!(event.status_event(status).unwrap(), "failed");
+        assert_eq!(
+            data,
+            Data::String("<html>Internal error</html>".to_string())
+        );
+
+        let event = ApiCallEvent {
+            url: "http://127.0.0.1:13354/missing".to_string(),
+            ..event
+        };
+        let (data, _, status) = event
+            .call_api(&Client::new(), &Data::default(), "call")
+            .unwrap();
+        assert_eq!(event.status_event(status).unwrap(), "missing");
+        assert_eq!(data, Data::Json(json!({"error": "missing"})));
+    }
+
+    #[test]
+    fn test_multipart_upload() {
+        let file = std::env::temp_dir().join("hvents_snapshot.jpg");
+        std::fs::write(&file, b"\x01\x02").unwrap();
+
+        let server = tiny_http::Server::http("127.0.0.1:13343").unwrap();
+        let (body_tx, body_rx) = channel();
+        spawn(move || {
+            let mut request = server.incoming_requests().next().unwrap();
+            let content_type = request
+                .headers()
+                .iter()
+                .find(|h| h.field.equiv("Content-Type"))
+                .map(|h| h.value.to_string())
+                .unwrap();
+            let mut body = Vec::new();
+            request.as_reader().read_to_end(&mut body).unwrap();
+            body_tx.send((content_type, body)).unwrap();
+            request.respond(tiny_http::Response::empty(200)).unwrap();
+        });
+
+        let event = ApiCallEvent {
+            url: "http://127.0.0.1:13343/sendPhoto".to_string(),
+            method: RequestMethod::Post,
+            request_content: RequestContent::Multipart,
+            files: [("photo".to_string(), file.to_string_lossy().to_string())].into(),
+            ..Default::default()
+        };
+        event
+            .call_api(&Client::new(), &Data::Json(json!({"chat_id": 1})), "call")
+            .unwrap();
+
+        let (content_type, body) = body_rx.recv().unwrap();
+        let parts = multipart::parse(&body, multipart::boundary(&content_type).unwrap());
+        assert_eq!(parts.len(), 2);
+        assert_eq!(parts[0].name, "chat_id");
+        assert_eq!(parts[0].content, b"1");
+        assert_eq!(parts[1].name, "photo");
+        assert_eq!(parts[1].filename.as_deref(), Some("hvents_snapshot.jpg"));
+        assert_eq!(parts[1].content, b"\x01\x02");
+    }
+
+    #[test]
+    fn test_response_file() {
+        let server = tiny_http::Server::http("127.0.0.1:13344").unwrap();
+        spawn(move || {
+            let request = server.incoming_requests().next().unwrap();
+            request
+                .respond(tiny_http::Response::from_data(vec![7; 100_000]))
+                .unwrap();
+        });
+
+        let path = std::env::temp_dir().join("hvents_firmware.bin");
+        let event = ApiCallEvent {
+            url: "http://127.0.0.1:13344/firmware.bin".to_string(),
+            response_content: ResponseContent::File,
+            response_file: Some(path.to_string_lossy().to_string()),
+            ..Default::default()
+        };
+        let (data, metadata, _) = event
+            .call_api(&Client::new(), &Data::default(), "download")
+            .unwrap();
+        assert_eq!(data, Data::Empty);
+        assert_eq!(
+            serde_json::to_value(&metadata).unwrap()["download"]["file"],
+            path.to_string_lossy().as_ref()
+        );
+        assert_eq!(
+            serde_json::to_value(&metadata).unwrap()["download"]["url"],
+            "http://127.0.0.1:13344/firmware.bin"
+        );
+        assert!(serde_json::to_value(&metadata).unwrap()["download"]["response_time"].is_u64());
+        assert_eq!(std::fs::read(&path).unwrap(), vec![7; 100_000]);
+    }
+
+    #[test]
+    fn test_response_file_error_status() {
+        let server = tiny_http::Server::http("127.0.0.1:13355").unwrap();
+        spawn(move || {
+            let request = server.incoming_requests().next().unwrap();
+            request
+                .respond(tiny_http::Response::from_string("Not found").with_status_code(404))
+                .unwrap();
+        });
+
+        let path = std::env::temp_dir().join("hvents_previous_firmware.bin");
+        std::fs::write(&path, b"previous").unwrap();
+        let event = ApiCallEvent {
+            url: "http://127.0.0.1:13355/firmware.bin".to_string(),
+            response_content: ResponseContent::File,
+            response_file: Some(path.to_string_lossy().to_string()),
+            ..Default::default()
+        };
+        let (data, metadata, status) = event
+            .call_api(&Client::new(), &Data::default(), "download")
+            .unwrap();
+        assert_eq!(status, 404);
+        assert_eq!(data, Data::String("Not found".to_string()));
+        assert!(serde_json::to_value(&metadata).unwrap()["download"]["file"].is_null());
+        assert_eq!(std::fs::read(&path).unwrap(), b"previous");
+    }
+
+    #[test]
+    fn test_pagination() {
+        let server = tiny_http::Server::http("127.0.0.1:13345").unwrap();
+        spawn(move || {
+            for request in server.incoming_requests() {
+                let response = match request.url() {
+                    "/devices" => tiny_http::Response::from_string(r#"[1, 2]"#).with_header(
+                        "Link: </devices?page=2>; rel=\"next\", </devices?page=3>; rel=\"last\""
+                            .parse::<tiny_http::Header>()
+                            .unwrap(),
+                    ),
+                    "/devices?page=2" => tiny_http::Response::from_string(r#"[3]"#),
+                    "/sensors" => tiny_http::Response::from_string(
+                        r#"{"data": [{"id": 1}], "meta": {"next": "abc"}}"#,
+                    ),
+                    "/sensors?cursor=abc" => tiny_http::Response::from_string(
+                        r#"{"data": [{"id": 2}], "meta": {"next": ""}}"#,
+                    ),
+                    _ => tiny_http::Response::from_string("").with_status_code(404),
+                };
+                request.respond(response).unwrap();
+            }
+        });
+
+        let event = ApiCallEvent {
+            url: "http://127.0.0.1:13345/devices".to_string(),
+            pagination: Some(ApiCallPagination {
+                cursor: None,
+                param: default_cursor_param(),
+                items: None,
+                max_pages: default_max_pages(),
+            }),
+            ..Default::default()
+        };
+        let (data, _, _) = event
+            .call_api(&Client::new(), &Data::default(), "call")
+            .unwrap();
+        assert_eq!(data, Data::Json(json!([1, 2, 3])));
+
+        let event = ApiCallEvent {
+            url: "http://127.0.0.1:13345/sensors".to_string(),
+            pagination: Some(ApiCallPagination {
+                cursor: Some("/meta/next".to_string()),
+                param: default_cursor_param(),
+                items: Some("/data".to_string()),
+                max_pages: default_max_pages(),
+            }),
+            ..Default::default()
+        };
+        let (data, _, _) = event
+            .call_api(&Client::new(), &Data::default(), "call")
+            .unwrap();
+        assert_eq!(data, Data::Json(json!([{"id": 1}, {"id": 2}])));
+    }
+
+    #[test]
+    fn test_extract() {
+        let event = ApiCallEvent {
+            extract: [
+                ("temperature".to_string(), "/main/temp".to_string()),
+                ("first".to_string(), "/list/0/name".to_string()),
+                ("missing".to_string(), "/wind/speed".to_string()),
+            ]
+            .into(),
+            ..Default::default()
+        };
+        let response = json!({"main": {"temp": 21.5, "humidity": 40}, "list": [{"name": "hall"}]});
+        assert_eq!(
+            event.extract(&response),
+            json!({"temperature": 21.5, "first": "hall"})
+        );
+    }
+
+    #[test]
+    fn test_graphql() {
+        let event = ApiCallEvent {
+            graphql: Some(GraphQl {
+                query: "query($id: ID!) { device(id: $id) { name } }".to_string(),
+                variables: None,
+            }),
+            ..Default::default()
+        };
+        let body: Value =
+            serde_json::from_slice(&event.body(&Data::Json(json!({"id": 1}))).unwrap()).unwrap();
+        assert_eq!(
+            body,
+            json!({"query": "query($id: ID!) { device(id: $id) { name } }", "variables": {"id": 1}})
+        );
+
+        assert_eq!(
+            graphql_data(json!({"data": {"device": {"name": "lamp"}}})).unwrap(),
+            Data::Json(json!({"device": {"name": "lamp"}}))
+        );
+        assert!(graphql_data(json!({"data": null, "errors": [{"message": "not found"}]})).is_err());
+    }
+
+    #[test]
+    fn test_retry_delay() {
+        let retry = ApiCallRetry {
+            attempts: 5,
+            delay: 2,
+            status: Vec::new(),
+        };
+        assert_eq!(retry.delay(1), Duration::from_secs(2));
+        assert_eq!(retry.delay(3), Duration::from_secs(8));
+    }
+
+    #[test]
+    fn test_body() {
+        let data = Data::Json(json!({"temperature": 21, "room": "hall"}));
+        let event = ApiCallEvent {
+            request_content: RequestContent::Form,
+            ..Default::default()
+        };
+        assert_eq!(event.body(&data).unwrap(), b"room=hall&temperature=21");
+
+        let event = ApiCallEvent {
+            body: Some("rendered".to_string()),
+            ..event
+        };
+        assert_eq!(event.body(&data).unwrap(), b"rendered");
+    }
+
+    #[test]
+    fn test_url() {
+        let mut event = ApiCallEvent {
+            url: "http://localhost/search".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(event.url(), "http://localhost/search");
+
+        event
+            .query
+            .insert("q".to_string(), "living room".to_string());
+        event.query.insert("a&b".to_string(), "1=2".to_string());
+        assert_eq!(
+            event.url(),
+            "http://localhost/search?q=living+room&a%26b=1%3D2"
+        );
+
+        event.url = "http://localhost/search?page=1".to_string();
+        assert_eq!(
+            event.url(),
+            "http://localhost/search?page=1&q=living+room&a%26b=1%3D2"
+        );
+    }
+}
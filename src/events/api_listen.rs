@@ -1,25 +1,49 @@
-use std::sync::{Arc, Mutex};
+use std::{
+    path::PathBuf,
+    sync::{mpsc::SyncSender, Arc, Mutex, RwLock},
+};
 
-use indexmap::IndexSet;
-use serde::{Deserialize, Serialize};
+use indexmap::{IndexMap, IndexSet};
+use regex::Regex;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
 
 use crate::config::{Headers, PoolId};
 
 use super::{
     api_call::{RequestContent, RequestMethod, ResponseContent},
-    ReferencingEvent,
+    EventName, ReferencingEvent,
 };
 
 pub type HttpQueue = Arc<Mutex<IndexSet<ReferencingEvent>>>;
+pub type HttpStreams = Arc<Mutex<Vec<HttpStream>>>;
+/// snapshot of the event executor state available to response templates
+pub type HttpState = Arc<RwLock<IndexMap<String, String>>>;
 
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+/// connection receiving server-sent events
+pub struct HttpStream {
+    pub events: Vec<EventName>,
+    pub sender: SyncSender<Vec<u8>>,
+}
+
+impl HttpStream {
+    pub fn matches(&self, name: &str) -> bool {
+        self.events.is_empty() || self.events.iter().any(|e| e == name)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ApiListenEvent {
-    pub path: String,
+    /// path and match, regular expressions are compiled when events are loaded
+    #[serde(flatten)]
+    pub path: PathMatch,
     #[serde(default)]
     pub headers: Headers,
     pub response_body: Option<String>,
-    #[serde(default)]
-    pub method: RequestMethod,
+    /// response template file used instead of response_body
+    pub template_file: Option<PathBuf>,
+    /// a single method or a list of methods
+    #[serde(default = "default_methods", deserialize_with = "deserialize_methods")]
+    pub method: Vec<RequestMethod>,
     #[serde(default)]
     pub request_content: RequestContent,
     #[serde(default)]
@@ -28,12 +52,139 @@ pub struct ApiListenEvent {
     pub action: ApiListenAction,
     #[serde(default)]
     pub pool_id: PoolId,
+    /// keep the connection open and stream executed events as server-sent events
+    pub stream: Option<StreamEvents>,
+    /// directory to save files uploaded with multipart request content
+    pub upload_directory: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct StreamEvents {
+    /// event names to stream, all events are streamed if empty
+    #[serde(default)]
+    pub events: Vec<EventName>,
+}
+
+impl Default for ApiListenEvent {
+    fn default() -> Self {
+        Self {
+            path: Default::default(),
+            headers: Default::default(),
+            response_body: Default::default(),
+            template_file: Default::default(),
+            method: default_methods(),
+            request_content: Default::default(),
+            response_content: Default::default(),
+            action: Default::default(),
+            pool_id: Default::default(),
+            stream: Default::default(),
+            upload_directory: Default::default(),
+        }
+    }
 }
 
 impl ApiListenEvent {
     pub fn matches(&self, url: &str, method: &str) -> bool {
-        url.starts_with(&self.path)
-            && self.method.to_string().to_uppercase() == method.to_uppercase()
+        // paths are matched without the query string
+        let path = url.split('?').next().unwrap_or_default();
+        self.path.matches(path)
+            && self
+                .method
+                .iter()
+                .any(|m| m.to_string().eq_ignore_ascii_case(method))
+    }
+}
+
+fn default_methods() -> Vec<RequestMethod> {
+    vec![RequestMethod::Get]
+}
+
+fn deserialize_methods<'de, D>(deserializer: D) -> Result<Vec<RequestMethod>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(RequestMethod),
+        Many(Vec<RequestMethod>),
+    }
+    let s: OneOrMany = de::Deserialize::deserialize(deserializer)?;
+    match s {
+        OneOrMany::One(method) => Ok(vec![method]),
+        OneOrMany::Many(methods) if methods.is_empty() => {
+            Err(de::Error::custom("expected at least one method"))
+        }
+        OneOrMany::Many(methods) => Ok(methods),
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum PathMatch {
+    Prefix(String),
+    Exact(String),
+    Regex(Regex),
+}
+
+impl Default for PathMatch {
+    fn default() -> Self {
+        Self::Prefix(Default::default())
+    }
+}
+
+impl PathMatch {
+    pub fn matches(&self, path: &str) -> bool {
+        match self {
+            Self::Prefix(p) => path.starts_with(p.as_str()),
+            Self::Exact(p) => path == p,
+            Self::Regex(r) => r.is_match(path),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+enum MatchKind {
+    #[default]
+    Prefix,
+    Exact,
+    Regex,
+}
+
+#[derive(Serialize, Deserialize)]
+struct PathConfig {
+    path: String,
+    #[serde(default, rename = "match")]
+    kind: MatchKind,
+}
+
+impl<'de> Deserialize<'de> for PathMatch {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let config = PathConfig::deserialize(deserializer)?;
+        Ok(match config.kind {
+            MatchKind::Prefix => Self::Prefix(config.path),
+            MatchKind::Exact => Self::Exact(config.path),
+            MatchKind::Regex => Self::Regex(Regex::new(&config.path).map_err(|e| {
+                de::Error::custom(format!("invalid path regex {} {e}", config.path))
+            })?),
+        })
+    }
+}
+
+impl Serialize for PathMatch {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let (path, kind) = match self {
+            Self::Prefix(p) => (p.clone(), MatchKind::Prefix),
+            Self::Exact(p) => (p.clone(), MatchKind::Exact),
+            Self::Regex(r) => (r.as_str().to_string(), MatchKind::Regex),
+        };
+        PathConfig { path, kind }.serialize(serializer)
     }
 }
 
@@ -80,6 +231,62 @@ mod tests {
                 "post",
                 false,
             ),
+            (
+                "exact match ignores longer urls",
+                create_listen_event("/clients", Default::default())
+                    .with_path(PathMatch::Exact("/clients".to_string())),
+                "/clients/health",
+                "get",
+                false,
+            ),
+            (
+                "exact match ignores query string",
+                create_listen_event("/clients", Default::default())
+                    .with_path(PathMatch::Exact("/clients".to_string())),
+                "/clients?id=1",
+                "get",
+                true,
+            ),
+            (
+                "prefix match ignores query string",
+                create_listen_event("/clients", Default::default()),
+                "/clients?id=1",
+                "get",
+                true,
+            ),
+            (
+                "prefix is not matched against query string",
+                create_listen_event("/clients/1", Default::default()),
+                "/clients?path=/clients/1",
+                "get",
+                false,
+            ),
+            (
+                "match url by regex",
+                create_listen_event("", Default::default())
+                    .with_path(PathMatch::Regex(Regex::new(r"^/clients/\d+$").unwrap())),
+                "/clients/12",
+                "get",
+                true,
+            ),
+            (
+                "regex does not match",
+                create_listen_event("", Default::default())
+                    .with_path(PathMatch::Regex(Regex::new(r"^/clients/\d+$").unwrap())),
+                "/clients/health",
+                "get",
+                false,
+            ),
+            (
+                "match one of many methods",
+                ApiListenEvent {
+                    method: vec![RequestMethod::Get, RequestMethod::Post],
+                    ..create_listen_event("/clients", Default::default())
+                },
+                "/clients",
+                "post",
+                true,
+            ),
             (
                 "different methods",
                 create_listen_event("/clients/1", RequestMethod::Post),
@@ -95,14 +302,55 @@ mod tests {
 
     fn create_listen_event(uri: &str, request_method: RequestMethod) -> ApiListenEvent {
         ApiListenEvent {
-            path: uri.to_string(),
+            path: PathMatch::Prefix(uri.to_string()),
             headers: Default::default(),
             response_body: Default::default(),
-            method: request_method,
+            template_file: Default::default(),
+            method: vec![request_method],
             request_content: Default::default(),
             response_content: Default::default(),
             action: Default::default(),
             pool_id: Default::default(),
+            stream: Default::default(),
+            upload_directory: Default::default(),
+        }
+    }
+
+    impl ApiListenEvent {
+        fn with_path(mut self, path: PathMatch) -> Self {
+            self.path = path;
+            self
         }
     }
+
+    #[test]
+    fn test_deserialize_methods() {
+        let event: ApiListenEvent = serde_yaml::from_str("path: /a").unwrap();
+        assert!(matches!(event.method[..], [RequestMethod::Get]));
+        let event: ApiListenEvent = serde_yaml::from_str("path: /a\nmethod: put").unwrap();
+        assert!(matches!(event.method[..], [RequestMethod::Put]));
+        let event: ApiListenEvent = serde_yaml::from_str("path: /a\nmethod: [get, post]").unwrap();
+        assert!(matches!(
+            event.method[..],
+            [RequestMethod::Get, RequestMethod::Post]
+        ));
+        let event: ApiListenEvent =
+            serde_yaml::from_str("path: /a\nmethod: [patch, options]").unwrap();
+        assert!(event.matches("/a", "PATCH"));
+        assert!(event.matches("/a", "OPTIONS"));
+        assert!(!event.matches("/a", "HEAD"));
+        assert!(serde_yaml::from_str::<ApiListenEvent>("path: /a\nmethod: []").is_err());
+    }
+
+    #[test]
+    fn test_deserialize_path_match() {
+        let event: ApiListenEvent = serde_yaml::from_str("path: /a").unwrap();
+        assert!(matches!(event.path, PathMatch::Prefix(ref p) if p == "/a"));
+        let event: ApiListenEvent = serde_yaml::from_str("path: /a\nmatch: exact").unwrap();
+        assert!(matches!(event.path, PathMatch::Exact(ref p) if p == "/a"));
+        let event: ApiListenEvent =
+            serde_yaml::from_str("path: ^/clients/\\d+$\nmatch: regex").unwrap();
+        assert!(event.matches("/clients/12?full=1", "get"));
+        assert!(serde_yaml::from_str::<ApiListenEvent>("path: /clients/(\nmatch: regex").is_err());
+    }
 }
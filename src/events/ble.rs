@@ -0,0 +1,94 @@
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+
+/// parsed advertising report
+#[derive(Debug, Clone, Serialize, PartialEq, Default)]
+pub struct Advertisement {
+    pub address: String,
+    pub name: Option<String>,
+    pub rssi: i8,
+    /// 16, 32 or 128 bit service uuids e.g. 181a
+    pub services: Vec<String>,
+    /// hex encoded data by service uuid
+    pub service_data: IndexMap<String, String>,
+    /// hex encoded data by company id e.g. 004c
+    pub manufacturer_data: IndexMap<String, String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BleEvent {
+    /// mac address e.g. A4:C1:38:00:00:01, any device matches if not specified
+    pub address: Option<String>,
+    /// advertised name
+    pub name: Option<String>,
+    /// advertised service uuid e.g. 181a
+    pub service: Option<String>,
+    /// seconds to ignore advertisements from the same device after an event
+    #[serde(default = "default_interval")]
+    pub interval: u64,
+}
+
+impl Default for BleEvent {
+    fn default() -> Self {
+        Self {
+            address: None,
+            name: None,
+            service: None,
+            interval: default_interval(),
+        }
+    }
+}
+
+impl BleEvent {
+    pub fn matches(&self, advertisement: &Advertisement) -> bool {
+        self.address
+            .as_ref()
+            .map(|a| a.eq_ignore_ascii_case(&advertisement.address))
+            .unwrap_or(true)
+            && self
+                .name
+                .as_ref()
+                .map(|n| advertisement.name.as_ref() == Some(n))
+                .unwrap_or(true)
+            && self
+                .service
+                .as_ref()
+                .map(|s| {
+                    advertisement
+                        .services
+                        .iter()
+                        .chain(advertisement.service_data.keys())
+                        .any(|u| u.eq_ignore_ascii_case(s))
+                })
+                .unwrap_or(true)
+    }
+}
+
+fn default_interval() -> u64 {
+    60
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ble_matches() {
+        let advertisement = Advertisement {
+            address: "A4:C1:38:12:34:56".to_string(),
+            name: Some("ATC_123456".to_string()),
+            service_data: [("181a".to_string(), "a4c138".to_string())].into(),
+            ..Default::default()
+        };
+        assert!(BleEvent::default().matches(&advertisement));
+
+        let event: BleEvent = serde_yaml::from_str("address: a4:c1:38:12:34:56").unwrap();
+        assert!(event.matches(&advertisement));
+
+        let event: BleEvent = serde_yaml::from_str("service: 181A\nname: ATC_123456").unwrap();
+        assert!(event.matches(&advertisement));
+
+        let event: BleEvent = serde_yaml::from_str("service: fe95").unwrap();
+        assert!(!event.matches(&advertisement));
+    }
+}
@@ -0,0 +1,511 @@
+use core::time::Duration;
+use std::{
+    io::ErrorKind,
+    net::UdpSocket,
+    sync::atomic::{AtomicU16, Ordering},
+    time::{Instant, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{anyhow, bail, Context};
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+const DEFAULT_PORT: u16 = 5683;
+/// initial retransmission timeout for confirmable messages
+const ACK_TIMEOUT: Duration = Duration::from_secs(2);
+/// observations are registered again if no notification arrives in time
+const OBSERVE_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+const CONFIRMABLE: u8 = 0;
+const ACKNOWLEDGEMENT: u8 = 2;
+const RESET: u8 = 3;
+
+const OPTION_URI_HOST: u16 = 3;
+const OPTION_OBSERVE: u16 = 6;
+const OPTION_URI_PORT: u16 = 7;
+const OPTION_URI_PATH: u16 = 11;
+const OPTION_CONTENT_FORMAT: u16 = 12;
+const OPTION_URI_QUERY: u16 = 15;
+
+static MESSAGE_ID: AtomicU16 = AtomicU16::new(0);
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum CoapMethod {
+    #[default]
+    Get,
+    Post,
+    Put,
+    Delete,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct CoapCallEvent {
+    /// template e.g. coap://192.168.1.20/sensors/temperature
+    pub url: String,
+    #[serde(default)]
+    pub method: CoapMethod,
+    /// template, sent with post and put requests
+    pub body: Option<String>,
+    /// e.g. 0 text/plain, 50 application/json
+    pub content_format: Option<u16>,
+    /// every notification of an observed resource is passed to the next event
+    #[serde(default)]
+    pub observe: bool,
+    /// seconds to wait for a response
+    #[serde(default = "default_timeout")]
+    pub timeout: u64,
+}
+
+fn default_timeout() -> u64 {
+    5
+}
+
+impl CoapCallEvent {
+    pub fn call(&self, url: &str, payload: &[u8]) -> anyhow::Result<CoapMessage> {
+        let (socket, request) = self.connect(url, payload, false)?;
+        exchange(&socket, &request, Duration::from_secs(self.timeout))
+    }
+
+    /// returns once notify returns false, the resource answers without the observe option
+    /// or the observation fails
+    pub fn observe(
+        &self,
+        url: &str,
+        mut notify: impl FnMut(CoapMessage) -> bool,
+    ) -> anyhow::Result<()> {
+        let (socket, request) = self.connect(url, &[], true)?;
+        let response = exchange(&socket, &request, Duration::from_secs(self.timeout))?;
+        let observed = response.option(OPTION_OBSERVE).is_some();
+        if !notify(response) || !observed {
+            return Ok(());
+        }
+        socket.set_read_timeout(Some(OBSERVE_TIMEOUT))?;
+        let mut buf = [0; 1500];
+        loop {
+            let size = socket.recv(&mut buf)?;
+            let Ok(message) = CoapMessage::decode(&buf[..size]) else {
+                continue;
+            };
+            if message.kind == CONFIRMABLE {
+                socket.send(&CoapMessage::acknowledge(message.message_id).encode())?;
+            }
+            if message.token != request.token || message.code == 0 {
+                continue;
+            }
+            if !notify(message) {
+                return Ok(());
+            }
+        }
+    }
+
+    fn connect(
+        &self,
+        url: &str,
+        payload: &[u8],
+        observe: bool,
+    ) -> anyhow::Result<(UdpSocket, CoapMessage)> {
+        let parsed = Url::parse(url).with_context(|| format!("Invalid coap url {url}"))?;
+        if parsed.scheme() != "coap" {
+            bail!("Unsupported coap url {url} only coap:// is supported");
+        }
+        let host = parsed
+            .host_str()
+            .ok_or_else(|| anyhow!("Coap url {url} is missing a host"))?;
+        let port = parsed.port().unwrap_or(DEFAULT_PORT);
+        let socket = UdpSocket::bind("0.0.0.0:0")?;
+        socket.connect((host.trim_start_matches('[').trim_end_matches(']'), port))?;
+
+        let mut options = Vec::new();
+        if parsed.domain().is_some() {
+            options.push((OPTION_URI_HOST, host.as_bytes().to_vec()));
+        }
+        if observe {
+            options.push((OPTION_OBSERVE, Vec::new()));
+        }
+        if port != DEFAULT_PORT {
+            options.push((OPTION_URI_PORT, encode_uint(port.into())));
+        }
+        for segment in parsed.path_segments().into_iter().flatten() {
+            if !segment.is_empty() {
+                options.push((OPTION_URI_PATH, segment.as_bytes().to_vec()));
+            }
+        }
+        if let Some(format) = self.content_format {
+            options.push((OPTION_CONTENT_FORMAT, encode_uint(format.into())));
+        }
+        for query in parsed.query().into_iter().flat_map(|q| q.split('&')) {
+            options.push((OPTION_URI_QUERY, query.as_bytes().to_vec()));
+        }
+        let with_payload = matches!(self.method, CoapMethod::Post | CoapMethod::Put);
+        Ok((
+            socket,
+            CoapMessage {
+                kind: CONFIRMABLE,
+                code: match self.method {
+                    CoapMethod::Get => 1,
+                    CoapMethod::Post => 2,
+                    CoapMethod::Put => 3,
+                    CoapMethod::Delete => 4,
+                },
+                message_id: MESSAGE_ID.fetch_add(1, Ordering::Relaxed),
+                token: random_token(),
+                options,
+                payload: if with_payload {
+                    payload.to_vec()
+                } else {
+                    Vec::new()
+                },
+            },
+        ))
+    }
+}
+
+/// sends a confirmable request and waits for a piggybacked or separate response
+fn exchange(
+    socket: &UdpSocket,
+    request: &CoapMessage,
+    timeout: Duration,
+) -> anyhow::Result<CoapMessage> {
+    let bytes = request.encode();
+    let deadline = Instant::now() + timeout;
+    let mut retransmit = ACK_TIMEOUT;
+    let mut next_send = Instant::now();
+    let mut acknowledged = false;
+    let mut buf = [0; 1500];
+    loop {
+        let now = Instant::now();
+        if now >= deadline {
+            bail!("No coap response received");
+        }
+        if !acknowledged && now >= next_send {
+            socket.send(&bytes)?;
+            next_send = now + retransmit;
+            retransmit *= 2;
+        }
+        let wait = if acknowledged {
+            deadline
+        } else {
+            deadline.min(next_send)
+        };
+        socket.set_read_timeout(Some(
+            wait.saturating_duration_since(now)
+                .max(Duration::from_millis(1)),
+        ))?;
+        let size = match socket.recv(&mut buf) {
+            Ok(size) => size,
+            Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => continue,
+            Err(e) => return Err(e.into()),
+        };
+        let Ok(message) = CoapMessage::decode(&buf[..size]) else {
+            continue;
+        };
+        if message.message_id == request.message_id {
+            match message.kind {
+                RESET => bail!("Coap request was reset"),
+                // empty acknowledgement, the response is sent separately
+                ACKNOWLEDGEMENT if message.code == 0 => {
+                    acknowledged = true;
+                    continue;
+                }
+                _ => (),
+            }
+        }
+        if message.token != request.token {
+            continue;
+        }
+        if message.kind == CONFIRMABLE {
+            socket.send(&CoapMessage::acknowledge(message.message_id).encode())?;
+        }
+        return Ok(message);
+    }
+}
+
+fn random_token() -> Vec<u8> {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .subsec_nanos();
+    (nanos ^ (u32::from(MESSAGE_ID.load(Ordering::Relaxed)) << 16))
+        .to_be_bytes()
+        .to_vec()
+}
+
+fn encode_uint(value: u32) -> Vec<u8> {
+    let bytes = value.to_be_bytes();
+    let skip = bytes.iter().take_while(|b| **b == 0).count();
+    bytes[skip..].to_vec()
+}
+
+fn decode_uint(bytes: &[u8]) -> u32 {
+    bytes.iter().fold(0, |v, b| (v << 8) | u32::from(*b))
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct CoapMessage {
+    pub kind: u8,
+    /// class in the upper 3 bits, detail in the lower 5 bits e.g. 2.05
+    pub code: u8,
+    pub message_id: u16,
+    pub token: Vec<u8>,
+    /// sorted by option number
+    pub options: Vec<(u16, Vec<u8>)>,
+    pub payload: Vec<u8>,
+}
+
+impl CoapMessage {
+    fn acknowledge(message_id: u16) -> Self {
+        Self {
+            kind: ACKNOWLEDGEMENT,
+            message_id,
+            ..Default::default()
+        }
+    }
+
+    pub fn is_success(&self) -> bool {
+        self.code >> 5 == 2
+    }
+
+    /// response code e.g. 2.05
+    pub fn status(&self) -> String {
+        format!("{}.{:02}", self.code >> 5, self.code & 0x1f)
+    }
+
+    pub fn option(&self, number: u16) -> Option<&[u8]> {
+        self.options
+            .iter()
+            .find(|(n, _)| *n == number)
+            .map(|(_, v)| v.as_slice())
+    }
+
+    pub fn metadata(&self) -> Value {
+        json!({
+            "code": self.status(),
+            "content_format": self.option(OPTION_CONTENT_FORMAT).map(decode_uint),
+            "observe": self.option(OPTION_OBSERVE).map(decode_uint),
+        })
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut bytes = vec![0x40 | (self.kind << 4) | self.token.len() as u8, self.code];
+        bytes.extend_from_slice(&self.message_id.to_be_bytes());
+        bytes.extend_from_slice(&self.token);
+        let mut previous = 0;
+        for (number, value) in &self.options {
+            let (delta, delta_ext) = encode_option_part(number - previous);
+            let (length, length_ext) = encode_option_part(value.len() as u16);
+            bytes.push((delta << 4) | length);
+            bytes.extend_from_slice(&delta_ext);
+            bytes.extend_from_slice(&length_ext);
+            bytes.extend_from_slice(value);
+            previous = *number;
+        }
+        if !self.payload.is_empty() {
+            bytes.push(0xff);
+            bytes.extend_from_slice(&self.payload);
+        }
+        bytes
+    }
+
+    pub fn decode(bytes: &[u8]) -> anyhow::Result<Self> {
+        let [first, code, id_high, id_low, rest @ ..] = bytes else {
+            bail!("Coap message is too short");
+        };
+        if first >> 6 != 1 {
+            bail!("Unsupported coap version");
+        }
+        let token_length = usize::from(first & 0x0f);
+        if token_length > 8 || rest.len() < token_length {
+            bail!("Invalid coap token");
+        }
+        let (token, mut rest) = rest.split_at(token_length);
+        let mut options = Vec::new();
+        let mut number = 0;
+        let mut payload = Vec::new();
+        while let [header, tail @ ..] = rest {
+            if *header == 0xff {
+                payload = tail.to_vec();
+                break;
+            }
+            let (delta, tail) = decode_option_part(header >> 4, tail)?;
+            let (length, tail) = decode_option_part(header & 0x0f, tail)?;
+            if tail.len() < usize::from(length) {
+                bail!("Invalid coap option length");
+            }
+            let (value, tail) = tail.split_at(usize::from(length));
+            number += delta;
+            options.push((number, value.to_vec()));
+            rest = tail;
+        }
+        Ok(Self {
+            kind: (first >> 4) & 0x03,
+            code: *code,
+            message_id: u16::from_be_bytes([*id_high, *id_low]),
+            token: token.to_vec(),
+            options,
+            payload,
+        })
+    }
+}
+
+fn encode_option_part(value: u16) -> (u8, Vec<u8>) {
+    match value {
+        0..=12 => (value as u8, Vec::new()),
+        13..=268 => (13, vec![(value - 13) as u8]),
+        _ => (14, (value - 269).to_be_bytes().to_vec()),
+    }
+}
+
+fn decode_option_part(nibble: u8, bytes: &[u8]) -> anyhow::Result<(u16, &[u8])> {
+    match (nibble, bytes) {
+        (0..=12, _) => Ok((nibble.into(), bytes)),
+        (13, [v, rest @ ..]) => Ok((u16::from(*v) + 13, rest)),
+        (14, [high, low, rest @ ..]) => Ok((u16::from_be_bytes([*high, *low]) + 269, rest)),
+        _ => bail!("Invalid coap option"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+
+    #[test]
+    fn test_encode_decode() {
+        let message = CoapMessage {
+            kind: CONFIRMABLE,
+            code: 3,
+            message_id: 0x1234,
+            token: vec![1, 2, 3, 4],
+            options: vec![
+                (OPTION_URI_PATH, b"lights".to_vec()),
+                (OPTION_URI_PATH, b"kitchen-ceiling-left-1".to_vec()),
+                (OPTION_CONTENT_FORMAT, encode_uint(50)),
+                (300, vec![0; 300]),
+            ],
+            payload: br#"{"on":true}"#.to_vec(),
+        };
+        let bytes = message.encode();
+        assert_eq!(bytes[..4], [0x44, 3, 0x12, 0x34]);
+        assert_eq!(CoapMessage::decode(&bytes).unwrap(), message);
+        assert!(CoapMessage::decode(&bytes[..2]).is_err());
+        assert_eq!(
+            message.metadata(),
+            json!({"code": "0.03", "content_format": 50, "observe": null})
+        );
+    }
+
+    #[test]
+    fn test_call_and_observe() {
+        let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let url = format!(
+            "coap://127.0.0.1:{}/sensors/temp?unit=c",
+            server.local_addr().unwrap().port()
+        );
+        let handle = thread::spawn(move || {
+            let mut buf = [0; 1500];
+            // piggybacked response
+            let (size, client) = server.recv_from(&mut buf).unwrap();
+            let request = CoapMessage::decode(&buf[..size]).unwrap();
+            assert_eq!(request.code, 1);
+            assert_eq!(request.option(OPTION_URI_PATH), Some(b"sensors".as_slice()));
+            assert_eq!(request.option(OPTION_URI_QUERY), Some(b"unit=c".as_slice()));
+            let response = CoapMessage {
+                kind: ACKNOWLEDGEMENT,
+                code: 0x45,
+                message_id: request.message_id,
+                token: request.token,
+                options: vec![(OPTION_CONTENT_FORMAT, encode_uint(50))],
+                payload: b"21.5".to_vec(),
+            };
+            server.send_to(&response.encode(), client).unwrap();
+
+            // empty acknowledgement followed by notifications
+            let (size, client) = server.recv_from(&mut buf).unwrap();
+            let request = CoapMessage::decode(&buf[..size]).unwrap();
+            assert_eq!(request.option(OPTION_OBSERVE), Some([].as_slice()));
+            server
+                .send_to(
+                    &CoapMessage::acknowledge(request.message_id).encode(),
+                    client,
+                )
+                .unwrap();
+            for (sequence, value) in [(1, "21.5"), (2, "22.0")] {
+                let notification = CoapMessage {
+                    kind: CONFIRMABLE,
+                    code: 0x45,
+                    message_id: 100 + sequence as u16,
+                    token: request.token.clone(),
+                    options: vec![(OPTION_OBSERVE, encode_uint(sequence))],
+                    payload: value.as_bytes().to_vec(),
+                };
+                server.send_to(&notification.encode(), client).unwrap();
+                let (size, _) = server.recv_from(&mut buf).unwrap();
+                let ack = CoapMessage::decode(&buf[..size]).unwrap();
+                assert_eq!(ack.kind, ACKNOWLEDGEMENT);
+                assert_eq!(ack.message_id, 100 + sequence as u16);
+            }
+        });
+
+        let event = CoapCallEvent {
+            url: url.clone(),
+            timeout: 5,
+            ..Default::default()
+        };
+        let response = event.call(&url, b"").unwrap();
+        assert!(response.is_success());
+        assert_eq!(response.status(), "2.05");
+        assert_eq!(response.payload, b"21.5");
+
+        let mut notifications = Vec::new();
+        event
+            .observe(&url, |message| {
+                notifications.push(message.metadata()["observe"].clone());
+                notifications.len() < 2
+            })
+            .unwrap();
+        assert_eq!(notifications, [json!(1), json!(2)]);
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn test_observe_unsupported() {
+        let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let url = format!(
+            "coap://127.0.0.1:{}/status",
+            server.local_addr().unwrap().port()
+        );
+        let handle = thread::spawn(move || {
+            let mut buf = [0; 1500];
+            let (size, client) = server.recv_from(&mut buf).unwrap();
+            let request = CoapMessage::decode(&buf[..size]).unwrap();
+            let response = CoapMessage {
+                kind: ACKNOWLEDGEMENT,
+                code: 0x45,
+                message_id: request.message_id,
+                token: request.token,
+                payload: b"on".to_vec(),
+                ..Default::default()
+            };
+            server.send_to(&response.encode(), client).unwrap();
+        });
+
+        let event = CoapCallEvent {
+            url: url.clone(),
+            observe: true,
+            timeout: 5,
+            ..Default::default()
+        };
+        let mut notifications = 0;
+        event
+            .observe(&url, |_| {
+                notifications += 1;
+                true
+            })
+            .unwrap();
+        assert_eq!(notifications, 1);
+        handle.join().unwrap();
+    }
+}
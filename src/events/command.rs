@@ -5,9 +5,12 @@ use std::{
 
 use anyhow::Result;
 use indexmap::IndexMap;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
 
-use super::data::{Data, DataType, Metadata};
+use super::{
+    data::{Data, DataType},
+    EventName,
+};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CommandEvent {
@@ -20,24 +23,71 @@ pub struct CommandEvent {
     pub vars: IndexMap<String, String>,
     #[serde(default)]
     pub data_type: DataType,
+    /// next event by exit code e.g. 1 or default for codes not listed
+    #[serde(default, deserialize_with = "deserialize_on_exit")]
+    pub on_exit: IndexMap<String, EventName>,
+}
+
+/// command result
+pub struct CommandOutput {
+    /// error if stdout can not be converted to the data type
+    pub stdout: Result<Data>,
+    pub stderr: String,
+    /// -1 if the command was terminated by a signal
+    pub exit_code: i32,
 }
 
 impl CommandEvent {
-    pub fn run(&self, data: &Data) -> Result<(Data, Metadata)> {
-        let child = Command::new(&self.command)
+    pub fn run(&self, data: &Data) -> Result<CommandOutput> {
+        let mut child = Command::new(&self.command)
             .args(&self.args)
             .envs(&self.vars)
             .stdin(Stdio::piped())
             .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
             .spawn()?;
 
-        child.stdin.expect("stdin").write_all(&data.as_bytes()?)?;
-        let reader = child.stdout.expect("stdout");
-        Ok((
-            Data::from_reader(reader, self.data_type)?,
-            Metadata::default(),
-        ))
+        // stdin is closed before waiting so that commands reading it can finish
+        child
+            .stdin
+            .take()
+            .expect("stdin")
+            .write_all(&data.as_bytes()?)?;
+        let output = child.wait_with_output()?;
+        Ok(CommandOutput {
+            stdout: Data::from_reader(output.stdout.as_slice(), self.data_type),
+            stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+            exit_code: output.status.code().unwrap_or(-1),
+        })
+    }
+
+    /// event to execute next for the exit code, exact codes take precedence
+    pub fn exit_event(&self, exit_code: i32) -> Option<&EventName> {
+        self.on_exit
+            .get(&exit_code.to_string())
+            .or_else(|| self.on_exit.get("default"))
+    }
+}
+
+fn deserialize_on_exit<'de, D>(deserializer: D) -> Result<IndexMap<String, EventName>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    // yaml keys such as 0 are numbers
+    #[derive(Deserialize, PartialEq, Eq, Hash)]
+    #[serde(untagged)]
+    enum ExitKey {
+        Code(i32),
+        Name(String),
     }
+    let map: IndexMap<ExitKey, EventName> = Deserialize::deserialize(deserializer)?;
+    Ok(map
+        .into_iter()
+        .map(|(key, event)| match key {
+            ExitKey::Code(c) => (c.to_string(), event),
+            ExitKey::Name(n) => (n.to_lowercase(), event),
+        })
+        .collect())
 }
 
 #[cfg(test)]
@@ -53,12 +103,14 @@ mod tests {
             vars: Default::default(),
             data_type: DataType::String,
             replace_args: Default::default(),
+            on_exit: Default::default(),
         };
 
         let input = Data::String("hello".to_string());
 
-        let (output, _) = event.run(&input).unwrap();
-        assert_eq!(output, Data::String("hello".to_string()));
+        let output = event.run(&input).unwrap();
+        assert_eq!(output.stdout.unwrap(), Data::String("hello".to_string()));
+        assert_eq!(output.exit_code, 0);
     }
 
     #[test]
@@ -69,12 +121,13 @@ mod tests {
             vars: Default::default(),
             data_type: DataType::Bytes,
             replace_args: Default::default(),
+            on_exit: Default::default(),
         };
 
         let input = Data::Empty;
 
-        let (output, _) = event.run(&input).unwrap();
-        assert_eq!(output, Data::Bytes(b"hello".to_vec()));
+        let output = event.run(&input).unwrap();
+        assert_eq!(output.stdout.unwrap(), Data::Bytes(b"hello".to_vec()));
     }
 
     #[test]
@@ -87,11 +140,68 @@ mod tests {
             },
             data_type: DataType::String,
             replace_args: Default::default(),
+            on_exit: Default::default(),
         };
 
         let input = Data::Empty;
 
-        let (output, _) = event.run(&input).unwrap();
-        assert_eq!(output, Data::String("defined\n".to_string()));
+        let output = event.run(&input).unwrap();
+        assert_eq!(
+            output.stdout.unwrap(),
+            Data::String("defined\n".to_string())
+        );
+    }
+
+    #[test]
+    fn test_failed_command() {
+        let event = CommandEvent {
+            command: "sh".to_string(),
+            args: ["-c".to_string(), "echo failed >&2; exit 3".to_string()].to_vec(),
+            vars: Default::default(),
+            data_type: DataType::String,
+            replace_args: Default::default(),
+            on_exit: Default::default(),
+        };
+
+        let output = event.run(&Data::Empty).unwrap();
+        assert_eq!(output.exit_code, 3);
+        assert_eq!(output.stderr, "failed\n");
+        assert_eq!(output.stdout.unwrap(), Data::String(String::new()));
+    }
+
+    #[test]
+    fn test_unparsed_output() {
+        let event = CommandEvent {
+            command: "sh".to_string(),
+            args: [
+                "-c".to_string(),
+                "echo invalid; echo failed >&2; exit 2".to_string(),
+            ]
+            .to_vec(),
+            vars: Default::default(),
+            data_type: DataType::Json,
+            replace_args: Default::default(),
+            on_exit: Default::default(),
+        };
+
+        let output = event.run(&Data::Empty).unwrap();
+        assert!(output.stdout.is_err());
+        assert_eq!(output.exit_code, 2);
+        assert_eq!(output.stderr, "failed\n");
+    }
+
+    #[test]
+    fn test_exit_event() {
+        let event: CommandEvent = serde_yaml::from_str(
+            "command: ping\non_exit:\n  0: present\n  2: retry\n  default: absent",
+        )
+        .unwrap();
+        assert_eq!(event.exit_event(0).unwrap(), "present");
+        assert_eq!(event.exit_event(2).unwrap(), "retry");
+        assert_eq!(event.exit_event(1).unwrap(), "absent");
+
+        let event: CommandEvent =
+            serde_yaml::from_str("command: ping\non_exit:\n  0: present").unwrap();
+        assert_eq!(event.exit_event(1), None);
     }
 }
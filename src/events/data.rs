@@ -1,8 +1,8 @@
 use core::str::from_utf8;
 use std::{borrow::Cow, io::Read};
 
-use serde::{de, Deserialize, Serialize};
-use serde_json::Value;
+use serde::{de, Deserialize, Serialize, Serializer};
+use serde_json::{Map, Value};
 
 use super::MergePolicy;
 
@@ -52,10 +52,29 @@ impl Data {
                 let value: Value = serde_json::from_reader(reader)?;
                 Data::Json(value)
             }
+            DataType::Csv(options) => Data::Json(options.parse(reader)?),
+            DataType::Yaml => {
+                let value: serde_yaml::Value = serde_yaml::from_reader(reader)?;
+                Data::Json(serde_json::to_value(value)?)
+            }
+            DataType::Toml => {
+                let mut s = String::default();
+                reader.read_to_string(&mut s)?;
+                Data::Json(toml_to_json(toml::from_str(&s)?))
+            }
+            DataType::Msgpack | DataType::Cbor => {
+                let mut buf = Vec::default();
+                reader.read_to_end(&mut buf)?;
+                let encoding = match data_type {
+                    DataType::Msgpack => PayloadEncoding::Msgpack,
+                    _ => PayloadEncoding::Cbor,
+                };
+                Data::Json(encoding.decode(&buf)?)
+            }
         })
     }
 
-    pub fn as_bytes(&self) -> anyhow::Result<Cow<[u8]>> {
+    pub fn as_bytes(&self) -> anyhow::Result<Cow<'_, [u8]>> {
         Ok(match self {
             Data::Json(j) => serde_json::to_vec(j)?.into(),
             Data::String(s) => s.as_bytes().into(),
@@ -92,6 +111,13 @@ impl Data {
         };
     }
 
+    /// json, string or bytes depending on the content
+    pub fn from_bytes(bytes: &[u8]) -> Self {
+        let mut data = Data::Empty;
+        data.try_merge_bytes(bytes);
+        data
+    }
+
     pub fn try_merge_bytes(&mut self, bytes: &[u8]) {
         let data: Data = if let Ok(v) = serde_json::from_slice(bytes) {
             Data::Json(v)
@@ -113,13 +139,197 @@ impl PartialEq<Value> for Data {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Default, Copy)]
+#[derive(Debug, Clone, Serialize, Default, Copy)]
 #[serde(rename_all = "lowercase")]
 pub enum DataType {
     #[default]
     String,
     Bytes,
     Json,
+    /// json array of objects by header or arrays of values without headers
+    Csv(CsvOptions),
+    Yaml,
+    Toml,
+    Msgpack,
+    Cbor,
+}
+
+impl<'de> Deserialize<'de> for DataType {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        #[derive(Debug, Deserialize)]
+        #[serde(rename_all = "lowercase")]
+        enum Name {
+            String,
+            Bytes,
+            Json,
+            Csv,
+            Yaml,
+            Toml,
+            Msgpack,
+            Cbor,
+        }
+        #[derive(Debug, Deserialize)]
+        #[serde(rename_all = "lowercase")]
+        enum Full {
+            Csv(CsvOptions),
+        }
+        #[derive(Debug, Deserialize)]
+        #[serde(untagged)]
+        enum OneOrFull {
+            One(Name),
+            Full(Full),
+        }
+        let s: OneOrFull = de::Deserialize::deserialize(deserializer)?;
+        Ok(match s {
+            OneOrFull::One(Name::String) => DataType::String,
+            OneOrFull::One(Name::Bytes) => DataType::Bytes,
+            OneOrFull::One(Name::Json) => DataType::Json,
+            OneOrFull::One(Name::Csv) => DataType::Csv(Default::default()),
+            OneOrFull::One(Name::Yaml) => DataType::Yaml,
+            OneOrFull::One(Name::Toml) => DataType::Toml,
+            OneOrFull::One(Name::Msgpack) => DataType::Msgpack,
+            OneOrFull::One(Name::Cbor) => DataType::Cbor,
+            OneOrFull::Full(Full::Csv(options)) => DataType::Csv(options),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Copy)]
+pub struct CsvOptions {
+    #[serde(default = "default_delimiter")]
+    pub delimiter: char,
+    /// first row contains the column names
+    #[serde(default = "default_headers")]
+    pub headers: bool,
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        Self {
+            delimiter: default_delimiter(),
+            headers: default_headers(),
+        }
+    }
+}
+
+impl CsvOptions {
+    pub fn parse(&self, reader: impl Read) -> anyhow::Result<Value> {
+        if !self.delimiter.is_ascii() {
+            anyhow::bail!(
+                "Csv delimiter {} must be an ascii character",
+                self.delimiter
+            );
+        }
+        let mut reader = csv::ReaderBuilder::new()
+            .delimiter(self.delimiter as u8)
+            .has_headers(self.headers)
+            .flexible(true)
+            .from_reader(reader);
+        let headers = if self.headers {
+            reader.headers()?.clone()
+        } else {
+            Default::default()
+        };
+        let mut rows = Vec::new();
+        for record in reader.records() {
+            let record = record?;
+            let row = if self.headers {
+                Value::Object(
+                    headers
+                        .iter()
+                        .zip(record.iter())
+                        .map(|(h, v)| (h.to_string(), Value::String(v.to_string())))
+                        .collect::<Map<_, _>>(),
+                )
+            } else {
+                record
+                    .iter()
+                    .map(|v| Value::String(v.to_string()))
+                    .collect()
+            };
+            rows.push(row);
+        }
+        Ok(Value::Array(rows))
+    }
+}
+
+/// binary payload encodings converted from and to json
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PayloadEncoding {
+    Msgpack,
+    Cbor,
+}
+
+impl PayloadEncoding {
+    /// json is encoded as is, strings and bytes as their encoded types
+    pub fn encode(&self, data: &Data) -> anyhow::Result<Vec<u8>> {
+        match data {
+            Data::Json(v) => self.encode_value(v),
+            Data::String(s) => self.encode_value(s),
+            Data::Bytes(b) => self.encode_value(&BinaryPayload(b)),
+            Data::Empty => self.encode_value(&Value::Null),
+        }
+    }
+
+    pub fn decode(&self, bytes: &[u8]) -> anyhow::Result<Value> {
+        Ok(match self {
+            PayloadEncoding::Msgpack => rmp_serde::from_slice(bytes)?,
+            PayloadEncoding::Cbor => ciborium::from_reader(bytes)?,
+        })
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            PayloadEncoding::Msgpack => "application/msgpack",
+            PayloadEncoding::Cbor => "application/cbor",
+        }
+    }
+
+    fn encode_value(&self, value: &impl Serialize) -> anyhow::Result<Vec<u8>> {
+        Ok(match self {
+            PayloadEncoding::Msgpack => rmp_serde::to_vec(value)?,
+            PayloadEncoding::Cbor => {
+                let mut buf = Vec::new();
+                ciborium::into_writer(value, &mut buf)?;
+                buf
+            }
+        })
+    }
+}
+
+struct BinaryPayload<'a>(&'a [u8]);
+
+impl Serialize for BinaryPayload<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(self.0)
+    }
+}
+
+/// datetimes are converted to strings
+fn toml_to_json(value: toml::Value) -> Value {
+    match value {
+        toml::Value::String(s) => Value::String(s),
+        toml::Value::Integer(i) => i.into(),
+        toml::Value::Float(f) => f.into(),
+        toml::Value::Boolean(b) => b.into(),
+        toml::Value::Datetime(d) => Value::String(d.to_string()),
+        toml::Value::Array(a) => a.into_iter().map(toml_to_json).collect(),
+        toml::Value::Table(t) => {
+            Value::Object(t.into_iter().map(|(k, v)| (k, toml_to_json(v))).collect())
+        }
+    }
+}
+
+fn default_delimiter() -> char {
+    ','
+}
+
+fn default_headers() -> bool {
+    true
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -240,6 +450,96 @@ mod tests {
         assert!(matches!(data, Data::Bytes(_)));
     }
 
+    #[test]
+    fn test_csv_data_type() {
+        let data_type: DataType = serde_yaml::from_str("csv").unwrap();
+        let data = Data::from_reader(
+            "time,kwh\n2024-10-01,12.5\n2024-10-02,9\n".as_bytes(),
+            data_type,
+        )
+        .unwrap();
+        assert_eq!(
+            data,
+            json!([
+                {"time": "2024-10-01", "kwh": "12.5"},
+                {"time": "2024-10-02", "kwh": "9"}
+            ])
+        );
+
+        let data_type: DataType =
+            serde_yaml::from_str("csv:\n  delimiter: ;\n  headers: false").unwrap();
+        let data = Data::from_reader("a;1\nb;2;extra\n".as_bytes(), data_type).unwrap();
+        assert_eq!(data, json!([["a", "1"], ["b", "2", "extra"]]));
+
+        let data_type: DataType = serde_yaml::from_str("bytes").unwrap();
+        assert!(matches!(data_type, DataType::Bytes));
+    }
+
+    #[test]
+    fn test_yaml_and_toml_data_types() {
+        let data_type: DataType = serde_yaml::from_str("yaml").unwrap();
+        let data = Data::from_reader(
+            "name: garage\nsensors:\n  - id: 1\n    enabled: true\n".as_bytes(),
+            data_type,
+        )
+        .unwrap();
+        assert_eq!(
+            data,
+            json!({"name": "garage", "sensors": [{"id": 1, "enabled": true}]})
+        );
+
+        let data_type: DataType = serde_yaml::from_str("toml").unwrap();
+        let data = Data::from_reader(
+            "version = \"1.2.0\"\nreleased = 2024-10-01T10:00:00Z\n[limits]\nmax = 2.5\n"
+                .as_bytes(),
+            data_type,
+        )
+        .unwrap();
+        assert_eq!(
+            data,
+            json!({"version": "1.2.0", "released": "2024-10-01T10:00:00Z", "limits": {"max": 2.5}})
+        );
+    }
+
+    #[test]
+    fn test_payload_encoding() {
+        let value = json!({"temperature": 21.5, "id": 3, "tags": ["garage"], "ok": true});
+        for encoding in [PayloadEncoding::Msgpack, PayloadEncoding::Cbor] {
+            let bytes = encoding.encode(&Data::Json(value.clone())).unwrap();
+            assert_eq!(encoding.decode(&bytes).unwrap(), value);
+            let data_type = match encoding {
+                PayloadEncoding::Msgpack => DataType::Msgpack,
+                PayloadEncoding::Cbor => DataType::Cbor,
+            };
+            assert_eq!(
+                Data::from_reader(bytes.as_slice(), data_type).unwrap(),
+                value
+            );
+            let bytes = encoding.encode(&"on".into()).unwrap();
+            assert_eq!(encoding.decode(&bytes).unwrap(), json!("on"));
+        }
+        // fixmap with a single fixstr key and positive fixint
+        assert_eq!(
+            PayloadEncoding::Msgpack
+                .decode(&[0x81, 0xa1, b'a', 0x01])
+                .unwrap(),
+            json!({"a": 1})
+        );
+        // map(1) text(1) "a" unsigned(1)
+        assert_eq!(
+            PayloadEncoding::Cbor
+                .decode(&[0xa1, 0x61, b'a', 0x01])
+                .unwrap(),
+            json!({"a": 1})
+        );
+        assert_eq!(
+            PayloadEncoding::Msgpack
+                .encode(&Data::Bytes(vec![1, 2]))
+                .unwrap(),
+            [0xc4, 0x02, 0x01, 0x02]
+        );
+    }
+
     #[test]
     fn test_skip_overwrite_if_empty() {
         let mut json_data: Data = json!({"a":"1"}).into();
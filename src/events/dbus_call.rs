@@ -0,0 +1,102 @@
+use anyhow::{anyhow, bail};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use zbus::zvariant::{ObjectPath, StructureBuilder, Value as DbusValue};
+
+use super::dbus_subscribe::{message_args, DbusBus};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DbusCallEvent {
+    #[serde(default)]
+    pub bus: DbusBus,
+    /// e.g. org.freedesktop.login1
+    pub destination: String,
+    /// e.g. /org/freedesktop/login1
+    pub path: String,
+    /// e.g. org.freedesktop.login1.Manager
+    pub interface: String,
+    /// e.g. Suspend
+    pub method: String,
+    /// templates converted to the types in the signature
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// basic types e.g. sb for a string and a boolean, all arguments are strings if not specified
+    pub signature: Option<String>,
+}
+
+impl DbusCallEvent {
+    /// call the method with rendered arguments returning the reply arguments
+    pub fn call(&self, args: &[String]) -> anyhow::Result<Value> {
+        let connection = self.bus.connect()?;
+        let reply = if args.is_empty() {
+            connection.call_method(
+                Some(self.destination.as_str()),
+                self.path.as_str(),
+                Some(self.interface.as_str()),
+                self.method.as_str(),
+                &(),
+            )?
+        } else {
+            let body = args
+                .iter()
+                .enumerate()
+                .try_fold(StructureBuilder::new(), |builder, (i, arg)| {
+                    let signature = self
+                        .signature
+                        .as_ref()
+                        .map(|s| {
+                            s.chars()
+                                .nth(i)
+                                .ok_or_else(|| anyhow!("Missing signature for argument {i}"))
+                        })
+                        .transpose()?
+                        .unwrap_or('s');
+                    Ok::<_, anyhow::Error>(builder.append_field(to_dbus(arg, signature)?))
+                })?
+                .build()?;
+            connection.call_method(
+                Some(self.destination.as_str()),
+                self.path.as_str(),
+                Some(self.interface.as_str()),
+                self.method.as_str(),
+                &body,
+            )?
+        };
+        Ok(message_args(&reply)?)
+    }
+}
+
+fn to_dbus(value: &str, signature: char) -> anyhow::Result<DbusValue<'static>> {
+    let value = value.trim();
+    Ok(match signature {
+        'y' => DbusValue::U8(value.parse()?),
+        'b' => DbusValue::Bool(value.parse()?),
+        'n' => DbusValue::I16(value.parse()?),
+        'q' => DbusValue::U16(value.parse()?),
+        'i' => DbusValue::I32(value.parse()?),
+        'u' => DbusValue::U32(value.parse()?),
+        'x' => DbusValue::I64(value.parse()?),
+        't' => DbusValue::U64(value.parse()?),
+        'd' => DbusValue::F64(value.parse()?),
+        's' => DbusValue::from(value.to_string()),
+        'o' => DbusValue::ObjectPath(ObjectPath::try_from(value.to_string())?),
+        s => bail!("Unsupported dbus argument type {s}"),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_dbus() {
+        assert_eq!(to_dbus("true", 'b').unwrap(), DbusValue::Bool(true));
+        assert_eq!(to_dbus(" 42 ", 'u').unwrap(), DbusValue::U32(42));
+        assert_eq!(
+            to_dbus("/org/freedesktop/login1", 'o').unwrap(),
+            DbusValue::ObjectPath(ObjectPath::try_from("/org/freedesktop/login1").unwrap())
+        );
+        assert!(to_dbus("yes", 'b').is_err());
+        assert!(to_dbus("a", 'v').is_err());
+    }
+}
@@ -0,0 +1,151 @@
+use core::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+use zbus::{
+    message::Type,
+    zvariant::{Structure, Value as DbusValue},
+    MatchRule, Message,
+};
+
+use serde_json::{json, Map, Value};
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum DbusBus {
+    #[default]
+    System,
+    Session,
+}
+
+impl DbusBus {
+    pub fn connect(&self) -> zbus::Result<zbus::blocking::Connection> {
+        match self {
+            DbusBus::System => zbus::blocking::Connection::system(),
+            DbusBus::Session => zbus::blocking::Connection::session(),
+        }
+    }
+}
+
+impl Display for DbusBus {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            DbusBus::System => write!(f, "system"),
+            DbusBus::Session => write!(f, "session"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct DbusSubscribeEvent {
+    #[serde(default)]
+    pub bus: DbusBus,
+    /// e.g. org.freedesktop.login1
+    pub sender: Option<String>,
+    /// e.g. /org/freedesktop/login1
+    pub path: Option<String>,
+    /// e.g. org.freedesktop.login1.Manager
+    pub interface: Option<String>,
+    /// signal name e.g. PrepareForSleep
+    pub member: Option<String>,
+}
+
+impl DbusSubscribeEvent {
+    pub fn match_rule(&self) -> zbus::Result<MatchRule<'_>> {
+        let mut builder = MatchRule::builder().msg_type(Type::Signal);
+        if let Some(sender) = &self.sender {
+            builder = builder.sender(sender.as_str())?;
+        }
+        if let Some(path) = &self.path {
+            builder = builder.path(path.as_str())?;
+        }
+        if let Some(interface) = &self.interface {
+            builder = builder.interface(interface.as_str())?;
+        }
+        if let Some(member) = &self.member {
+            builder = builder.member(member.as_str())?;
+        }
+        Ok(builder.build())
+    }
+}
+
+/// message arguments as a json array
+pub fn message_args(message: &Message) -> zbus::Result<Value> {
+    let body = message.body();
+    if body.signature().to_string().is_empty() {
+        return Ok(Value::Array(Vec::new()));
+    }
+    let args: Structure = body.deserialize()?;
+    Ok(Value::Array(args.fields().iter().map(to_json).collect()))
+}
+
+pub fn to_json(value: &DbusValue) -> Value {
+    match value {
+        DbusValue::U8(v) => json!(v),
+        DbusValue::Bool(v) => json!(v),
+        DbusValue::I16(v) => json!(v),
+        DbusValue::U16(v) => json!(v),
+        DbusValue::I32(v) => json!(v),
+        DbusValue::U32(v) => json!(v),
+        DbusValue::I64(v) => json!(v),
+        DbusValue::U64(v) => json!(v),
+        DbusValue::F64(v) => json!(v),
+        DbusValue::Str(v) => json!(v.as_str()),
+        DbusValue::Signature(v) => json!(v.to_string()),
+        DbusValue::ObjectPath(v) => json!(v.as_str()),
+        DbusValue::Value(v) => to_json(v),
+        DbusValue::Array(v) => Value::Array(v.inner().iter().map(to_json).collect()),
+        DbusValue::Dict(v) => Value::Object(
+            v.iter()
+                .map(|(k, v)| {
+                    let key = match to_json(k) {
+                        Value::String(s) => s,
+                        k => k.to_string(),
+                    };
+                    (key, to_json(v))
+                })
+                .collect::<Map<String, Value>>(),
+        ),
+        DbusValue::Structure(v) => Value::Array(v.fields().iter().map(to_json).collect()),
+        #[allow(unreachable_patterns)]
+        _ => Value::Null,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    #[test]
+    fn test_message_args() {
+        let message = Message::signal(
+            "/org/freedesktop/UPower/devices/battery_BAT0",
+            "org.freedesktop.DBus.Properties",
+            "PropertiesChanged",
+        )
+        .unwrap()
+        .build(&(
+            "org.freedesktop.UPower.Device",
+            HashMap::from([("Percentage", DbusValue::F64(42.0))]),
+            Vec::<String>::new(),
+        ))
+        .unwrap();
+        assert_eq!(
+            message_args(&message).unwrap(),
+            json!(["org.freedesktop.UPower.Device", {"Percentage": 42.0}, []])
+        );
+
+        let event = DbusSubscribeEvent {
+            interface: Some("org.freedesktop.DBus.Properties".to_string()),
+            member: Some("PropertiesChanged".to_string()),
+            ..Default::default()
+        };
+        assert!(event.match_rule().unwrap().matches(&message).unwrap());
+        let event = DbusSubscribeEvent {
+            member: Some("PrepareForSleep".to_string()),
+            ..Default::default()
+        };
+        assert!(!event.match_rule().unwrap().matches(&message).unwrap());
+    }
+}
@@ -0,0 +1,61 @@
+use core::fmt::Display;
+use std::path::PathBuf;
+
+use anyhow::bail;
+use serde::{Deserialize, Serialize};
+
+use crate::docker::{error_message, request};
+
+use super::docker_events::default_socket;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DockerAction {
+    Start,
+    Stop,
+    Restart,
+    Kill,
+    Pause,
+    Unpause,
+}
+
+impl Display for DockerAction {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let s = match self {
+            DockerAction::Start => "start",
+            DockerAction::Stop => "stop",
+            DockerAction::Restart => "restart",
+            DockerAction::Kill => "kill",
+            DockerAction::Pause => "pause",
+            DockerAction::Unpause => "unpause",
+        };
+        write!(f, "{s}")
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DockerControlEvent {
+    /// docker engine api socket
+    #[serde(default = "default_socket")]
+    pub socket: PathBuf,
+    /// container name or id template
+    pub container: String,
+    pub action: DockerAction,
+}
+
+impl DockerControlEvent {
+    /// returns false if the container was already in the requested state
+    pub fn run(&self, container: &str) -> anyhow::Result<bool> {
+        let path = format!(
+            "/containers/{}/{}",
+            form_urlencoded::byte_serialize(container.as_bytes()).collect::<String>(),
+            self.action
+        );
+        let (status, mut reader) = request(&self.socket, "POST", &path)?;
+        match status {
+            200..=299 => Ok(true),
+            304 => Ok(false),
+            _ => bail!("Status {status} {}", error_message(&mut reader)),
+        }
+    }
+}
@@ -0,0 +1,142 @@
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DockerEventsEvent {
+    /// docker engine api socket
+    #[serde(default = "default_socket")]
+    pub socket: PathBuf,
+    /// container name, any container matches if not specified
+    pub container: Option<String>,
+    /// e.g. start, stop, die or health_status, any action matches if not specified
+    pub action: Option<String>,
+    /// e.g. healthy or unhealthy for health_status actions
+    pub health: Option<String>,
+}
+
+impl Default for DockerEventsEvent {
+    fn default() -> Self {
+        Self {
+            socket: default_socket(),
+            container: None,
+            action: None,
+            health: None,
+        }
+    }
+}
+
+impl DockerEventsEvent {
+    pub fn matches(&self, message: &DockerMessage) -> bool {
+        self.container
+            .as_ref()
+            .map(|c| c == &message.container)
+            .unwrap_or(true)
+            && self
+                .action
+                .as_ref()
+                .map(|a| a == &message.action)
+                .unwrap_or(true)
+            && self
+                .health
+                .as_ref()
+                .map(|h| message.health.as_ref() == Some(h))
+                .unwrap_or(true)
+    }
+}
+
+/// container event received from the docker engine
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct DockerMessage {
+    pub id: String,
+    pub container: String,
+    pub action: String,
+    pub health: Option<String>,
+    pub attributes: Map<String, Value>,
+}
+
+impl DockerMessage {
+    /// only container events are parsed
+    pub fn parse(value: &Value) -> Option<Self> {
+        if value["Type"] != "container" {
+            return None;
+        }
+        // health changes are reported as "health_status: healthy"
+        let (action, health) = match value["Action"].as_str()?.split_once(": ") {
+            Some((action, health)) => (action.to_string(), Some(health.to_string())),
+            None => (value["Action"].as_str()?.to_string(), None),
+        };
+        let attributes = value["Actor"]["Attributes"]
+            .as_object()
+            .cloned()
+            .unwrap_or_default();
+        Some(Self {
+            id: value["Actor"]["ID"]
+                .as_str()
+                .unwrap_or_default()
+                .to_string(),
+            container: attributes
+                .get("name")
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_string(),
+            action,
+            health,
+            attributes,
+        })
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "id": self.id,
+            "container": self.container,
+            "action": self.action,
+            "health": self.health,
+            "image": self.attributes.get("image"),
+            "exit_code": self.attributes.get("exitCode"),
+            "attributes": self.attributes,
+        })
+    }
+}
+
+pub fn default_socket() -> PathBuf {
+    "/var/run/docker.sock".into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let message = DockerMessage::parse(&json!({
+            "Type": "container",
+            "Action": "health_status: unhealthy",
+            "Actor": {"ID": "abc", "Attributes": {"name": "homeassistant", "image": "ha:stable"}},
+        }))
+        .unwrap();
+        assert_eq!(message.container, "homeassistant");
+        assert_eq!(message.action, "health_status");
+        assert_eq!(message.health.as_deref(), Some("unhealthy"));
+        assert_eq!(
+            message.to_json()["image"],
+            Value::String("ha:stable".to_string())
+        );
+
+        let event = DockerEventsEvent {
+            container: Some("homeassistant".to_string()),
+            action: Some("health_status".to_string()),
+            health: Some("unhealthy".to_string()),
+            ..Default::default()
+        };
+        assert!(event.matches(&message));
+        let event = DockerEventsEvent {
+            action: Some("die".to_string()),
+            ..Default::default()
+        };
+        assert!(!event.matches(&message));
+
+        assert!(DockerMessage::parse(&json!({"Type": "network", "Action": "connect"})).is_none());
+    }
+}
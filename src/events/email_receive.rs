@@ -0,0 +1,149 @@
+use mail_parser::{Address, MessageParser};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+
+use crate::config::PoolId;
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct EmailReceiveEvent {
+    /// messages from any mailbox are matched if empty
+    #[serde(default)]
+    pub pool_id: PoolId,
+    /// case insensitive part of the sender address or name e.g. @bank.com
+    pub from: Option<String>,
+    /// case insensitive part of the subject
+    pub subject: Option<String>,
+}
+
+impl EmailReceiveEvent {
+    pub fn matches(&self, pool_id: &str, message: &EmailMessage) -> bool {
+        (self.pool_id.is_empty() || self.pool_id == pool_id)
+            && self
+                .from
+                .as_ref()
+                .map(|f| {
+                    let f = f.to_lowercase();
+                    message.from.to_lowercase().contains(&f)
+                        || message
+                            .from_name
+                            .as_ref()
+                            .map(|n| n.to_lowercase().contains(&f))
+                            .unwrap_or(false)
+                })
+                .unwrap_or(true)
+            && self
+                .subject
+                .as_ref()
+                .map(|s| message.subject.to_lowercase().contains(&s.to_lowercase()))
+                .unwrap_or(true)
+    }
+}
+
+/// message fetched from an imap mailbox
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct EmailMessage {
+    pub uid: u32,
+    pub from: String,
+    pub from_name: Option<String>,
+    pub to: Vec<String>,
+    pub subject: String,
+    pub date: Option<String>,
+    /// text body, html is used if the message has no text part
+    pub body: String,
+    /// first value of each header
+    pub headers: Map<String, Value>,
+}
+
+impl EmailMessage {
+    pub fn parse(uid: u32, raw: &[u8]) -> Option<Self> {
+        let message = MessageParser::default().parse(raw)?;
+        let sender = message.from().and_then(Address::first);
+        let mut headers = Map::new();
+        for (name, value) in message.headers_raw() {
+            headers
+                .entry(name.to_lowercase())
+                .or_insert_with(|| Value::String(value.trim().to_string()));
+        }
+        Some(Self {
+            uid,
+            from: sender
+                .and_then(|a| a.address())
+                .unwrap_or_default()
+                .to_string(),
+            from_name: sender.and_then(|a| a.name()).map(str::to_string),
+            to: message
+                .to()
+                .map(|to| {
+                    to.iter()
+                        .filter_map(|a| a.address())
+                        .map(str::to_string)
+                        .collect()
+                })
+                .unwrap_or_default(),
+            subject: message.subject().unwrap_or_default().to_string(),
+            date: message.date().map(|d| d.to_rfc3339()),
+            body: message
+                .body_text(0)
+                .or_else(|| message.body_html(0))
+                .unwrap_or_default()
+                .trim_end()
+                .to_string(),
+            headers,
+        })
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "uid": self.uid,
+            "from": self.from,
+            "from_name": self.from_name,
+            "to": self.to,
+            "subject": self.subject,
+            "date": self.date,
+            "body": self.body,
+            "headers": self.headers,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let message = EmailMessage::parse(
+            7,
+            b"From: Alarm Panel <panel@alarm.example.com>\r\n\
+To: home@example.com, backup@example.com\r\n\
+Subject: Zone 3 triggered\r\n\
+Date: Sat, 12 Oct 2024 21:15:00 +0000\r\n\
+\r\n\
+Motion detected in the garage\r\n",
+        )
+        .unwrap();
+        assert_eq!(message.from, "panel@alarm.example.com");
+        assert_eq!(message.from_name.as_deref(), Some("Alarm Panel"));
+        assert_eq!(message.to, ["home@example.com", "backup@example.com"]);
+        assert_eq!(message.date.as_deref(), Some("2024-10-12T21:15:00Z"));
+        assert_eq!(message.body, "Motion detected in the garage");
+        assert_eq!(message.to_json()["headers"]["subject"], "Zone 3 triggered");
+
+        let event = EmailReceiveEvent {
+            from: Some("ALARM panel".to_string()),
+            subject: Some("triggered".to_string()),
+            ..Default::default()
+        };
+        assert!(event.matches("default", &message));
+        let event = EmailReceiveEvent {
+            pool_id: "work".to_string(),
+            ..Default::default()
+        };
+        assert!(!event.matches("default", &message));
+        let event = EmailReceiveEvent {
+            from: Some("@bank.com".to_string()),
+            ..Default::default()
+        };
+        assert!(!event.matches("default", &message));
+    }
+}
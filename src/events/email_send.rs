@@ -0,0 +1,19 @@
+use serde::{Deserialize, Serialize};
+
+use crate::config::PoolId;
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct EmailSendEvent {
+    /// the first smtp server is used if empty
+    #[serde(default)]
+    pub pool_id: PoolId,
+    /// recipient templates e.g. Me <me@example.com>
+    pub to: Vec<String>,
+    /// template
+    pub subject: String,
+    /// template, event data is sent if not specified
+    pub body: Option<String>,
+    /// send the body as text/html
+    #[serde(default)]
+    pub html: bool,
+}
@@ -0,0 +1,21 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum GpioOutput {
+    High,
+    Low,
+    Toggle,
+    /// software pwm e.g. for dimming leds
+    Pwm {
+        frequency: f64,
+        duty_cycle: f64,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GpioSetEvent {
+    /// bcm pin number
+    pub pin: u8,
+    pub output: GpioOutput,
+}
@@ -0,0 +1,60 @@
+use core::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum GpioEdge {
+    Rising,
+    Falling,
+}
+
+impl Display for GpioEdge {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            GpioEdge::Rising => write!(f, "rising"),
+            GpioEdge::Falling => write!(f, "falling"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum GpioPull {
+    Up,
+    Down,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GpioWatchEvent {
+    /// bcm pin number
+    pub pin: u8,
+    /// any edge matches if not specified
+    pub edge: Option<GpioEdge>,
+    /// internal pull resistor
+    pub pull: Option<GpioPull>,
+    /// milliseconds to ignore changes after an edge
+    #[serde(default = "default_debounce")]
+    pub debounce: u64,
+}
+
+impl Default for GpioWatchEvent {
+    fn default() -> Self {
+        Self {
+            pin: Default::default(),
+            edge: None,
+            pull: None,
+            debounce: default_debounce(),
+        }
+    }
+}
+
+impl GpioWatchEvent {
+    pub fn matches(&self, pin: u8, edge: GpioEdge) -> bool {
+        self.pin == pin && self.edge.map(|e| e == edge).unwrap_or(true)
+    }
+}
+
+fn default_debounce() -> u64 {
+    50
+}
@@ -0,0 +1,259 @@
+use std::net::UdpSocket;
+
+use anyhow::{anyhow, bail};
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::config::PoolId;
+
+use super::{
+    api_call::{ApiCallEvent, ApiCallRetry, RequestContent, RequestMethod},
+    data::Data,
+};
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(tag = "target", rename_all = "lowercase")]
+pub enum InfluxTarget {
+    /// influxdb 1.x http api
+    V1 {
+        url: String,
+        database: String,
+        user: Option<String>,
+        pass: Option<String>,
+    },
+    /// influxdb 2.x http api
+    V2 {
+        url: String,
+        org: String,
+        bucket: String,
+        token: String,
+    },
+    /// line protocol datagrams e.g. influxdb udp listener or telegraf socket_listener
+    Udp { address: String },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InfluxWriteEvent {
+    #[serde(flatten)]
+    pub target: InfluxTarget,
+    /// template
+    pub measurement: String,
+    /// tag values are templates
+    #[serde(default)]
+    pub tags: IndexMap<String, String>,
+    /// field values are json pointers into event data e.g. /temperature,
+    /// numbers, booleans and strings of the data object are written if not specified
+    #[serde(default)]
+    pub fields: IndexMap<String, String>,
+    /// api client used for http targets
+    #[serde(default)]
+    pub pool_id: PoolId,
+    #[serde(default)]
+    pub retry: ApiCallRetry,
+}
+
+impl InfluxWriteEvent {
+    /// line protocol without a timestamp, the server time is used
+    pub fn line(
+        &self,
+        measurement: &str,
+        tags: &IndexMap<String, String>,
+        data: &Data,
+    ) -> anyhow::Result<String> {
+        // string data e.g. serial readings is parsed as json
+        let data = match data {
+            Data::Json(v) => v.clone(),
+            data => {
+                let s = String::from_utf8_lossy(&data.as_bytes()?)
+                    .trim()
+                    .to_string();
+                serde_json::from_str(&s).unwrap_or(Value::String(s))
+            }
+        };
+        let data = &data;
+        let fields: Vec<(&str, &Value)> = if self.fields.is_empty() {
+            match data {
+                Value::Object(values) => values
+                    .iter()
+                    .filter(|(_, v)| !v.is_null() && !v.is_array() && !v.is_object())
+                    .map(|(k, v)| (k.as_str(), v))
+                    .collect(),
+                Value::Number(_) | Value::Bool(_) | Value::String(_) => vec![("value", data)],
+                _ => Vec::new(),
+            }
+        } else {
+            self.fields
+                .iter()
+                .filter_map(|(k, pointer)| Some((k.as_str(), data.pointer(pointer)?)))
+                .filter(|(_, v)| !v.is_null())
+                .collect()
+        };
+        if fields.is_empty() {
+            bail!("No fields found in data for measurement {measurement}");
+        }
+
+        let mut line = escape(measurement, &[',', ' ']);
+        for (key, value) in tags.iter().filter(|(_, v)| !v.is_empty()) {
+            line.push(',');
+            line.push_str(&escape(key, &[',', '=', ' ']));
+            line.push('=');
+            line.push_str(&escape(value, &[',', '=', ' ']));
+        }
+        for (index, (key, value)) in fields.into_iter().enumerate() {
+            line.push(if index == 0 { ' ' } else { ',' });
+            line.push_str(&escape(key, &[',', '=', ' ']));
+            line.push('=');
+            line.push_str(&field_value(value));
+        }
+        Ok(line)
+    }
+
+    /// http write request for v1 and v2 targets
+    pub fn api_call(&self, line: String) -> Option<ApiCallEvent> {
+        let (url, query, headers) = match &self.target {
+            InfluxTarget::V1 {
+                url,
+                database,
+                user,
+                pass,
+            } => {
+                let mut query: IndexMap<String, String> =
+                    [("db".to_string(), database.clone())].into();
+                if let (Some(user), Some(pass)) = (user, pass) {
+                    query.insert("u".to_string(), user.clone());
+                    query.insert("p".to_string(), pass.clone());
+                }
+                (
+                    format!("{}/write", url.trim_end_matches('/')),
+                    query,
+                    Default::default(),
+                )
+            }
+            InfluxTarget::V2 {
+                url,
+                org,
+                bucket,
+                token,
+            } => (
+                format!("{}/api/v2/write", url.trim_end_matches('/')),
+                [
+                    ("org".to_string(), org.clone()),
+                    ("bucket".to_string(), bucket.clone()),
+                ]
+                .into(),
+                [("Authorization".to_string(), format!("Token {token}"))].into(),
+            ),
+            InfluxTarget::Udp { .. } => return None,
+        };
+        Some(ApiCallEvent {
+            url,
+            query,
+            headers,
+            method: RequestMethod::Post,
+            request_content: RequestContent::Text,
+            body: Some(line),
+            pool_id: self.pool_id.clone(),
+            retry: Some(self.retry.clone()),
+            ..Default::default()
+        })
+    }
+
+    pub fn send_udp(&self, line: &str) -> anyhow::Result<()> {
+        let InfluxTarget::Udp { address } = &self.target else {
+            return Err(anyhow!("Influx target is not udp"));
+        };
+        let socket = UdpSocket::bind(("0.0.0.0", 0))?;
+        socket.send_to(line.as_bytes(), address)?;
+        Ok(())
+    }
+}
+
+fn escape(value: &str, characters: &[char]) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if c == '\\' || characters.contains(&c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+fn field_value(value: &Value) -> String {
+    match value {
+        Value::Number(n) if n.is_i64() || n.is_u64() => format!("{n}i"),
+        Value::Number(n) => n.to_string(),
+        Value::Bool(b) => b.to_string(),
+        Value::String(s) => format!("\"{}\"", escape(s, &['"'])),
+        v => format!("\"{}\"", escape(&v.to_string(), &['"'])),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_line() {
+        let event: InfluxWriteEvent = serde_yaml::from_str(
+            r#"
+target: v2
+url: http://influx.local:8086/
+org: home
+bucket: sensors
+token: secret
+measurement: climate
+tags:
+  room: "{{data.room}}"
+fields:
+  temperature: /temperature
+  battery: /battery/level
+  missing: /missing
+"#,
+        )
+        .unwrap();
+        let tags = [("room".to_string(), "living room".to_string())].into();
+        let data =
+            json!({"room": "living room", "temperature": 21.5, "battery": {"level": 87}}).into();
+        let line = event.line("climate", &tags, &data).unwrap();
+        assert_eq!(
+            line,
+            r"climate,room=living\ room temperature=21.5,battery=87i"
+        );
+        let api_call = event.api_call(line).unwrap();
+        assert_eq!(api_call.url, "http://influx.local:8086/api/v2/write");
+        assert_eq!(api_call.query["bucket"], "sensors");
+        assert_eq!(api_call.headers["Authorization"], "Token secret");
+
+        let event = InfluxWriteEvent {
+            target: InfluxTarget::Udp {
+                address: "127.0.0.1:8089".to_string(),
+            },
+            fields: Default::default(),
+            ..event
+        };
+        assert!(event.api_call(String::new()).is_none());
+        assert_eq!(
+            event
+                .line(
+                    "door",
+                    &Default::default(),
+                    &json!({"open": true, "name": "front \"main\"", "extra": [1]}).into()
+                )
+                .unwrap(),
+            r#"door name="front \"main\"",open=true"#
+        );
+        assert_eq!(
+            event
+                .line("power", &Default::default(), &"230\n".into())
+                .unwrap(),
+            "power value=230i"
+        );
+        assert!(event
+            .line("power", &Default::default(), &json!({}).into())
+            .is_err());
+    }
+}
@@ -0,0 +1,194 @@
+use regex::Regex;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::{Map, Value};
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum JournalPriority {
+    Emerg,
+    Alert,
+    Crit,
+    Err,
+    Warning,
+    Notice,
+    Info,
+    Debug,
+}
+
+impl JournalPriority {
+    /// name accepted by journalctl --priority
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Emerg => "emerg",
+            Self::Alert => "alert",
+            Self::Crit => "crit",
+            Self::Err => "err",
+            Self::Warning => "warning",
+            Self::Notice => "notice",
+            Self::Info => "info",
+            Self::Debug => "debug",
+        }
+    }
+
+    fn from_level(level: u8) -> Option<Self> {
+        Some(match level {
+            0 => Self::Emerg,
+            1 => Self::Alert,
+            2 => Self::Crit,
+            3 => Self::Err,
+            4 => Self::Warning,
+            5 => Self::Notice,
+            6 => Self::Info,
+            7 => Self::Debug,
+            _ => return None,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct JournalWatchEvent {
+    /// systemd unit e.g. ssh.service
+    pub unit: Option<String>,
+    /// syslog identifier e.g. sshd
+    pub identifier: Option<String>,
+    /// entries with the same or higher priority match e.g. warning
+    pub priority: Option<JournalPriority>,
+    /// regex matching the message
+    #[serde(
+        default,
+        deserialize_with = "deserialize_regex",
+        serialize_with = "serialize_regex"
+    )]
+    pub message: Option<Regex>,
+}
+
+impl JournalWatchEvent {
+    pub fn matches(&self, entry: &JournalEntry) -> bool {
+        self.unit
+            .as_ref()
+            .map(|u| entry.field("_SYSTEMD_UNIT") == Some(u) || entry.field("UNIT") == Some(u))
+            .unwrap_or(true)
+            && self
+                .identifier
+                .as_ref()
+                .map(|i| entry.field("SYSLOG_IDENTIFIER") == Some(i))
+                .unwrap_or(true)
+            && self
+                .priority
+                .map(|p| entry.priority().is_some_and(|e| e <= p))
+                .unwrap_or(true)
+            && self
+                .message
+                .as_ref()
+                .map(|m| m.is_match(&entry.message()))
+                .unwrap_or(true)
+    }
+}
+
+fn deserialize_regex<'de, D>(deserializer: D) -> Result<Option<Regex>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Option::<String>::deserialize(deserializer)?
+        .map(|m| {
+            Regex::new(&m)
+                .map_err(|e| de::Error::custom(format!("invalid journal message regex {m} {e}")))
+        })
+        .transpose()
+}
+
+fn serialize_regex<S>(regex: &Option<Regex>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    regex.as_ref().map(Regex::as_str).serialize(serializer)
+}
+
+/// entry as written by journalctl --output=json
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct JournalEntry(pub Map<String, Value>);
+
+impl JournalEntry {
+    pub fn field(&self, name: &str) -> Option<&str> {
+        self.0.get(name).and_then(Value::as_str)
+    }
+
+    /// binary messages are provided as byte arrays
+    pub fn message(&self) -> String {
+        match self.0.get("MESSAGE") {
+            Some(Value::String(s)) => s.clone(),
+            Some(Value::Array(bytes)) => String::from_utf8_lossy(
+                &bytes
+                    .iter()
+                    .filter_map(|b| b.as_u64().map(|b| b as u8))
+                    .collect::<Vec<u8>>(),
+            )
+            .into_owned(),
+            _ => String::new(),
+        }
+    }
+
+    pub fn priority(&self) -> Option<JournalPriority> {
+        self.field("PRIORITY")
+            .and_then(|p| p.parse().ok())
+            .and_then(JournalPriority::from_level)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_matches() {
+        let Value::Object(fields) = json!({
+            "MESSAGE": "Failed password for root from 192.168.1.10 port 52144 ssh2",
+            "PRIORITY": "6",
+            "SYSLOG_IDENTIFIER": "sshd",
+            "_SYSTEMD_UNIT": "ssh.service",
+        }) else {
+            unreachable!()
+        };
+        let entry = JournalEntry(fields);
+
+        let event = JournalWatchEvent {
+            unit: Some("ssh.service".to_string()),
+            message: Some(Regex::new("^Failed password for (\\S+)").unwrap()),
+            ..Default::default()
+        };
+        assert!(event.matches(&entry));
+        let event = JournalWatchEvent {
+            identifier: Some("sshd".to_string()),
+            priority: Some(JournalPriority::Info),
+            ..Default::default()
+        };
+        assert!(event.matches(&entry));
+        let event = JournalWatchEvent {
+            priority: Some(JournalPriority::Warning),
+            ..Default::default()
+        };
+        assert!(!event.matches(&entry));
+        let event = JournalWatchEvent {
+            unit: Some("cron.service".to_string()),
+            ..Default::default()
+        };
+        assert!(!event.matches(&entry));
+    }
+
+    #[test]
+    fn test_binary_message() {
+        let Value::Object(fields) = json!({"MESSAGE": [104, 105]}) else {
+            unreachable!()
+        };
+        assert_eq!(JournalEntry(fields).message(), "hi");
+    }
+
+    #[test]
+    fn test_deserialize_message() {
+        let event: JournalWatchEvent = serde_yaml::from_str("message: ^Failed").unwrap();
+        assert_eq!(event.message.unwrap().as_str(), "^Failed");
+        assert!(serde_yaml::from_str::<JournalWatchEvent>("message: Failed (").is_err());
+    }
+}
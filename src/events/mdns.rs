@@ -0,0 +1,67 @@
+use core::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct MdnsEvent {
+    /// service type e.g. _googlecast._tcp
+    pub service: String,
+    /// any change matches if not specified
+    pub when: Option<MdnsState>,
+}
+
+impl MdnsEvent {
+    /// fully qualified service type e.g. _googlecast._tcp.local.
+    pub fn service_type(&self) -> String {
+        let service = self.service.trim_end_matches('.');
+        if service.ends_with(".local") {
+            format!("{service}.")
+        } else {
+            format!("{service}.local.")
+        }
+    }
+
+    pub fn matches(&self, service_type: &str, state: MdnsState) -> bool {
+        self.service_type() == service_type && self.when.is_none_or(|when| when == state)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum MdnsState {
+    Appeared,
+    Disappeared,
+}
+
+impl Display for MdnsState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MdnsState::Appeared => write!(f, "appeared"),
+            MdnsState::Disappeared => write!(f, "disappeared"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mdns_matches() {
+        let event = MdnsEvent {
+            service: "_googlecast._tcp".to_string(),
+            when: Some(MdnsState::Appeared),
+        };
+        assert_eq!(event.service_type(), "_googlecast._tcp.local.");
+        assert!(event.matches("_googlecast._tcp.local.", MdnsState::Appeared));
+        assert!(!event.matches("_googlecast._tcp.local.", MdnsState::Disappeared));
+        assert!(!event.matches("_hap._tcp.local.", MdnsState::Appeared));
+
+        let event = MdnsEvent {
+            service: "_hap._tcp.local.".to_string(),
+            when: None,
+        };
+        assert_eq!(event.service_type(), "_hap._tcp.local.");
+        assert!(event.matches("_hap._tcp.local.", MdnsState::Disappeared));
+    }
+}
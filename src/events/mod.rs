@@ -1,32 +1,138 @@
+#[cfg(feature = "amqp")]
+pub mod amqp_ack;
+#[cfg(feature = "amqp")]
+pub mod amqp_consume;
+#[cfg(feature = "amqp")]
+pub mod amqp_publish;
 pub mod api_call;
 pub mod api_listen;
+#[cfg(feature = "ble")]
+pub mod ble;
+pub mod coap_call;
 pub mod command;
 pub mod data;
+#[cfg(feature = "dbus")]
+pub mod dbus_call;
+#[cfg(feature = "dbus")]
+pub mod dbus_subscribe;
+#[cfg(unix)]
+pub mod docker_control;
+#[cfg(unix)]
+pub mod docker_events;
+#[cfg(feature = "email")]
+pub mod email_receive;
+#[cfg(feature = "email")]
+pub mod email_send;
+#[cfg(unix)]
+pub mod fifo_read;
 pub mod file_changed;
 pub mod file_read;
 pub mod file_watch;
 pub mod file_write;
+#[cfg(feature = "gpio")]
+pub mod gpio_set;
+#[cfg(feature = "gpio")]
+pub mod gpio_watch;
+pub mod influx_write;
+#[cfg(target_os = "linux")]
+pub mod journal_watch;
+#[cfg(feature = "mdns")]
+pub mod mdns;
+pub mod modbus_read;
+pub mod modbus_write;
 pub mod mqtt_publish;
 pub mod mqtt_subscribe;
 pub mod mqtt_unsubscribe;
+pub mod nats_publish;
+pub mod nats_request;
+pub mod nats_subscribe;
+pub mod notify;
 pub mod period;
+pub mod ping;
+pub mod port_check;
 pub mod print;
+pub mod protobuf;
+pub mod push;
+#[cfg(feature = "redis")]
+pub mod redis_command;
+#[cfg(feature = "redis")]
+pub mod redis_publish;
+#[cfg(feature = "redis")]
+pub mod redis_subscribe;
 #[cfg(target_os = "linux")]
 pub mod scan_code_read;
+#[cfg(all(target_os = "linux", feature = "sensors"))]
+pub mod sensor_read;
+#[cfg(feature = "serial")]
+pub mod serial_read;
+#[cfg(feature = "serial")]
+pub mod serial_write;
+#[cfg(feature = "ssh")]
+pub mod sftp_transfer;
+pub mod sms_send;
+#[cfg(feature = "snmp")]
+pub mod snmp_get;
+pub mod speak;
+#[cfg(feature = "sql")]
+pub mod sql_query;
+pub mod ssdp;
+#[cfg(feature = "ssh")]
+pub mod ssh_execute;
+pub mod stdin_read;
+pub mod syslog_listen;
+pub mod tcp_listen;
+pub mod tcp_send;
 pub mod time;
+pub mod udp_listen;
+pub mod udp_send;
+#[cfg(unix)]
+pub mod unix_listen;
+#[cfg(unix)]
+pub mod unix_send;
+pub mod wake_on_lan;
+pub mod ws_listen;
+pub mod ws_send;
+#[cfg(feature = "zmq")]
+pub mod zmq_publish;
+#[cfg(feature = "zmq")]
+pub mod zmq_request;
+#[cfg(feature = "zmq")]
+pub mod zmq_subscribe;
 
+use coap_call::CoapCallEvent;
 use command::CommandEvent;
 use core::ops::Deref;
 use data::{Data, Metadata};
 use indexmap::{IndexMap, IndexSet};
+use influx_write::InfluxWriteEvent;
+use modbus_read::ModbusReadEvent;
+use modbus_write::ModbusWriteEvent;
 use mqtt_unsubscribe::MqttUnsubscribeEvent;
+use nats_publish::NatsPublishEvent;
+use nats_request::NatsRequestEvent;
+use nats_subscribe::NatsSubscribeEvent;
+use notify::NotifyEvent;
 use period::PeriodEvent;
+use ping::PingEvent;
+use port_check::PortCheckEvent;
 use print::PrintEvent;
+use push::PushEvent;
 use serde::{de, Deserialize, Serialize};
+use sms_send::SmsSendEvent;
+use speak::SpeakEvent;
+use ssdp::SsdpEvent;
 use std::{borrow::Borrow, hash::Hash, path::PathBuf};
+use syslog_listen::SyslogListenEvent;
+use tcp_listen::TcpListenEvent;
+use tcp_send::TcpSendEvent;
 use time::{str_to_time, ExecuteTime};
+use udp_listen::UdpListenEvent;
+use udp_send::UdpSendEvent;
+use wake_on_lan::WakeOnLanEvent;
+use ws_listen::WsListenEvent;
+use ws_send::WsSendEvent;
 
-use api_listen::ApiListenEvent;
+use api_listen::{ApiListenEvent, PathMatch};
 use file_changed::FileChangedEvent;
 use file_read::FileReadEvent;
 use file_watch::WatchEvent;
@@ -51,9 +157,43 @@ pub enum EventType {
     Repeat(TimeEvent),
     Period(PeriodEvent),
     #[serde(deserialize_with = "deserialize_api_call_event")]
-    ApiCall(ApiCallEvent),
+    ApiCall(Box<ApiCallEvent>),
     #[serde(deserialize_with = "deserialize_api_listen_event")]
     ApiListen(ApiListenEvent),
+    #[serde(deserialize_with = "deserialize_ws_listen_event")]
+    WsListen(WsListenEvent),
+    #[serde(deserialize_with = "deserialize_ws_send_event")]
+    WsSend(WsSendEvent),
+    #[serde(deserialize_with = "deserialize_tcp_listen_event")]
+    TcpListen(TcpListenEvent),
+    #[serde(deserialize_with = "deserialize_tcp_send_event")]
+    TcpSend(TcpSendEvent),
+    #[serde(deserialize_with = "deserialize_udp_listen_event")]
+    UdpListen(UdpListenEvent),
+    #[serde(deserialize_with = "deserialize_udp_send_event")]
+    UdpSend(UdpSendEvent),
+    #[serde(deserialize_with = "deserialize_syslog_listen_event")]
+    SyslogListen(SyslogListenEvent),
+    #[serde(deserialize_with = "deserialize_wake_on_lan_event")]
+    WakeOnLan(WakeOnLanEvent),
+    #[serde(deserialize_with = "deserialize_ping_event")]
+    Ping(PingEvent),
+    PortCheck(PortCheckEvent),
+    #[cfg(feature = "mdns")]
+    #[serde(deserialize_with = "deserialize_mdns_event")]
+    Mdns(mdns::MdnsEvent),
+    #[serde(deserialize_with = "deserialize_ssdp_event")]
+    Ssdp(SsdpEvent),
+    #[cfg(feature = "serial")]
+    #[serde(deserialize_with = "deserialize_serial_read_event")]
+    SerialRead(serial_read::SerialReadEvent),
+    #[cfg(feature = "serial")]
+    #[serde(deserialize_with = "deserialize_serial_write_event")]
+    SerialWrite(serial_write::SerialWriteEvent),
+    #[cfg(feature = "snmp")]
+    SnmpGet(snmp_get::SnmpGetEvent),
+    ModbusRead(ModbusReadEvent),
+    ModbusWrite(ModbusWriteEvent),
     #[serde(deserialize_with = "deserialize_file_read_event")]
     FileRead(FileReadEvent),
     #[serde(deserialize_with = "deserialize_file_write_event")]
@@ -63,11 +203,90 @@ pub enum EventType {
     #[serde(deserialize_with = "deserialize_file_changed_event")]
     FileChanged(FileChangedEvent),
     Execute(CommandEvent),
+    #[cfg(feature = "email")]
+    EmailSend(email_send::EmailSendEvent),
+    #[cfg(feature = "email")]
+    #[serde(deserialize_with = "deserialize_email_receive_event")]
+    EmailReceive(email_receive::EmailReceiveEvent),
+    Notify(NotifyEvent),
+    Push(PushEvent),
+    SmsSend(SmsSendEvent),
+    InfluxWrite(InfluxWriteEvent),
+    #[cfg(feature = "redis")]
+    #[serde(deserialize_with = "deserialize_redis_subscribe_event")]
+    RedisSubscribe(redis_subscribe::RedisSubscribeEvent),
+    #[cfg(feature = "redis")]
+    #[serde(deserialize_with = "deserialize_redis_publish_event")]
+    RedisPublish(redis_publish::RedisPublishEvent),
+    #[cfg(feature = "redis")]
+    RedisCommand(redis_command::RedisCommandEvent),
+    #[serde(deserialize_with = "deserialize_nats_subscribe_event")]
+    NatsSubscribe(NatsSubscribeEvent),
+    #[serde(deserialize_with = "deserialize_nats_publish_event")]
+    NatsPublish(NatsPublishEvent),
+    #[serde(deserialize_with = "deserialize_nats_request_event")]
+    NatsRequest(NatsRequestEvent),
+    #[cfg(feature = "amqp")]
+    #[serde(deserialize_with = "deserialize_amqp_consume_event")]
+    AmqpConsume(amqp_consume::AmqpConsumeEvent),
+    #[cfg(feature = "amqp")]
+    #[serde(deserialize_with = "deserialize_amqp_publish_event")]
+    AmqpPublish(amqp_publish::AmqpPublishEvent),
+    #[cfg(feature = "amqp")]
+    #[serde(deserialize_with = "deserialize_amqp_ack_event")]
+    AmqpAck(amqp_ack::AmqpAckEvent),
+    CoapCall(CoapCallEvent),
+    #[serde(deserialize_with = "deserialize_speak_event")]
+    Speak(SpeakEvent),
+    #[serde(deserialize_with = "deserialize_print_event")]
     Print(PrintEvent),
     #[default]
     Pass,
     #[cfg(target_os = "linux")]
     ScanCodeRead(scan_code_read::ScanCodeReadEvent),
+    #[cfg(feature = "ble")]
+    #[serde(deserialize_with = "deserialize_ble_event")]
+    Ble(ble::BleEvent),
+    #[cfg(feature = "gpio")]
+    GpioWatch(gpio_watch::GpioWatchEvent),
+    #[cfg(feature = "gpio")]
+    GpioSet(gpio_set::GpioSetEvent),
+    #[cfg(all(target_os = "linux", feature = "sensors"))]
+    SensorRead(sensor_read::SensorReadEvent),
+    #[cfg(feature = "dbus")]
+    DbusSubscribe(dbus_subscribe::DbusSubscribeEvent),
+    #[cfg(feature = "dbus")]
+    DbusCall(dbus_call::DbusCallEvent),
+    #[cfg(target_os = "linux")]
+    JournalWatch(journal_watch::JournalWatchEvent),
+    #[cfg(unix)]
+    DockerEvents(docker_events::DockerEventsEvent),
+    #[cfg(unix)]
+    DockerControl(docker_control::DockerControlEvent),
+    #[serde(deserialize_with = "deserialize_stdin_read_event")]
+    StdinRead(stdin_read::StdinReadEvent),
+    #[cfg(unix)]
+    #[serde(deserialize_with = "deserialize_fifo_read_event")]
+    FifoRead(fifo_read::FifoReadEvent),
+    #[cfg(unix)]
+    #[serde(deserialize_with = "deserialize_unix_listen_event")]
+    UnixListen(unix_listen::UnixListenEvent),
+    #[cfg(unix)]
+    #[serde(deserialize_with = "deserialize_unix_send_event")]
+    UnixSend(unix_send::UnixSendEvent),
+    #[cfg(feature = "sql")]
+    SqlQuery(sql_query::SqlQueryEvent),
+    #[cfg(feature = "zmq")]
+    #[serde(deserialize_with = "deserialize_zmq_subscribe_event")]
+    ZmqSubscribe(zmq_subscribe::ZmqSubscribeEvent),
+    #[cfg(feature = "zmq")]
+    ZmqPublish(zmq_publish::ZmqPublishEvent),
+    #[cfg(feature = "zmq")]
+    ZmqRequest(zmq_request::ZmqRequestEvent),
+    #[cfg(feature = "ssh")]
+    SshExecute(ssh_execute::SshExecuteEvent),
+    #[cfg(feature = "ssh")]
+    SftpTransfer(sftp_transfer::SftpTransferEvent),
 }
 
 #[derive(Default, Debug, Clone, Serialize, Deserialize)]
@@ -225,6 +444,16 @@ impl Events {
             if let Some(NextEvent::Name(name)) = event.next_event {
                 event.next_event = NextEvent::Name(format!("{prefix}_{name}")).into()
             }
+            if let EventType::ApiCall(e) = &mut event.event_type {
+                for name in e.on_status.values_mut() {
+                    *name = format!("{prefix}_{name}");
+                }
+            }
+            if let EventType::PortCheck(e) = &mut event.event_type {
+                for name in [&mut e.on_open, &mut e.on_closed].into_iter().flatten() {
+                    *name = format!("{prefix}_{name}");
+                }
+            }
             event
         }));
         self
@@ -432,11 +661,31 @@ where
     #[serde(untagged)]
     enum OneOrFull {
         One(String),
-        Full(ApiListenEvent),
+        Full(Box<ApiListenEvent>),
     }
     let s: OneOrFull = de::Deserialize::deserialize(deserializer)?;
     match s {
         OneOrFull::One(path) => Ok(ApiListenEvent {
+            path: PathMatch::Prefix(path),
+            ..Default::default()
+        }),
+        OneOrFull::Full(t) => Ok(*t),
+    }
+}
+
+fn deserialize_ws_listen_event<'de, D>(deserializer: D) -> Result<WsListenEvent, D::Error>
+where
+    D: de::Deserializer<'de>,
+{
+    #[derive(Debug, Deserialize)]
+    #[serde(untagged)]
+    enum OneOrFull {
+        One(String),
+        Full(WsListenEvent),
+    }
+    let s: OneOrFull = de::Deserialize::deserialize(deserializer)?;
+    match s {
+        OneOrFull::One(path) => Ok(WsListenEvent {
             path,
             ..Default::default()
         }),
@@ -444,7 +693,129 @@ where
     }
 }
 
-fn deserialize_api_call_event<'de, D>(deserializer: D) -> Result<ApiCallEvent, D::Error>
+fn deserialize_tcp_listen_event<'de, D>(deserializer: D) -> Result<TcpListenEvent, D::Error>
+where
+    D: de::Deserializer<'de>,
+{
+    #[derive(Debug, Deserialize)]
+    #[serde(untagged)]
+    enum OneOrFull {
+        One(String),
+        Full(TcpListenEvent),
+    }
+    let s: OneOrFull = de::Deserialize::deserialize(deserializer)?;
+    match s {
+        OneOrFull::One(pool_id) => Ok(TcpListenEvent {
+            pool_id,
+            ..Default::default()
+        }),
+        OneOrFull::Full(t) => Ok(t),
+    }
+}
+
+fn deserialize_tcp_send_event<'de, D>(deserializer: D) -> Result<TcpSendEvent, D::Error>
+where
+    D: de::Deserializer<'de>,
+{
+    #[derive(Debug, Deserialize)]
+    #[serde(untagged)]
+    enum OneOrFull {
+        One(String),
+        Full(TcpSendEvent),
+    }
+    let s: OneOrFull = de::Deserialize::deserialize(deserializer)?;
+    match s {
+        OneOrFull::One(address) => {
+            let (host, port) = address
+                .rsplit_once(':')
+                .and_then(|(host, port)| Some((host, port.parse().ok()?)))
+                .ok_or_else(|| {
+                    de::Error::custom(format!(
+                        "Invalid tcp_send address {address} expected host:port"
+                    ))
+                })?;
+            Ok(TcpSendEvent {
+                host: host.to_string(),
+                port,
+                ..Default::default()
+            })
+        }
+        OneOrFull::Full(t) => Ok(t),
+    }
+}
+
+fn deserialize_stdin_read_event<'de, D>(
+    deserializer: D,
+) -> Result<stdin_read::StdinReadEvent, D::Error>
+where
+    D: de::Deserializer<'de>,
+{
+    #[derive(Debug, Deserialize)]
+    #[serde(untagged)]
+    enum OneOrFull {
+        One(stdin_read::StdinFraming),
+        Full(stdin_read::StdinReadEvent),
+    }
+    let s: OneOrFull = de::Deserialize::deserialize(deserializer)?;
+    match s {
+        OneOrFull::One(framing) => Ok(stdin_read::StdinReadEvent {
+            framing,
+            ..Default::default()
+        }),
+        OneOrFull::Full(t) => Ok(t),
+    }
+}
+
+#[cfg(unix)]
+fn deserialize_fifo_read_event<'de, D>(
+    deserializer: D,
+) -> Result<fifo_read::FifoReadEvent, D::Error>
+where
+    D: de::Deserializer<'de>,
+{
+    #[derive(Debug, Deserialize)]
+    #[serde(untagged)]
+    enum OneOrFull {
+        One(String),
+        Full(fifo_read::FifoReadEvent),
+    }
+    let s: OneOrFull = de::Deserialize::deserialize(deserializer)?;
+    match s {
+        OneOrFull::One(pool_id) => Ok(fifo_read::FifoReadEvent {
+            pool_id,
+            ..Default::default()
+        }),
+        OneOrFull::Full(t) => Ok(t),
+    }
+}
+
+#[cfg(unix)]
+fn deserialize_unix_listen_event<'de, D>(
+    deserializer: D,
+) -> Result<unix_listen::UnixListenEvent, D::Error>
+where
+    D: de::Deserializer<'de>,
+{
+    #[derive(Debug, Deserialize)]
+    #[serde(untagged)]
+    enum OneOrFull {
+        One(String),
+        Full(unix_listen::UnixListenEvent),
+    }
+    let s: OneOrFull = de::Deserialize::deserialize(deserializer)?;
+    match s {
+        OneOrFull::One(pool_id) => Ok(unix_listen::UnixListenEvent {
+            pool_id,
+            ..Default::default()
+        }),
+        OneOrFull::Full(t) => Ok(t),
+    }
+}
+
+#[cfg(unix)]
+fn deserialize_unix_send_event<'de, D>(
+    deserializer: D,
+) -> Result<unix_send::UnixSendEvent, D::Error>
 where
     D: de::Deserializer<'de>,
 {
@@ -452,14 +823,523 @@ where
     #[serde(untagged)]
     enum OneOrFull {
         One(String),
-        Full(ApiCallEvent),
+        Full(unix_send::UnixSendEvent),
+    }
+    let s: OneOrFull = de::Deserialize::deserialize(deserializer)?;
+    match s {
+        OneOrFull::One(path) => Ok(unix_send::UnixSendEvent {
+            path,
+            ..Default::default()
+        }),
+        OneOrFull::Full(t) => Ok(t),
+    }
+}
+
+fn deserialize_print_event<'de, D>(deserializer: D) -> Result<PrintEvent, D::Error>
+where
+    D: de::Deserializer<'de>,
+{
+    #[derive(Debug, Deserialize)]
+    #[serde(untagged)]
+    enum OneOrFull {
+        One(print::Output),
+        Full(PrintEvent),
+    }
+    let s: OneOrFull = de::Deserialize::deserialize(deserializer)?;
+    match s {
+        OneOrFull::One(output) => Ok(PrintEvent {
+            output,
+            ..Default::default()
+        }),
+        OneOrFull::Full(t) => Ok(t),
+    }
+}
+
+fn deserialize_udp_listen_event<'de, D>(deserializer: D) -> Result<UdpListenEvent, D::Error>
+where
+    D: de::Deserializer<'de>,
+{
+    #[derive(Debug, Deserialize)]
+    #[serde(untagged)]
+    enum OneOrFull {
+        One(String),
+        Full(UdpListenEvent),
+    }
+    let s: OneOrFull = de::Deserialize::deserialize(deserializer)?;
+    match s {
+        OneOrFull::One(pool_id) => Ok(UdpListenEvent {
+            pool_id,
+            ..Default::default()
+        }),
+        OneOrFull::Full(t) => Ok(t),
+    }
+}
+
+fn deserialize_udp_send_event<'de, D>(deserializer: D) -> Result<UdpSendEvent, D::Error>
+where
+    D: de::Deserializer<'de>,
+{
+    #[derive(Debug, Deserialize)]
+    #[serde(untagged)]
+    enum OneOrFull {
+        One(String),
+        Full(UdpSendEvent),
+    }
+    let s: OneOrFull = de::Deserialize::deserialize(deserializer)?;
+    match s {
+        OneOrFull::One(address) => Ok(UdpSendEvent {
+            address,
+            ..Default::default()
+        }),
+        OneOrFull::Full(t) => Ok(t),
+    }
+}
+
+fn deserialize_syslog_listen_event<'de, D>(deserializer: D) -> Result<SyslogListenEvent, D::Error>
+where
+    D: de::Deserializer<'de>,
+{
+    #[derive(Debug, Deserialize)]
+    #[serde(untagged)]
+    enum OneOrFull {
+        One(String),
+        Full(SyslogListenEvent),
+    }
+    let s: OneOrFull = de::Deserialize::deserialize(deserializer)?;
+    match s {
+        OneOrFull::One(pool_id) => Ok(SyslogListenEvent {
+            pool_id,
+            ..Default::default()
+        }),
+        OneOrFull::Full(t) => Ok(t),
+    }
+}
+
+#[cfg(feature = "email")]
+fn deserialize_email_receive_event<'de, D>(
+    deserializer: D,
+) -> Result<email_receive::EmailReceiveEvent, D::Error>
+where
+    D: de::Deserializer<'de>,
+{
+    #[derive(Debug, Deserialize)]
+    #[serde(untagged)]
+    enum OneOrFull {
+        One(String),
+        Full(email_receive::EmailReceiveEvent),
+    }
+    let s: OneOrFull = de::Deserialize::deserialize(deserializer)?;
+    match s {
+        OneOrFull::One(pool_id) => Ok(email_receive::EmailReceiveEvent {
+            pool_id,
+            ..Default::default()
+        }),
+        OneOrFull::Full(t) => Ok(t),
+    }
+}
+
+fn deserialize_speak_event<'de, D>(deserializer: D) -> Result<SpeakEvent, D::Error>
+where
+    D: de::Deserializer<'de>,
+{
+    #[derive(Debug, Deserialize)]
+    #[serde(untagged)]
+    enum OneOrFull {
+        One(String),
+        Full(SpeakEvent),
+    }
+    let s: OneOrFull = de::Deserialize::deserialize(deserializer)?;
+    match s {
+        OneOrFull::One(text) => Ok(SpeakEvent {
+            text: Some(text),
+            ..Default::default()
+        }),
+        OneOrFull::Full(t) => Ok(t),
+    }
+}
+
+#[cfg(feature = "redis")]
+fn deserialize_redis_subscribe_event<'de, D>(
+    deserializer: D,
+) -> Result<redis_subscribe::RedisSubscribeEvent, D::Error>
+where
+    D: de::Deserializer<'de>,
+{
+    #[derive(Debug, Deserialize)]
+    #[serde(untagged)]
+    enum OneOrFull {
+        One(String),
+        Full(redis_subscribe::RedisSubscribeEvent),
+    }
+    let s: OneOrFull = de::Deserialize::deserialize(deserializer)?;
+    match s {
+        OneOrFull::One(channel) => Ok(redis_subscribe::RedisSubscribeEvent {
+            channel,
+            ..Default::default()
+        }),
+        OneOrFull::Full(t) => Ok(t),
+    }
+}
+
+#[cfg(feature = "redis")]
+fn deserialize_redis_publish_event<'de, D>(
+    deserializer: D,
+) -> Result<redis_publish::RedisPublishEvent, D::Error>
+where
+    D: de::Deserializer<'de>,
+{
+    #[derive(Debug, Deserialize)]
+    #[serde(untagged)]
+    enum OneOrFull {
+        One(String),
+        Full(redis_publish::RedisPublishEvent),
+    }
+    let s: OneOrFull = de::Deserialize::deserialize(deserializer)?;
+    match s {
+        OneOrFull::One(channel) => Ok(redis_publish::RedisPublishEvent {
+            channel,
+            ..Default::default()
+        }),
+        OneOrFull::Full(t) => Ok(t),
+    }
+}
+
+fn deserialize_nats_subscribe_event<'de, D>(deserializer: D) -> Result<NatsSubscribeEvent, D::Error>
+where
+    D: de::Deserializer<'de>,
+{
+    #[derive(Debug, Deserialize)]
+    #[serde(untagged)]
+    enum OneOrFull {
+        One(String),
+        Full(NatsSubscribeEvent),
+    }
+    let s: OneOrFull = de::Deserialize::deserialize(deserializer)?;
+    match s {
+        OneOrFull::One(subject) => Ok(NatsSubscribeEvent {
+            subject,
+            ..Default::default()
+        }),
+        OneOrFull::Full(t) => Ok(t),
+    }
+}
+
+fn deserialize_nats_publish_event<'de, D>(deserializer: D) -> Result<NatsPublishEvent, D::Error>
+where
+    D: de::Deserializer<'de>,
+{
+    #[derive(Debug, Deserialize)]
+    #[serde(untagged)]
+    enum OneOrFull {
+        One(String),
+        Full(NatsPublishEvent),
+    }
+    let s: OneOrFull = de::Deserialize::deserialize(deserializer)?;
+    match s {
+        OneOrFull::One(subject) => Ok(NatsPublishEvent {
+            subject,
+            ..Default::default()
+        }),
+        OneOrFull::Full(t) => Ok(t),
+    }
+}
+
+fn deserialize_nats_request_event<'de, D>(deserializer: D) -> Result<NatsRequestEvent, D::Error>
+where
+    D: de::Deserializer<'de>,
+{
+    #[derive(Debug, Deserialize)]
+    #[serde(untagged)]
+    enum OneOrFull {
+        One(String),
+        Full(NatsRequestEvent),
+    }
+    let s: OneOrFull = de::Deserialize::deserialize(deserializer)?;
+    match s {
+        OneOrFull::One(subject) => Ok(NatsRequestEvent {
+            subject,
+            timeout: nats_request::default_timeout(),
+            ..Default::default()
+        }),
+        OneOrFull::Full(t) => Ok(t),
+    }
+}
+
+#[cfg(feature = "amqp")]
+fn deserialize_amqp_consume_event<'de, D>(
+    deserializer: D,
+) -> Result<amqp_consume::AmqpConsumeEvent, D::Error>
+where
+    D: de::Deserializer<'de>,
+{
+    #[derive(Debug, Deserialize)]
+    #[serde(untagged)]
+    enum OneOrFull {
+        One(String),
+        Full(amqp_consume::AmqpConsumeEvent),
+    }
+    let s: OneOrFull = de::Deserialize::deserialize(deserializer)?;
+    match s {
+        OneOrFull::One(queue) => Ok(amqp_consume::AmqpConsumeEvent {
+            queue,
+            ..Default::default()
+        }),
+        OneOrFull::Full(t) => Ok(t),
+    }
+}
+
+#[cfg(feature = "amqp")]
+fn deserialize_amqp_publish_event<'de, D>(
+    deserializer: D,
+) -> Result<amqp_publish::AmqpPublishEvent, D::Error>
+where
+    D: de::Deserializer<'de>,
+{
+    #[derive(Debug, Deserialize)]
+    #[serde(untagged)]
+    enum OneOrFull {
+        One(String),
+        Full(amqp_publish::AmqpPublishEvent),
+    }
+    let s: OneOrFull = de::Deserialize::deserialize(deserializer)?;
+    match s {
+        OneOrFull::One(routing_key) => Ok(amqp_publish::AmqpPublishEvent {
+            routing_key,
+            ..Default::default()
+        }),
+        OneOrFull::Full(t) => Ok(t),
+    }
+}
+
+#[cfg(feature = "amqp")]
+fn deserialize_amqp_ack_event<'de, D>(deserializer: D) -> Result<amqp_ack::AmqpAckEvent, D::Error>
+where
+    D: de::Deserializer<'de>,
+{
+    #[derive(Debug, Deserialize)]
+    #[serde(untagged)]
+    enum OneOrFull {
+        One(String),
+        Full(amqp_ack::AmqpAckEvent),
+    }
+    let s: OneOrFull = de::Deserialize::deserialize(deserializer)?;
+    match s {
+        OneOrFull::One(delivery_id) => Ok(amqp_ack::AmqpAckEvent {
+            delivery_id,
+            ..Default::default()
+        }),
+        OneOrFull::Full(t) => Ok(t),
+    }
+}
+
+#[cfg(feature = "zmq")]
+fn deserialize_zmq_subscribe_event<'de, D>(
+    deserializer: D,
+) -> Result<zmq_subscribe::ZmqSubscribeEvent, D::Error>
+where
+    D: de::Deserializer<'de>,
+{
+    #[derive(Debug, Deserialize)]
+    #[serde(untagged)]
+    enum OneOrFull {
+        One(String),
+        Full(zmq_subscribe::ZmqSubscribeEvent),
+    }
+    let s: OneOrFull = de::Deserialize::deserialize(deserializer)?;
+    match s {
+        OneOrFull::One(topic) => Ok(zmq_subscribe::ZmqSubscribeEvent {
+            topic,
+            ..Default::default()
+        }),
+        OneOrFull::Full(t) => Ok(t),
+    }
+}
+
+fn deserialize_wake_on_lan_event<'de, D>(deserializer: D) -> Result<WakeOnLanEvent, D::Error>
+where
+    D: de::Deserializer<'de>,
+{
+    #[derive(Debug, Deserialize)]
+    #[serde(untagged)]
+    enum OneOrFull {
+        One(String),
+        Full(WakeOnLanEvent),
+    }
+    let s: OneOrFull = de::Deserialize::deserialize(deserializer)?;
+    match s {
+        OneOrFull::One(mac) => Ok(WakeOnLanEvent {
+            mac,
+            ..Default::default()
+        }),
+        OneOrFull::Full(t) => Ok(t),
+    }
+}
+
+fn deserialize_ping_event<'de, D>(deserializer: D) -> Result<PingEvent, D::Error>
+where
+    D: de::Deserializer<'de>,
+{
+    #[derive(Debug, Deserialize)]
+    #[serde(untagged)]
+    enum OneOrFull {
+        One(String),
+        Full(PingEvent),
+    }
+    let s: OneOrFull = de::Deserialize::deserialize(deserializer)?;
+    match s {
+        OneOrFull::One(pool_id) => Ok(PingEvent {
+            pool_id,
+            ..Default::default()
+        }),
+        OneOrFull::Full(t) => Ok(t),
+    }
+}
+
+#[cfg(feature = "mdns")]
+fn deserialize_mdns_event<'de, D>(deserializer: D) -> Result<mdns::MdnsEvent, D::Error>
+where
+    D: de::Deserializer<'de>,
+{
+    #[derive(Debug, Deserialize)]
+    #[serde(untagged)]
+    enum OneOrFull {
+        One(String),
+        Full(mdns::MdnsEvent),
+    }
+    let s: OneOrFull = de::Deserialize::deserialize(deserializer)?;
+    match s {
+        OneOrFull::One(service) => Ok(mdns::MdnsEvent {
+            service,
+            ..Default::default()
+        }),
+        OneOrFull::Full(t) => Ok(t),
+    }
+}
+
+fn deserialize_ssdp_event<'de, D>(deserializer: D) -> Result<SsdpEvent, D::Error>
+where
+    D: de::Deserializer<'de>,
+{
+    #[derive(Debug, Deserialize)]
+    #[serde(untagged)]
+    enum OneOrFull {
+        One(String),
+        Full(SsdpEvent),
+    }
+    let s: OneOrFull = de::Deserialize::deserialize(deserializer)?;
+    match s {
+        OneOrFull::One(search_target) => Ok(SsdpEvent {
+            search_target,
+            ..Default::default()
+        }),
+        OneOrFull::Full(t) => Ok(t),
+    }
+}
+
+#[cfg(feature = "serial")]
+fn deserialize_serial_read_event<'de, D>(
+    deserializer: D,
+) -> Result<serial_read::SerialReadEvent, D::Error>
+where
+    D: de::Deserializer<'de>,
+{
+    #[derive(Debug, Deserialize)]
+    #[serde(untagged)]
+    enum OneOrFull {
+        One(String),
+        Full(serial_read::SerialReadEvent),
+    }
+    let s: OneOrFull = de::Deserialize::deserialize(deserializer)?;
+    match s {
+        OneOrFull::One(pool_id) => Ok(serial_read::SerialReadEvent {
+            pool_id,
+            ..Default::default()
+        }),
+        OneOrFull::Full(t) => Ok(t),
+    }
+}
+
+#[cfg(feature = "serial")]
+fn deserialize_serial_write_event<'de, D>(
+    deserializer: D,
+) -> Result<serial_write::SerialWriteEvent, D::Error>
+where
+    D: de::Deserializer<'de>,
+{
+    #[derive(Debug, Deserialize)]
+    #[serde(untagged)]
+    enum OneOrFull {
+        One(String),
+        Full(serial_write::SerialWriteEvent),
+    }
+    let s: OneOrFull = de::Deserialize::deserialize(deserializer)?;
+    match s {
+        OneOrFull::One(pool_id) => Ok(serial_write::SerialWriteEvent {
+            pool_id,
+            ..Default::default()
+        }),
+        OneOrFull::Full(t) => Ok(t),
+    }
+}
+
+#[cfg(feature = "ble")]
+fn deserialize_ble_event<'de, D>(deserializer: D) -> Result<ble::BleEvent, D::Error>
+where
+    D: de::Deserializer<'de>,
+{
+    #[derive(Debug, Deserialize)]
+    #[serde(untagged)]
+    enum OneOrFull {
+        One(String),
+        Full(ble::BleEvent),
+    }
+    let s: OneOrFull = de::Deserialize::deserialize(deserializer)?;
+    match s {
+        OneOrFull::One(address) => Ok(ble::BleEvent {
+            address: Some(address),
+            ..Default::default()
+        }),
+        OneOrFull::Full(t) => Ok(t),
+    }
+}
+
+fn deserialize_ws_send_event<'de, D>(deserializer: D) -> Result<WsSendEvent, D::Error>
+where
+    D: de::Deserializer<'de>,
+{
+    #[derive(Debug, Deserialize)]
+    #[serde(untagged)]
+    enum OneOrFull {
+        One(String),
+        Full(WsSendEvent),
+    }
+    let s: OneOrFull = de::Deserialize::deserialize(deserializer)?;
+    match s {
+        OneOrFull::One(path) => Ok(WsSendEvent {
+            path: path.into(),
+            ..Default::default()
+        }),
+        OneOrFull::Full(t) => Ok(t),
+    }
+}
+
+fn deserialize_api_call_event<'de, D>(deserializer: D) -> Result<Box<ApiCallEvent>, D::Error>
+where
+    D: de::Deserializer<'de>,
+{
+    #[derive(Debug, Deserialize)]
+    #[serde(untagged)]
+    enum OneOrFull {
+        One(String),
+        Full(Box<ApiCallEvent>),
     }
     let s: OneOrFull = de::Deserialize::deserialize(deserializer)?;
     match s {
         OneOrFull::One(url) => Ok(ApiCallEvent {
             url,
             ..Default::default()
-        }),
+        }
+        .into()),
         OneOrFull::Full(t) => Ok(t),
     }
 }
@@ -508,6 +1388,10 @@ mod tests {
                 topic: "topic".to_string(),
                 body: None,
                 pool_id: "".to_string(),
+                qos: None,
+                captures: Vec::new(),
+                encoding: None,
+                protobuf: None,
             }),
             next_event: None,
             metadata: json!({}).into(),
@@ -533,4 +1417,42 @@ mod tests {
         let event: ReferencingEvent = serde_yaml::from_str(yaml).unwrap();
         assert_eq!(event, expected);
     }
+
+    #[test]
+    fn test_deserialize_tcp_send() {
+        let event: ReferencingEvent =
+            serde_yaml::from_str("tcp_send: projector.local:4352").unwrap();
+        let EventType::TcpSend(e) = event.event_type else {
+            panic!("Expected tcp_send event");
+        };
+        assert_eq!(e.host, "projector.local");
+        assert_eq!(e.port, 4352);
+        assert_eq!(e.timeout, 5);
+    }
+
+    #[test]
+    fn test_deserialize_print() {
+        let event: ReferencingEvent = serde_yaml::from_str("print: stderr").unwrap();
+        let EventType::Print(e) = event.event_type else {
+            panic!("Expected print event");
+        };
+        assert_eq!(e.output, print::Output::Stderr);
+        assert!(e.template.is_none());
+
+        let event: ReferencingEvent =
+            serde_yaml::from_str("print:\n  template: \"{{data.temperature}} C\"").unwrap();
+        let EventType::Print(e) = event.event_type else {
+            panic!("Expected print event");
+        };
+        assert_eq!(e.output, print::Output::Stdout);
+        assert_eq!(e.template.as_deref(), Some("{{data.temperature}} C"));
+
+        let event: ReferencingEvent =
+            serde_yaml::from_str("print:\n  output: log\n  level: warn").unwrap();
+        let EventType::Print(e) = event.event_type else {
+            panic!("Expected print event");
+        };
+        assert_eq!(e.output, print::Output::Log);
+        assert_eq!(e.level, print::LogLevel::Warn);
+    }
 }
@@ -0,0 +1,281 @@
+use anyhow::{anyhow, bail};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::config::PoolId;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ModbusRegister {
+    #[default]
+    Holding,
+    Input,
+    Coil,
+    Discrete,
+}
+
+impl ModbusRegister {
+    /// coils and discrete inputs hold a single bit
+    pub fn is_bit(&self) -> bool {
+        matches!(self, ModbusRegister::Coil | ModbusRegister::Discrete)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ModbusDataType {
+    #[default]
+    U16,
+    I16,
+    U32,
+    I32,
+    F32,
+}
+
+impl ModbusDataType {
+    /// number of registers used by a value
+    pub fn size(&self) -> usize {
+        match self {
+            ModbusDataType::U16 | ModbusDataType::I16 => 1,
+            ModbusDataType::U32 | ModbusDataType::I32 | ModbusDataType::F32 => 2,
+        }
+    }
+
+    pub fn decode(&self, registers: &[u16], swap_words: bool, scale: Option<f64>) -> Value {
+        let mut registers = registers.to_vec();
+        if swap_words {
+            registers.reverse();
+        }
+        let raw = registers
+            .iter()
+            .fold(0u32, |value, r| (value << 16) | u32::from(*r));
+        let value = match self {
+            ModbusDataType::U16 => json!(raw as u16),
+            ModbusDataType::I16 => json!(raw as u16 as i16),
+            ModbusDataType::U32 => json!(raw),
+            ModbusDataType::I32 => json!(raw as i32),
+            // shortest representation e.g. 21.5 instead of 21.500000476837158
+            ModbusDataType::F32 => json!(f32::from_bits(raw)
+                .to_string()
+                .parse::<f64>()
+                .unwrap_or_default()),
+        };
+        match (scale, value.as_f64()) {
+            (Some(scale), Some(v)) => json!(v * scale),
+            _ => value,
+        }
+    }
+
+    pub fn encode(&self, value: &str, swap_words: bool) -> anyhow::Result<Vec<u16>> {
+        let value = value.trim();
+        let raw = match self {
+            ModbusDataType::U16 => u32::from(value.parse::<u16>()?),
+            ModbusDataType::I16 => u32::from(value.parse::<i16>()? as u16),
+            ModbusDataType::U32 => value.parse::<u32>()?,
+            ModbusDataType::I32 => value.parse::<i32>()? as u32,
+            ModbusDataType::F32 => value.parse::<f32>()?.to_bits(),
+        };
+        let mut registers: Vec<u16> = match self.size() {
+            1 => vec![raw as u16],
+            _ => vec![(raw >> 16) as u16, raw as u16],
+        };
+        if swap_words {
+            registers.reverse();
+        }
+        Ok(registers)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModbusReadEvent {
+    /// the first modbus connection is used if empty
+    #[serde(default)]
+    pub pool_id: PoolId,
+    /// slave id
+    #[serde(default = "default_unit")]
+    pub unit: u8,
+    #[serde(default)]
+    pub register: ModbusRegister,
+    /// zero based register address
+    pub address: u16,
+    /// ignored for coil and discrete registers
+    #[serde(default)]
+    pub data_type: ModbusDataType,
+    /// number of values to read, an array is returned if more than one
+    #[serde(default = "default_count")]
+    pub count: u16,
+    /// 32 bit values are sent with the low word first
+    #[serde(default)]
+    pub swap_words: bool,
+    /// multiply values e.g. 0.1
+    pub scale: Option<f64>,
+}
+
+impl Default for ModbusReadEvent {
+    fn default() -> Self {
+        Self {
+            pool_id: Default::default(),
+            unit: default_unit(),
+            register: Default::default(),
+            address: Default::default(),
+            data_type: Default::default(),
+            count: default_count(),
+            swap_words: false,
+            scale: None,
+        }
+    }
+}
+
+impl ModbusReadEvent {
+    /// number of registers or bits to read
+    pub fn register_count(&self) -> u16 {
+        if self.register.is_bit() {
+            self.count
+        } else {
+            self.count * self.data_type.size() as u16
+        }
+    }
+
+    pub fn values(&self, registers: &[u16]) -> Value {
+        let values: Vec<Value> = if self.register.is_bit() {
+            registers.iter().map(|r| Value::Bool(*r != 0)).collect()
+        } else {
+            registers
+                .chunks(self.data_type.size())
+                .map(|r| self.data_type.decode(r, self.swap_words, self.scale))
+                .collect()
+        };
+        if self.count == 1 {
+            values.into_iter().next().unwrap_or_default()
+        } else {
+            Value::Array(values)
+        }
+    }
+}
+
+pub fn parse_bit(value: &str) -> anyhow::Result<u16> {
+    match value.trim().to_lowercase().as_str() {
+        "1" | "true" | "on" => Ok(1),
+        "0" | "false" | "off" => Ok(0),
+        v => bail!("Unable to use {v} as coil value, expected true or false"),
+    }
+}
+
+/// registers from a json array or a single value
+pub fn parse_values(
+    value: &str,
+    register: ModbusRegister,
+    data_type: ModbusDataType,
+    swap_words: bool,
+) -> anyhow::Result<Vec<u16>> {
+    let values = match serde_json::from_str::<Value>(value) {
+        Ok(Value::Array(values)) => values
+            .iter()
+            .map(|v| match v {
+                Value::String(s) => s.clone(),
+                v => v.to_string(),
+            })
+            .collect(),
+        _ => vec![value.to_string()],
+    };
+    let registers: Vec<u16> = values
+        .iter()
+        .map(|v| {
+            if register.is_bit() {
+                parse_bit(v).map(|b| vec![b])
+            } else {
+                data_type.encode(v, swap_words)
+            }
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?
+        .into_iter()
+        .flatten()
+        .collect();
+    if registers.is_empty() {
+        return Err(anyhow!("No values provided to write"));
+    }
+    Ok(registers)
+}
+
+pub(crate) fn default_unit() -> u8 {
+    1
+}
+
+fn default_count() -> u16 {
+    1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode() {
+        assert_eq!(ModbusDataType::I16.decode(&[0xffff], false, None), -1);
+        assert_eq!(
+            ModbusDataType::U32.decode(&[0x0001, 0x0002], false, None),
+            65538
+        );
+        assert_eq!(
+            ModbusDataType::F32.decode(&[0x0000, 0x41ac], true, None),
+            21.5
+        );
+        assert_eq!(ModbusDataType::U16.decode(&[215], false, Some(0.1)), 21.5);
+    }
+
+    #[test]
+    fn test_encode() {
+        assert_eq!(
+            ModbusDataType::F32.encode("21.5", false).unwrap(),
+            vec![0x41ac, 0x0000]
+        );
+        assert_eq!(
+            ModbusDataType::I16.encode("-1", false).unwrap(),
+            vec![0xffff]
+        );
+        assert_eq!(
+            ModbusDataType::U32.encode("65538", true).unwrap(),
+            vec![0x0002, 0x0001]
+        );
+        assert!(ModbusDataType::U16.encode("on", false).is_err());
+    }
+
+    #[test]
+    fn test_values() {
+        let event = ModbusReadEvent {
+            count: 2,
+            data_type: ModbusDataType::I32,
+            ..Default::default()
+        };
+        assert_eq!(event.register_count(), 4);
+        assert_eq!(
+            event.values(&[0, 1, 0xffff, 0xffff]),
+            serde_json::json!([1, -1])
+        );
+
+        let event = ModbusReadEvent {
+            register: ModbusRegister::Coil,
+            ..Default::default()
+        };
+        assert_eq!(event.register_count(), 1);
+        assert_eq!(event.values(&[1]), true);
+    }
+
+    #[test]
+    fn test_parse_values() {
+        assert_eq!(
+            parse_values(
+                "[1, \"2\"]",
+                ModbusRegister::Holding,
+                ModbusDataType::U16,
+                false
+            )
+            .unwrap(),
+            vec![1, 2]
+        );
+        assert_eq!(
+            parse_values("on", ModbusRegister::Coil, ModbusDataType::U16, false).unwrap(),
+            vec![1]
+        );
+    }
+}
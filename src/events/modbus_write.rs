@@ -0,0 +1,29 @@
+use serde::{Deserialize, Serialize};
+
+use crate::config::PoolId;
+
+use super::modbus_read::{default_unit, ModbusDataType, ModbusRegister};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModbusWriteEvent {
+    /// the first modbus connection is used if empty
+    #[serde(default)]
+    pub pool_id: PoolId,
+    /// slave id
+    #[serde(default = "default_unit")]
+    pub unit: u8,
+    /// holding or coil
+    #[serde(default)]
+    pub register: ModbusRegister,
+    /// zero based register address
+    pub address: u16,
+    /// ignored for coil registers
+    #[serde(default)]
+    pub data_type: ModbusDataType,
+    /// 32 bit values are sent with the low word first
+    #[serde(default)]
+    pub swap_words: bool,
+    /// template e.g. "{{data.setpoint}}" or a json array for consecutive registers,
+    /// event data is used if not specified
+    pub value: Option<String>,
+}
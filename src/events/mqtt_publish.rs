@@ -1,13 +1,98 @@
+use std::path::PathBuf;
+
+use indexmap::IndexMap;
+use rumqttc::QoS;
 use serde::{Deserialize, Serialize};
 
 use crate::config::PoolId;
 
+use super::data::PayloadEncoding;
+
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct MqttPublishEvent {
     pub topic: String,
     pub body: Option<String>,
+    /// body template file used instead of body
+    pub template_file: Option<PathBuf>,
     #[serde(default)]
     pub retain: bool,
+    /// publish an empty retained payload to remove the retained message from the topic
+    #[serde(default)]
+    pub clear_retained: bool,
     #[serde(default)]
     pub pool_id: PoolId,
+    /// defaults to 1 (at least once)
+    pub qos: Option<MqttQos>,
+    /// mqtt v5 properties, ignored for v4 clients
+    #[serde(default)]
+    pub properties: MqttPublishProperties,
+    /// encode the json body or data before publishing
+    pub encoding: Option<PayloadEncoding>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct MqttPublishProperties {
+    #[serde(default)]
+    pub user_properties: IndexMap<String, String>,
+    /// seconds
+    pub message_expiry: Option<u32>,
+    pub topic_alias: Option<u16>,
+    pub response_topic: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(try_from = "u8", into = "u8")]
+pub enum MqttQos {
+    AtMostOnce,
+    AtLeastOnce,
+    ExactlyOnce,
+}
+
+impl TryFrom<u8> for MqttQos {
+    type Error = String;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        Ok(match value {
+            0 => MqttQos::AtMostOnce,
+            1 => MqttQos::AtLeastOnce,
+            2 => MqttQos::ExactlyOnce,
+            v => return Err(format!("Invalid qos {v}, expected 0, 1 or 2")),
+        })
+    }
+}
+
+impl From<MqttQos> for u8 {
+    fn from(value: MqttQos) -> Self {
+        match value {
+            MqttQos::AtMostOnce => 0,
+            MqttQos::AtLeastOnce => 1,
+            MqttQos::ExactlyOnce => 2,
+        }
+    }
+}
+
+impl From<MqttQos> for QoS {
+    fn from(value: MqttQos) -> Self {
+        match value {
+            MqttQos::AtMostOnce => QoS::AtMostOnce,
+            MqttQos::AtLeastOnce => QoS::AtLeastOnce,
+            MqttQos::ExactlyOnce => QoS::ExactlyOnce,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deserialize_qos() {
+        let event: MqttPublishEvent = serde_yaml::from_str("topic: a\nqos: 2").unwrap();
+        assert_eq!(event.qos, Some(MqttQos::ExactlyOnce));
+        assert!(!event.clear_retained);
+        let event: MqttPublishEvent = serde_yaml::from_str("topic: a").unwrap();
+        assert_eq!(event.qos, None);
+        let result = serde_yaml::from_str::<MqttPublishEvent>("topic: a\nqos: 3");
+        assert!(result.is_err());
+    }
 }
@@ -1,9 +1,27 @@
 use core::str::from_utf8;
+use std::borrow::Cow;
+use std::sync::{Arc, Mutex};
 
+use indexmap::IndexMap;
+use rumqttc::QoS;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use serde_json_path::JsonPath;
 
 use crate::config::PoolId;
 
+use super::{data::PayloadEncoding, mqtt_publish::MqttQos, protobuf::ProtobufMessage, EventName};
+
+/// topics rendered from templates at subscribe time by event name
+pub type MqttSubscriptions = Arc<Mutex<IndexMap<EventName, MqttSubscription>>>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MqttSubscription {
+    pub topic: String,
+    /// qos used to subscribe again after reconnecting
+    pub qos: QoS,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct MqttSubscribeEvent {
     pub topic: String,
@@ -11,22 +29,69 @@ pub struct MqttSubscribeEvent {
     pub body: Option<MqttBodyMatch>,
     #[serde(default)]
     pub pool_id: PoolId,
+    /// defaults to 0 (at most once)
+    pub qos: Option<MqttQos>,
+    /// names for the values matched by + and # wildcards in order
+    #[serde(default)]
+    pub captures: Vec<String>,
+    /// decode payloads to json before matching, other payloads are ignored
+    pub encoding: Option<PayloadEncoding>,
+    /// decode protobuf payloads to json before matching, other payloads are ignored
+    pub protobuf: Option<ProtobufMessage>,
 }
 
 impl MqttSubscribeEvent {
     pub fn matches(&self, topic: &str, body: &[u8]) -> bool {
-        let topic_matches = if self.topic.ends_with('#') {
-            topic.starts_with(self.topic.trim_end_matches('#'))
-        } else if self.topic.contains("+") {
-            self.topic
-                .split('/')
-                .zip(topic.split('/'))
-                .all(|(expected, received)| expected == "+" || expected == received)
-        } else {
-            topic == self.topic
+        self.matches_subscribed(&self.topic, topic, body)
+    }
+
+    /// json bytes of payloads with an encoding or protobuf message, the payload otherwise
+    pub fn decode<'a>(&self, payload: &'a [u8]) -> anyhow::Result<Cow<'a, [u8]>> {
+        let value = match (&self.protobuf, &self.encoding) {
+            (Some(message), _) => message.decode(payload)?,
+            (None, Some(encoding)) => encoding.decode(payload)?,
+            (None, None) => return Ok(payload.into()),
         };
-        topic_matches && self.body.as_ref().map(|b| b.matches(body)).unwrap_or(true)
+        Ok(serde_json::to_vec(&value)?.into())
+    }
+
+    /// match against the topic rendered at subscribe time
+    pub fn matches_subscribed(&self, subscribed_topic: &str, topic: &str, body: &[u8]) -> bool {
+        topic_matches(subscribed_topic, topic)
+            && self.body.as_ref().map(|b| b.matches(body)).unwrap_or(true)
+    }
+
+    /// wildcard values from the topic by capture name, # captures the remaining topic
+    pub fn captures(&self, subscribed_topic: &str, topic: &str) -> IndexMap<String, String> {
+        let mut received = topic.split('/');
+        let wildcards = subscribed_topic
+            .split('/')
+            .filter_map(|expected| match expected {
+                "+" => received.next().map(str::to_string),
+                "#" => Some(received.by_ref().collect::<Vec<&str>>().join("/")),
+                _ => {
+                    received.next();
+                    None
+                }
+            });
+        self.captures.iter().cloned().zip(wildcards).collect()
+    }
+}
+
+/// mqtt filter matching level by level, + matches exactly one level, # must be last and matches the remaining levels
+pub fn topic_matches(filter: &str, topic: &str) -> bool {
+    let mut levels = topic.split('/');
+    let mut expected_levels = filter.split('/').peekable();
+    while let Some(expected) = expected_levels.next() {
+        if expected == "#" {
+            return expected_levels.peek().is_none();
+        }
+        match levels.next() {
+            Some(received) if expected == "+" || expected == received => continue,
+            _ => return false,
+        }
     }
+    levels.next().is_none()
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -34,13 +99,34 @@ impl MqttSubscribeEvent {
 pub enum MqttBodyMatch {
     Body(String),
     BodyContains(String),
+    BodyJson(JsonMatch),
 }
 
 impl MqttBodyMatch {
-    fn matches(&self, body: &[u8]) -> bool {
+    pub fn matches(&self, body: &[u8]) -> bool {
         match self {
             Self::Body(b) => Ok(b.as_str()) == from_utf8(body),
             Self::BodyContains(b) => from_utf8(body).map(|r| r.contains(b)).unwrap_or_default(),
+            Self::BodyJson(j) => serde_json::from_slice(body)
+                .map(|v| j.matches(&v))
+                .unwrap_or_default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JsonMatch {
+    pub path: JsonPath,
+    /// match any value if not specified
+    pub equals: Option<Value>,
+}
+
+impl JsonMatch {
+    pub fn matches(&self, value: &Value) -> bool {
+        let nodes = self.path.query(value);
+        match &self.equals {
+            Some(expected) => nodes.iter().any(|v| *v == expected),
+            None => !nodes.is_empty(),
         }
     }
 }
@@ -59,6 +145,10 @@ mod tests {
                     topic: "topic1".to_string(),
                     body: MqttBodyMatch::Body("payload".to_string()).into(),
                     pool_id: Default::default(),
+                    qos: None,
+                    captures: Vec::new(),
+                    encoding: None,
+                    protobuf: None,
                 },
                 true,
             ),
@@ -69,6 +159,10 @@ mod tests {
                     topic: "topic2".to_string(),
                     body: MqttBodyMatch::BodyContains("payload".to_string()).into(),
                     pool_id: Default::default(),
+                    qos: None,
+                    captures: Vec::new(),
+                    encoding: None,
+                    protobuf: None,
                 },
                 true,
             ),
@@ -79,6 +173,10 @@ mod tests {
                     topic: "topic3/#".to_string(),
                     body: MqttBodyMatch::BodyContains("payload".to_string()).into(),
                     pool_id: Default::default(),
+                    qos: None,
+                    captures: Vec::new(),
+                    encoding: None,
+                    protobuf: None,
                 },
                 true,
             ),
@@ -89,6 +187,10 @@ mod tests {
                     topic: "topic4/#".to_string(),
                     body: MqttBodyMatch::BodyContains("payload".to_string()).into(),
                     pool_id: Default::default(),
+                    qos: None,
+                    captures: Vec::new(),
+                    encoding: None,
+                    protobuf: None,
                 },
                 false,
             ),
@@ -99,6 +201,10 @@ mod tests {
                     topic: "topic5/#".to_string(),
                     body: MqttBodyMatch::BodyContains("payload".to_string()).into(),
                     pool_id: Default::default(),
+                    qos: None,
+                    captures: Vec::new(),
+                    encoding: None,
+                    protobuf: None,
                 },
                 false,
             ),
@@ -109,6 +215,10 @@ mod tests {
                     topic: "#".to_string(),
                     body: MqttBodyMatch::BodyContains("payload".to_string()).into(),
                     pool_id: Default::default(),
+                    qos: None,
+                    captures: Vec::new(),
+                    encoding: None,
+                    protobuf: None,
                 },
                 true,
             ),
@@ -119,6 +229,10 @@ mod tests {
                     topic: "topic1/+/hello/+".to_string(),
                     body: MqttBodyMatch::Body("payload".to_string()).into(),
                     pool_id: Default::default(),
+                    qos: None,
+                    captures: Vec::new(),
+                    encoding: None,
+                    protobuf: None,
                 },
                 true,
             ),
@@ -129,6 +243,10 @@ mod tests {
                     topic: "+/hello".to_string(),
                     body: MqttBodyMatch::Body("payload".to_string()).into(),
                     pool_id: Default::default(),
+                    qos: None,
+                    captures: Vec::new(),
+                    encoding: None,
+                    protobuf: None,
                 },
                 false,
             ),
@@ -139,6 +257,10 @@ mod tests {
                     topic: "+/+/hello/peter".to_string(),
                     body: MqttBodyMatch::Body("payload".to_string()).into(),
                     pool_id: Default::default(),
+                    qos: None,
+                    captures: Vec::new(),
+                    encoding: None,
+                    protobuf: None,
                 },
                 true,
             ),
@@ -149,6 +271,10 @@ mod tests {
                     topic: "+/+/hello/peter".to_string(),
                     body: None,
                     pool_id: Default::default(),
+                    qos: None,
+                    captures: Vec::new(),
+                    encoding: None,
+                    protobuf: None,
                 },
                 true,
             ),
@@ -157,4 +283,78 @@ mod tests {
             assert_eq!(event.matches(topic, body), equal, "{topic}");
         }
     }
+
+    #[test]
+    fn test_matches_body_json() {
+        let yaml = r#"
+            topic: zigbee2mqtt/button
+            body_json:
+                path: $.action
+                equals: single
+        "#;
+        let event: MqttSubscribeEvent = serde_yaml::from_str(yaml).unwrap();
+        let data = [
+            (r#"{"action":"single","battery":100}"#, true),
+            (r#"{"action":"double","battery":100}"#, false),
+            (r#"{"battery":100}"#, false),
+            ("single", false),
+        ];
+        for (body, expected) in data {
+            assert_eq!(
+                event.matches("zigbee2mqtt/button", body.as_bytes()),
+                expected,
+                "{body}"
+            );
+        }
+
+        let yaml = r#"
+            topic: zigbee2mqtt/sensor
+            body_json:
+                path: $.occupancy
+        "#;
+        let event: MqttSubscribeEvent = serde_yaml::from_str(yaml).unwrap();
+        assert!(event.matches("zigbee2mqtt/sensor", br#"{"occupancy":false}"#));
+        assert!(!event.matches("zigbee2mqtt/sensor", br#"{"temperature":20}"#));
+    }
+
+    #[test]
+    fn test_topic_matches() {
+        let data = [
+            ("home/+/+/#", "home/kitchen/lamp/state/power", true),
+            ("home/+/+/#", "home/kitchen/lamp", true),
+            ("home/+/+/#", "home/kitchen", false),
+            ("home/+", "home/a", true),
+            ("home/+", "home/a/b/c", false),
+            ("home/+", "home", false),
+            ("home/#", "home", true),
+            ("home/#/state", "home/kitchen/state", false),
+            ("#", "home/kitchen", true),
+            ("home/kitchen", "home/kitchen/lamp", false),
+        ];
+        for (filter, topic, expected) in data {
+            assert_eq!(topic_matches(filter, topic), expected, "{filter} {topic}");
+        }
+    }
+
+    #[test]
+    fn test_captures() {
+        let event = MqttSubscribeEvent {
+            topic: "home/+/+/#".to_string(),
+            captures: vec!["room".to_string(), "device".to_string(), "rest".to_string()],
+            ..Default::default()
+        };
+        let captures = event.captures(&event.topic, "home/kitchen/lamp/state/power");
+        assert_eq!(captures["room"], "kitchen");
+        assert_eq!(captures["device"], "lamp");
+        assert_eq!(captures["rest"], "state/power");
+
+        let event = MqttSubscribeEvent {
+            topic: "home/+/status".to_string(),
+            captures: vec!["room".to_string(), "unused".to_string()],
+            ..Default::default()
+        };
+        let captures = event.captures(&event.topic, "home/hall/status");
+        assert_eq!(captures.len(), 1);
+        assert_eq!(captures["room"], "hall");
+    }
 }
@@ -0,0 +1,21 @@
+use serde::{Deserialize, Serialize};
+
+use crate::config::PoolId;
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct NatsRequestEvent {
+    /// template
+    pub subject: String,
+    /// template, event data is sent if not defined
+    pub body: Option<String>,
+    /// seconds to wait for a response
+    #[serde(default = "default_timeout")]
+    pub timeout: u64,
+    /// the first nats server is used if empty
+    #[serde(default)]
+    pub pool_id: PoolId,
+}
+
+pub fn default_timeout() -> u64 {
+    5
+}
@@ -0,0 +1,27 @@
+use serde::{Deserialize, Serialize};
+
+use crate::config::PoolId;
+
+use super::mqtt_subscribe::MqttBodyMatch;
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct NatsSubscribeEvent {
+    /// subject with optional wildcards e.g. sensors.*.temperature or sensors.>
+    pub subject: String,
+    /// messages are delivered to a single member of the queue group
+    pub queue_group: Option<String>,
+    #[serde(flatten)]
+    pub body: Option<MqttBodyMatch>,
+    /// the first nats server is used if empty
+    #[serde(default)]
+    pub pool_id: PoolId,
+}
+
+impl NatsSubscribeEvent {
+    /// messages are matched by the subscription they were received with
+    pub fn matches(&self, subject: &str, queue_group: Option<&str>, body: &[u8]) -> bool {
+        self.subject == subject
+            && self.queue_group.as_deref() == queue_group
+            && self.body.as_ref().map(|b| b.matches(body)).unwrap_or(true)
+    }
+}
@@ -0,0 +1,91 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+
+use crate::config::PoolId;
+
+use super::api_call::{ApiCallEvent, ApiCallRetry, RequestContent, RequestMethod};
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum NotifyProvider {
+    Slack,
+    Discord,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NotifyEvent {
+    pub provider: NotifyProvider,
+    /// incoming webhook url template
+    pub url: String,
+    /// message template, event data is sent if not defined
+    pub text: Option<String>,
+    /// overrides the name configured for the webhook
+    pub username: Option<String>,
+    /// api client used for the request
+    #[serde(default)]
+    pub pool_id: PoolId,
+    #[serde(default)]
+    pub retry: ApiCallRetry,
+}
+
+impl NotifyEvent {
+    pub fn payload(&self, text: &str) -> Value {
+        let mut payload = Map::new();
+        let key = match self.provider {
+            NotifyProvider::Slack => "text",
+            NotifyProvider::Discord => "content",
+        };
+        payload.insert(key.to_string(), json!(text));
+        if let Some(username) = &self.username {
+            payload.insert("username".to_string(), json!(username));
+        }
+        Value::Object(payload)
+    }
+
+    /// webhook request posting the rendered text
+    pub fn api_call(&self, url: String, text: &str) -> ApiCallEvent {
+        ApiCallEvent {
+            url,
+            method: RequestMethod::Post,
+            request_content: RequestContent::Json,
+            body: Some(self.payload(text).to_string()),
+            pool_id: self.pool_id.clone(),
+            retry: Some(self.retry.clone()),
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_payload() {
+        let event: NotifyEvent = serde_yaml::from_str(
+            r#"
+provider: discord
+url: https://discord.com/api/webhooks/1/token
+username: hvents
+"#,
+        )
+        .unwrap();
+        assert_eq!(event.retry.attempts, 3);
+        assert_eq!(
+            event.payload("Water \"leak\""),
+            json!({"content": "Water \"leak\"", "username": "hvents"})
+        );
+        let api_call = event.api_call(event.url.clone(), "Water leak");
+        assert_eq!(
+            api_call.body.as_deref(),
+            Some(r#"{"content":"Water leak","username":"hvents"}"#)
+        );
+
+        let event = NotifyEvent {
+            provider: NotifyProvider::Slack,
+            username: None,
+            ..event
+        };
+        assert_eq!(event.payload("Door open"), json!({"text": "Door open"}));
+    }
+}
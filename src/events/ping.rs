@@ -0,0 +1,37 @@
+use core::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::PoolId;
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct PingEvent {
+    /// transitions of any pinged host are matched if empty
+    #[serde(default)]
+    pub pool_id: PoolId,
+    /// any transition matches if not specified
+    pub when: Option<PingState>,
+}
+
+impl PingEvent {
+    pub fn matches(&self, pool_id: &str, state: PingState) -> bool {
+        (self.pool_id.is_empty() || self.pool_id == pool_id)
+            && self.when.is_none_or(|when| when == state)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum PingState {
+    Up,
+    Down,
+}
+
+impl Display for PingState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PingState::Up => write!(f, "up"),
+            PingState::Down => write!(f, "down"),
+        }
+    }
+}
@@ -0,0 +1,97 @@
+use core::time::Duration;
+use std::{
+    net::{TcpStream, ToSocketAddrs},
+    time::Instant,
+};
+
+use log::debug;
+use serde::{Deserialize, Serialize};
+
+use super::EventName;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PortCheckEvent {
+    /// template e.g. "{{data.host}}"
+    pub host: String,
+    pub port: u16,
+    /// seconds to wait for the connection
+    #[serde(default = "default_timeout")]
+    pub timeout: u64,
+    /// next event if the port is open, next_event is used if not specified
+    pub on_open: Option<EventName>,
+    /// next event if the port is closed, next_event is used if not specified
+    pub on_closed: Option<EventName>,
+}
+
+impl Default for PortCheckEvent {
+    fn default() -> Self {
+        Self {
+            host: Default::default(),
+            port: Default::default(),
+            timeout: default_timeout(),
+            on_open: None,
+            on_closed: None,
+        }
+    }
+}
+
+impl PortCheckEvent {
+    /// time to connect if the port is open
+    pub fn check(&self, host: &str) -> Option<Duration> {
+        let timeout = Duration::from_secs(self.timeout);
+        let addresses = match (host, self.port).to_socket_addrs() {
+            Ok(a) => a,
+            Err(e) => {
+                debug!("Unable to resolve {host} {e}");
+                return None;
+            }
+        };
+        addresses.into_iter().find_map(|address| {
+            let started = Instant::now();
+            match TcpStream::connect_timeout(&address, timeout) {
+                Ok(_) => Some(started.elapsed()),
+                Err(e) => {
+                    debug!("Port check {address} failed {e}");
+                    None
+                }
+            }
+        })
+    }
+
+    /// event to execute next for the port state
+    pub fn state_event(&self, open: bool) -> Option<&EventName> {
+        if open {
+            self.on_open.as_ref()
+        } else {
+            self.on_closed.as_ref()
+        }
+    }
+}
+
+fn default_timeout() -> u64 {
+    1
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::TcpListener;
+
+    use super::*;
+
+    #[test]
+    fn test_check() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let event = PortCheckEvent {
+            port: listener.local_addr().unwrap().port(),
+            on_open: Some("open".to_string()),
+            ..Default::default()
+        };
+        assert!(event.check("127.0.0.1").is_some());
+        assert_eq!(event.state_event(true).unwrap(), "open");
+        assert_eq!(event.state_event(false), None);
+
+        drop(listener);
+        assert!(event.check("127.0.0.1").is_none());
+        assert!(event.check("host.invalid").is_none());
+    }
+}
@@ -1,23 +1,61 @@
-use serde::{Deserialize, Serialize};
+use std::io::{stderr, stdout, Write};
 
-use super::data::Data;
+use log::{log, Level};
+use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
-pub struct PrintEvent(Output);
+pub struct PrintEvent {
+    /// event.data is printed as text if not specified
+    pub template: Option<String>,
+    #[serde(default)]
+    pub output: Output,
+    /// level used by the log output
+    #[serde(default)]
+    pub level: LogLevel,
+}
 
 impl PrintEvent {
-    pub fn run(&self, data: &Data) {
-        match self.0 {
-            Output::Stdout => println!("{data:?}"),
-            Output::Stderr => eprintln!("{data:?}"),
+    pub fn run(&self, text: &str) -> std::io::Result<()> {
+        match self.output {
+            Output::Stdout => writeln!(stdout().lock(), "{text}"),
+            Output::Stderr => writeln!(stderr().lock(), "{text}"),
+            Output::Log => {
+                log!(self.level.into(), "{text}");
+                Ok(())
+            }
         }
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq, Eq)]
 #[serde(rename_all = "lowercase")]
-enum Output {
+pub enum Output {
     #[default]
     Stdout,
     Stderr,
+    /// write through the logger filtered by RUST_LOG
+    Log,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Error,
+    Warn,
+    #[default]
+    Info,
+    Debug,
+    Trace,
+}
+
+impl From<LogLevel> for Level {
+    fn from(level: LogLevel) -> Self {
+        match level {
+            LogLevel::Error => Level::Error,
+            LogLevel::Warn => Level::Warn,
+            LogLevel::Info => Level::Info,
+            LogLevel::Debug => Level::Debug,
+            LogLevel::Trace => Level::Trace,
+        }
+    }
 }
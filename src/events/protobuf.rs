@@ -0,0 +1,134 @@
+use std::path::PathBuf;
+#[cfg(feature = "protobuf")]
+use std::sync::OnceLock;
+
+#[cfg(feature = "protobuf")]
+use anyhow::Context;
+#[cfg(feature = "protobuf")]
+use prost_reflect::{DescriptorPool, DynamicMessage, MessageDescriptor, SerializeOptions};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// protobuf message decoded to json, requires the protobuf feature
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct ProtobufMessage {
+    /// descriptor set file e.g. protoc --include_imports --descriptor_set_out=sensor.desc sensor.proto
+    pub descriptor: PathBuf,
+    /// fully qualified message name e.g. esp.SensorReading
+    pub message: String,
+    #[cfg(feature = "protobuf")]
+    #[serde(skip)]
+    loaded: OnceLock<MessageDescriptor>,
+}
+
+impl ProtobufMessage {
+    /// json with proto field names
+    #[cfg(feature = "protobuf")]
+    pub fn decode(&self, bytes: &[u8]) -> anyhow::Result<Value> {
+        let message = DynamicMessage::decode(self.load()?, bytes)?;
+        let options = SerializeOptions::new().use_proto_field_name(true);
+        Ok(message.serialize_with_options(serde_json::value::Serializer, &options)?)
+    }
+
+    #[cfg(not(feature = "protobuf"))]
+    pub fn decode(&self, _bytes: &[u8]) -> anyhow::Result<Value> {
+        anyhow::bail!("Protobuf decoding requires the protobuf feature")
+    }
+
+    /// descriptor is read once and reused by further calls
+    #[cfg(feature = "protobuf")]
+    pub fn load(&self) -> anyhow::Result<MessageDescriptor> {
+        if let Some(descriptor) = self.loaded.get() {
+            return Ok(descriptor.clone());
+        }
+        let bytes = std::fs::read(&self.descriptor).with_context(|| {
+            format!(
+                "Unable to read descriptor set {}",
+                self.descriptor.display()
+            )
+        })?;
+        let pool = DescriptorPool::decode(bytes.as_slice())
+            .with_context(|| format!("Invalid descriptor set {}", self.descriptor.display()))?;
+        let descriptor = pool.get_message_by_name(&self.message).with_context(|| {
+            format!(
+                "Message {} not found in {}",
+                self.message,
+                self.descriptor.display()
+            )
+        })?;
+        Ok(self.loaded.get_or_init(|| descriptor).clone())
+    }
+}
+
+#[cfg(all(test, feature = "protobuf"))]
+mod tests {
+    use std::env::temp_dir;
+
+    use prost_reflect::{
+        prost::Message,
+        prost_types::{
+            field_descriptor_proto::{Label, Type},
+            DescriptorProto, FieldDescriptorProto, FileDescriptorProto, FileDescriptorSet,
+        },
+        Value as ProtobufValue,
+    };
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_decode() {
+        let field = |name: &str, number: i32, field_type: Type| FieldDescriptorProto {
+            name: Some(name.to_string()),
+            number: Some(number),
+            label: Some(Label::Optional as i32),
+            r#type: Some(field_type as i32),
+            json_name: Some(name.to_string()),
+            ..Default::default()
+        };
+        let set = FileDescriptorSet {
+            file: vec![FileDescriptorProto {
+                name: Some("sensor.proto".to_string()),
+                package: Some("esp".to_string()),
+                syntax: Some("proto3".to_string()),
+                message_type: vec![DescriptorProto {
+                    name: Some("SensorReading".to_string()),
+                    field: vec![
+                        field("device_id", 1, Type::String),
+                        field("temperature", 2, Type::Float),
+                        field("battery", 3, Type::Uint32),
+                    ],
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }],
+        };
+        let path = temp_dir().join(format!("hvents_test_{}.desc", std::process::id()));
+        std::fs::write(&path, set.encode_to_vec()).unwrap();
+
+        let message: ProtobufMessage = serde_yaml::from_str(&format!(
+            "descriptor: {}\nmessage: esp.SensorReading",
+            path.display()
+        ))
+        .unwrap();
+        let mut reading = DynamicMessage::new(message.load().unwrap());
+        reading.set_field_by_name("device_id", ProtobufValue::String("garage".to_string()));
+        reading.set_field_by_name("temperature", ProtobufValue::F32(21.5));
+        reading.set_field_by_name("battery", ProtobufValue::U32(87));
+
+        let value = message.decode(&reading.encode_to_vec()).unwrap();
+        assert_eq!(
+            value,
+            json!({"device_id": "garage", "temperature": 21.5, "battery": 87})
+        );
+        assert!(message.decode(b"\xff\xff").is_err());
+
+        let missing: ProtobufMessage = serde_yaml::from_str(&format!(
+            "descriptor: {}\nmessage: esp.Missing",
+            path.display()
+        ))
+        .unwrap();
+        assert!(missing.load().is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
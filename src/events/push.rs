@@ -0,0 +1,184 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+
+use crate::config::PoolId;
+
+use super::api_call::{ApiCallEvent, ApiCallRetry, RequestContent, RequestMethod};
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(tag = "provider", rename_all = "lowercase")]
+pub enum PushProvider {
+    Ntfy {
+        /// server url
+        #[serde(default = "default_ntfy_url")]
+        url: String,
+        topic: String,
+        /// access token for protected topics
+        token: Option<String>,
+    },
+    Gotify {
+        /// server url
+        url: String,
+        /// application token
+        token: String,
+    },
+    Pushover {
+        /// application token
+        token: String,
+        /// user or group key
+        user: String,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PushEvent {
+    #[serde(flatten)]
+    pub provider: PushProvider,
+    /// template
+    pub title: Option<String>,
+    /// template, event data is sent if not defined
+    pub message: Option<String>,
+    /// template rendering a number in the provider range e.g. 1-5 for ntfy
+    pub priority: Option<String>,
+    /// template rendering the url opened when the notification is clicked
+    pub click: Option<String>,
+    /// api client used for the request
+    #[serde(default)]
+    pub pool_id: PoolId,
+    #[serde(default)]
+    pub retry: ApiCallRetry,
+}
+
+/// rendered notification
+#[derive(Debug, Clone, Default)]
+pub struct PushMessage {
+    pub title: Option<String>,
+    pub message: String,
+    pub priority: Option<i64>,
+    pub click: Option<String>,
+}
+
+impl PushEvent {
+    /// provider request posting the message
+    pub fn api_call(&self, message: &PushMessage) -> ApiCallEvent {
+        let mut headers = Default::default();
+        let mut payload = Map::new();
+        payload.insert("message".to_string(), json!(message.message));
+        if let Some(title) = &message.title {
+            payload.insert("title".to_string(), json!(title));
+        }
+        if let Some(priority) = message.priority {
+            payload.insert("priority".to_string(), json!(priority));
+        }
+        let url = match &self.provider {
+            PushProvider::Ntfy { url, topic, token } => {
+                payload.insert("topic".to_string(), json!(topic));
+                if let Some(click) = &message.click {
+                    payload.insert("click".to_string(), json!(click));
+                }
+                if let Some(token) = token {
+                    headers = [("Authorization".to_string(), format!("Bearer {token}"))].into();
+                }
+                url.clone()
+            }
+            PushProvider::Gotify { url, token } => {
+                if let Some(click) = &message.click {
+                    payload.insert(
+                        "extras".to_string(),
+                        json!({"client::notification": {"click": {"url": click}}}),
+                    );
+                }
+                headers = [("X-Gotify-Key".to_string(), token.clone())].into();
+                format!("{}/message", url.trim_end_matches('/'))
+            }
+            PushProvider::Pushover { token, user } => {
+                payload.insert("token".to_string(), json!(token));
+                payload.insert("user".to_string(), json!(user));
+                if let Some(click) = &message.click {
+                    payload.insert("url".to_string(), json!(click));
+                }
+                "https://api.pushover.net/1/messages.json".to_string()
+            }
+        };
+        ApiCallEvent {
+            url,
+            headers,
+            method: RequestMethod::Post,
+            request_content: RequestContent::Json,
+            body: Some(Value::Object(payload).to_string()),
+            pool_id: self.pool_id.clone(),
+            retry: Some(self.retry.clone()),
+            ..Default::default()
+        }
+    }
+}
+
+fn default_ntfy_url() -> String {
+    "https://ntfy.sh".to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn body(api_call: &ApiCallEvent) -> Value {
+        serde_json::from_str(api_call.body.as_deref().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_api_call() {
+        let message = PushMessage {
+            title: Some("Garage".to_string()),
+            message: "Door left open".to_string(),
+            priority: Some(4),
+            click: Some("http://home.local/garage".to_string()),
+        };
+
+        let event: PushEvent = serde_yaml::from_str(
+            r#"
+provider: ntfy
+topic: garage
+title: "{{data.name}}"
+"#,
+        )
+        .unwrap();
+        let api_call = event.api_call(&message);
+        assert_eq!(api_call.url, "https://ntfy.sh");
+        assert!(api_call.headers.is_empty());
+        assert_eq!(
+            body(&api_call),
+            json!({"topic": "garage", "message": "Door left open", "title": "Garage", "priority": 4, "click": "http://home.local/garage"})
+        );
+
+        let event: PushEvent = serde_yaml::from_str(
+            r#"
+provider: gotify
+url: http://gotify.local/
+token: app
+"#,
+        )
+        .unwrap();
+        let api_call = event.api_call(&message);
+        assert_eq!(api_call.url, "http://gotify.local/message");
+        assert_eq!(api_call.headers["X-Gotify-Key"], "app");
+        assert_eq!(
+            body(&api_call)["extras"]["client::notification"]["click"]["url"],
+            "http://home.local/garage"
+        );
+
+        let event: PushEvent = serde_yaml::from_str(
+            r#"
+provider: pushover
+token: app
+user: me
+"#,
+        )
+        .unwrap();
+        let api_call = event.api_call(&message);
+        assert_eq!(api_call.url, "https://api.pushover.net/1/messages.json");
+        assert_eq!(
+            body(&api_call),
+            json!({"token": "app", "user": "me", "message": "Door left open", "title": "Garage", "priority": 4, "url": "http://home.local/garage"})
+        );
+    }
+}
@@ -0,0 +1,99 @@
+use anyhow::{anyhow, Context};
+use redis::{Commands, Connection};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::config::PoolId;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum RedisCommand {
+    #[default]
+    Get,
+    Set,
+    Incr,
+    Decr,
+    Expire,
+    Del,
+}
+
+impl RedisCommand {
+    /// commands which have the same effect when run again after a lost reply
+    pub fn is_idempotent(self) -> bool {
+        !matches!(self, Self::Incr | Self::Decr)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct RedisCommandEvent {
+    pub command: RedisCommand,
+    /// template
+    pub key: String,
+    /// template, value for set, amount for incr and decr, seconds for expire
+    pub value: Option<String>,
+    /// seconds until a set key expires
+    pub ttl: Option<u64>,
+    /// the first redis server is used if empty
+    #[serde(default)]
+    pub pool_id: PoolId,
+}
+
+impl RedisCommandEvent {
+    /// get values are parsed as json if possible, nil is returned as null
+    pub fn run(
+        &self,
+        connection: &mut Connection,
+        key: &str,
+        value: Option<&str>,
+    ) -> anyhow::Result<Value> {
+        let number = |default: i64| -> anyhow::Result<i64> {
+            value
+                .map(|v| v.trim().parse())
+                .transpose()
+                .with_context(|| format!("Invalid number {value:?} for {key}"))
+                .map(|v| v.unwrap_or(default))
+        };
+        Ok(match self.command {
+            RedisCommand::Get => {
+                let value: Option<String> = connection.get(key)?;
+                value
+                    .map(|v| serde_json::from_str(&v).unwrap_or(Value::String(v)))
+                    .unwrap_or(Value::Null)
+            }
+            RedisCommand::Set => {
+                let value = value.ok_or_else(|| anyhow!("Value is required to set {key}"))?;
+                match self.ttl {
+                    Some(ttl) => connection.set_ex::<_, _, ()>(key, value, ttl)?,
+                    None => connection.set::<_, _, ()>(key, value)?,
+                }
+                Value::String(value.to_string())
+            }
+            RedisCommand::Incr => connection.incr::<_, _, i64>(key, number(1)?)?.into(),
+            RedisCommand::Decr => connection.decr::<_, _, i64>(key, number(1)?)?.into(),
+            RedisCommand::Expire => connection.expire::<_, bool>(key, number(0)?)?.into(),
+            RedisCommand::Del => connection.del::<_, u64>(key)?.into(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deserialize() {
+        let event: RedisCommandEvent = serde_yaml::from_str(
+            r#"
+command: incr
+key: "counter:{{data.door}}"
+value: "2"
+"#,
+        )
+        .unwrap();
+        assert_eq!(event.command, RedisCommand::Incr);
+        assert_eq!(event.key, "counter:{{data.door}}");
+        assert!(event.ttl.is_none());
+        assert!(!event.command.is_idempotent());
+        assert!(RedisCommand::Set.is_idempotent());
+    }
+}
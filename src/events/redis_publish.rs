@@ -0,0 +1,14 @@
+use serde::{Deserialize, Serialize};
+
+use crate::config::PoolId;
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct RedisPublishEvent {
+    /// template
+    pub channel: String,
+    /// template, event data is published if not defined
+    pub body: Option<String>,
+    /// the first redis server is used if empty
+    #[serde(default)]
+    pub pool_id: PoolId,
+}
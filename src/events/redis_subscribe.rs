@@ -0,0 +1,23 @@
+use serde::{Deserialize, Serialize};
+
+use crate::config::PoolId;
+
+use super::mqtt_subscribe::MqttBodyMatch;
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct RedisSubscribeEvent {
+    /// channel or glob style pattern e.g. sensors.*
+    pub channel: String,
+    #[serde(flatten)]
+    pub body: Option<MqttBodyMatch>,
+    /// the first redis server is used if empty
+    #[serde(default)]
+    pub pool_id: PoolId,
+}
+
+impl RedisSubscribeEvent {
+    /// messages are matched by the pattern they were received with
+    pub fn matches(&self, pattern: &str, body: &[u8]) -> bool {
+        self.channel == pattern && self.body.as_ref().map(|b| b.matches(body)).unwrap_or(true)
+    }
+}
@@ -0,0 +1,298 @@
+use core::time::Duration;
+use std::{
+    fs::{read_dir, read_to_string, File, OpenOptions},
+    io::{self, Read, Write},
+    os::fd::AsRawFd,
+    path::Path,
+    thread::sleep,
+};
+
+use anyhow::{anyhow, bail, Context};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+const W1_DEVICES: &str = "/sys/bus/w1/devices";
+const I2C_SLAVE: libc::c_ulong = 0x0703;
+const BME280_CHIP_ID: u8 = 0x60;
+const BMP280_CHIP_ID: u8 = 0x58;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "sensor", rename_all = "snake_case")]
+pub enum SensorReadEvent {
+    Ds18b20 {
+        /// w1 device id e.g. 28-0316a2794cff, the first sensor is used if not specified
+        id: Option<String>,
+    },
+    Bme280 {
+        /// i2c bus e.g. 1 for /dev/i2c-1
+        #[serde(default = "default_bus")]
+        bus: u8,
+        #[serde(default = "default_bme280_address")]
+        address: u16,
+    },
+}
+
+impl SensorReadEvent {
+    pub fn read(&self) -> anyhow::Result<Value> {
+        match self {
+            SensorReadEvent::Ds18b20 { id } => {
+                let id = match id {
+                    Some(id) => id.clone(),
+                    None => first_w1_device()?,
+                };
+                let path = Path::new(W1_DEVICES).join(&id).join("w1_slave");
+                let content = read_to_string(&path)
+                    .with_context(|| format!("Unable to read {}", path.to_string_lossy()))?;
+                Ok(json!({"id": id, "temperature": parse_w1_slave(&content)?}))
+            }
+            SensorReadEvent::Bme280 { bus, address } => {
+                let mut device = I2cDevice::open(*bus, *address)?;
+                read_bme280(&mut device)
+            }
+        }
+    }
+}
+
+fn first_w1_device() -> anyhow::Result<String> {
+    read_dir(W1_DEVICES)?
+        .filter_map(|e| e.ok())
+        .map(|e| e.file_name().to_string_lossy().to_string())
+        .filter(|name| name.starts_with("28-"))
+        .min()
+        .ok_or_else(|| anyhow!("No ds18b20 sensors found in {W1_DEVICES}"))
+}
+
+/// temperature in celsius from the w1_slave content
+fn parse_w1_slave(content: &str) -> anyhow::Result<f64> {
+    let mut lines = content.lines();
+    if !lines.next().is_some_and(|l| l.ends_with("YES")) {
+        bail!("Ds18b20 crc check failed");
+    }
+    let temperature = lines
+        .next()
+        .and_then(|l| l.split_once("t="))
+        .and_then(|(_, t)| t.trim().parse::<i64>().ok())
+        .ok_or_else(|| anyhow!("Ds18b20 temperature not found"))?;
+    Ok(temperature as f64 / 1000.0)
+}
+
+struct I2cDevice(File);
+
+impl I2cDevice {
+    fn open(bus: u8, address: u16) -> anyhow::Result<Self> {
+        let path = format!("/dev/i2c-{bus}");
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(&path)
+            .with_context(|| format!("Unable to open {path}"))?;
+        // SAFETY: the descriptor is valid while file is alive
+        if unsafe { libc::ioctl(file.as_raw_fd(), I2C_SLAVE, libc::c_ulong::from(address)) } < 0 {
+            return Err(io::Error::last_os_error())
+                .with_context(|| format!("Unable to select i2c address {address:#x}"));
+        }
+        Ok(Self(file))
+    }
+
+    fn read_registers(&mut self, register: u8, buffer: &mut [u8]) -> io::Result<()> {
+        self.0.write_all(&[register])?;
+        self.0.read_exact(buffer)
+    }
+
+    fn write_register(&mut self, register: u8, value: u8) -> io::Result<()> {
+        self.0.write_all(&[register, value])
+    }
+}
+
+/// bme280 or bmp280 readings using a forced measurement
+fn read_bme280(device: &mut I2cDevice) -> anyhow::Result<Value> {
+    let mut chip_id = [0];
+    device.read_registers(0xd0, &mut chip_id)?;
+    let humidity = match chip_id[0] {
+        BME280_CHIP_ID => true,
+        BMP280_CHIP_ID => false,
+        id => bail!("Unsupported chip id {id:#x}"),
+    };
+
+    let mut calibration = Calibration::default();
+    let mut buffer = [0; 26];
+    device.read_registers(0x88, &mut buffer)?;
+    calibration.set_temperature_pressure(&buffer);
+    if humidity {
+        let mut buffer = [0; 7];
+        device.read_registers(0xe1, &mut buffer)?;
+        calibration.set_humidity(calibration.h1, &buffer);
+    }
+
+    // oversampling x1 and forced mode
+    if humidity {
+        device.write_register(0xf2, 0x01)?;
+    }
+    device.write_register(0xf4, 0x25)?;
+    let mut status = [0];
+    for _ in 0..10 {
+        sleep(Duration::from_millis(10));
+        device.read_registers(0xf3, &mut status)?;
+        if status[0] & 0x08 == 0 {
+            break;
+        }
+    }
+
+    let mut raw = [0; 8];
+    device.read_registers(0xf7, &mut raw)?;
+    let adc_p = (u32::from(raw[0]) << 12) | (u32::from(raw[1]) << 4) | (u32::from(raw[2]) >> 4);
+    let adc_t = (u32::from(raw[3]) << 12) | (u32::from(raw[4]) << 4) | (u32::from(raw[5]) >> 4);
+    let adc_h = (u32::from(raw[6]) << 8) | u32::from(raw[7]);
+    let (temperature, t_fine) = calibration.temperature(adc_t);
+    Ok(json!({
+        "temperature": round(temperature),
+        "pressure": round(calibration.pressure(adc_p, t_fine) / 100.0),
+        "humidity": humidity.then(|| round(calibration.humidity(adc_h, t_fine))),
+    }))
+}
+
+fn round(value: f64) -> f64 {
+    (value * 100.0).round() / 100.0
+}
+
+/// compensation parameters from the datasheet
+#[derive(Default)]
+struct Calibration {
+    t1: f64,
+    t2: f64,
+    t3: f64,
+    p: [f64; 9],
+    h1: f64,
+    h2: f64,
+    h3: f64,
+    h4: f64,
+    h5: f64,
+    h6: f64,
+}
+
+impl Calibration {
+    fn set_temperature_pressure(&mut self, buffer: &[u8; 26]) {
+        let unsigned = |i: usize| f64::from(u16::from_le_bytes([buffer[i], buffer[i + 1]]));
+        let signed = |i: usize| f64::from(i16::from_le_bytes([buffer[i], buffer[i + 1]]));
+        self.t1 = unsigned(0);
+        self.t2 = signed(2);
+        self.t3 = signed(4);
+        self.p[0] = unsigned(6);
+        for (i, p) in self.p.iter_mut().enumerate().skip(1) {
+            *p = signed(6 + i * 2);
+        }
+        self.h1 = f64::from(buffer[25]);
+    }
+
+    fn set_humidity(&mut self, h1: f64, buffer: &[u8; 7]) {
+        self.h1 = h1;
+        self.h2 = f64::from(i16::from_le_bytes([buffer[0], buffer[1]]));
+        self.h3 = f64::from(buffer[2]);
+        self.h4 = f64::from((i16::from(buffer[3] as i8) << 4) | i16::from(buffer[4] & 0x0f));
+        self.h5 = f64::from((i16::from(buffer[5] as i8) << 4) | i16::from(buffer[4] >> 4));
+        self.h6 = f64::from(buffer[6] as i8);
+    }
+
+    /// celsius and the fine temperature used by other compensations
+    fn temperature(&self, adc_t: u32) -> (f64, f64) {
+        let adc_t = f64::from(adc_t);
+        let var1 = (adc_t / 16384.0 - self.t1 / 1024.0) * self.t2;
+        let var2 = (adc_t / 131072.0 - self.t1 / 8192.0).powi(2) * self.t3;
+        let t_fine = var1 + var2;
+        (t_fine / 5120.0, t_fine)
+    }
+
+    /// pascal
+    fn pressure(&self, adc_p: u32, t_fine: f64) -> f64 {
+        let p = &self.p;
+        let mut var1 = t_fine / 2.0 - 64000.0;
+        let mut var2 = var1 * var1 * p[5] / 32768.0;
+        var2 += var1 * p[4] * 2.0;
+        var2 = var2 / 4.0 + p[3] * 65536.0;
+        var1 = (p[2] * var1 * var1 / 524288.0 + p[1] * var1) / 524288.0;
+        var1 = (1.0 + var1 / 32768.0) * p[0];
+        if var1 == 0.0 {
+            return 0.0;
+        }
+        let mut pressure = 1048576.0 - f64::from(adc_p);
+        pressure = (pressure - var2 / 4096.0) * 6250.0 / var1;
+        var1 = p[8] * pressure * pressure / 2147483648.0;
+        var2 = pressure * p[7] / 32768.0;
+        pressure + (var1 + var2 + p[6]) / 16.0
+    }
+
+    /// relative humidity percentage
+    fn humidity(&self, adc_h: u32, t_fine: f64) -> f64 {
+        let mut humidity = t_fine - 76800.0;
+        humidity = (f64::from(adc_h) - (self.h4 * 64.0 + self.h5 / 16384.0 * humidity))
+            * (self.h2 / 65536.0
+                * (1.0
+                    + self.h6 / 67108864.0 * humidity * (1.0 + self.h3 / 67108864.0 * humidity)));
+        humidity *= 1.0 - self.h1 * humidity / 524288.0;
+        humidity.clamp(0.0, 100.0)
+    }
+}
+
+fn default_bus() -> u8 {
+    1
+}
+
+fn default_bme280_address() -> u16 {
+    0x76
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_w1_slave() {
+        let content =
+            "72 01 4b 46 7f ff 0e 10 57 : crc=57 YES\n72 01 4b 46 7f ff 0e 10 57 t=23125\n";
+        assert_eq!(parse_w1_slave(content).unwrap(), 23.125);
+        let content =
+            "72 01 4b 46 7f ff 0e 10 57 : crc=57 NO\n72 01 4b 46 7f ff 0e 10 57 t=23125\n";
+        assert!(parse_w1_slave(content).is_err());
+    }
+
+    #[test]
+    fn test_compensation() {
+        // example values from the bmp280 datasheet
+        let calibration = Calibration {
+            t1: 27504.0,
+            t2: 26435.0,
+            t3: -1000.0,
+            p: [
+                36477.0, -10685.0, 3024.0, 2855.0, 140.0, -7.0, 15500.0, -14600.0, 6000.0,
+            ],
+            ..Default::default()
+        };
+        let (temperature, t_fine) = calibration.temperature(519888);
+        assert_eq!(round(temperature), 25.08);
+        assert_eq!(round(calibration.pressure(415148, t_fine)), 100653.27);
+    }
+
+    #[test]
+    fn test_deserialize() {
+        let event: SensorReadEvent = serde_yaml::from_str("sensor: bme280").unwrap();
+        assert!(matches!(
+            event,
+            SensorReadEvent::Bme280 {
+                bus: 1,
+                address: 0x76
+            }
+        ));
+        let event: SensorReadEvent =
+            serde_yaml::from_str("sensor: bme280\nbus: 0\naddress: 0x77").unwrap();
+        assert!(matches!(
+            event,
+            SensorReadEvent::Bme280 {
+                bus: 0,
+                address: 0x77
+            }
+        ));
+        let event: SensorReadEvent =
+            serde_yaml::from_str("sensor: ds18b20\nid: 28-0316a2794cff").unwrap();
+        assert!(matches!(event, SensorReadEvent::Ds18b20 { id: Some(_) }));
+    }
+}
@@ -0,0 +1,21 @@
+use std::io::Write;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{config::PoolId, pools::serial::SerialWriter};
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct SerialWriteEvent {
+    #[serde(default)]
+    pub pool_id: PoolId,
+    /// template, event data is written if not specified
+    pub body: Option<String>,
+}
+
+impl SerialWriteEvent {
+    pub fn write(&self, port: &SerialWriter, payload: &[u8]) -> std::io::Result<()> {
+        let mut port = port.lock().expect("serial port lock");
+        port.write_all(payload)?;
+        port.flush()
+    }
+}
@@ -0,0 +1,50 @@
+use std::{fs::File, io::copy, path::Path};
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+
+use super::ssh_execute::SshConnection;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SftpDirection {
+    Upload,
+    Download,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SftpTransferEvent {
+    #[serde(flatten)]
+    pub connection: SshConnection,
+    pub direction: SftpDirection,
+    /// template
+    pub local: String,
+    /// template
+    pub remote: String,
+}
+
+impl SftpTransferEvent {
+    /// returns the number of bytes transferred
+    pub fn transfer(&self, host: &str, local: &Path, remote: &Path) -> anyhow::Result<u64> {
+        let session = self.connection.session(host)?;
+        let sftp = session.sftp()?;
+        Ok(match self.direction {
+            SftpDirection::Upload => {
+                let mut source = File::open(local)
+                    .with_context(|| format!("Unable to open {}", local.to_string_lossy()))?;
+                let mut destination = sftp
+                    .create(remote)
+                    .with_context(|| format!("Unable to create {}", remote.to_string_lossy()))?;
+                copy(&mut source, &mut destination)?
+            }
+            SftpDirection::Download => {
+                let mut source = sftp
+                    .open(remote)
+                    .with_context(|| format!("Unable to open {}", remote.to_string_lossy()))?;
+                let mut destination = File::create(local)
+                    .with_context(|| format!("Unable to create {}", local.to_string_lossy()))?;
+                copy(&mut source, &mut destination)?
+            }
+        })
+    }
+}
@@ -0,0 +1,101 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde::{Deserialize, Serialize};
+
+use crate::config::PoolId;
+
+use super::api_call::{ApiCallEvent, ApiCallRetry, RequestContent, RequestMethod};
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(tag = "provider", rename_all = "lowercase")]
+pub enum SmsProvider {
+    Twilio {
+        account_sid: String,
+        auth_token: String,
+        /// twilio phone number or messaging service sender
+        from: String,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SmsSendEvent {
+    #[serde(flatten)]
+    pub provider: SmsProvider,
+    /// phone numbers in e.164 format e.g. +37060000000, templates
+    pub to: Vec<String>,
+    /// template, event data is sent if not defined
+    pub body: Option<String>,
+    /// api client used for the request
+    #[serde(default)]
+    pub pool_id: PoolId,
+    #[serde(default)]
+    pub retry: ApiCallRetry,
+}
+
+impl SmsSendEvent {
+    /// provider request sending the message to a single recipient
+    pub fn api_call(&self, to: &str, body: &str) -> ApiCallEvent {
+        let (url, headers, form) = match &self.provider {
+            SmsProvider::Twilio {
+                account_sid,
+                auth_token,
+                from,
+            } => (
+                format!("https://api.twilio.com/2010-04-01/Accounts/{account_sid}/Messages.json"),
+                [(
+                    "Authorization".to_string(),
+                    format!(
+                        "Basic {}",
+                        STANDARD.encode(format!("{account_sid}:{auth_token}"))
+                    ),
+                )]
+                .into(),
+                form_urlencoded::Serializer::new(String::new())
+                    .append_pair("To", to)
+                    .append_pair("From", from)
+                    .append_pair("Body", body)
+                    .finish(),
+            ),
+        };
+        ApiCallEvent {
+            url,
+            headers,
+            method: RequestMethod::Post,
+            request_content: RequestContent::Form,
+            body: Some(form),
+            pool_id: self.pool_id.clone(),
+            retry: Some(self.retry.clone()),
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_api_call() {
+        let event: SmsSendEvent = serde_yaml::from_str(
+            r#"
+provider: twilio
+account_sid: AC123
+auth_token: secret
+from: "+15005550006"
+to:
+  - "{{data.phone}}"
+body: "Water leak in {{data.room}}"
+"#,
+        )
+        .unwrap();
+        let api_call = event.api_call("+37060000000", "Water leak & flood");
+        assert_eq!(
+            api_call.url,
+            "https://api.twilio.com/2010-04-01/Accounts/AC123/Messages.json"
+        );
+        assert_eq!(api_call.headers["Authorization"], "Basic QUMxMjM6c2VjcmV0");
+        assert_eq!(
+            api_call.body.as_deref(),
+            Some("To=%2B37060000000&From=%2B15005550006&Body=Water+leak+%26+flood")
+        );
+    }
+}
@@ -0,0 +1,260 @@
+use core::{str::FromStr, time::Duration};
+
+use anyhow::{anyhow, bail};
+use indexmap::IndexMap;
+use serde::{de, Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use snmp2::{
+    v3::{Auth, AuthProtocol, Cipher, Security},
+    Oid, SyncSession,
+};
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SnmpVersion {
+    V1,
+    #[default]
+    V2c,
+    V3,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SnmpAuthProtocol {
+    #[default]
+    Md5,
+    Sha1,
+    Sha256,
+    Sha512,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SnmpPrivacyProtocol {
+    Des,
+    #[default]
+    Aes128,
+    Aes256,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct SnmpSecurity {
+    pub username: String,
+    /// authentication is not used if not specified
+    pub auth_password: Option<String>,
+    #[serde(default)]
+    pub auth_protocol: SnmpAuthProtocol,
+    /// messages are not encrypted if not specified
+    pub privacy_password: Option<String>,
+    #[serde(default)]
+    pub privacy_protocol: SnmpPrivacyProtocol,
+}
+
+impl SnmpSecurity {
+    fn security(&self) -> Security {
+        let auth_protocol = match self.auth_protocol {
+            SnmpAuthProtocol::Md5 => AuthProtocol::Md5,
+            SnmpAuthProtocol::Sha1 => AuthProtocol::Sha1,
+            SnmpAuthProtocol::Sha256 => AuthProtocol::Sha256,
+            SnmpAuthProtocol::Sha512 => AuthProtocol::Sha512,
+        };
+        let auth = match (&self.auth_password, &self.privacy_password) {
+            (Some(_), Some(password)) => Auth::AuthPriv {
+                cipher: match self.privacy_protocol {
+                    SnmpPrivacyProtocol::Des => Cipher::Des,
+                    SnmpPrivacyProtocol::Aes128 => Cipher::Aes128,
+                    SnmpPrivacyProtocol::Aes256 => Cipher::Aes256,
+                },
+                privacy_password: password.as_bytes().to_vec(),
+            },
+            (Some(_), None) => Auth::AuthNoPriv,
+            (None, _) => Auth::NoAuthNoPriv,
+        };
+        Security::new(
+            self.username.as_bytes(),
+            self.auth_password.as_deref().unwrap_or_default().as_bytes(),
+        )
+        .with_auth(auth)
+        .with_auth_protocol(auth_protocol)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnmpGetEvent {
+    /// template e.g. "{{data.host}}"
+    pub host: String,
+    #[serde(default = "default_port")]
+    pub port: u16,
+    #[serde(default)]
+    pub version: SnmpVersion,
+    /// v1 and v2c community
+    #[serde(default = "default_community")]
+    pub community: String,
+    /// v3 credentials
+    pub security: Option<SnmpSecurity>,
+    /// list of oids or names with oids used as keys in the event data
+    #[serde(deserialize_with = "deserialize_oids")]
+    pub oids: IndexMap<String, String>,
+    /// seconds to wait for the response
+    #[serde(default = "default_timeout")]
+    pub timeout: u64,
+}
+
+impl Default for SnmpGetEvent {
+    fn default() -> Self {
+        Self {
+            host: Default::default(),
+            port: default_port(),
+            version: Default::default(),
+            community: default_community(),
+            security: None,
+            oids: Default::default(),
+            timeout: default_timeout(),
+        }
+    }
+}
+
+impl SnmpGetEvent {
+    /// values by name or oid
+    pub fn get(&self, host: &str) -> anyhow::Result<Value> {
+        let address = (host, self.port);
+        let timeout = Some(Duration::from_secs(self.timeout));
+        let mut session = match self.version {
+            SnmpVersion::V1 => SyncSession::new_v1(address, self.community.as_bytes(), timeout, 0)?,
+            SnmpVersion::V2c => {
+                SyncSession::new_v2c(address, self.community.as_bytes(), timeout, 0)?
+            }
+            SnmpVersion::V3 => {
+                let security = self
+                    .security
+                    .as_ref()
+                    .ok_or_else(|| anyhow!("Please provide security for snmp v3"))?;
+                let mut session = SyncSession::new_v3(address, timeout, 0, security.security())?;
+                session
+                    .init()
+                    .map_err(|e| anyhow!("Snmp v3 init failed {e:?}"))?;
+                session
+            }
+        };
+        let oids = self
+            .oids
+            .values()
+            .map(|o| Oid::from_str(o).map_err(|e| anyhow!("Invalid oid {o} {e:?}")))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let response = session
+            .get_many(&oids.iter().collect::<Vec<_>>())
+            .map_err(|e| anyhow!("Snmp get failed {e:?}"))?;
+        if response.error_status != 0 {
+            bail!(
+                "Snmp get failed with error status {} index {}",
+                response.error_status,
+                response.error_index
+            );
+        }
+        let values: Map<String, Value> = self
+            .oids
+            .keys()
+            .cloned()
+            .zip(response.varbinds.map(|(_, v)| to_json(&v)))
+            .collect();
+        Ok(Value::Object(values))
+    }
+}
+
+fn to_json(value: &snmp2::Value) -> Value {
+    use snmp2::Value as V;
+    match value {
+        V::Boolean(b) => json!(b),
+        V::Integer(i) => json!(i),
+        V::OctetString(s) => match core::str::from_utf8(s) {
+            Ok(s) => json!(s),
+            Err(_) => json!(hex::encode(s)),
+        },
+        V::ObjectIdentifier(o) => json!(o.to_id_string()),
+        V::IpAddress(a) => json!(std::net::Ipv4Addr::from(*a).to_string()),
+        V::Counter32(c) | V::Unsigned32(c) | V::Timeticks(c) => json!(c),
+        V::Counter64(c) => json!(c),
+        V::Opaque(o) => json!(hex::encode(o)),
+        _ => Value::Null,
+    }
+}
+
+fn deserialize_oids<'de, D>(deserializer: D) -> Result<IndexMap<String, String>, D::Error>
+where
+    D: de::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum ListOrMap {
+        List(Vec<String>),
+        Map(IndexMap<String, String>),
+    }
+    let s: ListOrMap = de::Deserialize::deserialize(deserializer)?;
+    Ok(match s {
+        ListOrMap::List(oids) => oids.into_iter().map(|o| (o.clone(), o)).collect(),
+        ListOrMap::Map(oids) => oids,
+    })
+}
+
+fn default_community() -> String {
+    "public".to_string()
+}
+
+fn default_port() -> u16 {
+    161
+}
+
+fn default_timeout() -> u64 {
+    2
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deserialize_oids() {
+        let event: SnmpGetEvent =
+            serde_yaml::from_str("host: ups\noids:\n  - 1.3.6.1.2.1.33.1.2.4.0").unwrap();
+        assert_eq!(
+            event.oids["1.3.6.1.2.1.33.1.2.4.0"],
+            "1.3.6.1.2.1.33.1.2.4.0"
+        );
+        assert_eq!(event.port, 161);
+        assert_eq!(event.version, SnmpVersion::V2c);
+
+        let event: SnmpGetEvent = serde_yaml::from_str(
+            "host: ups\nversion: v3\nsecurity:\n  username: hvents\n  auth_password: secret\noids:\n  charge: 1.3.6.1.2.1.33.1.2.4.0",
+        )
+        .unwrap();
+        assert_eq!(event.oids["charge"], "1.3.6.1.2.1.33.1.2.4.0");
+        assert_eq!(event.security.unwrap().auth_protocol, SnmpAuthProtocol::Md5);
+    }
+
+    #[test]
+    fn test_to_json() {
+        assert_eq!(to_json(&snmp2::Value::Integer(-5)), json!(-5));
+        assert_eq!(to_json(&snmp2::Value::OctetString(b"APC")), json!("APC"));
+        assert_eq!(
+            to_json(&snmp2::Value::OctetString(&[0xff, 0x01])),
+            json!("ff01")
+        );
+        assert_eq!(
+            to_json(&snmp2::Value::IpAddress([192, 168, 1, 1])),
+            json!("192.168.1.1")
+        );
+        assert_eq!(to_json(&snmp2::Value::Timeticks(100)), json!(100));
+        assert_eq!(to_json(&snmp2::Value::NoSuchInstance), Value::Null);
+    }
+
+    #[test]
+    fn test_get_timeout() {
+        let event = SnmpGetEvent {
+            oids: [("uptime".to_string(), "1.3.6.1.2.1.1.3.0".to_string())].into(),
+            timeout: 1,
+            ..Default::default()
+        };
+        // nothing listens on the port
+        assert!(event.get("127.0.0.1").is_err());
+    }
+}
@@ -0,0 +1,170 @@
+use std::{
+    io::Write,
+    path::PathBuf,
+    process::{Command, Stdio},
+};
+
+use anyhow::{anyhow, bail};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum SpeechEngine {
+    #[default]
+    Espeak,
+    /// neural voices, raw audio is played with the player
+    Piper,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpeakEvent {
+    /// template, event data is spoken if not defined
+    pub text: Option<String>,
+    #[serde(default)]
+    pub engine: SpeechEngine,
+    /// overrides the engine executable e.g. espeak-ng
+    pub command: Option<String>,
+    /// espeak voice e.g. en-us
+    pub voice: Option<String>,
+    /// espeak words per minute
+    pub speed: Option<u32>,
+    /// piper voice model e.g. /opt/piper/en_US-lessac-medium.onnx
+    pub model: Option<PathBuf>,
+    /// piper model sample rate
+    #[serde(default = "default_sample_rate")]
+    pub sample_rate: u32,
+    /// plays raw piper audio from stdin
+    #[serde(default = "default_player")]
+    pub player: String,
+}
+
+impl Default for SpeakEvent {
+    fn default() -> Self {
+        Self {
+            text: None,
+            engine: Default::default(),
+            command: None,
+            voice: None,
+            speed: None,
+            model: None,
+            sample_rate: default_sample_rate(),
+            player: default_player(),
+        }
+    }
+}
+
+impl SpeakEvent {
+    /// blocks until the text is spoken
+    pub fn speak(&self, text: &str) -> anyhow::Result<()> {
+        match self.engine {
+            SpeechEngine::Espeak => {
+                let mut child = Command::new(self.command.as_deref().unwrap_or("espeak"))
+                    .args(self.espeak_args())
+                    .stdin(Stdio::piped())
+                    .spawn()?;
+                child
+                    .stdin
+                    .take()
+                    .expect("stdin")
+                    .write_all(text.as_bytes())?;
+                check_status("espeak", child.wait()?.success())
+            }
+            SpeechEngine::Piper => {
+                let model = self
+                    .model
+                    .as_ref()
+                    .ok_or_else(|| anyhow!("model must be provided for piper"))?;
+                let mut piper = Command::new(self.command.as_deref().unwrap_or("piper"))
+                    .arg("--model")
+                    .arg(model)
+                    .arg("--output-raw")
+                    .stdin(Stdio::piped())
+                    .stdout(Stdio::piped())
+                    .stderr(Stdio::null())
+                    .spawn()?;
+                let mut player = Command::new(&self.player)
+                    .args(self.player_args())
+                    .stdin(piper.stdout.take().expect("stdout"))
+                    .spawn()?;
+                piper
+                    .stdin
+                    .take()
+                    .expect("stdin")
+                    .write_all(text.as_bytes())?;
+                check_status("piper", piper.wait()?.success())?;
+                check_status(&self.player, player.wait()?.success())
+            }
+        }
+    }
+
+    fn espeak_args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        if let Some(voice) = &self.voice {
+            args.extend(["-v".to_string(), voice.clone()]);
+        }
+        if let Some(speed) = self.speed {
+            args.extend(["-s".to_string(), speed.to_string()]);
+        }
+        args.push("--stdin".to_string());
+        args
+    }
+
+    /// aplay arguments for 16 bit mono audio
+    fn player_args(&self) -> Vec<String> {
+        [
+            "-r",
+            &self.sample_rate.to_string(),
+            "-f",
+            "S16_LE",
+            "-t",
+            "raw",
+            "-",
+        ]
+        .map(ToString::to_string)
+        .to_vec()
+    }
+}
+
+fn check_status(command: &str, success: bool) -> anyhow::Result<()> {
+    if !success {
+        bail!("{command} exited with failure");
+    }
+    Ok(())
+}
+
+fn default_sample_rate() -> u32 {
+    22050
+}
+
+fn default_player() -> String {
+    "aplay".to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_args() {
+        let event: SpeakEvent = serde_yaml::from_str(
+            r#"
+text: "{{data.appliance}} finished"
+voice: en-us
+speed: 150
+"#,
+        )
+        .unwrap();
+        assert_eq!(event.engine, SpeechEngine::Espeak);
+        assert_eq!(event.espeak_args(), ["-v", "en-us", "-s", "150", "--stdin"]);
+        assert_eq!(
+            event.player_args(),
+            ["-r", "22050", "-f", "S16_LE", "-t", "raw", "-"]
+        );
+
+        let event = SpeakEvent {
+            engine: SpeechEngine::Piper,
+            ..event
+        };
+        assert!(event.speak("hello").is_err());
+    }
+}
@@ -0,0 +1,78 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::config::PoolId;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SqlQueryEvent {
+    /// the first database is used if empty
+    #[serde(default)]
+    pub pool_id: PoolId,
+    /// query with placeholders e.g. $1 for postgres and sqlite or ? for mysql
+    pub query: String,
+    /// values bound to the placeholders, strings are templates bound as text
+    #[serde(default)]
+    pub params: Vec<Value>,
+}
+
+/// value bound to a query placeholder
+#[derive(Debug, Clone, PartialEq)]
+pub enum SqlParam {
+    Null,
+    Bool(bool),
+    Integer(i64),
+    Float(f64),
+    Text(String),
+}
+
+impl SqlQueryEvent {
+    pub fn params<E>(
+        &self,
+        render: impl Fn(&str) -> Result<String, E>,
+    ) -> Result<Vec<SqlParam>, E> {
+        self.params
+            .iter()
+            .map(|p| {
+                Ok(match p {
+                    Value::String(template) => SqlParam::Text(render(template)?),
+                    Value::Bool(b) => SqlParam::Bool(*b),
+                    Value::Number(n) => match n.as_i64() {
+                        Some(i) => SqlParam::Integer(i),
+                        None => SqlParam::Float(n.as_f64().unwrap_or_default()),
+                    },
+                    Value::Null => SqlParam::Null,
+                    // arrays and objects are bound as json text
+                    v => SqlParam::Text(v.to_string()),
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_params() {
+        let event: SqlQueryEvent = serde_yaml::from_str(
+            r#"
+query: "INSERT INTO readings (sensor, value, ok, note) VALUES ($1, $2, $3, $4)"
+params: ["{{data.sensor}}", 21.5, true, null]
+"#,
+        )
+        .unwrap();
+        let params = event
+            .params(|t| Ok::<_, ()>(t.replace("{{data.sensor}}", "kitchen")))
+            .unwrap();
+        assert_eq!(
+            params,
+            [
+                SqlParam::Text("kitchen".to_string()),
+                SqlParam::Float(21.5),
+                SqlParam::Bool(true),
+                SqlParam::Null
+            ]
+        );
+    }
+}
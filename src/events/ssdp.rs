@@ -0,0 +1,52 @@
+use core::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+
+/// matches any device or service
+pub const SSDP_ALL: &str = "ssdp:all";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SsdpEvent {
+    /// device or service type e.g. urn:schemas-upnp-org:device:ZonePlayer:1
+    #[serde(default = "default_search_target")]
+    pub search_target: String,
+    /// any change matches if not specified
+    pub when: Option<SsdpState>,
+}
+
+impl Default for SsdpEvent {
+    fn default() -> Self {
+        Self {
+            search_target: default_search_target(),
+            when: None,
+        }
+    }
+}
+
+impl SsdpEvent {
+    /// target is the notification type or search target of the message
+    pub fn matches(&self, target: &str, state: SsdpState) -> bool {
+        (self.search_target == SSDP_ALL || self.search_target == target)
+            && self.when.is_none_or(|when| when == state)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum SsdpState {
+    Alive,
+    Byebye,
+}
+
+impl Display for SsdpState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SsdpState::Alive => write!(f, "alive"),
+            SsdpState::Byebye => write!(f, "byebye"),
+        }
+    }
+}
+
+fn default_search_target() -> String {
+    SSDP_ALL.to_string()
+}
@@ -0,0 +1,139 @@
+use core::time::Duration;
+use std::{
+    io::Read,
+    net::{TcpStream, ToSocketAddrs},
+    path::PathBuf,
+};
+
+use anyhow::{anyhow, bail, Context};
+use serde::{Deserialize, Serialize};
+use ssh2::{CheckResult, KnownHostFileKind, Session};
+
+use super::data::{Data, DataType};
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct SshConnection {
+    /// template
+    pub host: String,
+    #[serde(default = "default_port")]
+    pub port: u16,
+    pub user: String,
+    pub password: Option<String>,
+    /// private key file, ssh agent is used if neither password nor key are specified
+    pub key: Option<PathBuf>,
+    pub key_passphrase: Option<String>,
+    /// host keys are verified against the file if specified e.g. /home/pi/.ssh/known_hosts
+    pub known_hosts: Option<PathBuf>,
+    /// seconds
+    #[serde(default = "default_timeout")]
+    pub timeout: u64,
+}
+
+impl SshConnection {
+    pub fn session(&self, host: &str) -> anyhow::Result<Session> {
+        let timeout = Duration::from_secs(self.timeout);
+        let address = (host, self.port)
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| anyhow!("Unable to resolve {host}"))?;
+        let stream = TcpStream::connect_timeout(&address, timeout)?;
+        let mut session = Session::new()?;
+        session.set_tcp_stream(stream);
+        session.set_timeout(timeout.as_millis() as u32);
+        session.handshake()?;
+
+        if let Some(path) = &self.known_hosts {
+            let mut known_hosts = session.known_hosts()?;
+            known_hosts
+                .read_file(path, KnownHostFileKind::OpenSSH)
+                .with_context(|| format!("Unable to read {}", path.to_string_lossy()))?;
+            let (key, _) = session
+                .host_key()
+                .ok_or_else(|| anyhow!("No host key received from {host}"))?;
+            match known_hosts.check_port(host, self.port, key) {
+                CheckResult::Match => (),
+                CheckResult::NotFound => bail!("Host key for {host} not found in known hosts"),
+                CheckResult::Mismatch => bail!("Host key for {host} does not match known hosts"),
+                CheckResult::Failure => bail!("Unable to verify host key for {host}"),
+            }
+        }
+
+        match (&self.key, &self.password) {
+            (Some(key), _) => session.userauth_pubkey_file(
+                &self.user,
+                None,
+                key,
+                self.key_passphrase.as_deref(),
+            )?,
+            (None, Some(password)) => session.userauth_password(&self.user, password)?,
+            (None, None) => session.userauth_agent(&self.user)?,
+        }
+        Ok(session)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SshExecuteEvent {
+    #[serde(flatten)]
+    pub connection: SshConnection,
+    /// template
+    pub command: String,
+    /// how stdout is converted to data
+    #[serde(default)]
+    pub data_type: DataType,
+}
+
+/// remote command result
+pub struct SshOutput {
+    pub stdout: Data,
+    pub stderr: String,
+    pub exit_code: i32,
+}
+
+impl SshExecuteEvent {
+    pub fn run(&self, host: &str, command: &str) -> anyhow::Result<SshOutput> {
+        let session = self.connection.session(host)?;
+        let mut channel = session.channel_session()?;
+        channel.exec(command)?;
+        let mut stdout = Vec::new();
+        channel.read_to_end(&mut stdout)?;
+        let mut stderr = String::new();
+        channel.stderr().read_to_string(&mut stderr)?;
+        channel.wait_close()?;
+        Ok(SshOutput {
+            stdout: Data::from_reader(stdout.as_slice(), self.data_type)?,
+            stderr,
+            exit_code: channel.exit_status()?,
+        })
+    }
+}
+
+fn default_port() -> u16 {
+    22
+}
+
+fn default_timeout() -> u64 {
+    30
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deserialize() {
+        let event: SshExecuteEvent = serde_yaml::from_str(
+            r#"
+host: nas.local
+user: pi
+key: /home/pi/.ssh/id_ed25519
+command: "systemctl is-active {{data.service}}"
+"#,
+        )
+        .unwrap();
+        assert_eq!(event.connection.host, "nas.local");
+        assert_eq!(event.connection.port, 22);
+        assert_eq!(event.connection.timeout, 30);
+        assert_eq!(event.command, "systemctl is-active {{data.service}}");
+    }
+}
@@ -0,0 +1,30 @@
+use serde::{Deserialize, Serialize};
+
+use super::mqtt_subscribe::MqttBodyMatch;
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct StdinReadEvent {
+    #[serde(default)]
+    pub framing: StdinFraming,
+    /// any line matches if not specified
+    #[serde(flatten)]
+    pub body: Option<MqttBodyMatch>,
+}
+
+impl StdinReadEvent {
+    pub fn matches(&self, line: &[u8]) -> bool {
+        (self.framing == StdinFraming::Line
+            || serde_json::from_slice::<serde_json::Value>(line).is_ok())
+            && self.body.as_ref().map(|b| b.matches(line)).unwrap_or(true)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum StdinFraming {
+    /// each line as a string
+    #[default]
+    Line,
+    /// each line parsed as json, other lines are ignored
+    Json,
+}
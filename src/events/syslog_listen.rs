@@ -0,0 +1,304 @@
+use core::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::config::PoolId;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum SyslogSeverity {
+    Emerg,
+    Alert,
+    Crit,
+    Err,
+    Warning,
+    Notice,
+    Info,
+    Debug,
+}
+
+impl SyslogSeverity {
+    fn from_level(level: u8) -> Self {
+        match level {
+            0 => Self::Emerg,
+            1 => Self::Alert,
+            2 => Self::Crit,
+            3 => Self::Err,
+            4 => Self::Warning,
+            5 => Self::Notice,
+            6 => Self::Info,
+            _ => Self::Debug,
+        }
+    }
+}
+
+impl Display for SyslogSeverity {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let s = match self {
+            Self::Emerg => "emerg",
+            Self::Alert => "alert",
+            Self::Crit => "crit",
+            Self::Err => "err",
+            Self::Warning => "warning",
+            Self::Notice => "notice",
+            Self::Info => "info",
+            Self::Debug => "debug",
+        };
+        write!(f, "{s}")
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct SyslogListenEvent {
+    /// messages from any syslog server are matched if empty
+    #[serde(default)]
+    pub pool_id: PoolId,
+    /// e.g. router
+    pub hostname: Option<String>,
+    /// program name e.g. dnsmasq-dhcp
+    pub app_name: Option<String>,
+    /// messages with the same or higher severity match e.g. warning
+    pub severity: Option<SyslogSeverity>,
+    /// any message matches if not specified
+    pub message_contains: Option<String>,
+}
+
+impl SyslogListenEvent {
+    pub fn matches(&self, pool_id: &str, message: &SyslogMessage) -> bool {
+        (self.pool_id.is_empty() || self.pool_id == pool_id)
+            && self
+                .hostname
+                .as_ref()
+                .map(|h| message.hostname.as_ref() == Some(h))
+                .unwrap_or(true)
+            && self
+                .app_name
+                .as_ref()
+                .map(|a| message.app_name.as_ref() == Some(a))
+                .unwrap_or(true)
+            && self.severity.map(|s| message.severity <= s).unwrap_or(true)
+            && self
+                .message_contains
+                .as_ref()
+                .map(|m| message.message.contains(m))
+                .unwrap_or(true)
+    }
+}
+
+/// rfc3164 or rfc5424 message
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SyslogMessage {
+    pub facility: u8,
+    pub severity: SyslogSeverity,
+    pub timestamp: Option<String>,
+    pub hostname: Option<String>,
+    pub app_name: Option<String>,
+    pub proc_id: Option<String>,
+    pub msg_id: Option<String>,
+    pub structured_data: Option<String>,
+    pub message: String,
+}
+
+impl SyslogMessage {
+    /// messages without a valid priority are treated as user.notice
+    pub fn parse(message: &str) -> Self {
+        let message = message.trim_end_matches(['\r', '\n', '\0']);
+        let (priority, rest) = message
+            .strip_prefix('<')
+            .and_then(|m| m.split_once('>'))
+            .and_then(|(p, rest)| Some((p.parse::<u8>().ok()?, rest)))
+            .unwrap_or((13, message));
+        let (facility, severity) = (priority >> 3, SyslogSeverity::from_level(priority & 7));
+        match rest.strip_prefix("1 ") {
+            Some(rest) => Self::parse_rfc5424(facility, severity, rest),
+            None => Self::parse_rfc3164(facility, severity, rest),
+        }
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "facility": self.facility,
+            "severity": self.severity.to_string(),
+            "timestamp": self.timestamp,
+            "hostname": self.hostname,
+            "app_name": self.app_name,
+            "proc_id": self.proc_id,
+            "msg_id": self.msg_id,
+            "structured_data": self.structured_data,
+            "message": self.message,
+        })
+    }
+
+    fn parse_rfc5424(facility: u8, severity: SyslogSeverity, rest: &str) -> Self {
+        let mut parts = rest.splitn(6, ' ');
+        let mut field = || parts.next().filter(|p| *p != "-").map(str::to_string);
+        let (timestamp, hostname, app_name, proc_id, msg_id) =
+            (field(), field(), field(), field(), field());
+        let rest = parts.next().unwrap_or_default();
+        let (structured_data, message) = match rest.strip_prefix('-') {
+            Some(message) => (None, message),
+            None => {
+                let end = structured_data_end(rest);
+                (Some(rest[..end].to_string()), &rest[end..])
+            }
+        };
+        let message = message.strip_prefix(' ').unwrap_or(message);
+        Self {
+            facility,
+            severity,
+            timestamp,
+            hostname,
+            app_name,
+            proc_id,
+            msg_id,
+            structured_data,
+            message: message.trim_start_matches('\u{feff}').to_string(),
+        }
+    }
+
+    fn parse_rfc3164(facility: u8, severity: SyslogSeverity, rest: &str) -> Self {
+        // Mmm dd hh:mm:ss
+        let (timestamp, rest) = match rest.get(..15) {
+            Some(t)
+                if t.as_bytes().get(9) == Some(&b':') && rest.as_bytes().get(15) == Some(&b' ') =>
+            {
+                (Some(t.to_string()), &rest[16..])
+            }
+            _ => (None, rest),
+        };
+        let (hostname, rest) = match (timestamp.is_some(), rest.split_once(' ')) {
+            (true, Some((hostname, rest))) if !hostname.ends_with(':') => {
+                (Some(hostname.to_string()), rest)
+            }
+            _ => (None, rest),
+        };
+        let (tag, message) = match rest.split_once(": ") {
+            Some((tag, message)) if !tag.contains(' ') => (Some(tag), message),
+            _ => (None, rest),
+        };
+        let (app_name, proc_id) = match tag.and_then(|t| t.strip_suffix(']')) {
+            Some(t) => match t.split_once('[') {
+                Some((app_name, proc_id)) => (Some(app_name), Some(proc_id.to_string())),
+                None => (tag, None),
+            },
+            None => (tag, None),
+        };
+        Self {
+            facility,
+            severity,
+            timestamp,
+            hostname,
+            app_name: app_name.map(str::to_string),
+            proc_id,
+            msg_id: None,
+            structured_data: None,
+            message: message.to_string(),
+        }
+    }
+}
+
+/// end of the structured data elements respecting escaped brackets
+fn structured_data_end(data: &str) -> usize {
+    let mut in_element = false;
+    let mut escaped = false;
+    for (i, c) in data.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '[' => in_element = true,
+            ']' => in_element = false,
+            ' ' if !in_element => return i,
+            _ => (),
+        }
+    }
+    data.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_rfc3164() {
+        let message = SyslogMessage::parse(
+            "<30>Oct 11 22:14:15 router dnsmasq-dhcp[312]: DHCPACK(br0) 192.168.1.23 phone\n",
+        );
+        assert_eq!(
+            message,
+            SyslogMessage {
+                facility: 3,
+                severity: SyslogSeverity::Info,
+                timestamp: Some("Oct 11 22:14:15".to_string()),
+                hostname: Some("router".to_string()),
+                app_name: Some("dnsmasq-dhcp".to_string()),
+                proc_id: Some("312".to_string()),
+                msg_id: None,
+                structured_data: None,
+                message: "DHCPACK(br0) 192.168.1.23 phone".to_string(),
+            }
+        );
+
+        let message = SyslogMessage::parse("<12>kernel: link down");
+        assert_eq!(message.severity, SyslogSeverity::Warning);
+        assert_eq!(message.hostname, None);
+        assert_eq!(message.app_name.as_deref(), Some("kernel"));
+        assert_eq!(message.message, "link down");
+
+        let message = SyslogMessage::parse("no priority here");
+        assert_eq!(message.facility, 1);
+        assert_eq!(message.severity, SyslogSeverity::Notice);
+        assert_eq!(message.message, "no priority here");
+    }
+
+    #[test]
+    fn test_parse_rfc5424() {
+        let message = SyslogMessage::parse(
+            r#"<165>1 2003-10-11T22:14:15.003Z ap.local hostapd 1234 ID47 [origin ip="192.168.1.2" note="a\]b"] station connected"#,
+        );
+        assert_eq!(
+            message,
+            SyslogMessage {
+                facility: 20,
+                severity: SyslogSeverity::Notice,
+                timestamp: Some("2003-10-11T22:14:15.003Z".to_string()),
+                hostname: Some("ap.local".to_string()),
+                app_name: Some("hostapd".to_string()),
+                proc_id: Some("1234".to_string()),
+                msg_id: Some("ID47".to_string()),
+                structured_data: Some(r#"[origin ip="192.168.1.2" note="a\]b"]"#.to_string()),
+                message: "station connected".to_string(),
+            }
+        );
+
+        let message = SyslogMessage::parse("<14>1 - - app - - - hello");
+        assert_eq!(message.timestamp, None);
+        assert_eq!(message.hostname, None);
+        assert_eq!(message.app_name.as_deref(), Some("app"));
+        assert_eq!(message.structured_data, None);
+        assert_eq!(message.message, "hello");
+    }
+
+    #[test]
+    fn test_matches() {
+        let message =
+            SyslogMessage::parse("<28>Oct 11 22:14:15 ap hostapd: wlan0: deauthenticated");
+        let event = SyslogListenEvent {
+            hostname: Some("ap".to_string()),
+            severity: Some(SyslogSeverity::Warning),
+            message_contains: Some("deauthenticated".to_string()),
+            ..Default::default()
+        };
+        assert!(event.matches("default", &message));
+        let event = SyslogListenEvent {
+            severity: Some(SyslogSeverity::Err),
+            ..Default::default()
+        };
+        assert!(!event.matches("default", &message));
+        let event = SyslogListenEvent {
+            pool_id: "other".to_string(),
+            ..Default::default()
+        };
+        assert!(!event.matches("default", &message));
+    }
+}
@@ -0,0 +1,40 @@
+use serde::{Deserialize, Serialize};
+
+use crate::config::PoolId;
+
+use super::mqtt_subscribe::MqttBodyMatch;
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct TcpListenEvent {
+    /// messages from any tcp listener are matched if empty
+    #[serde(default)]
+    pub pool_id: PoolId,
+    /// any message matches if not specified
+    #[serde(flatten)]
+    pub body: Option<MqttBodyMatch>,
+}
+
+impl TcpListenEvent {
+    pub fn matches(&self, pool_id: &str, body: &[u8]) -> bool {
+        (self.pool_id.is_empty() || self.pool_id == pool_id)
+            && self.body.as_ref().map(|b| b.matches(body)).unwrap_or(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tcp_listen_matches() {
+        let event = TcpListenEvent::default();
+        assert!(event.matches("default", b"ARM"));
+        assert!(event.matches("alarm", b""));
+
+        let event: TcpListenEvent =
+            serde_yaml::from_str("pool_id: alarm\nbody_contains: ZONE 1").unwrap();
+        assert!(event.matches("alarm", b"ALARM ZONE 1"));
+        assert!(!event.matches("alarm", b"ALARM ZONE 2"));
+        assert!(!event.matches("default", b"ALARM ZONE 1"));
+    }
+}
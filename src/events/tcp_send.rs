@@ -0,0 +1,132 @@
+use core::time::Duration;
+use std::{
+    io::{BufRead, BufReader, ErrorKind, Read, Write},
+    net::{TcpStream, ToSocketAddrs},
+};
+
+use anyhow::{anyhow, bail};
+use serde::{Deserialize, Serialize};
+
+/// longer responses are truncated
+const MAX_RESPONSE_SIZE: u64 = 65536;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TcpSendEvent {
+    /// template e.g. "{{data.projector}}"
+    pub host: String,
+    pub port: u16,
+    /// template, event data is sent if not specified
+    pub body: Option<String>,
+    /// read a response line and use it as event data
+    #[serde(default)]
+    pub response: bool,
+    /// the response must contain the value, the next event is not executed otherwise
+    pub expect: Option<String>,
+    /// seconds to connect, send and read the response
+    #[serde(default = "default_timeout")]
+    pub timeout: u64,
+}
+
+impl Default for TcpSendEvent {
+    fn default() -> Self {
+        Self {
+            host: Default::default(),
+            port: Default::default(),
+            body: None,
+            response: false,
+            expect: None,
+            timeout: default_timeout(),
+        }
+    }
+}
+
+impl TcpSendEvent {
+    /// send the payload returning the response if expected
+    pub fn send(&self, host: &str, payload: &[u8]) -> anyhow::Result<Option<Vec<u8>>> {
+        let timeout = Duration::from_secs(self.timeout);
+        let address = (host, self.port)
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| anyhow!("No address found for {host}"))?;
+        let mut stream = TcpStream::connect_timeout(&address, timeout)?;
+        stream.set_read_timeout(Some(timeout))?;
+        stream.set_write_timeout(Some(timeout))?;
+        stream.write_all(payload)?;
+        if !self.response && self.expect.is_none() {
+            return Ok(None);
+        }
+
+        let mut response = Vec::new();
+        let mut reader = BufReader::new(stream).take(MAX_RESPONSE_SIZE);
+        match reader.read_until(b'\n', &mut response) {
+            Ok(_) => (),
+            // devices may not terminate the response
+            Err(e)
+                if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut)
+                    && !response.is_empty() => {}
+            Err(e) => return Err(e.into()),
+        }
+        let response = response
+            .strip_suffix(b"\n")
+            .map(|r| r.strip_suffix(b"\r").unwrap_or(r))
+            .unwrap_or(&response)
+            .to_vec();
+        if let Some(expect) = &self.expect {
+            if !String::from_utf8_lossy(&response).contains(expect.as_str()) {
+                bail!(
+                    "Unexpected response {} expected {expect}",
+                    String::from_utf8_lossy(&response)
+                );
+            }
+        }
+        Ok(self.response.then_some(response))
+    }
+}
+
+fn default_timeout() -> u64 {
+    5
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{net::TcpListener, thread::spawn};
+
+    use super::*;
+
+    #[test]
+    fn test_send() {
+        let listener = TcpListener::bind("127.0.0.1:13348").unwrap();
+        spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut line = String::new();
+                BufReader::new(&stream).read_line(&mut line).unwrap();
+                let response = match line.as_str() {
+                    "%1POWR ?\r\n" => "%1POWR=1\r\n",
+                    _ => "ERR\r\n",
+                };
+                stream.write_all(response.as_bytes()).unwrap();
+            }
+        });
+
+        let event = TcpSendEvent {
+            host: "127.0.0.1".to_string(),
+            port: 13348,
+            response: true,
+            timeout: 1,
+            ..Default::default()
+        };
+        assert_eq!(
+            event.send("127.0.0.1", b"%1POWR ?\r\n").unwrap(),
+            Some(b"%1POWR=1".to_vec())
+        );
+
+        let event = TcpSendEvent {
+            response: false,
+            expect: Some("=1".to_string()),
+            ..event
+        };
+        assert_eq!(event.send("127.0.0.1", b"%1POWR ?\r\n").unwrap(), None);
+        assert!(event.send("127.0.0.1", b"%1POWR 1\r\n").is_err());
+    }
+}
@@ -9,6 +9,8 @@ use crate::config::{location, now};
 
 pub const COOL_DOWN_DURATION: Duration = Duration::from_millis(3000);
 pub const EXECUTION_PERIOD: Duration = Duration::from_millis(1000);
+/// days after execution time for stored events to be considered stale
+pub const STALE_AFTER_DAYS: u64 = 1;
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct TimeEvent {
@@ -35,6 +37,16 @@ impl TimeEvent {
         self.execute_time = self.execute_time.reset();
         self
     }
+
+    /// daily time events are never stale
+    pub fn stale_at(&self) -> Option<DateTime<Local>> {
+        let execute_time = match &self.execute_time {
+            ExecuteTime::Time(_) => return None,
+            ExecuteTime::DateTime((d, _)) => *d,
+            ExecuteTime::Date((d, _)) => d.and_local_timezone(Local).earliest()?,
+        };
+        execute_time.checked_add_days(Days::new(STALE_AFTER_DAYS))
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -0,0 +1,22 @@
+use serde::{Deserialize, Serialize};
+
+use crate::config::PoolId;
+
+use super::mqtt_subscribe::MqttBodyMatch;
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct UdpListenEvent {
+    /// datagrams from any udp socket are matched if empty
+    #[serde(default)]
+    pub pool_id: PoolId,
+    /// any datagram matches if not specified
+    #[serde(flatten)]
+    pub body: Option<MqttBodyMatch>,
+}
+
+impl UdpListenEvent {
+    pub fn matches(&self, pool_id: &str, body: &[u8]) -> bool {
+        (self.pool_id.is_empty() || self.pool_id == pool_id)
+            && self.body.as_ref().map(|b| b.matches(body)).unwrap_or(true)
+    }
+}
@@ -0,0 +1,22 @@
+use std::net::UdpSocket;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct UdpSendEvent {
+    /// host:port template e.g. 192.168.1.255:9999
+    pub address: String,
+    /// template, event data is sent if not specified
+    pub body: Option<String>,
+    /// allow sending to a broadcast address
+    #[serde(default)]
+    pub broadcast: bool,
+}
+
+impl UdpSendEvent {
+    pub fn send(&self, address: &str, payload: &[u8]) -> std::io::Result<usize> {
+        let socket = UdpSocket::bind("0.0.0.0:0")?;
+        socket.set_broadcast(self.broadcast)?;
+        socket.send_to(payload, address)
+    }
+}
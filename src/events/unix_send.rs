@@ -0,0 +1,96 @@
+use core::time::Duration;
+use std::{
+    io::{BufRead, BufReader, ErrorKind, Read, Write},
+    os::unix::net::{UnixDatagram, UnixStream},
+};
+
+use anyhow::bail;
+use serde::{Deserialize, Serialize};
+
+use crate::config::UnixSocketType;
+
+/// longer responses are truncated
+const MAX_RESPONSE_SIZE: u64 = 65536;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UnixSendEvent {
+    /// template e.g. /run/hvents/lights.sock
+    pub path: String,
+    #[serde(default)]
+    pub socket: UnixSocketType,
+    /// template, event data is sent if not specified
+    pub body: Option<String>,
+    /// read a response line from stream sockets and use it as event data
+    #[serde(default)]
+    pub response: bool,
+    /// the response must contain the value, the next event is not executed otherwise
+    pub expect: Option<String>,
+    /// seconds to connect, send and read the response
+    #[serde(default = "default_timeout")]
+    pub timeout: u64,
+}
+
+impl Default for UnixSendEvent {
+    fn default() -> Self {
+        Self {
+            path: Default::default(),
+            socket: Default::default(),
+            body: None,
+            response: false,
+            expect: None,
+            timeout: default_timeout(),
+        }
+    }
+}
+
+impl UnixSendEvent {
+    /// send the payload returning the response if expected
+    pub fn send(&self, path: &str, payload: &[u8]) -> anyhow::Result<Option<Vec<u8>>> {
+        let timeout = Duration::from_secs(self.timeout);
+        if self.socket == UnixSocketType::Datagram {
+            if self.response || self.expect.is_some() {
+                bail!("Responses are not supported for datagram sockets");
+            }
+            let socket = UnixDatagram::unbound()?;
+            socket.set_write_timeout(Some(timeout))?;
+            socket.send_to(payload, path)?;
+            return Ok(None);
+        }
+        let mut stream = UnixStream::connect(path)?;
+        stream.set_read_timeout(Some(timeout))?;
+        stream.set_write_timeout(Some(timeout))?;
+        stream.write_all(payload)?;
+        if !self.response && self.expect.is_none() {
+            return Ok(None);
+        }
+
+        let mut response = Vec::new();
+        let mut reader = BufReader::new(stream).take(MAX_RESPONSE_SIZE);
+        match reader.read_until(b'\n', &mut response) {
+            Ok(_) => (),
+            // programs may not terminate the response
+            Err(e)
+                if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut)
+                    && !response.is_empty() => {}
+            Err(e) => return Err(e.into()),
+        }
+        let response = response
+            .strip_suffix(b"\n")
+            .map(|r| r.strip_suffix(b"\r").unwrap_or(r))
+            .unwrap_or(&response)
+            .to_vec();
+        if let Some(expect) = &self.expect {
+            if !String::from_utf8_lossy(&response).contains(expect.as_str()) {
+                bail!(
+                    "Unexpected response {} expected {expect}",
+                    String::from_utf8_lossy(&response)
+                );
+            }
+        }
+        Ok(self.response.then_some(response))
+    }
+}
+
+fn default_timeout() -> u64 {
+    5
+}
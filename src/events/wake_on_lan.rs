@@ -0,0 +1,75 @@
+use std::net::UdpSocket;
+
+use anyhow::{anyhow, bail};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WakeOnLanEvent {
+    /// template e.g. 00:11:22:33:44:55 or 00-11-22-33-44-55
+    pub mac: String,
+    /// address to send the magic packet to
+    #[serde(default = "default_broadcast")]
+    pub broadcast: String,
+}
+
+impl Default for WakeOnLanEvent {
+    fn default() -> Self {
+        Self {
+            mac: Default::default(),
+            broadcast: default_broadcast(),
+        }
+    }
+}
+
+impl WakeOnLanEvent {
+    pub fn send(&self, mac: &str) -> anyhow::Result<()> {
+        let packet = magic_packet(mac)?;
+        let socket = UdpSocket::bind("0.0.0.0:0")?;
+        socket.set_broadcast(true)?;
+        socket.send_to(&packet, &self.broadcast)?;
+        Ok(())
+    }
+}
+
+/// 6 bytes of 0xff followed by the mac address repeated 16 times
+fn magic_packet(mac: &str) -> anyhow::Result<Vec<u8>> {
+    let address = mac
+        .trim()
+        .split([':', '-'])
+        .map(|b| u8::from_str_radix(b, 16))
+        .collect::<Result<Vec<u8>, _>>()
+        .map_err(|e| anyhow!("Invalid mac address {mac} {e}"))?;
+    if address.len() != 6 {
+        bail!("Invalid mac address {mac} expected 6 bytes");
+    }
+    let mut packet = vec![0xff; 6];
+    for _ in 0..16 {
+        packet.extend(&address);
+    }
+    Ok(packet)
+}
+
+fn default_broadcast() -> String {
+    "255.255.255.255:9".to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_magic_packet() {
+        let packet = magic_packet("00:11:22:aa:BB:ff").unwrap();
+        assert_eq!(packet.len(), 102);
+        assert_eq!(packet[..6], [0xff; 6]);
+        assert_eq!(packet[6..12], [0x00, 0x11, 0x22, 0xaa, 0xbb, 0xff]);
+        assert_eq!(packet[96..], [0x00, 0x11, 0x22, 0xaa, 0xbb, 0xff]);
+        assert_eq!(
+            magic_packet("00-11-22-aa-bb-ff").unwrap(),
+            magic_packet("00:11:22:aa:bb:ff").unwrap()
+        );
+
+        assert!(magic_packet("00:11:22:aa:bb").is_err());
+        assert!(magic_packet("00:11:22:aa:bb:zz").is_err());
+    }
+}
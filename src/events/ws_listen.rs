@@ -0,0 +1,50 @@
+use std::sync::{mpsc::Sender, Arc, Mutex};
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::PoolId;
+
+pub type WebSocketClients = Arc<Mutex<Vec<WebSocketClient>>>;
+
+/// connected client receiving messages from ws_send events
+pub struct WebSocketClient {
+    pub path: String,
+    pub sender: Sender<Vec<u8>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct WsListenEvent {
+    pub path: String,
+    /// messages from any http server are matched if empty
+    #[serde(default)]
+    pub pool_id: PoolId,
+}
+
+impl WsListenEvent {
+    pub fn matches(&self, pool_id: &str, path: &str) -> bool {
+        (self.pool_id.is_empty() || self.pool_id == pool_id) && path.starts_with(&self.path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ws_listen_matches() {
+        let event = WsListenEvent {
+            path: "/dashboard".to_string(),
+            pool_id: Default::default(),
+        };
+        assert!(event.matches("default", "/dashboard"));
+        assert!(event.matches("other", "/dashboard/1"));
+        assert!(!event.matches("default", "/"));
+
+        let event = WsListenEvent {
+            path: "/".to_string(),
+            pool_id: "other".to_string(),
+        };
+        assert!(event.matches("other", "/dashboard"));
+        assert!(!event.matches("default", "/dashboard"));
+    }
+}
@@ -0,0 +1,81 @@
+use log::debug;
+use serde::{Deserialize, Serialize};
+
+use crate::config::PoolId;
+
+use super::ws_listen::WebSocketClients;
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct WsSendEvent {
+    /// send to clients connected to the path, all clients receive the message if not specified
+    pub path: Option<String>,
+    /// template, event data is sent if not specified
+    pub body: Option<String>,
+    #[serde(default)]
+    pub pool_id: PoolId,
+}
+
+impl WsSendEvent {
+    /// send the payload and drop disconnected clients, returns the number of clients
+    pub fn send(&self, clients: &WebSocketClients, payload: &[u8]) -> usize {
+        let mut clients = clients.lock().expect("websocket clients lock");
+        let mut sent = 0;
+        clients.retain(|c| {
+            if self.path.as_ref().map(|p| p != &c.path).unwrap_or_default() {
+                return true;
+            }
+            if c.sender.send(payload.to_vec()).is_err() {
+                debug!("Websocket client removed path={}", c.path);
+                return false;
+            }
+            sent += 1;
+            true
+        });
+        sent
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc::channel;
+
+    use crate::events::ws_listen::WebSocketClient;
+
+    use super::*;
+
+    #[test]
+    fn test_send() {
+        let clients = WebSocketClients::default();
+        let (sender1, receiver1) = channel();
+        let (sender2, receiver2) = channel();
+        let (sender3, receiver3) = channel();
+        clients.lock().unwrap().extend([
+            WebSocketClient {
+                path: "/a".to_string(),
+                sender: sender1,
+            },
+            WebSocketClient {
+                path: "/b".to_string(),
+                sender: sender2,
+            },
+            WebSocketClient {
+                path: "/a".to_string(),
+                sender: sender3,
+            },
+        ]);
+        drop(receiver3);
+
+        let event = WsSendEvent {
+            path: Some("/a".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(event.send(&clients, b"a"), 1);
+        assert_eq!(receiver1.try_recv().unwrap(), b"a");
+        assert!(receiver2.try_recv().is_err());
+        assert_eq!(clients.lock().unwrap().len(), 2);
+
+        assert_eq!(WsSendEvent::default().send(&clients, b"b"), 2);
+        assert_eq!(receiver1.try_recv().unwrap(), b"b");
+        assert_eq!(receiver2.try_recv().unwrap(), b"b");
+    }
+}
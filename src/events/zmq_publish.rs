@@ -0,0 +1,14 @@
+use serde::{Deserialize, Serialize};
+
+use crate::config::PoolId;
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct ZmqPublishEvent {
+    /// template, sent as a separate first frame
+    pub topic: Option<String>,
+    /// template, event data is published if not defined
+    pub body: Option<String>,
+    /// the first zmq socket is used if empty
+    #[serde(default)]
+    pub pool_id: PoolId,
+}
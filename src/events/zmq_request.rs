@@ -0,0 +1,19 @@
+use serde::{Deserialize, Serialize};
+
+use crate::config::PoolId;
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct ZmqRequestEvent {
+    /// template, event data is sent if not defined
+    pub body: Option<String>,
+    /// seconds to wait for a reply
+    #[serde(default = "default_timeout")]
+    pub timeout: u64,
+    /// the first zmq socket is used if empty
+    #[serde(default)]
+    pub pool_id: PoolId,
+}
+
+fn default_timeout() -> u64 {
+    5
+}
@@ -0,0 +1,24 @@
+use serde::{Deserialize, Serialize};
+
+use crate::config::PoolId;
+
+use super::mqtt_subscribe::MqttBodyMatch;
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct ZmqSubscribeEvent {
+    /// prefix of the first frame, all messages are received if empty
+    #[serde(default)]
+    pub topic: String,
+    #[serde(flatten)]
+    pub body: Option<MqttBodyMatch>,
+    /// the first zmq socket is used if empty
+    #[serde(default)]
+    pub pool_id: PoolId,
+}
+
+impl ZmqSubscribeEvent {
+    pub fn matches(&self, first_frame: &[u8], body: &[u8]) -> bool {
+        first_frame.starts_with(self.topic.as_bytes())
+            && self.body.as_ref().map(|b| b.matches(body)).unwrap_or(true)
+    }
+}
@@ -0,0 +1,228 @@
+use core::time::Duration;
+use std::{
+    sync::mpsc::Sender,
+    thread::{scope, sleep, Builder},
+};
+
+use futures_lite::{future::block_on, StreamExt};
+use indexmap::IndexSet;
+use lapin::{
+    options::{BasicAckOptions, BasicConsumeOptions, BasicQosOptions},
+    types::FieldTable,
+    Consumer,
+};
+use log::{debug, info, warn};
+use serde_json::json;
+
+use crate::{
+    events::{amqp_consume::AmqpAckMode, EventType, Events, ReferencingEvent},
+    pools::amqp::AmqpClient,
+};
+
+/// seconds to wait before reconnecting to the amqp broker
+const RECONNECT_DELAY: u64 = 5;
+/// unacknowledged messages delivered at once
+const PREFETCH: u16 = 10;
+
+/// consumes queues of amqp_consume events for the pool,
+/// events without a pool_id consume from the first pool
+pub fn amqp_executor(
+    pool_id: &str,
+    client: &AmqpClient,
+    first_pool: bool,
+    events: &Events,
+    queue_tx: Sender<ReferencingEvent>,
+) -> anyhow::Result<()> {
+    let queues: IndexSet<&str> = events
+        .iter()
+        .filter_map(|e| match &e.event_type {
+            EventType::AmqpConsume(e)
+                if e.pool_id == pool_id || (e.pool_id.is_empty() && first_pool) =>
+            {
+                Some(e.queue.as_str())
+            }
+            _ => None,
+        })
+        .collect();
+    if queues.is_empty() {
+        return Ok(());
+    }
+    loop {
+        match consume(pool_id, client, &queues, events, &queue_tx) {
+            Ok(()) => return Ok(()),
+            Err(e) => warn!("Amqp consumer for {pool_id} failed {e}"),
+        }
+        sleep(Duration::from_secs(RECONNECT_DELAY));
+    }
+}
+
+/// returns once the queue is closed
+fn consume(
+    pool_id: &str,
+    client: &AmqpClient,
+    queues: &IndexSet<&str>,
+    events: &Events,
+    queue_tx: &Sender<ReferencingEvent>,
+) -> anyhow::Result<()> {
+    let connection = client.connect()?;
+    let channel = block_on(connection.create_channel())?;
+    block_on(channel.basic_qos(PREFETCH, BasicQosOptions::default()))?;
+    let mut consumers = Vec::new();
+    for queue in queues {
+        consumers.push((
+            *queue,
+            block_on(channel.basic_consume(
+                queue,
+                "",
+                BasicConsumeOptions::default(),
+                FieldTable::default(),
+            ))?,
+        ));
+    }
+    info!("Consuming amqp queues {queues:?} for {pool_id}");
+    scope(|s| {
+        let handles = consumers
+            .into_iter()
+            .map(|(queue, consumer)| {
+                Builder::new()
+                    .name(format!("amqp {queue}"))
+                    .spawn_scoped(s, move || {
+                        receive(pool_id, client, queue, consumer, events, queue_tx)
+                    })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        for h in handles {
+            h.join().expect("amqp consumer")?;
+        }
+        Ok(())
+    })
+}
+
+fn receive(
+    pool_id: &str,
+    client: &AmqpClient,
+    queue: &str,
+    mut consumer: Consumer,
+    events: &Events,
+    queue_tx: &Sender<ReferencingEvent>,
+) -> anyhow::Result<()> {
+    while let Some(delivery) = block_on(consumer.next()) {
+        let delivery = delivery?;
+        debug!("Amqp message received from {queue}");
+        match handle_incoming(
+            events,
+            pool_id,
+            queue,
+            delivery.exchange.as_str(),
+            delivery.routing_key.as_str(),
+            &delivery.data,
+        ) {
+            Some((mut event, name, AmqpAckMode::Manual)) => {
+                let delivery_id = client.track(delivery.acker);
+                event
+                    .metadata
+                    .merge(json!({ name: { "delivery_id": delivery_id }}).into());
+                if queue_tx.send(event).is_err() {
+                    return Ok(());
+                }
+            }
+            Some((event, _, AmqpAckMode::Receive)) => {
+                if queue_tx.send(event).is_err() {
+                    return Ok(());
+                }
+                block_on(delivery.acker.ack(BasicAckOptions::default()))?;
+            }
+            // messages without further handlers are dropped
+            None => block_on(delivery.acker.ack(BasicAckOptions::default()))?,
+        }
+    }
+    anyhow::bail!("Amqp consumer for {queue} cancelled")
+}
+
+/// returns the event to execute, the name of the consume event and how to acknowledge the message
+fn handle_incoming<'a>(
+    events: &'a Events,
+    pool_id: &str,
+    queue: &str,
+    exchange: &str,
+    routing_key: &str,
+    payload: &[u8],
+) -> Option<(ReferencingEvent, &'a str, AmqpAckMode)> {
+    let (ref_event, ack) = events
+        .iter()
+        .find_map(|ref_event| match &ref_event.event_type {
+            EventType::AmqpConsume(e) if e.matches(queue, payload) => Some((ref_event, e.ack)),
+            _ => None,
+        })?;
+
+    if let Some(mut event) = events.get_next_event(ref_event) {
+        event.try_merge_bytes(payload);
+        let mut metadata = ref_event.metadata.clone();
+        metadata.merge(
+            json!({ ref_event.name.as_str(): {"pool_id": pool_id, "queue": queue, "exchange": exchange, "routing_key": routing_key }})
+                .into(),
+        );
+        event.metadata.merge(metadata);
+        Some((event, ref_event.name.as_str(), ack))
+    } else {
+        debug!(
+            "Received amqp message {} without further handler",
+            ref_event.name
+        );
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::events::{
+        amqp_consume::AmqpConsumeEvent, data::Data, mqtt_subscribe::MqttBodyMatch, time::TimeEvent,
+        NextEvent,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_handle_incoming() {
+        let events = Events::new(
+            [
+                ReferencingEvent {
+                    event_type: EventType::AmqpConsume(AmqpConsumeEvent {
+                        queue: "orders".to_string(),
+                        ack: AmqpAckMode::Manual,
+                        body: Some(MqttBodyMatch::BodyContains("order_id".to_string())),
+                        ..Default::default()
+                    }),
+                    next_event: NextEvent::Name("test1".to_string()).into(),
+                    name: "order_received".to_string(),
+                    ..Default::default()
+                },
+                ReferencingEvent {
+                    event_type: EventType::Time(TimeEvent {
+                        execute_time: "now".parse().unwrap(),
+                        event_id: None,
+                    }),
+                    name: "test1".to_string(),
+                    ..Default::default()
+                },
+            ]
+            .into_iter()
+            .collect(),
+        );
+        let payload = br#"{"order_id": 7}"#;
+        assert!(handle_incoming(&events, "default", "invoices", "", "invoices", payload).is_none());
+        assert!(handle_incoming(&events, "default", "orders", "", "orders", b"ping").is_none());
+
+        let (event, name, ack) =
+            handle_incoming(&events, "default", "orders", "shop", "orders.new", payload).unwrap();
+        assert_eq!(event.name, "test1");
+        assert_eq!(name, "order_received");
+        assert_eq!(ack, AmqpAckMode::Manual);
+        assert_eq!(event.data, Data::Json(json!({"order_id": 7})));
+        let metadata = serde_json::to_value(&event.metadata).unwrap();
+        assert_eq!(
+            metadata["order_received"]["routing_key"],
+            json!("orders.new")
+        );
+    }
+}
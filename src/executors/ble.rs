@@ -0,0 +1,248 @@
+use std::{
+    collections::HashMap,
+    sync::mpsc::Sender,
+    time::{Duration, Instant},
+};
+
+use anyhow::{anyhow, bail, Context};
+use btleplug::{
+    api::{Central, CentralEvent, Manager as _, Peripheral, PeripheralProperties, ScanFilter},
+    platform::Manager,
+};
+use futures_lite::StreamExt;
+use log::{debug, warn};
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::{
+    config::BleConfiguration,
+    events::{ble::Advertisement, EventType, Events, ReferencingEvent},
+};
+
+/// 16 and 32 bit uuids are shortened from the bluetooth base uuid 0000xxxx-0000-1000-8000-00805f9b34fb
+const BASE_UUID_SUFFIX: u128 = 0x0000_1000_8000_0080_5f9b_34fb;
+const BASE_UUID_MASK: u128 = (1 << 96) - 1;
+
+pub fn ble_executor(
+    config: &BleConfiguration,
+    events: &Events,
+    queue_tx: Sender<ReferencingEvent>,
+) -> anyhow::Result<()> {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+    runtime.block_on(scan(config, events, queue_tx))
+}
+
+/// advertisements are received from bluetoothd over dbus
+async fn scan(
+    config: &BleConfiguration,
+    events: &Events,
+    queue_tx: Sender<ReferencingEvent>,
+) -> anyhow::Result<()> {
+    let manager = Manager::new()
+        .await
+        .context("Unable to connect to bluetooth")?;
+    let adapter = manager
+        .adapters()
+        .await?
+        .into_iter()
+        .nth(config.adapter.into())
+        .ok_or_else(|| anyhow!("Bluetooth adapter hci{} not found", config.adapter))?;
+    let mut stream = adapter.events().await?;
+    adapter
+        .start_scan(ScanFilter::default())
+        .await
+        .with_context(|| {
+            format!(
+                "Unable to scan with bluetooth adapter hci{}",
+                config.adapter
+            )
+        })?;
+
+    let mut last_sent: HashMap<(String, String), Instant> = HashMap::new();
+    while let Some(event) = stream.next().await {
+        let id = match event {
+            CentralEvent::DeviceDiscovered(id)
+            | CentralEvent::DeviceUpdated(id)
+            | CentralEvent::ManufacturerDataAdvertisement { id, .. }
+            | CentralEvent::ServiceDataAdvertisement { id, .. }
+            | CentralEvent::ServicesAdvertisement { id, .. } => id,
+            _ => continue,
+        };
+        let properties = match adapter.peripheral(&id).await {
+            Ok(peripheral) => peripheral.properties().await,
+            Err(e) => Err(e),
+        };
+        let properties = match properties {
+            Ok(Some(p)) => p,
+            Ok(None) => continue,
+            Err(e) => {
+                warn!("Unable to read ble device {id:?} {e}");
+                continue;
+            }
+        };
+        if let Some(e) = handle_incoming(events, &advertisement(properties), &mut last_sent) {
+            queue_tx.send(e)?;
+        }
+    }
+    bail!("Bluetooth event stream closed")
+}
+
+fn handle_incoming(
+    events: &Events,
+    advertisement: &Advertisement,
+    last_sent: &mut HashMap<(String, String), Instant>,
+) -> Option<ReferencingEvent> {
+    let (ref_event, interval) =
+        events
+            .iter()
+            .find_map(|ref_event| match &ref_event.event_type {
+                EventType::Ble(e) if e.matches(advertisement) => Some((ref_event, e.interval)),
+                _ => None,
+            })?;
+
+    let key = (ref_event.name.clone(), advertisement.address.clone());
+    if last_sent
+        .get(&key)
+        .is_some_and(|t| t.elapsed() < Duration::from_secs(interval))
+    {
+        return None;
+    }
+    last_sent.insert(key, Instant::now());
+
+    if let Some(mut event) = events.get_next_event(ref_event) {
+        event.merge(json!(advertisement).into());
+        event.metadata.merge(ref_event.metadata.clone());
+        Some(event)
+    } else {
+        debug!(
+            "Received ble event {} without further handler",
+            ref_event.name
+        );
+        None
+    }
+}
+
+fn advertisement(properties: PeripheralProperties) -> Advertisement {
+    Advertisement {
+        address: properties.address.to_string(),
+        name: properties.local_name,
+        rssi: properties
+            .rssi
+            .map(|r| r.clamp(i8::MIN.into(), i8::MAX.into()) as i8)
+            .unwrap_or_default(),
+        services: properties.services.iter().map(short_uuid).collect(),
+        service_data: properties
+            .service_data
+            .iter()
+            .map(|(k, v)| (short_uuid(k), hex::encode(v)))
+            .collect(),
+        manufacturer_data: properties
+            .manufacturer_data
+            .iter()
+            .map(|(k, v)| (format!("{k:04x}"), hex::encode(v)))
+            .collect(),
+    }
+}
+
+/// formatted as e.g. 181a or 6e400001-b5a3-f393-e0a9-e50e24dcca9e
+fn short_uuid(uuid: &Uuid) -> String {
+    let value = uuid.as_u128();
+    if value & BASE_UUID_MASK != BASE_UUID_SUFFIX {
+        return uuid.to_string();
+    }
+    match value >> 96 {
+        short @ 0..=0xffff => format!("{short:04x}"),
+        short => format!("{short:08x}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use btleplug::api::BDAddr;
+
+    use crate::events::{ble::BleEvent, data::Data, time::TimeEvent, NextEvent};
+
+    use super::*;
+
+    fn properties() -> PeripheralProperties {
+        PeripheralProperties {
+            address: BDAddr::from([0xa4, 0xc1, 0x38, 0x12, 0x34, 0x56]),
+            local_name: Some("ATC_123456".to_string()),
+            rssi: Some(-60),
+            service_data: [(
+                Uuid::from_u128(0x0000_181a_0000_1000_8000_0080_5f9b_34fb),
+                vec![0xa4, 0xc1, 0x38, 0x12, 0x34, 0x56, 0x00, 0xd7],
+            )]
+            .into(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_advertisement() {
+        assert_eq!(
+            advertisement(properties()),
+            Advertisement {
+                address: "A4:C1:38:12:34:56".to_string(),
+                name: Some("ATC_123456".to_string()),
+                rssi: -60,
+                service_data: [("181a".to_string(), "a4c13812345600d7".to_string())].into(),
+                ..Default::default()
+            }
+        );
+        let advertisement = advertisement(PeripheralProperties {
+            manufacturer_data: [(0x004c, vec![0x02, 0x15])].into(),
+            services: vec![
+                Uuid::from_u128(0x0000_fe95_0000_1000_8000_0080_5f9b_34fb),
+                Uuid::from_u128(0x6e40_0001_b5a3_f393_e0a9_e50e_24dc_ca9e),
+            ],
+            ..Default::default()
+        });
+        assert_eq!(advertisement.manufacturer_data["004c"], "0215");
+        assert_eq!(
+            advertisement.services,
+            vec!["fe95", "6e400001-b5a3-f393-e0a9-e50e24dcca9e"]
+        );
+    }
+
+    #[test]
+    fn test_handle_incoming() {
+        let events = Events::new(
+            [
+                ReferencingEvent {
+                    event_type: EventType::Ble(BleEvent {
+                        service: Some("181a".to_string()),
+                        ..Default::default()
+                    }),
+                    next_event: NextEvent::Name("test1".to_string()).into(),
+                    name: "sensor".to_string(),
+                    ..Default::default()
+                },
+                ReferencingEvent {
+                    event_type: EventType::Time(TimeEvent {
+                        execute_time: "now".parse().unwrap(),
+                        event_id: None,
+                    }),
+                    name: "test1".to_string(),
+                    ..Default::default()
+                },
+            ]
+            .into_iter()
+            .collect(),
+        );
+        let mut last_sent = HashMap::new();
+        let advertisement = &advertisement(properties());
+        let event = handle_incoming(&events, advertisement, &mut last_sent).unwrap();
+        assert_eq!(event.name, "test1");
+        let Data::Json(data) = event.data else {
+            panic!("Expected json data");
+        };
+        assert_eq!(data["rssi"], -60);
+        assert_eq!(data["service_data"]["181a"], "a4c13812345600d7");
+
+        // throttled within the interval
+        assert!(handle_incoming(&events, advertisement, &mut last_sent).is_none());
+    }
+}
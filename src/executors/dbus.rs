@@ -0,0 +1,146 @@
+use std::{
+    collections::HashMap,
+    sync::mpsc::Sender,
+    thread::{scope, Builder},
+};
+
+use anyhow::Context;
+use log::{debug, warn};
+use serde_json::json;
+use zbus::{
+    blocking::{Connection, MessageIterator},
+    Message,
+};
+
+use crate::events::{
+    data::Data,
+    dbus_subscribe::{message_args, DbusBus},
+    EventType, Events, ReferencingEvent,
+};
+
+pub fn dbus_executor(events: &Events, queue_tx: Sender<ReferencingEvent>) -> anyhow::Result<()> {
+    let mut connections: HashMap<DbusBus, Connection> = HashMap::new();
+    scope(|s| -> anyhow::Result<()> {
+        for ref_event in events.iter() {
+            let EventType::DbusSubscribe(e) = &ref_event.event_type else {
+                continue;
+            };
+            let connection = match connections.get(&e.bus) {
+                Some(c) => c,
+                None => {
+                    let connection = e
+                        .bus
+                        .connect()
+                        .with_context(|| format!("Unable to connect to dbus {} bus", e.bus))?;
+                    connections.entry(e.bus).or_insert(connection)
+                }
+            };
+            let messages = MessageIterator::for_match_rule(e.match_rule()?, connection, None)
+                .with_context(|| format!("Unable to subscribe to dbus for {}", ref_event.name))?;
+            let queue_tx = queue_tx.clone();
+            Builder::new()
+                .name(format!("dbus {}", ref_event.name))
+                .spawn_scoped(s, move || {
+                    for message in messages {
+                        match message {
+                            Ok(message) => {
+                                if let Some(e) = handle_incoming(events, ref_event, &message) {
+                                    if queue_tx.send(e).is_err() {
+                                        return;
+                                    }
+                                }
+                            }
+                            Err(e) => warn!("Dbus message failed {e}"),
+                        }
+                    }
+                })?;
+        }
+        Ok(())
+    })
+}
+
+fn handle_incoming(
+    events: &Events,
+    ref_event: &ReferencingEvent,
+    message: &Message,
+) -> Option<ReferencingEvent> {
+    let args = match message_args(message) {
+        Ok(a) => a,
+        Err(e) => {
+            warn!("Unable to read dbus signal arguments {e}");
+            return None;
+        }
+    };
+    if let Some(mut event) = events.get_next_event(ref_event) {
+        event.merge(Data::Json(args));
+        let header = message.header();
+        let mut metadata = ref_event.metadata.clone();
+        metadata.merge(
+            json!({ ref_event.name.as_str(): {
+                "sender": header.sender().map(|s| s.to_string()),
+                "path": header.path().map(|p| p.to_string()),
+                "interface": header.interface().map(|i| i.to_string()),
+                "member": header.member().map(|m| m.to_string()),
+            }})
+            .into(),
+        );
+        event.metadata.merge(metadata);
+        Some(event)
+    } else {
+        debug!(
+            "Received dbus event {} without further handler",
+            ref_event.name
+        );
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+
+    use crate::events::{dbus_subscribe::DbusSubscribeEvent, time::TimeEvent, NextEvent};
+
+    use super::*;
+
+    #[test]
+    fn test_handle_incoming() {
+        let events = Events::new(
+            [
+                ReferencingEvent {
+                    event_type: EventType::DbusSubscribe(DbusSubscribeEvent {
+                        member: Some("PrepareForSleep".to_string()),
+                        ..Default::default()
+                    }),
+                    next_event: NextEvent::Name("test1".to_string()).into(),
+                    name: "sleep".to_string(),
+                    ..Default::default()
+                },
+                ReferencingEvent {
+                    event_type: EventType::Time(TimeEvent {
+                        execute_time: "now".parse().unwrap(),
+                        event_id: None,
+                    }),
+                    name: "test1".to_string(),
+                    ..Default::default()
+                },
+            ]
+            .into_iter()
+            .collect(),
+        );
+        let message = Message::signal(
+            "/org/freedesktop/login1",
+            "org.freedesktop.login1.Manager",
+            "PrepareForSleep",
+        )
+        .unwrap()
+        .build(&(true,))
+        .unwrap();
+        let ref_event = events.get_event_by_name("sleep").unwrap();
+        let event = handle_incoming(&events, &ref_event, &message).unwrap();
+        assert_eq!(event.name, "test1");
+        assert_eq!(event.data, Data::Json(json!([true])));
+        let metadata: Value = serde_json::to_value(&event.metadata).unwrap();
+        assert_eq!(metadata["sleep"]["member"], "PrepareForSleep");
+    }
+}
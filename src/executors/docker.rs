@@ -0,0 +1,161 @@
+use core::time::Duration;
+use std::{
+    collections::BTreeSet,
+    io::BufRead,
+    path::Path,
+    sync::mpsc::Sender,
+    thread::{scope, sleep, Builder},
+};
+
+use anyhow::{anyhow, bail};
+use log::{debug, info, warn};
+use serde_json::Value;
+
+use crate::{
+    docker::{error_message, request},
+    events::{data::Data, docker_events::DockerMessage, EventType, Events, ReferencingEvent},
+};
+
+/// seconds to wait before reconnecting to the docker engine
+const RECONNECT_DELAY: u64 = 5;
+
+pub fn docker_executor(events: &Events, queue_tx: Sender<ReferencingEvent>) -> anyhow::Result<()> {
+    let sockets: BTreeSet<&Path> = events
+        .iter()
+        .filter_map(|e| match &e.event_type {
+            EventType::DockerEvents(e) => Some(e.socket.as_path()),
+            _ => None,
+        })
+        .collect();
+    scope(|s| -> anyhow::Result<()> {
+        for socket in sockets {
+            let queue_tx = queue_tx.clone();
+            Builder::new()
+                .name(format!("docker {}", socket.to_string_lossy()))
+                .spawn_scoped(s, move || loop {
+                    match follow_events(socket, events, &queue_tx) {
+                        Ok(()) => return,
+                        Err(e) => {
+                            warn!("Docker events from {} failed {e}", socket.to_string_lossy())
+                        }
+                    }
+                    sleep(Duration::from_secs(RECONNECT_DELAY));
+                })?;
+        }
+        Ok(())
+    })
+}
+
+/// returns once the queue is closed
+fn follow_events(
+    socket: &Path,
+    events: &Events,
+    queue_tx: &Sender<ReferencingEvent>,
+) -> anyhow::Result<()> {
+    let filters = form_urlencoded::byte_serialize(br#"{"type":["container"]}"#).collect::<String>();
+    let (status, mut reader) = request(socket, "GET", &format!("/events?filters={filters}"))?;
+    if status != 200 {
+        bail!("Status {status} {}", error_message(&mut reader));
+    }
+    info!("Following docker events from {}", socket.to_string_lossy());
+    for line in reader.lines() {
+        let value: Value = match serde_json::from_str(&line?) {
+            Ok(v) => v,
+            Err(e) => {
+                warn!("Unable to parse docker event {e}");
+                continue;
+            }
+        };
+        let Some(message) = DockerMessage::parse(&value) else {
+            continue;
+        };
+        debug!("Docker container {} {}", message.container, message.action);
+        if let Some(e) = handle_incoming(events, socket, &message) {
+            if queue_tx.send(e).is_err() {
+                return Ok(());
+            }
+        }
+    }
+    Err(anyhow!("Stream closed"))
+}
+
+fn handle_incoming(
+    events: &Events,
+    socket: &Path,
+    message: &DockerMessage,
+) -> Option<ReferencingEvent> {
+    let ref_event = events.iter().find(|ref_event| {
+        matches!(&ref_event.event_type, EventType::DockerEvents(e) if e.socket == socket && e.matches(message))
+    })?;
+
+    if let Some(mut event) = events.get_next_event(ref_event) {
+        event.merge(Data::Json(message.to_json()));
+        event.metadata.merge(ref_event.metadata.clone());
+        Some(event)
+    } else {
+        debug!(
+            "Received docker event {} without further handler",
+            ref_event.name
+        );
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::events::{docker_events::DockerEventsEvent, time::TimeEvent, NextEvent};
+
+    use super::*;
+
+    #[test]
+    fn test_handle_incoming() {
+        let events = Events::new(
+            [
+                ReferencingEvent {
+                    event_type: EventType::DockerEvents(DockerEventsEvent {
+                        action: Some("die".to_string()),
+                        ..Default::default()
+                    }),
+                    next_event: NextEvent::Name("test1".to_string()).into(),
+                    name: "container_died".to_string(),
+                    ..Default::default()
+                },
+                ReferencingEvent {
+                    event_type: EventType::Time(TimeEvent {
+                        execute_time: "now".parse().unwrap(),
+                        event_id: None,
+                    }),
+                    name: "test1".to_string(),
+                    ..Default::default()
+                },
+            ]
+            .into_iter()
+            .collect(),
+        );
+        let socket = Path::new("/var/run/docker.sock");
+        let message = DockerMessage::parse(&json!({
+            "Type": "container",
+            "Action": "start",
+            "Actor": {"ID": "abc", "Attributes": {"name": "zigbee2mqtt"}},
+        }))
+        .unwrap();
+        assert!(handle_incoming(&events, socket, &message).is_none());
+
+        let message = DockerMessage::parse(&json!({
+            "Type": "container",
+            "Action": "die",
+            "Actor": {"ID": "abc", "Attributes": {"name": "zigbee2mqtt", "exitCode": "137"}},
+        }))
+        .unwrap();
+        assert!(handle_incoming(&events, Path::new("/run/other.sock"), &message).is_none());
+        let event = handle_incoming(&events, socket, &message).unwrap();
+        assert_eq!(event.name, "test1");
+        let Data::Json(data) = event.data else {
+            panic!("Expected json data");
+        };
+        assert_eq!(data["container"], "zigbee2mqtt");
+        assert_eq!(data["exit_code"], "137");
+    }
+}
@@ -0,0 +1,178 @@
+use std::{
+    ffi::CString,
+    fs::OpenOptions,
+    io::{BufRead, BufReader, ErrorKind, Read},
+    os::unix::{ffi::OsStrExt, fs::FileTypeExt},
+    path::Path,
+    sync::mpsc::Sender,
+};
+
+use anyhow::{bail, Context};
+use log::{debug, info};
+use serde_json::json;
+
+use crate::events::{EventType, Events, ReferencingEvent};
+
+/// longer lines are split into multiple messages
+const MAX_MESSAGE_SIZE: u64 = 65536;
+
+/// reads lines written to the fifo, the fifo is created if it does not exist
+pub fn fifo_executor(
+    pool_id: &str,
+    path: &Path,
+    events: &Events,
+    queue_tx: Sender<ReferencingEvent>,
+) -> anyhow::Result<()> {
+    create_fifo(path)?;
+    // opening for writing as well keeps the fifo open once writers close it
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(path)
+        .with_context(|| format!("Unable to open fifo {}", path.display()))?;
+    info!("Reading lines from fifo {}", path.display());
+    let mut reader = BufReader::new(file);
+    let mut buffer = Vec::new();
+    loop {
+        buffer.clear();
+        if reader
+            .by_ref()
+            .take(MAX_MESSAGE_SIZE)
+            .read_until(b'\n', &mut buffer)?
+            == 0
+        {
+            return Ok(());
+        }
+        let line = buffer
+            .strip_suffix(b"\n")
+            .map(|b| b.strip_suffix(b"\r").unwrap_or(b))
+            .unwrap_or(&buffer);
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(e) = handle_incoming(events, pool_id, path, line) {
+            queue_tx.send(e)?;
+        }
+    }
+}
+
+fn create_fifo(path: &Path) -> anyhow::Result<()> {
+    match path.metadata() {
+        Ok(m) if m.file_type().is_fifo() => return Ok(()),
+        Ok(_) => bail!("{} exists and is not a fifo", path.display()),
+        Err(e) if e.kind() == ErrorKind::NotFound => (),
+        Err(e) => return Err(e.into()),
+    }
+    let c_path = CString::new(path.as_os_str().as_bytes())?;
+    if unsafe { libc::mkfifo(c_path.as_ptr(), 0o620) } != 0 {
+        return Err(std::io::Error::last_os_error())
+            .with_context(|| format!("Unable to create fifo {}", path.display()));
+    }
+    Ok(())
+}
+
+fn handle_incoming(
+    events: &Events,
+    pool_id: &str,
+    path: &Path,
+    line: &[u8],
+) -> Option<ReferencingEvent> {
+    let ref_event = events.iter().find(|ref_event| {
+        matches!(&ref_event.event_type, EventType::FifoRead(e) if e.matches(pool_id, line))
+    })?;
+
+    if let Some(mut event) = events.get_next_event(ref_event) {
+        event.try_merge_bytes(line);
+        let mut metadata = ref_event.metadata.clone();
+        metadata
+            .merge(json!({ ref_event.name.as_str(): {"pool_id": pool_id, "path": path }}).into());
+        event.metadata.merge(metadata);
+        Some(event)
+    } else {
+        debug!(
+            "Received fifo line {} without further handler",
+            ref_event.name
+        );
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::time::Duration;
+    use std::{
+        env::temp_dir,
+        fs::{remove_file, write},
+        io::Write,
+        sync::mpsc::channel,
+        thread::{sleep, spawn},
+    };
+
+    use serde_json::Value;
+
+    use crate::events::{
+        data::Data, fifo_read::FifoReadEvent, mqtt_subscribe::MqttBodyMatch, time::TimeEvent,
+        NextEvent,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_executor() {
+        let (queue_tx, queue_rx) = channel();
+        let path = temp_dir().join(format!("hvents_test_{}.fifo", std::process::id()));
+        let _ = remove_file(&path);
+        let executor_path = path.clone();
+        spawn(move || {
+            let events = Events::new(
+                [
+                    ReferencingEvent {
+                        event_type: EventType::FifoRead(FifoReadEvent {
+                            pool_id: Default::default(),
+                            body: Some(MqttBodyMatch::BodyContains("deploy".to_string())),
+                        }),
+                        next_event: NextEvent::Name("test1".to_string()).into(),
+                        name: "fifo1".to_string(),
+                        ..Default::default()
+                    },
+                    ReferencingEvent {
+                        event_type: EventType::Time(TimeEvent {
+                            execute_time: "now".parse().unwrap(),
+                            event_id: None,
+                        }),
+                        name: "test1".to_string(),
+                        ..Default::default()
+                    },
+                ]
+                .into_iter()
+                .collect(),
+            );
+            fifo_executor("scripts", &executor_path, &events, queue_tx).unwrap();
+        });
+
+        // wait for the fifo to be created
+        let mut writer = (0..20)
+            .find_map(|_| {
+                OpenOptions::new()
+                    .write(true)
+                    .open(&path)
+                    .map_err(|_| sleep(Duration::from_millis(50)))
+                    .ok()
+            })
+            .unwrap();
+        writer.write_all(b"hello\n\ndeploy started\n").unwrap();
+        drop(writer);
+        // writers closing the fifo do not stop the executor
+        write(&path, b"{\"deploy\": \"done\"}\n").unwrap();
+
+        let event = queue_rx.recv_timeout(Duration::from_secs(1)).unwrap();
+        assert_eq!(event.name, "test1");
+        assert_eq!(event.data, Data::String("deploy started".to_string()));
+        let metadata: Value = serde_json::to_value(&event.metadata).unwrap();
+        assert_eq!(metadata["fifo1"]["pool_id"], "scripts");
+
+        let event = queue_rx.recv_timeout(Duration::from_secs(1)).unwrap();
+        assert_eq!(event.data, Data::Json(json!({"deploy": "done"})));
+        remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,120 @@
+use core::time::Duration;
+use std::{collections::BTreeMap, sync::mpsc::Sender};
+
+use log::debug;
+use rppal::gpio::{Gpio, InputPin, Trigger};
+use serde_json::json;
+
+use crate::events::{
+    data::Data,
+    gpio_watch::{GpioEdge, GpioPull, GpioWatchEvent},
+    EventType, Events, ReferencingEvent,
+};
+
+pub fn gpio_executor(events: &Events, queue_tx: Sender<ReferencingEvent>) -> anyhow::Result<()> {
+    // the first event watching the pin configures it
+    let mut watched: BTreeMap<u8, &GpioWatchEvent> = BTreeMap::new();
+    for ref_event in events.iter() {
+        if let EventType::GpioWatch(e) = &ref_event.event_type {
+            watched.entry(e.pin).or_insert(e);
+        }
+    }
+
+    let gpio = Gpio::new()?;
+    let mut pins: Vec<InputPin> = Vec::new();
+    for (pin, e) in watched {
+        let pin = gpio.get(pin)?;
+        let mut input = match e.pull {
+            Some(GpioPull::Up) => pin.into_input_pullup(),
+            Some(GpioPull::Down) => pin.into_input_pulldown(),
+            None => pin.into_input(),
+        };
+        let debounce = (e.debounce > 0).then(|| Duration::from_millis(e.debounce));
+        input.set_interrupt(Trigger::Both, debounce)?;
+        pins.push(input);
+    }
+    let pins: Vec<&InputPin> = pins.iter().collect();
+
+    loop {
+        let Some((pin, event)) = gpio.poll_interrupts(&pins, false, None)? else {
+            continue;
+        };
+        let edge = match event.trigger {
+            Trigger::RisingEdge => GpioEdge::Rising,
+            Trigger::FallingEdge => GpioEdge::Falling,
+            _ => continue,
+        };
+        debug!("Gpio pin {} {edge} edge", pin.pin());
+        if let Some(e) = handle_incoming(events, pin.pin(), edge) {
+            queue_tx.send(e)?;
+        }
+    }
+}
+
+fn handle_incoming(events: &Events, pin: u8, edge: GpioEdge) -> Option<ReferencingEvent> {
+    let ref_event = events.iter().find(|ref_event| {
+        matches!(&ref_event.event_type, EventType::GpioWatch(e) if e.matches(pin, edge))
+    })?;
+
+    if let Some(mut event) = events.get_next_event(ref_event) {
+        let level = match edge {
+            GpioEdge::Rising => "high",
+            GpioEdge::Falling => "low",
+        };
+        event.merge(Data::Json(
+            json!({"pin": pin, "edge": edge.to_string(), "level": level}),
+        ));
+        event.metadata.merge(ref_event.metadata.clone());
+        Some(event)
+    } else {
+        debug!(
+            "Received gpio event {} without further handler",
+            ref_event.name
+        );
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::events::{time::TimeEvent, NextEvent};
+
+    use super::*;
+
+    #[test]
+    fn test_handle_incoming() {
+        let events = Events::new(
+            [
+                ReferencingEvent {
+                    event_type: EventType::GpioWatch(GpioWatchEvent {
+                        pin: 17,
+                        edge: Some(GpioEdge::Falling),
+                        ..Default::default()
+                    }),
+                    next_event: NextEvent::Name("test1".to_string()).into(),
+                    name: "button".to_string(),
+                    ..Default::default()
+                },
+                ReferencingEvent {
+                    event_type: EventType::Time(TimeEvent {
+                        execute_time: "now".parse().unwrap(),
+                        event_id: None,
+                    }),
+                    name: "test1".to_string(),
+                    ..Default::default()
+                },
+            ]
+            .into_iter()
+            .collect(),
+        );
+        assert!(handle_incoming(&events, 17, GpioEdge::Rising).is_none());
+        assert!(handle_incoming(&events, 18, GpioEdge::Falling).is_none());
+
+        let event = handle_incoming(&events, 17, GpioEdge::Falling).unwrap();
+        assert_eq!(event.name, "test1");
+        assert_eq!(
+            event.data,
+            Data::Json(json!({"pin": 17, "edge": "falling", "level": "low"}))
+        );
+    }
+}
@@ -1,86 +1,412 @@
-use std::sync::mpsc::Sender;
+use core::time::Duration;
+use std::{
+    fs::{create_dir_all, write, File},
+    io::{ErrorKind, Read, Write},
+    net::TcpListener,
+    path::{Component, Path},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc::{sync_channel, RecvTimeoutError, Sender},
+    },
+    thread::{scope, Builder},
+    time::Instant,
+};
 
-use anyhow::anyhow;
-use indexmap::IndexSet;
+use anyhow::{anyhow, Context};
+use indexmap::IndexMap;
 use log::{debug, error, warn};
+use percent_encoding::percent_decode_str;
 use serde::Serialize;
-use serde_json::{json, Value};
+use serde_json::{json, Map, Value};
 use tiny_http::{Header, Method, Request, Response, Server};
 
 use crate::{
-    config::Headers,
+    config::{now, CorsConfiguration, Headers, HttpConfiguration, StaticConfiguration},
     events::{
         api_call::{RequestContent, ResponseContent},
-        api_listen::HttpQueue,
+        api_listen::{HttpQueue, HttpState, HttpStream, HttpStreams},
         data::Data,
-        EventType, Events, ReferencingEvent,
+        EventName, EventType, Events, ReferencingEvent,
     },
-    renderer::load_handlebars,
+    multipart,
+    rate_limit::RateLimiter,
+    renderer::{load_handlebars, load_templates, render_to_write},
 };
 
+/// messages buffered for a server-sent event connection
+const STREAM_BUFFER: usize = 64;
+/// interval to send comments to idle server-sent event connections
+const STREAM_HEARTBEAT: Duration = Duration::from_secs(15);
+
+#[allow(clippy::too_many_arguments)]
 pub fn http_executor(
     http_queue: HttpQueue,
-    listen: &str,
+    http_streams: HttpStreams,
+    http_state: HttpState,
+    config: &HttpConfiguration,
     events: &Events,
+    templates_dir: Option<&Path>,
+    template_env: &IndexMap<String, String>,
     queue_tx: Sender<ReferencingEvent>,
 ) -> anyhow::Result<()> {
-    let server = Server::http(listen)
+    let listen = &config.listen;
+    let listener = TcpListener::bind(listen)
+        .with_context(|| format!("Http server failed to listen to {listen}"))?;
+    // accepted connections inherit the timeouts so that stalled clients do not block request threads
+    let socket = socket2::SockRef::from(&listener);
+    socket.set_read_timeout(Some(read_timeout(config)))?;
+    socket.set_write_timeout(Some(read_timeout(config)))?;
+    let server = Server::from_listener(listener, None)
         .map_err(|e| anyhow!("Http server failed to listen to {listen} {e}"))?;
-    let handlebars = load_handlebars();
-
-    for mut request in server.incoming_requests() {
-        debug!(
-            "Incoming request method: {}, url: {}, headers: {:?}",
-            request.method(),
-            request.url(),
-            request.headers()
-        );
+    let mut handlebars = load_handlebars();
+    load_templates(&mut handlebars, events, templates_dir)?;
+    let rate_limiter = config.rate_limit.as_ref().map(RateLimiter::new);
 
-        let response = match handle_incoming(
-            events,
-            &http_queue.lock().expect("http queue locked"),
-            &handlebars,
-            &mut request,
-        ) {
-            Some(output) => {
-                if let Some(e) = output.event {
-                    queue_tx.send(e)?;
+    // requests are handled in separate threads so that slow clients do not block the server
+    let active = AtomicUsize::new(0);
+    scope(|s| {
+        for request in server.incoming_requests() {
+            if let Some(limiter) = &rate_limiter {
+                let ip = request.remote_addr().map(|a| a.ip());
+                if !limiter.allow(ip) {
+                    debug!("Http rate limit reached for {ip:?}");
+                    if let Err(e) = request.respond(Response::empty(429)) {
+                        warn!("Http response failed {e}");
+                    }
+                    continue;
                 }
-                let mut response = Response::from_data(output.data);
-                for (k, v) in output.headers {
-                    match Header::from_bytes(k.as_bytes(), v.as_bytes()) {
-                        Ok(h) => response.add_header(h),
-                        Err(_) => warn!("Failed to add header {k} {v}"),
-                    };
+            }
+            if active.fetch_add(1, Ordering::SeqCst) >= config.max_requests {
+                active.fetch_sub(1, Ordering::SeqCst);
+                debug!("Http request limit {} reached", config.max_requests);
+                if let Err(e) = request.respond(Response::empty(503)) {
+                    warn!("Http response failed {e}");
                 }
-                response
+                continue;
             }
-            None => Response::from_string("Not Found").with_status_code(404),
-        };
+            let active = &active;
+            let result = Builder::new()
+                .name(format!("http {listen}"))
+                .spawn_scoped(s, || {
+                    handle_request(
+                        request,
+                        config,
+                        &http_queue,
+                        &http_streams,
+                        &http_state,
+                        template_env,
+                        events,
+                        &handlebars,
+                        &queue_tx,
+                    );
+                    active.fetch_sub(1, Ordering::SeqCst);
+                });
+            if let Err(e) = result {
+                active.fetch_sub(1, Ordering::SeqCst);
+                error!("Unable to handle http request {e}");
+            }
+        }
+    });
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+fn handle_request(
+    mut request: Request,
+    config: &HttpConfiguration,
+    http_queue: &HttpQueue,
+    http_streams: &HttpStreams,
+    http_state: &HttpState,
+    template_env: &IndexMap<String, String>,
+    events: &Events,
+    handlebars: &handlebars::Handlebars,
+    queue_tx: &Sender<ReferencingEvent>,
+) {
+    debug!(
+        "Incoming request method: {}, url: {}, headers: {:?}",
+        request.method(),
+        request.url(),
+        request.headers()
+    );
+
+    let cors_headers = config
+        .cors
+        .as_ref()
+        .map(|c| cors_headers(c, &request))
+        .unwrap_or_default();
 
+    // options requests without Access-Control-Request-Method can be handled by api_listen events
+    let preflight = request.method() == &Method::Options
+        && request
+            .headers()
+            .iter()
+            .any(|h| h.field.equiv("Access-Control-Request-Method"));
+    if config.cors.is_some() && preflight {
+        let mut response = Response::empty(204);
+        add_headers(&mut response, cors_headers);
         match request.respond(response) {
-            Ok(_) => debug!("Http response sent"),
-            Err(e) => warn!("Http response failed {e}"),
+            Ok(_) => debug!("Http preflight response sent"),
+            Err(e) => warn!("Http preflight response failed {e}"),
         };
+        return;
     }
-    Ok(())
+
+    let origin_headers: Headers = cors_headers
+        .into_iter()
+        .filter(|(k, _)| k == "Access-Control-Allow-Origin" || k == "Vary")
+        .collect();
+
+    let response = handle_incoming(
+        events,
+        http_queue,
+        http_state,
+        template_env,
+        config,
+        handlebars,
+        &mut request,
+    );
+    if let Some(e) = response.as_ref().and_then(|r| r.event.clone()) {
+        if let Err(e) = queue_tx.send(e) {
+            error!("Unable to queue event={} {e}", e.0.name);
+        }
+    }
+    let mut response = match response {
+        Some(ResponseData {
+            headers,
+            stream: Some(stream_events),
+            ..
+        }) => {
+            start_stream(
+                request,
+                stream_events,
+                headers.into_iter().chain(origin_headers),
+                http_streams,
+            );
+            return;
+        }
+        Some(output) => {
+            let mut response =
+                Response::from_data(output.data).with_status_code(output.status_code);
+            add_headers(&mut response, output.headers);
+            response
+        }
+        None => {
+            if let Some(mut response) = config
+                .static_files
+                .as_ref()
+                .and_then(|c| static_file(c, &request))
+            {
+                add_headers(&mut response, origin_headers);
+                match request.respond(response) {
+                    Ok(_) => debug!("Http file response sent"),
+                    Err(e) => warn!("Http file response failed {e}"),
+                };
+                return;
+            }
+            Response::from_string("Not Found").with_status_code(404)
+        }
+    };
+    add_headers(&mut response, origin_headers);
+
+    match request.respond(response) {
+        Ok(_) => debug!("Http response sent"),
+        Err(e) => warn!("Http response failed {e}"),
+    };
+}
+
+/// file from the static directory matching the request url, index.html is used for directories
+fn static_file(config: &StaticConfiguration, request: &Request) -> Option<Response<File>> {
+    if !matches!(request.method(), Method::Get | Method::Head) {
+        return None;
+    }
+    let url = request.url().split('?').next().unwrap_or_default();
+    let relative = url.strip_prefix(config.path.trim_end_matches('/'))?;
+    if !relative.is_empty() && !relative.starts_with('/') {
+        return None;
+    }
+    let Ok(relative) = percent_decode_str(relative.trim_start_matches('/')).decode_utf8() else {
+        warn!("Http static path is not valid utf-8 {url}");
+        return None;
+    };
+    let relative = Path::new(relative.as_ref());
+    // do not allow escaping the static directory, checked after decoding
+    if !relative
+        .components()
+        .all(|c| matches!(c, Component::Normal(_)))
+    {
+        warn!("Http static path not allowed {url}");
+        return None;
+    }
+    let mut path = config.dir.join(relative);
+    if path.is_dir() {
+        path = path.join("index.html");
+    }
+    let file = File::open(&path).ok()?;
+    debug!("Http static file {}", path.to_string_lossy());
+    let content_type = match path.extension().and_then(|e| e.to_str()) {
+        Some("html" | "htm") => "text/html; charset=utf-8",
+        Some("css") => "text/css",
+        Some("js") => "text/javascript",
+        Some("json") => "application/json",
+        Some("svg") => "image/svg+xml",
+        Some("png") => "image/png",
+        Some("jpg" | "jpeg") => "image/jpeg",
+        Some("ico") => "image/x-icon",
+        Some("txt") => "text/plain; charset=utf-8",
+        Some("woff2") => "font/woff2",
+        _ => "application/octet-stream",
+    };
+    let mut response = Response::from_file(file);
+    add_headers(
+        &mut response,
+        [("Content-Type".to_string(), content_type.to_string())],
+    );
+    Some(response)
+}
+
+/// respond with an event stream and write events from the queue executor until the client disconnects,
+/// the request thread is kept so that open streams count against max_requests
+fn start_stream(
+    request: Request,
+    events: Vec<EventName>,
+    headers: impl IntoIterator<Item = (String, String)>,
+    http_streams: &HttpStreams,
+) {
+    let mut head = "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\nConnection: close\r\n".to_string();
+    for (k, v) in headers {
+        head.push_str(&format!("{k}: {v}\r\n"));
+    }
+    head.push_str("\r\n");
+
+    // streams of clients not keeping up are dropped when the buffer is full
+    let (sender, receiver) = sync_channel::<Vec<u8>>(STREAM_BUFFER);
+    let url = request.url().to_string();
+    let mut writer = request.into_writer();
+    // register before responding to not miss any events, closed streams are dropped on send
+    http_streams
+        .lock()
+        .expect("http streams lock")
+        .push(HttpStream { events, sender });
+    if let Err(e) = writer
+        .write_all(head.as_bytes())
+        .and_then(|_| writer.flush())
+    {
+        warn!("Http stream response failed {e}");
+        return;
+    }
+    debug!("Http stream started url={url}");
+
+    loop {
+        let message = match receiver.recv_timeout(STREAM_HEARTBEAT) {
+            Ok(m) => m,
+            // heartbeats detect disconnected clients while no events are streamed
+            Err(RecvTimeoutError::Timeout) => b":\n".to_vec(),
+            Err(RecvTimeoutError::Disconnected) => {
+                debug!("Http stream dropped url={url}");
+                return;
+            }
+        };
+        if let Err(e) = writer.write_all(&message).and_then(|_| writer.flush()) {
+            debug!("Http stream closed url={url} {e}");
+            return;
+        }
+    }
+}
+
+fn add_headers<R: Read>(
+    response: &mut Response<R>,
+    headers: impl IntoIterator<Item = (String, String)>,
+) {
+    for (k, v) in headers {
+        match Header::from_bytes(k.as_bytes(), v.as_bytes()) {
+            Ok(h) => response.add_header(h),
+            Err(_) => warn!("Failed to add header {k} {v}"),
+        };
+    }
+}
+
+/// headers for preflight requests, only allowed origin headers are added to other responses
+fn cors_headers(cors: &CorsConfiguration, request: &Request) -> Vec<(String, String)> {
+    let origin = request
+        .headers()
+        .iter()
+        .find(|h| h.field.equiv("Origin"))
+        .map(|h| h.value.as_str());
+    let Some(allowed_origin) = allowed_origin(cors, origin) else {
+        return Vec::new();
+    };
+    let mut headers = vec![
+        (
+            "Access-Control-Allow-Origin".to_string(),
+            allowed_origin.to_string(),
+        ),
+        (
+            "Access-Control-Allow-Methods".to_string(),
+            cors.methods.join(", "),
+        ),
+        (
+            "Access-Control-Allow-Headers".to_string(),
+            cors.headers.join(", "),
+        ),
+    ];
+    if allowed_origin != "*" {
+        headers.push(("Vary".to_string(), "Origin".to_string()));
+    }
+    if let Some(max_age) = cors.max_age {
+        headers.push(("Access-Control-Max-Age".to_string(), max_age.to_string()));
+    }
+    headers
+}
+
+/// origin to allow, any origin is allowed with *
+pub(crate) fn allowed_origin<'a>(
+    cors: &'a CorsConfiguration,
+    origin: Option<&'a str>,
+) -> Option<&'a str> {
+    if cors.origins.iter().any(|o| o == "*") {
+        return Some("*");
+    }
+    let allowed = origin.filter(|o| cors.origins.iter().any(|a| a == o));
+    if allowed.is_none() {
+        debug!("Http cors origin {origin:?} not allowed");
+    }
+    allowed
+}
+
+/// save the uploaded file with a timestamp prefix and return its path
+fn save_upload(directory: &Path, filename: &str, content: &[u8]) -> anyhow::Result<String> {
+    create_dir_all(directory)
+        .with_context(|| format!("Unable to create {}", directory.to_string_lossy()))?;
+    // ignore any directories provided by the client
+    let filename = Path::new(filename)
+        .file_name()
+        .map(|f| f.to_string_lossy().to_string())
+        .unwrap_or_else(|| "upload".to_string());
+    let path = directory.join(format!("{}_{filename}", now().format("%Y%m%d%H%M%S%3f")));
+    write(&path, content).with_context(|| format!("Unable to write {}", path.to_string_lossy()))?;
+    Ok(path.to_string_lossy().to_string())
 }
 
 fn handle_incoming(
     events: &Events,
-    http_events: &IndexSet<ReferencingEvent>,
+    http_queue: &HttpQueue,
+    http_state: &HttpState,
+    template_env: &IndexMap<String, String>,
+    config: &HttpConfiguration,
     handlebars: &handlebars::Handlebars,
     request: &mut Request,
 ) -> Option<ResponseData> {
-    let (ref_event, listen_event) =
-        http_events
-            .iter()
-            .find_map(|ref_event| match &ref_event.event_type {
-                EventType::ApiListen(e) if e.matches(request.url(), request.method().as_str()) => {
-                    Some((ref_event, e))
-                }
-                _ => None,
-            })?;
+    let (ref_event, listen_event) = http_queue
+        .lock()
+        .expect("http queue lock")
+        .iter()
+        .find_map(|ref_event| match &ref_event.event_type {
+            EventType::ApiListen(e) if e.matches(request.url(), request.method().as_str()) => {
+                Some((ref_event.clone(), e.clone()))
+            }
+            _ => None,
+        })?;
+    let ref_event = &ref_event;
 
     debug!(
         "Http found event={} next event={} request_content={} response_content={}",
@@ -90,56 +416,138 @@ fn handle_incoming(
         listen_event.response_content
     );
 
-    let request_content: Option<Data> = match (request.method(), &listen_event.request_content) {
-        (Method::Post | Method::Put, RequestContent::Json) => {
-            match serde_json::from_reader::<_, Value>(request.as_reader()) {
-                Ok(v) => Data::Json(v).into(),
-                Err(e) => {
-                    error!("Failed to read request payload {e}");
-                    return None;
-                }
+    let has_body = has_body(request.method());
+    let content = if has_body {
+        match read_body(request, config) {
+            Ok(c) => c,
+            Err(status_code) => {
+                warn!(
+                    "Failed to read request payload status={status_code} event={}",
+                    ref_event.name
+                );
+                return ResponseData::status(status_code).into();
             }
         }
-        (Method::Post | Method::Put, RequestContent::Text) => {
-            let mut content = String::new();
-            if let Err(e) = request.as_reader().read_to_string(&mut content) {
+    } else {
+        Vec::new()
+    };
+
+    // uploaded file paths by field name
+    let mut files: IndexMap<String, String> = IndexMap::new();
+    let request_content: Option<Data> = match (has_body, &listen_event.request_content) {
+        (true, RequestContent::Json) => match serde_json::from_slice::<Value>(&content) {
+            Ok(v) => Data::Json(v).into(),
+            Err(e) => {
                 error!("Failed to read request payload {e}");
                 return None;
             }
-            Data::String(content).into()
-        }
-        (Method::Post | Method::Put, RequestContent::Bytes) => {
-            let mut content = Vec::default();
-            if let Err(e) = request.as_reader().read_to_end(&mut content) {
+        },
+        (true, RequestContent::Text) => match String::from_utf8(content) {
+            Ok(c) => Data::String(c).into(),
+            Err(e) => {
                 error!("Failed to read request payload {e}");
                 return None;
             }
-            Data::Bytes(content).into()
+        },
+        (true, RequestContent::Bytes) => Data::Bytes(content).into(),
+        (true, RequestContent::Form) => {
+            let fields = form_urlencoded::parse(&content)
+                .into_owned()
+                .map(|(k, v)| (k, Value::String(v)))
+                .collect();
+            Data::Json(Value::Object(fields)).into()
+        }
+        (true, RequestContent::Multipart) => {
+            let Some(boundary) = request
+                .headers()
+                .iter()
+                .find(|h| h.field.equiv("Content-Type"))
+                .and_then(|h| multipart::boundary(h.value.as_str()))
+            else {
+                error!(
+                    "Multipart request without boundary event={}",
+                    ref_event.name
+                );
+                return None;
+            };
+            let mut fields = Map::new();
+            for part in multipart::parse(&content, boundary) {
+                match (&part.filename, &listen_event.upload_directory) {
+                    (Some(filename), Some(directory)) => {
+                        match save_upload(directory, filename, part.content) {
+                            Ok(path) => {
+                                files.insert(part.name, path);
+                            }
+                            Err(e) => {
+                                error!("Failed to save uploaded file {e}");
+                                return None;
+                            }
+                        }
+                    }
+                    (Some(filename), None) => {
+                        warn!(
+                            "No upload_directory defined, ignoring file {filename} event={}",
+                            ref_event.name
+                        )
+                    }
+                    (None, _) => {
+                        fields.insert(
+                            part.name,
+                            String::from_utf8_lossy(part.content).to_string().into(),
+                        );
+                    }
+                }
+            }
+            Data::Json(Value::Object(fields)).into()
+        }
+        (true, binary @ (RequestContent::Msgpack | RequestContent::Cbor)) => {
+            let encoding = binary.encoding().expect("binary request content");
+            match encoding.decode(&content) {
+                Ok(v) => Data::Json(v).into(),
+                Err(e) => {
+                    error!("Failed to read request payload {e}");
+                    return None;
+                }
+            }
         }
         _ => None,
     };
 
     let mut headers = listen_event.headers.clone();
     let segments: Vec<&str> = request.url().split('/').filter(|s| !s.is_empty()).collect();
+    let query: IndexMap<String, String> = request
+        .url()
+        .split_once('?')
+        .map(|(_, q)| form_urlencoded::parse(q.as_bytes()).into_owned().collect())
+        .unwrap_or_default();
 
-    let template_response = if let Some(t) = &listen_event.response_body {
-        let template_data = TemplateData {
-            request: match &request_content {
-                Some(Data::Json(v)) => v.into(),
-                _ => None,
-            },
-            url: request.url(),
-            segments: segments.clone(),
-            data: &ref_event.data,
-        };
-        let mut content = Vec::default();
-        if let Err(e) = handlebars.render_template_to_write(t, &template_data, &mut content) {
+    let state = http_state.read().expect("http state lock").clone();
+    let template_data = TemplateData {
+        request: match &request_content {
+            Some(Data::Json(v)) => v.into(),
+            _ => None,
+        },
+        url: request.url(),
+        segments: segments.clone(),
+        query: &query,
+        data: &ref_event.data,
+        state: &state,
+        env: template_env,
+    };
+    let mut content = Vec::default();
+    let template_response = match render_to_write(
+        handlebars,
+        listen_event.template_file.as_deref(),
+        listen_event.response_body.as_deref(),
+        &template_data,
+        &mut content,
+    ) {
+        Some(Ok(_)) => content.into(),
+        Some(Err(e)) => {
             error!("Failed to render template {e} event={}", ref_event.name);
             return None;
         }
-        content.into()
-    } else {
-        None
+        None => None,
     };
 
     let response_content = match (&listen_event.response_content, template_response) {
@@ -167,13 +575,35 @@ fn handle_incoming(
         },
         (ResponseContent::Text, Some(t)) if !t.is_empty() => t,
         (ResponseContent::Text, Some(_)) => return None,
-        (ResponseContent::Bytes, _) => match ref_event.data.to_bytes() {
-            Ok(b) => b,
-            Err(e) => {
-                warn!("Responding with OK unknown data {e}");
-                "OK".as_bytes().to_vec()
+        (binary @ (ResponseContent::Msgpack | ResponseContent::Cbor), template) => {
+            let encoding = binary.encoding().expect("binary response content");
+            let data = match template {
+                Some(t) => Data::from_bytes(&t),
+                None => ref_event.data.clone(),
+            };
+            match encoding.encode(&data) {
+                Ok(b) => {
+                    headers.insert(
+                        "Content-Type".to_string(),
+                        encoding.content_type().to_string(),
+                    );
+                    b
+                }
+                Err(e) => {
+                    error!("Failed to encode response {e}");
+                    return None;
+                }
             }
-        },
+        }
+        (ResponseContent::Bytes | ResponseContent::Csv | ResponseContent::File, _) => {
+            match ref_event.data.to_bytes() {
+                Ok(b) => b,
+                Err(e) => {
+                    warn!("Responding with OK unknown data {e}");
+                    "OK".as_bytes().to_vec()
+                }
+            }
+        }
     };
 
     if let Some(mut event) = events.get_next_event(ref_event) {
@@ -182,13 +612,15 @@ fn handle_incoming(
         }
         event.merge(ref_event.data.clone());
         let mut metadata = ref_event.metadata.clone();
-        metadata.merge(json!({ref_event.name.as_str(): {"url": request.url(), "segments": segments, "remote_address": request.remote_addr()}}).into());
+        metadata.merge(json!({ref_event.name.as_str(): {"url": request.url(), "segments": segments, "query": query, "files": files, "remote_address": request.remote_addr()}}).into());
         event.metadata.merge(metadata);
 
         ResponseData {
             event: event.into(),
             data: response_content,
             headers,
+            stream: listen_event.stream.as_ref().map(|s| s.events.clone()),
+            status_code: 200,
         }
         .into()
     } else {
@@ -197,6 +629,8 @@ fn handle_incoming(
             event: None,
             data: response_content,
             headers,
+            stream: listen_event.stream.as_ref().map(|s| s.events.clone()),
+            status_code: 200,
         }
         .into()
     }
@@ -207,30 +641,138 @@ struct TemplateData<'a> {
     request: Option<&'a Value>,
     url: &'a str,
     segments: Vec<&'a str>,
+    query: &'a IndexMap<String, String>,
     data: &'a Data,
+    state: &'a IndexMap<String, String>,
+    env: &'a IndexMap<String, String>,
 }
 
 struct ResponseData {
     event: Option<ReferencingEvent>,
     data: Vec<u8>,
     headers: Headers,
+    /// event names to stream to the client
+    stream: Option<Vec<EventName>>,
+    status_code: u16,
+}
+
+impl ResponseData {
+    fn status(status_code: u16) -> Self {
+        Self {
+            event: None,
+            data: Vec::new(),
+            headers: Default::default(),
+            stream: None,
+            status_code,
+        }
+    }
+}
+
+/// upnp devices send state changes with NOTIFY requests
+fn has_body(method: &Method) -> bool {
+    matches!(method, Method::Post | Method::Put | Method::Patch)
+        || method.as_str().eq_ignore_ascii_case("NOTIFY")
+}
+
+fn read_timeout(config: &HttpConfiguration) -> Duration {
+    Duration::from_secs(config.read_timeout.max(1))
+}
+
+/// read the request body within the configured size and time limits, returns the status code on failure
+fn read_body(request: &mut Request, config: &HttpConfiguration) -> Result<Vec<u8>, u16> {
+    if request
+        .body_length()
+        .map(|l| l > config.max_body_size)
+        .unwrap_or_default()
+    {
+        return Err(413);
+    }
+    let started = Instant::now();
+    let timeout = read_timeout(config);
+    let mut content = Vec::new();
+    let mut buffer = [0; 8192];
+    let reader = request.as_reader();
+    loop {
+        // stalled reads time out on the socket, clients trickling the body are stopped here
+        if started.elapsed() > timeout {
+            return Err(408);
+        }
+        let size = match reader.read(&mut buffer) {
+            Ok(0) => return Ok(content),
+            Ok(s) => s,
+            Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+            Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+                return Err(408)
+            }
+            Err(_) => return Err(400),
+        };
+        if content.len() + size > config.max_body_size {
+            return Err(413);
+        }
+        content.extend_from_slice(&buffer[..size]);
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use std::{sync::mpsc::channel, thread::spawn, time::Duration};
+    use core::str::from_utf8;
+    use std::{
+        net::TcpStream,
+        sync::mpsc::channel,
+        thread::{sleep, spawn},
+        time::Duration,
+    };
 
     use serde_json::json;
+    use tiny_http::TestRequest;
 
-    use crate::events::{
-        api_call::RequestMethod,
-        api_listen::{ApiListenEvent, HttpQueue},
-        time::TimeEvent,
-        NextEvent,
+    use crate::{
+        config::RateLimitConfiguration,
+        events::{
+            api_call::RequestMethod,
+            api_listen::{ApiListenEvent, HttpQueue, PathMatch, StreamEvents},
+            time::TimeEvent,
+            NextEvent,
+        },
+        pools::http::HttpQueuePool,
     };
 
     use super::*;
 
+    #[test]
+    fn test_cors_headers() {
+        let cors = CorsConfiguration {
+            origins: vec!["http://dashboard".to_string()],
+            methods: vec!["GET".to_string()],
+            headers: vec!["Content-Type".to_string()],
+            max_age: Some(60),
+        };
+        let request: Request = TestRequest::new()
+            .with_method(Method::Options)
+            .with_header(Header::from_bytes("Origin", "http://dashboard").unwrap())
+            .into();
+        let headers: Headers = cors_headers(&cors, &request).into_iter().collect();
+        assert_eq!(headers["Access-Control-Allow-Origin"], "http://dashboard");
+        assert_eq!(headers["Access-Control-Allow-Methods"], "GET");
+        assert_eq!(headers["Access-Control-Max-Age"], "60");
+        assert_eq!(headers["Vary"], "Origin");
+
+        let request: Request = TestRequest::new()
+            .with_header(Header::from_bytes("Origin", "http://other").unwrap())
+            .into();
+        assert!(cors_headers(&cors, &request).is_empty());
+
+        let cors = CorsConfiguration {
+            origins: vec!["*".to_string()],
+            max_age: None,
+            ..cors
+        };
+        let request: Request = TestRequest::new().into();
+        let headers: Headers = cors_headers(&cors, &request).into_iter().collect();
+        assert_eq!(headers["Access-Control-Allow-Origin"], "*");
+        assert!(!headers.contains_key("Vary"));
+    }
+
     #[test]
     fn test_executor() {
         let (queue_tx, queue_rx) = channel();
@@ -258,12 +800,32 @@ mod tests {
                 json!({ "listen2": "currently" }),
                 "/clients",
                 RequestMethod::Post,
-                r#"{{data.listen2}} {{request.time}}"#.to_string().into(),
+                r#"{{data.listen2}} {{request.time}} {{query.source}} {{state.mode}} {{env.SITE}}"#
+                    .to_string()
+                    .into(),
             ));
             let events = Events::new(events.into_iter().collect());
-            http_executor(queue, "127.0.0.1:13333", &events, queue_tx.clone()).unwrap();
+            let config = HttpConfiguration::new("127.0.0.1:13333".to_string());
+            let state = HttpState::default();
+            state
+                .write()
+                .unwrap()
+                .insert("mode".to_string(), "away".to_string());
+            let env = IndexMap::from([("SITE".to_string(), "garden".to_string())]);
+            http_executor(
+                queue,
+                Default::default(),
+                state,
+                &config,
+                &events,
+                None,
+                &env,
+                queue_tx.clone(),
+            )
+            .unwrap();
         });
 
+        wait_for_server("127.0.0.1:13333");
         let body = reqwest::blocking::get("http://127.0.0.1:13333/clients/listen1")
             .unwrap()
             .text()
@@ -272,14 +834,14 @@ mod tests {
         assert_eq!(body, r#"{"listen1":"text"}"#);
 
         let body = reqwest::blocking::Client::new()
-            .post("http://127.0.0.1:13333/clients/listen1")
+            .post("http://127.0.0.1:13333/clients/listen1?source=web%20ui")
             .body(r#"{"time":"2024-01-01"}"#)
             .send()
             .unwrap()
             .text()
             .unwrap();
 
-        assert_eq!(body, r#"currently 2024-01-01"#);
+        assert_eq!(body, r#"currently 2024-01-01 web ui away garden"#);
 
         let event = queue_rx.recv_timeout(Duration::from_millis(200)).unwrap();
         assert_eq!(event.name, "test1");
@@ -292,6 +854,338 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_stream() {
+        let (queue_tx, _queue_rx) = channel();
+        let pool = HttpQueuePool::default();
+        let http_streams = pool.streams().clone();
+
+        spawn(move || {
+            let queue = HttpQueue::default();
+            let mut event = create_listen_event(
+                "listen1",
+                None,
+                Default::default(),
+                "/events",
+                RequestMethod::Get,
+                None,
+            );
+            if let EventType::ApiListen(e) = &mut event.event_type {
+                e.stream = StreamEvents {
+                    events: vec!["test1".to_string()],
+                }
+                .into();
+            }
+            queue.lock().unwrap().insert(event);
+            let config = HttpConfiguration {
+                max_requests: 1,
+                ..HttpConfiguration::new("127.0.0.1:13334".to_string())
+            };
+            http_executor(
+                queue,
+                http_streams,
+                Default::default(),
+                &config,
+                &Events::new(Default::default()),
+                None,
+                &Default::default(),
+                queue_tx,
+            )
+            .unwrap();
+        });
+
+        wait_for_server("127.0.0.1:13334");
+        let mut response = reqwest::blocking::get("http://127.0.0.1:13334/events").unwrap();
+        assert_eq!(
+            response.headers()["content-type"].to_str().unwrap(),
+            "text/event-stream"
+        );
+
+        pool.stream(&create_time_event("test2", json!({ "test2": "ignored" })));
+        pool.stream(&create_time_event("test1", json!({ "test1": "text" })));
+
+        let mut buffer = [0; 64];
+        let size = response.read(&mut buffer).unwrap();
+        assert_eq!(
+            from_utf8(&buffer[..size]).unwrap(),
+            "event: test1\ndata: {\"test1\":\"text\"}\n\n"
+        );
+
+        // open streams count against max_requests
+        let response = reqwest::blocking::get("http://127.0.0.1:13334/events").unwrap();
+        assert_eq!(response.status(), 503);
+
+        // streams not keeping up are dropped once the buffer is full
+        let (sender, _receiver) = sync_channel(1);
+        pool.streams().lock().unwrap().push(HttpStream {
+            events: Vec::new(),
+            sender,
+        });
+        pool.stream(&create_time_event("test2", json!({})));
+        assert_eq!(pool.streams().lock().unwrap().len(), 2);
+        pool.stream(&create_time_event("test2", json!({})));
+        assert_eq!(pool.streams().lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_static_file() {
+        let directory = "/tmp/_test_http_static";
+        std::fs::remove_dir_all(directory).ok();
+        std::fs::create_dir_all(format!("{directory}/css")).unwrap();
+        std::fs::write(format!("{directory}/index.html"), "index").unwrap();
+        std::fs::write(format!("{directory}/css/main.css"), "body {}").unwrap();
+        std::fs::write(format!("{directory}/my file.txt"), "spaces").unwrap();
+        let config = StaticConfiguration {
+            path: "/ui".to_string(),
+            dir: directory.into(),
+        };
+
+        let request = TestRequest::new().with_path("/ui/").into();
+        let response = static_file(&config, &request).unwrap();
+        assert_eq!(response.data_length(), Some(5));
+
+        let request = TestRequest::new().with_path("/ui/css/main.css?v=1").into();
+        let response = static_file(&config, &request).unwrap();
+        assert!(response
+            .headers()
+            .iter()
+            .any(|h| h.field.equiv("Content-Type") && h.value == "text/css"));
+
+        let request = TestRequest::new().with_path("/ui/my%20file.txt").into();
+        let response = static_file(&config, &request).unwrap();
+        assert_eq!(response.data_length(), Some(6));
+
+        for path in [
+            "/ui/../etc/passwd",
+            "/ui/%2e%2e/etc/passwd",
+            "/ui/css%2F..%2F..%2Fetc/passwd",
+            "/ui/%ff",
+            "/uix",
+            "/ui/missing",
+            "/other",
+        ] {
+            let request = TestRequest::new().with_path(path).into();
+            assert!(static_file(&config, &request).is_none(), "{path}");
+        }
+        let request = TestRequest::new()
+            .with_method(Method::Post)
+            .with_path("/ui/")
+            .into();
+        assert!(static_file(&config, &request).is_none());
+    }
+
+    #[test]
+    fn test_rate_limit() {
+        let (queue_tx, _queue_rx) = channel();
+
+        spawn(move || {
+            let queue = HttpQueue::default();
+            queue.lock().unwrap().insert(create_listen_event(
+                "listen1",
+                None,
+                json!({}),
+                "/webhook",
+                RequestMethod::Get,
+                None,
+            ));
+            let config = HttpConfiguration {
+                rate_limit: RateLimitConfiguration {
+                    rate: 0.1,
+                    burst: 2,
+                }
+                .into(),
+                ..HttpConfiguration::new("127.0.0.1:13338".to_string())
+            };
+            http_executor(
+                queue,
+                Default::default(),
+                Default::default(),
+                &config,
+                &Events::new(Default::default()),
+                None,
+                &Default::default(),
+                queue_tx,
+            )
+            .unwrap();
+        });
+
+        wait_for_server("127.0.0.1:13338");
+        let statuses: Vec<u16> = (0..3)
+            .map(|_| {
+                reqwest::blocking::get("http://127.0.0.1:13338/webhook")
+                    .unwrap()
+                    .status()
+                    .as_u16()
+            })
+            .collect();
+        assert_eq!(statuses, [200, 200, 429]);
+    }
+
+    #[test]
+    fn test_body_limit() {
+        let (queue_tx, queue_rx) = channel();
+
+        spawn(move || {
+            let queue = HttpQueue::default();
+            queue.lock().unwrap().insert(create_listen_event(
+                "listen1",
+                Some("test1".to_string()),
+                json!({}),
+                "/limited",
+                RequestMethod::Post,
+                None,
+            ));
+            let events = Events::new([create_time_event("test1", json!({}))].into());
+            let config = HttpConfiguration {
+                max_body_size: 10,
+                ..HttpConfiguration::new("127.0.0.1:13337".to_string())
+            };
+            http_executor(
+                queue,
+                Default::default(),
+                Default::default(),
+                &config,
+                &events,
+                None,
+                &Default::default(),
+                queue_tx,
+            )
+            .unwrap();
+        });
+
+        wait_for_server("127.0.0.1:13337");
+        let client = reqwest::blocking::Client::new();
+        let response = client
+            .post("http://127.0.0.1:13337/limited")
+            .body(r#"{"value":"too large"}"#)
+            .send()
+            .unwrap();
+        assert_eq!(response.status(), 413);
+        assert!(queue_rx.recv_timeout(Duration::from_millis(100)).is_err());
+
+        let response = client
+            .post("http://127.0.0.1:13337/limited")
+            .body(r#"{"a":1}"#)
+            .send()
+            .unwrap();
+        assert_eq!(response.status(), 200);
+        let event = queue_rx.recv_timeout(Duration::from_millis(200)).unwrap();
+        assert_eq!(event.data, json!({ "a": 1 }));
+    }
+
+    #[test]
+    fn test_read_timeout() {
+        let (queue_tx, queue_rx) = channel();
+
+        spawn(move || {
+            let queue = HttpQueue::default();
+            queue.lock().unwrap().insert(create_listen_event(
+                "listen1",
+                Some("test1".to_string()),
+                json!({}),
+                "/stalled",
+                RequestMethod::Post,
+                None,
+            ));
+            let events = Events::new([create_time_event("test1", json!({}))].into());
+            let config = HttpConfiguration {
+                read_timeout: 1,
+                ..HttpConfiguration::new("127.0.0.1:13356".to_string())
+            };
+            http_executor(
+                queue,
+                Default::default(),
+                Default::default(),
+                &config,
+                &events,
+                None,
+                &Default::default(),
+                queue_tx,
+            )
+            .unwrap();
+        });
+
+        wait_for_server("127.0.0.1:13356");
+        // smaller bodies are read by tiny_http before the request is handled
+        let mut stream = TcpStream::connect("127.0.0.1:13356").unwrap();
+        stream
+            .write_all(
+                b"POST /stalled HTTP/1.1\r\nHost: localhost\r\nContent-Length: 2048\r\n\r\n{\"a\"",
+            )
+            .unwrap();
+        stream
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        let mut response = [0; 12];
+        stream.read_exact(&mut response).unwrap();
+        assert_eq!(&response, b"HTTP/1.1 408");
+        assert!(queue_rx.recv_timeout(Duration::from_millis(100)).is_err());
+    }
+
+    #[test]
+    fn test_multipart_upload() {
+        let (queue_tx, queue_rx) = channel();
+        let directory = "/tmp/_test_http_uploads";
+        std::fs::remove_dir_all(directory).ok();
+
+        spawn(move || {
+            let queue = HttpQueue::default();
+            let mut event = create_listen_event(
+                "listen1",
+                Some("test1".to_string()),
+                json!({}),
+                "/snapshot",
+                RequestMethod::Post,
+                None,
+            );
+            if let EventType::ApiListen(e) = &mut event.event_type {
+                e.request_content = RequestContent::Multipart;
+                e.upload_directory = Some(directory.into());
+            }
+            queue.lock().unwrap().insert(event);
+            let events = Events::new([create_time_event("test1", json!({}))].into());
+            let config = HttpConfiguration::new("127.0.0.1:13336".to_string());
+            http_executor(
+                queue,
+                Default::default(),
+                Default::default(),
+                &config,
+                &events,
+                None,
+                &Default::default(),
+                queue_tx,
+            )
+            .unwrap();
+        });
+
+        wait_for_server("127.0.0.1:13336");
+        let body = "--xyz\r\nContent-Disposition: form-data; name=\"camera\"\r\n\r\nhall\r\n--xyz\r\nContent-Disposition: form-data; name=\"snapshot\"; filename=\"../image.jpg\"\r\n\r\nimage\r\n--xyz--\r\n";
+        reqwest::blocking::Client::new()
+            .post("http://127.0.0.1:13336/snapshot")
+            .header("Content-Type", "multipart/form-data; boundary=xyz")
+            .body(body)
+            .send()
+            .unwrap();
+
+        let event = queue_rx.recv_timeout(Duration::from_millis(200)).unwrap();
+        assert_eq!(event.data, json!({ "camera": "hall" }));
+        let metadata = serde_json::to_value(&event.metadata).unwrap();
+        let path = metadata["listen1"]["files"]["snapshot"].as_str().unwrap();
+        assert!(path.starts_with(directory));
+        assert!(path.ends_with("_image.jpg"));
+        assert_eq!(std::fs::read_to_string(path).unwrap(), "image");
+    }
+
+    fn wait_for_server(address: &str) {
+        (0..20)
+            .find_map(|_| {
+                TcpStream::connect(address)
+                    .map_err(|_| sleep(Duration::from_millis(50)))
+                    .ok()
+            })
+            .unwrap();
+    }
+
     fn create_time_event(name: &str, data: Value) -> ReferencingEvent {
         ReferencingEvent {
             event_type: EventType::Time(TimeEvent {
@@ -314,14 +1208,17 @@ mod tests {
     ) -> ReferencingEvent {
         ReferencingEvent {
             event_type: EventType::ApiListen(ApiListenEvent {
-                path: uri.to_string(),
+                path: PathMatch::Prefix(uri.to_string()),
                 headers: Default::default(),
                 response_body: template,
-                method: request_method,
+                template_file: Default::default(),
+                method: vec![request_method],
                 request_content: RequestContent::Json,
                 response_content: ResponseContent::Json,
                 action: Default::default(),
                 pool_id: Default::default(),
+                stream: Default::default(),
+                upload_directory: Default::default(),
             }),
             next_event: next_event.map(NextEvent::Name),
             data: Data::Json(data),
@@ -0,0 +1,411 @@
+use core::time::Duration;
+use std::{
+    io::{BufRead, BufReader, ErrorKind, Read, Write},
+    net::TcpStream,
+    sync::{mpsc::Sender, Arc},
+    thread::sleep,
+};
+
+use anyhow::{anyhow, bail};
+use log::{debug, info, warn};
+use rumqttc::tokio_rustls::rustls::{
+    pki_types::ServerName, ClientConfig, ClientConnection, RootCertStore, StreamOwned,
+};
+use serde_json::json;
+
+use crate::{
+    config::ImapConfiguration,
+    events::{data::Data, email_receive::EmailMessage, EventType, Events, ReferencingEvent},
+};
+
+/// seconds to wait before reconnecting to the imap server
+const RECONNECT_DELAY: u64 = 30;
+/// seconds before idle is renewed, servers drop idle connections after 30 minutes
+const IDLE_TIMEOUT: u64 = 25 * 60;
+
+pub fn imap_executor(
+    pool_id: &str,
+    config: &ImapConfiguration,
+    events: &Events,
+    queue_tx: Sender<ReferencingEvent>,
+) -> anyhow::Result<()> {
+    loop {
+        match connect(pool_id, config, events, &queue_tx) {
+            Ok(()) => return Ok(()),
+            Err(e) => warn!(
+                "Imap mailbox {} on {} failed {e}",
+                config.mailbox, config.host
+            ),
+        }
+        sleep(Duration::from_secs(RECONNECT_DELAY));
+    }
+}
+
+fn connect(
+    pool_id: &str,
+    config: &ImapConfiguration,
+    events: &Events,
+    queue_tx: &Sender<ReferencingEvent>,
+) -> anyhow::Result<()> {
+    let stream = TcpStream::connect((config.host.as_str(), config.port()))?;
+    stream.set_read_timeout(Some(Duration::from_secs(IDLE_TIMEOUT)))?;
+    if config.tls {
+        let connection =
+            ClientConnection::new(tls_config()?, ServerName::try_from(config.host.clone())?)?;
+        watch_mailbox(
+            ImapClient::new(StreamOwned::new(connection, stream))?,
+            pool_id,
+            config,
+            events,
+            queue_tx,
+        )
+    } else {
+        watch_mailbox(ImapClient::new(stream)?, pool_id, config, events, queue_tx)
+    }
+}
+
+/// returns once the queue is closed
+fn watch_mailbox<S: Read + Write>(
+    mut client: ImapClient<S>,
+    pool_id: &str,
+    config: &ImapConfiguration,
+    events: &Events,
+    queue_tx: &Sender<ReferencingEvent>,
+) -> anyhow::Result<()> {
+    client.command(&format!(
+        "LOGIN {} {}",
+        quote(&config.user),
+        quote(&config.pass)
+    ))?;
+    let mut next_uid = client.select(&config.mailbox)?;
+    info!(
+        "Watching imap mailbox {} on {} for new messages",
+        config.mailbox, config.host
+    );
+    loop {
+        for uid in client.search(next_uid)? {
+            let raw = client.fetch(uid)?;
+            next_uid = uid + 1;
+            let Some(message) = EmailMessage::parse(uid, &raw) else {
+                warn!("Unable to parse email {uid} in {}", config.mailbox);
+                continue;
+            };
+            debug!("Email received from {} {}", message.from, message.subject);
+            if let Some(e) = handle_incoming(events, pool_id, &message) {
+                if queue_tx.send(e).is_err() {
+                    return Ok(());
+                }
+            }
+        }
+        client.idle()?;
+    }
+}
+
+fn handle_incoming(
+    events: &Events,
+    pool_id: &str,
+    message: &EmailMessage,
+) -> Option<ReferencingEvent> {
+    let ref_event = events.iter().find(|ref_event| {
+        matches!(&ref_event.event_type, EventType::EmailReceive(e) if e.matches(pool_id, message))
+    })?;
+
+    if let Some(mut event) = events.get_next_event(ref_event) {
+        event.merge(Data::Json(message.to_json()));
+        let mut metadata = ref_event.metadata.clone();
+        metadata.merge(json!({ ref_event.name.as_str(): {"pool_id": pool_id }}).into());
+        event.metadata.merge(metadata);
+        Some(event)
+    } else {
+        debug!("Received email {} without further handler", ref_event.name);
+        None
+    }
+}
+
+fn tls_config() -> anyhow::Result<Arc<ClientConfig>> {
+    let mut roots = RootCertStore::empty();
+    for cert in rustls_native_certs::load_native_certs()? {
+        if let Err(e) = roots.add(cert) {
+            warn!("Ignoring invalid system certificate {e}");
+        }
+    }
+    Ok(Arc::new(
+        ClientConfig::builder()
+            .with_root_certificates(roots)
+            .with_no_client_auth(),
+    ))
+}
+
+fn quote(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// server response with literals e.g. message bodies separated from the text
+#[derive(Debug, Default)]
+struct Response {
+    text: String,
+    literals: Vec<Vec<u8>>,
+}
+
+/// minimal imap client supporting the commands required to follow a mailbox
+struct ImapClient<S: Read + Write> {
+    reader: BufReader<S>,
+    tag: u32,
+}
+
+impl<S: Read + Write> ImapClient<S> {
+    fn new(stream: S) -> anyhow::Result<Self> {
+        let mut client = Self {
+            reader: BufReader::new(stream),
+            tag: 0,
+        };
+        let greeting = client.read_response()?;
+        if !greeting.text.starts_with("* OK") && !greeting.text.starts_with("* PREAUTH") {
+            bail!("Unexpected greeting {}", greeting.text);
+        }
+        Ok(client)
+    }
+
+    /// returns the next uid
+    fn select(&mut self, mailbox: &str) -> anyhow::Result<u32> {
+        let responses = self.command(&format!("SELECT {}", quote(mailbox)))?;
+        if let Some(uid) = responses.iter().find_map(|r| {
+            let (_, rest) = r.text.split_once("[UIDNEXT ")?;
+            rest.split_once(']')?.0.parse().ok()
+        }) {
+            return Ok(uid);
+        }
+        Ok(self
+            .search(1)?
+            .into_iter()
+            .max()
+            .map(|u| u + 1)
+            .unwrap_or(1))
+    }
+
+    /// uids of messages starting with the specified uid
+    fn search(&mut self, from_uid: u32) -> anyhow::Result<Vec<u32>> {
+        let responses = self.command(&format!("UID SEARCH UID {from_uid}:*"))?;
+        // the last message is always returned for ranges ending with *
+        Ok(responses
+            .iter()
+            .filter_map(|r| r.text.strip_prefix("* SEARCH"))
+            .flat_map(|s| s.split_whitespace().filter_map(|u| u.parse().ok()))
+            .filter(|u| *u >= from_uid)
+            .collect())
+    }
+
+    /// raw message without marking it as seen
+    fn fetch(&mut self, uid: u32) -> anyhow::Result<Vec<u8>> {
+        self.command(&format!("UID FETCH {uid} (BODY.PEEK[])"))?
+            .into_iter()
+            .find_map(|r| r.literals.into_iter().next())
+            .ok_or_else(|| anyhow!("No body returned for message {uid}"))
+    }
+
+    /// waits until new messages arrive or the idle timeout expires
+    fn idle(&mut self) -> anyhow::Result<()> {
+        let tag = self.send("IDLE")?;
+        loop {
+            let response = self.read_response()?;
+            if response.text.starts_with('+') {
+                break;
+            }
+            if response.text.starts_with(&tag) {
+                bail!("Idle failed {}", response.text);
+            }
+        }
+        loop {
+            match self.read_response() {
+                Ok(r) if r.text.ends_with(" EXISTS") => break,
+                Ok(_) => continue,
+                Err(e)
+                    if matches!(
+                        e.downcast_ref::<std::io::Error>().map(|e| e.kind()),
+                        Some(ErrorKind::WouldBlock | ErrorKind::TimedOut)
+                    ) =>
+                {
+                    break
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        self.reader.get_mut().write_all(b"DONE\r\n")?;
+        self.reader.get_mut().flush()?;
+        self.read_until_tagged(&tag)?;
+        Ok(())
+    }
+
+    /// returns untagged responses
+    fn command(&mut self, command: &str) -> anyhow::Result<Vec<Response>> {
+        let tag = self.send(command)?;
+        self.read_until_tagged(&tag)
+    }
+
+    fn send(&mut self, command: &str) -> anyhow::Result<String> {
+        self.tag += 1;
+        let tag = format!("A{}", self.tag);
+        let stream = self.reader.get_mut();
+        stream.write_all(format!("{tag} {command}\r\n").as_bytes())?;
+        stream.flush()?;
+        Ok(tag)
+    }
+
+    fn read_until_tagged(&mut self, tag: &str) -> anyhow::Result<Vec<Response>> {
+        let mut responses = Vec::new();
+        loop {
+            let response = self.read_response()?;
+            let Some(status) = response.text.strip_prefix(tag) else {
+                responses.push(response);
+                continue;
+            };
+            if !status.starts_with(" OK") {
+                bail!("Imap command failed{status}");
+            }
+            return Ok(responses);
+        }
+    }
+
+    fn read_response(&mut self) -> anyhow::Result<Response> {
+        let mut response = Response::default();
+        loop {
+            let mut line = Vec::new();
+            if self.reader.read_until(b'\n', &mut line)? == 0 {
+                bail!("Connection closed");
+            }
+            let line = String::from_utf8_lossy(&line);
+            let line = line.trim_end_matches(['\r', '\n']);
+            response.text.push_str(line);
+            // literals are announced as {size} at the end of the line
+            let Some(size) = line
+                .strip_suffix('}')
+                .and_then(|l| l.rsplit_once('{'))
+                .and_then(|(_, size)| size.parse::<usize>().ok())
+            else {
+                return Ok(response);
+            };
+            let mut literal = vec![0; size];
+            self.reader.read_exact(&mut literal)?;
+            response.literals.push(literal);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use crate::events::{email_receive::EmailReceiveEvent, time::TimeEvent, NextEvent};
+
+    use super::*;
+
+    struct ScriptedStream {
+        input: Cursor<Vec<u8>>,
+        output: Vec<u8>,
+    }
+
+    impl Read for ScriptedStream {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            self.input.read(buf)
+        }
+    }
+
+    impl Write for ScriptedStream {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.output.write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_client() {
+        let message = b"From: panel@alarm.example.com\r\nSubject: Zone 3\r\n\r\nGarage\r\n";
+        let mut input = b"* OK IMAP4rev1 ready\r\n\
+A1 OK LOGIN completed\r\n\
+* 3 EXISTS\r\n\
+* OK [UIDNEXT 12] Predicted next UID\r\n\
+A2 OK [READ-WRITE] SELECT completed\r\n\
+* SEARCH 11 12\r\n\
+A3 OK SEARCH completed\r\n"
+            .to_vec();
+        input.extend_from_slice(
+            format!("* 3 FETCH (UID 12 BODY[] {{{}}}\r\n", message.len()).as_bytes(),
+        );
+        input.extend_from_slice(message);
+        input.extend_from_slice(
+            b")\r\nA4 OK FETCH completed\r\n+ idling\r\n* 4 EXISTS\r\nA5 OK IDLE terminated\r\n",
+        );
+
+        let mut client = ImapClient::new(ScriptedStream {
+            input: Cursor::new(input),
+            output: Vec::new(),
+        })
+        .unwrap();
+        client
+            .command(&format!("LOGIN {} {}", quote("home"), quote("pa\"ss")))
+            .unwrap();
+        assert_eq!(client.select("INBOX").unwrap(), 12);
+        assert_eq!(client.search(12).unwrap(), [12]);
+        assert_eq!(client.fetch(12).unwrap(), message);
+        client.idle().unwrap();
+        assert_eq!(
+            String::from_utf8_lossy(&client.reader.get_ref().output),
+            "A1 LOGIN \"home\" \"pa\\\"ss\"\r\n\
+A2 SELECT \"INBOX\"\r\n\
+A3 UID SEARCH UID 12:*\r\n\
+A4 UID FETCH 12 (BODY.PEEK[])\r\n\
+A5 IDLE\r\n\
+DONE\r\n"
+        );
+        assert!(client.search(13).is_err());
+    }
+
+    #[test]
+    fn test_handle_incoming() {
+        let events = Events::new(
+            [
+                ReferencingEvent {
+                    event_type: EventType::EmailReceive(EmailReceiveEvent {
+                        subject: Some("invoice".to_string()),
+                        ..Default::default()
+                    }),
+                    next_event: NextEvent::Name("test1".to_string()).into(),
+                    name: "invoice_received".to_string(),
+                    ..Default::default()
+                },
+                ReferencingEvent {
+                    event_type: EventType::Time(TimeEvent {
+                        execute_time: "now".parse().unwrap(),
+                        event_id: None,
+                    }),
+                    name: "test1".to_string(),
+                    ..Default::default()
+                },
+            ]
+            .into_iter()
+            .collect(),
+        );
+        let message = EmailMessage {
+            uid: 3,
+            from: "billing@power.example.com".to_string(),
+            subject: "Newsletter".to_string(),
+            ..Default::default()
+        };
+        assert!(handle_incoming(&events, "default", &message).is_none());
+
+        let message = EmailMessage {
+            subject: "Your Invoice for October".to_string(),
+            ..message
+        };
+        let event = handle_incoming(&events, "default", &message).unwrap();
+        assert_eq!(event.name, "test1");
+        let Data::Json(data) = event.data else {
+            panic!("Expected json data");
+        };
+        assert_eq!(data["from"], "billing@power.example.com");
+        assert_eq!(data["uid"], 3);
+    }
+}
@@ -0,0 +1,189 @@
+use std::{
+    io::{BufRead, BufReader},
+    process::{Command, Stdio},
+    sync::mpsc::Sender,
+};
+
+use anyhow::{anyhow, Context};
+use indexmap::IndexSet;
+use log::{debug, warn};
+use serde_json::{json, Value};
+
+use crate::events::{
+    data::Data,
+    journal_watch::{JournalEntry, JournalPriority, JournalWatchEvent},
+    EventType, Events, ReferencingEvent,
+};
+
+pub fn journal_executor(events: &Events, queue_tx: Sender<ReferencingEvent>) -> anyhow::Result<()> {
+    // only new entries are followed
+    let mut child = Command::new("journalctl")
+        .args(["--follow", "--output=json", "--lines=0"])
+        .args(filter_args(events))
+        .stdout(Stdio::piped())
+        .spawn()
+        .context("Unable to start journalctl")?;
+    let reader = BufReader::new(child.stdout.take().expect("stdout"));
+    for line in reader.lines() {
+        let line = line?;
+        let entry = match serde_json::from_str(&line) {
+            Ok(Value::Object(fields)) => JournalEntry(fields),
+            Ok(_) => continue,
+            Err(e) => {
+                warn!("Unable to parse journal entry {e}");
+                continue;
+            }
+        };
+        if let Some(e) = handle_incoming(events, &entry) {
+            queue_tx.send(e)?;
+        }
+    }
+    let status = child.wait()?;
+    Err(anyhow!("Journalctl exited with {status}"))
+}
+
+/// units and priority are filtered by journalctl when every journal_watch event specifies them
+fn filter_args(events: &Events) -> Vec<String> {
+    let watched: Vec<&JournalWatchEvent> = events
+        .iter()
+        .filter_map(|ref_event| match &ref_event.event_type {
+            EventType::JournalWatch(e) => Some(e),
+            _ => None,
+        })
+        .collect();
+    let mut args = Vec::new();
+    if let Some(units) = watched
+        .iter()
+        .map(|e| e.unit.as_deref())
+        .collect::<Option<IndexSet<&str>>>()
+    {
+        args.extend(units.into_iter().map(|u| format!("--unit={u}")));
+    }
+    if let Some(priority) = watched
+        .iter()
+        .map(|e| e.priority)
+        .collect::<Option<Vec<JournalPriority>>>()
+        .and_then(|p| p.into_iter().max())
+    {
+        args.push(format!("--priority={}", priority.as_str()));
+    }
+    args
+}
+
+fn handle_incoming(events: &Events, entry: &JournalEntry) -> Option<ReferencingEvent> {
+    let ref_event = events.iter().find(
+        |ref_event| matches!(&ref_event.event_type, EventType::JournalWatch(e) if e.matches(entry)),
+    )?;
+
+    if let Some(mut event) = events.get_next_event(ref_event) {
+        event.merge(Data::Json(
+            json!({"message": entry.message(), "fields": entry.0}),
+        ));
+        event.metadata.merge(ref_event.metadata.clone());
+        Some(event)
+    } else {
+        debug!(
+            "Received journal event {} without further handler",
+            ref_event.name
+        );
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use regex::Regex;
+
+    use crate::events::{time::TimeEvent, NextEvent};
+
+    use super::*;
+
+    #[test]
+    fn test_handle_incoming() {
+        let events = Events::new(
+            [
+                ReferencingEvent {
+                    event_type: EventType::JournalWatch(JournalWatchEvent {
+                        identifier: Some("sshd".to_string()),
+                        message: Some(Regex::new("^Failed password").unwrap()),
+                        ..Default::default()
+                    }),
+                    next_event: NextEvent::Name("test1".to_string()).into(),
+                    name: "ssh_failure".to_string(),
+                    ..Default::default()
+                },
+                ReferencingEvent {
+                    event_type: EventType::Time(TimeEvent {
+                        execute_time: "now".parse().unwrap(),
+                        event_id: None,
+                    }),
+                    name: "test1".to_string(),
+                    ..Default::default()
+                },
+            ]
+            .into_iter()
+            .collect(),
+        );
+        let Value::Object(fields) = json!({
+            "MESSAGE": "Accepted publickey for pi",
+            "SYSLOG_IDENTIFIER": "sshd",
+        }) else {
+            unreachable!()
+        };
+        assert!(handle_incoming(&events, &JournalEntry(fields)).is_none());
+
+        let Value::Object(fields) = json!({
+            "MESSAGE": "Failed password for root",
+            "SYSLOG_IDENTIFIER": "sshd",
+        }) else {
+            unreachable!()
+        };
+        let event = handle_incoming(&events, &JournalEntry(fields)).unwrap();
+        assert_eq!(event.name, "test1");
+        assert_eq!(
+            event.data,
+            Data::Json(json!({
+                "message": "Failed password for root",
+                "fields": {"MESSAGE": "Failed password for root", "SYSLOG_IDENTIFIER": "sshd"}
+            }))
+        );
+    }
+
+    #[test]
+    fn test_filter_args() {
+        let watch = |unit: Option<&str>, priority| ReferencingEvent {
+            event_type: EventType::JournalWatch(JournalWatchEvent {
+                unit: unit.map(str::to_string),
+                priority,
+                ..Default::default()
+            }),
+            name: format!("{unit:?}"),
+            ..Default::default()
+        };
+        let events = Events::new(
+            [
+                watch(Some("ssh.service"), Some(JournalPriority::Err)),
+                watch(Some("cron.service"), Some(JournalPriority::Warning)),
+            ]
+            .into_iter()
+            .collect(),
+        );
+        assert_eq!(
+            filter_args(&events),
+            [
+                "--unit=ssh.service",
+                "--unit=cron.service",
+                "--priority=warning"
+            ]
+        );
+        let events = Events::new(
+            [
+                watch(Some("ssh.service"), Some(JournalPriority::Err)),
+                watch(None, None),
+            ]
+            .into_iter()
+            .collect(),
+        );
+        assert!(filter_args(&events).is_empty());
+    }
+}
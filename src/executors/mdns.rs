@@ -0,0 +1,169 @@
+use std::{
+    collections::HashSet,
+    sync::mpsc::Sender,
+    thread::{scope, Builder},
+};
+
+use indexmap::IndexSet;
+use log::{debug, error, info};
+use mdns_sd::{ResolvedService, ServiceDaemon, ServiceEvent};
+use serde_json::{json, Map, Value};
+
+use crate::events::{data::Data, mdns::MdnsState, EventType, Events, ReferencingEvent};
+
+/// browse service types referenced by mdns events
+pub fn mdns_executor(events: &Events, queue_tx: Sender<ReferencingEvent>) -> anyhow::Result<()> {
+    let service_types: IndexSet<String> = events
+        .iter()
+        .filter_map(|e| match &e.event_type {
+            EventType::Mdns(m) => Some(m.service_type()),
+            _ => None,
+        })
+        .collect();
+    if service_types.is_empty() {
+        return Ok(());
+    }
+    let daemon = ServiceDaemon::new()?;
+
+    scope(|s| -> anyhow::Result<()> {
+        for service_type in service_types {
+            let receiver = daemon.browse(&service_type)?;
+            let queue_tx = queue_tx.clone();
+            let result = Builder::new()
+                .name(format!("mdns {service_type}"))
+                .spawn_scoped(s, move || {
+                    // resolved services are announced again when their records change
+                    let mut known = HashSet::new();
+                    for service_event in receiver {
+                        let (state, data) = match service_event {
+                            ServiceEvent::ServiceResolved(service)
+                                if known.insert(service.fullname.clone()) =>
+                            {
+                                (MdnsState::Appeared, service_data(&service))
+                            }
+                            ServiceEvent::ServiceRemoved(ty, fullname)
+                                if known.remove(&fullname) =>
+                            {
+                                (
+                                    MdnsState::Disappeared,
+                                    json!({"name": fullname, "service": ty}),
+                                )
+                            }
+                            _ => continue,
+                        };
+                        info!("Mdns service {} {state}", data["name"]);
+                        if let Some(e) = handle_incoming(events, &service_type, state, data) {
+                            if queue_tx.send(e).is_err() {
+                                return;
+                            }
+                        }
+                    }
+                });
+            if let Err(e) = result {
+                error!("Unable to browse mdns services {e}");
+            }
+        }
+        Ok(())
+    })
+}
+
+fn handle_incoming(
+    events: &Events,
+    service_type: &str,
+    state: MdnsState,
+    data: Value,
+) -> Option<ReferencingEvent> {
+    let ref_event = events.iter().find(|ref_event| {
+        matches!(&ref_event.event_type, EventType::Mdns(e) if e.matches(service_type, state))
+    })?;
+
+    if let Some(mut event) = events.get_next_event(ref_event) {
+        event.merge(Data::Json(data));
+        let mut metadata = ref_event.metadata.clone();
+        metadata.merge(json!({ ref_event.name.as_str(): {"state": state.to_string() }}).into());
+        event.metadata.merge(metadata);
+        Some(event)
+    } else {
+        debug!(
+            "Received mdns event {} without further handler",
+            ref_event.name
+        );
+        None
+    }
+}
+
+fn service_data(service: &ResolvedService) -> Value {
+    let mut addresses: Vec<String> = service
+        .addresses
+        .iter()
+        .map(|a| a.to_ip_addr().to_string())
+        .collect();
+    addresses.sort();
+    let properties: Map<String, Value> = service
+        .txt_properties
+        .iter()
+        .map(|p| (p.key().to_string(), p.val_str().into()))
+        .collect();
+    json!({
+        "name": service.fullname,
+        "service": service.ty_domain,
+        "host": service.host,
+        "port": service.port,
+        "addresses": addresses,
+        "properties": properties,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::events::{mdns::MdnsEvent, time::TimeEvent, NextEvent};
+
+    use super::*;
+
+    #[test]
+    fn test_handle_incoming() {
+        let events = Events::new(
+            [
+                ReferencingEvent {
+                    event_type: EventType::Mdns(MdnsEvent {
+                        service: "_googlecast._tcp".to_string(),
+                        when: Some(MdnsState::Disappeared),
+                    }),
+                    next_event: NextEvent::Name("test1".to_string()).into(),
+                    name: "cast_removed".to_string(),
+                    ..Default::default()
+                },
+                ReferencingEvent {
+                    event_type: EventType::Time(TimeEvent {
+                        execute_time: "now".parse().unwrap(),
+                        event_id: None,
+                    }),
+                    name: "test1".to_string(),
+                    ..Default::default()
+                },
+            ]
+            .into_iter()
+            .collect(),
+        );
+        let data = json!({"name": "tv._googlecast._tcp.local."});
+        assert!(handle_incoming(
+            &events,
+            "_googlecast._tcp.local.",
+            MdnsState::Appeared,
+            data.clone()
+        )
+        .is_none());
+
+        let event = handle_incoming(
+            &events,
+            "_googlecast._tcp.local.",
+            MdnsState::Disappeared,
+            data.clone(),
+        )
+        .unwrap();
+        assert_eq!(event.name, "test1");
+        assert_eq!(event.data, Data::Json(data));
+        let metadata: Value = serde_json::to_value(&event.metadata).unwrap();
+        assert_eq!(metadata["cast_removed"]["state"], "disappeared");
+    }
+}
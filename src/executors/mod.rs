@@ -1,7 +1,41 @@
+#[cfg(feature = "amqp")]
+pub mod amqp;
+#[cfg(feature = "ble")]
+pub mod ble;
+#[cfg(feature = "dbus")]
+pub mod dbus;
+#[cfg(unix)]
+pub mod docker;
 #[cfg(target_os = "linux")]
 pub mod evdev;
+#[cfg(unix)]
+pub mod fifo;
 pub mod file;
+#[cfg(feature = "gpio")]
+pub mod gpio;
 pub mod http;
+#[cfg(feature = "email")]
+pub mod imap;
+#[cfg(target_os = "linux")]
+pub mod journal;
+#[cfg(feature = "mdns")]
+pub mod mdns;
 pub mod mqtt;
+pub mod nats;
+pub mod ping;
 pub mod queue;
+#[cfg(feature = "redis")]
+pub mod redis;
+#[cfg(feature = "serial")]
+pub mod serial;
+pub mod ssdp;
+pub mod stdin;
+pub mod syslog;
+pub mod tcp;
 pub mod time;
+pub mod udp;
+#[cfg(unix)]
+pub mod unix;
+pub mod websocket;
+#[cfg(feature = "zmq")]
+pub mod zmq;
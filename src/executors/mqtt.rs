@@ -1,59 +1,297 @@
-use std::sync::mpsc::Sender;
+use core::{str::from_utf8, time::Duration};
+use std::{sync::mpsc::Sender, thread::sleep};
 
-use log::{debug, error};
-use rumqttc::{Connection, Event, Incoming};
-use serde_json::json;
+use indexmap::IndexMap;
+use log::{debug, error, info};
+use rumqttc::{
+    v5::{self, mqttbytes::v5::PublishProperties},
+    Connection, Event, Incoming, QoS,
+};
+use serde_json::{json, Value};
 
-use crate::events::{EventType, Events, ReferencingEvent};
+use crate::{
+    config::{MqttMessage, MqttReconnect},
+    events::{
+        mqtt_subscribe::{MqttSubscription, MqttSubscriptions},
+        EventName, EventType, Events, ReferencingEvent,
+    },
+    pools::mqtt::{MqttClient, MqttConnection, MqttEventLoop},
+};
+
+pub const CONNECTED_EVENT: &str = "__mqtt_connected";
+pub const DISCONNECTED_EVENT: &str = "__mqtt_disconnected";
 
 pub fn mqtt_executor(
-    mut connection: Connection,
+    connection: MqttConnection,
     events: &Events,
     queue_tx: Sender<ReferencingEvent>,
 ) -> anyhow::Result<()> {
-    let mut show_error = true;
+    let MqttConnection {
+        pool_id,
+        client,
+        event_loop,
+        birth,
+        reconnect,
+        subscriptions,
+    } = connection;
+    let mut state = ConnectionState {
+        pool_id: &pool_id,
+        client: &client,
+        birth: birth.as_ref(),
+        reconnect,
+        delay: Duration::ZERO,
+        connected: false,
+        resubscribe: false,
+        show_error: true,
+        subscriptions: &subscriptions,
+    };
+    match event_loop {
+        MqttEventLoop::V4(mut event_loop) => {
+            mqtt_v4_executor(&mut event_loop, events, queue_tx, &mut state)
+        }
+        MqttEventLoop::V5(mut event_loop) => {
+            mqtt_v5_executor(&mut event_loop, events, queue_tx, &mut state)
+        }
+    }
+}
+
+fn mqtt_v4_executor(
+    connection: &mut Connection,
+    events: &Events,
+    queue_tx: Sender<ReferencingEvent>,
+    state: &mut ConnectionState,
+) -> anyhow::Result<()> {
     for notification in connection.iter() {
         match notification {
+            Ok(Event::Incoming(Incoming::ConnAck(_))) => {
+                for e in state.on_connected(events) {
+                    queue_tx.send(e)?;
+                }
+            }
             Ok(Event::Incoming(Incoming::Publish(packet))) => {
-                show_error = true;
+                state.show_error = true;
                 debug!("Incoming mqtt event {} {:?}", packet.topic, packet.payload);
-                if let Some(e) = handle_incoming(events, &packet.topic, &packet.payload) {
+                if let Some(e) = handle_incoming(
+                    events,
+                    &state.subscriptions.lock().expect("subscriptions lock"),
+                    &packet.topic,
+                    &packet.payload,
+                    None,
+                ) {
                     queue_tx.send(e)?;
                 }
             }
             Ok(_) => {
-                show_error = true;
+                state.show_error = true;
                 continue;
             }
             Err(e) => {
-                if show_error {
-                    error!("Receive mqtt error {e}. Suppressing further messages until success");
+                for e in state.on_error(events, &e.to_string()) {
+                    queue_tx.send(e)?;
                 }
-                show_error = false;
+                state.wait();
             }
         };
     }
     Ok(())
 }
 
-fn handle_incoming(events: &Events, topic: &str, payload: &[u8]) -> Option<ReferencingEvent> {
-    let event_associated = events
-        .iter()
-        .find_map(|ref_event| match &ref_event.event_type {
-            EventType::MqttSubscribe(e) if e.matches(topic, payload) => {
-                debug!(
-                    "Event found event {} next event {:?}",
-                    ref_event.name, ref_event.next_event
-                );
-                ref_event.into()
+fn mqtt_v5_executor(
+    connection: &mut v5::Connection,
+    events: &Events,
+    queue_tx: Sender<ReferencingEvent>,
+    state: &mut ConnectionState,
+) -> anyhow::Result<()> {
+    for notification in connection.iter() {
+        match notification {
+            Ok(v5::Event::Incoming(v5::Incoming::ConnAck(_))) => {
+                for e in state.on_connected(events) {
+                    queue_tx.send(e)?;
+                }
+            }
+            Ok(v5::Event::Incoming(v5::Incoming::Publish(packet))) => {
+                state.show_error = true;
+                let Ok(topic) = from_utf8(&packet.topic) else {
+                    error!("Received invalid mqtt topic {:?}", packet.topic);
+                    continue;
+                };
+                debug!("Incoming mqtt event {topic} {:?}", packet.payload);
+                let properties = packet.properties.as_ref().map(properties_to_json);
+                if let Some(e) = handle_incoming(
+                    events,
+                    &state.subscriptions.lock().expect("subscriptions lock"),
+                    topic,
+                    &packet.payload,
+                    properties,
+                ) {
+                    queue_tx.send(e)?;
+                }
+            }
+            Ok(_) => {
+                state.show_error = true;
+                continue;
+            }
+            Err(e) => {
+                for e in state.on_error(events, &e.to_string()) {
+                    queue_tx.send(e)?;
+                }
+                state.wait();
             }
-            _ => None,
-        })?;
+        };
+    }
+    Ok(())
+}
+
+struct ConnectionState<'a> {
+    pool_id: &'a str,
+    client: &'a MqttClient,
+    birth: Option<&'a MqttMessage>,
+    reconnect: MqttReconnect,
+    delay: Duration,
+    connected: bool,
+    /// subscriptions are lost with the session after the first connection
+    resubscribe: bool,
+    show_error: bool,
+    subscriptions: &'a MqttSubscriptions,
+}
+
+impl ConnectionState<'_> {
+    fn on_connected(&mut self, events: &Events) -> Vec<ReferencingEvent> {
+        info!("Mqtt pool={} connected", self.pool_id);
+        self.show_error = true;
+        self.connected = true;
+        self.delay = Duration::ZERO;
+        if self.resubscribe {
+            resubscribe(
+                self.client,
+                &self.subscriptions.lock().expect("subscriptions lock"),
+            );
+        }
+        self.resubscribe = true;
+        if let Some(birth) = self.birth {
+            publish_birth(self.client, birth);
+        }
+        connection_events(events, CONNECTED_EVENT, self.pool_id)
+    }
+
+    fn on_error(&mut self, events: &Events, error: &str) -> Vec<ReferencingEvent> {
+        if self.show_error {
+            error!("Receive mqtt error {error}. Suppressing further messages until success");
+        }
+        self.show_error = false;
+        self.delay = (self.delay * 2)
+            .max(Duration::from_secs(self.reconnect.delay))
+            .min(Duration::from_secs(self.reconnect.max_delay));
+        if !self.connected {
+            return Vec::new();
+        }
+        self.connected = false;
+        connection_events(events, DISCONNECTED_EVENT, self.pool_id)
+    }
+
+    fn wait(&self) {
+        debug!(
+            "Reconnect mqtt pool={} in {}s",
+            self.pool_id,
+            self.delay.as_secs()
+        );
+        sleep(self.delay);
+    }
+}
+
+/// events named __mqtt_connected, __mqtt_connected_{pool_id} and the same for disconnected
+fn connection_events(events: &Events, name: &str, pool_id: &str) -> Vec<ReferencingEvent> {
+    [name.to_string(), format!("{name}_{pool_id}")]
+        .iter()
+        .filter_map(|name| events.get_event_by_name(name))
+        .map(|mut event| {
+            debug!("Queue connection event={}", event.name);
+            event
+                .metadata
+                .merge(json!({ name: { "pool_id": pool_id }}).into());
+            event
+        })
+        .collect()
+}
+
+fn publish_birth(client: &MqttClient, birth: &MqttMessage) {
+    debug!("Publish birth message to topic={}", birth.topic);
+    if let Err(e) = client.try_publish(
+        &birth.topic,
+        birth.qos.map(Into::into).unwrap_or(QoS::AtLeastOnce),
+        birth.retain,
+        birth.body.as_bytes(),
+        &Default::default(),
+    ) {
+        error!("Failed to publish birth message topic={} {e}", birth.topic);
+    }
+}
+
+fn resubscribe(client: &MqttClient, subscriptions: &IndexMap<EventName, MqttSubscription>) {
+    let topics: IndexMap<&str, QoS> = subscriptions
+        .values()
+        .map(|s| (s.topic.as_str(), s.qos))
+        .collect();
+    for (topic, qos) in topics {
+        debug!("Subscribe again to topic={topic}");
+        if let Err(e) = client.try_subscribe(topic, qos) {
+            error!("Failed to subscribe again topic={topic} {e}");
+        }
+    }
+}
+
+fn properties_to_json(properties: &PublishProperties) -> Value {
+    json!({
+        "user_properties": properties.user_properties.iter().cloned().collect::<IndexMap<String, String>>(),
+        "message_expiry": properties.message_expiry_interval,
+        "response_topic": properties.response_topic,
+        "correlation_data": properties.correlation_data.as_ref().map(|c| String::from_utf8_lossy(c).to_string()),
+        "content_type": properties.content_type,
+    })
+}
+
+fn handle_incoming(
+    events: &Events,
+    subscriptions: &IndexMap<EventName, MqttSubscription>,
+    topic: &str,
+    payload: &[u8],
+    properties: Option<Value>,
+) -> Option<ReferencingEvent> {
+    let (event_associated, captures, payload) =
+        events
+            .iter()
+            .find_map(|ref_event| match &ref_event.event_type {
+                EventType::MqttSubscribe(e) => {
+                    let subscribed_topic = subscriptions
+                        .get(&ref_event.name)
+                        .map(|s| s.topic.as_str())
+                        .unwrap_or(&e.topic);
+                    // encoded payloads are matched and passed as json
+                    let payload = e
+                        .decode(payload)
+                        .map_err(|err| {
+                            debug!(
+                                "Unable to decode payload topic={topic} event={} {err}",
+                                ref_event.name
+                            )
+                        })
+                        .ok()?;
+                    if !e.matches_subscribed(subscribed_topic, topic, &payload) {
+                        return None;
+                    }
+                    debug!(
+                        "Event found event {} next event {:?}",
+                        ref_event.name, ref_event.next_event
+                    );
+                    (ref_event, e.captures(subscribed_topic, topic), payload).into()
+                }
+                _ => None,
+            })?;
 
     if let Some(mut event) = events.get_next_event(event_associated) {
-        event.try_merge_bytes(payload);
+        event.try_merge_bytes(&payload);
         let mut metadata = event_associated.metadata.clone();
-        metadata.merge(json!({ event_associated.name.as_str(): {"topic": topic, "segments": topic.split('/').collect::<Vec<&str>>() }}).into());
+        metadata.merge(json!({ event_associated.name.as_str(): {"topic": topic, "segments": topic.split('/').collect::<Vec<&str>>(), "captures": captures, "properties": properties }}).into());
         event.metadata.merge(metadata);
         Some(event)
     } else {
@@ -69,6 +307,7 @@ fn handle_incoming(events: &Events, topic: &str, payload: &[u8]) -> Option<Refer
 mod tests {
 
     use crate::events::{
+        data::PayloadEncoding,
         mqtt_subscribe::{MqttBodyMatch, MqttSubscribeEvent},
         EventName, NextEvent,
     };
@@ -107,18 +346,281 @@ mod tests {
             .into_iter()
             .collect(),
         );
-        let event = handle_incoming(&events, "topic1", b"content1");
+        let event = handle_incoming(&events, &Default::default(), "topic1", b"content1", None);
         assert_eq!(event.unwrap().next_event.as_deref().unwrap(), "expected");
-        let event = handle_incoming(&events, "topic2", b"content2");
+        let event = handle_incoming(&events, &Default::default(), "topic2", b"content2", None);
         // no referencing event
         assert!(event.is_none());
-        let event = handle_incoming(&events, "topic3", b"content3");
+        let event = handle_incoming(&events, &Default::default(), "topic3", b"content3", None);
         assert_eq!(event.unwrap().next_event.as_deref().unwrap(), "expected");
 
-        let event = handle_incoming(&events, "topic1", b"content4");
+        let event = handle_incoming(&events, &Default::default(), "topic1", b"content4", None);
         assert_eq!(event.unwrap().next_event.as_deref().unwrap(), "expected");
     }
 
+    #[test]
+    fn test_handle_incoming_rendered_topic() {
+        let events = Events::new(
+            [
+                create_mqtt_event(
+                    "test1".to_string(),
+                    Some("test2".to_string()),
+                    "devices/{{state.device}}/status",
+                    MqttBodyMatch::BodyContains("on".to_string()),
+                ),
+                create_mqtt_event(
+                    "test2".to_string(),
+                    Some("expected".to_string()),
+                    "topic2",
+                    MqttBodyMatch::Body("content2".to_string()),
+                ),
+            ]
+            .into_iter()
+            .collect(),
+        );
+        let subscriptions: IndexMap<EventName, MqttSubscription> = [(
+            "test1".to_string(),
+            MqttSubscription {
+                topic: "devices/lamp/status".to_string(),
+                qos: QoS::AtMostOnce,
+            },
+        )]
+        .into();
+
+        let event = handle_incoming(&events, &subscriptions, "devices/lamp/status", b"on", None);
+        assert_eq!(event.unwrap().next_event.as_deref().unwrap(), "expected");
+        let event = handle_incoming(&events, &subscriptions, "devices/fan/status", b"on", None);
+        assert!(event.is_none());
+        let event = handle_incoming(
+            &events,
+            &Default::default(),
+            "devices/lamp/status",
+            b"on",
+            None,
+        );
+        assert!(event.is_none());
+    }
+
+    #[test]
+    fn test_handle_incoming_captures() {
+        let mut subscribe = create_mqtt_event(
+            "test1".to_string(),
+            Some("test2".to_string()),
+            "home/+/+/#",
+            MqttBodyMatch::Body("on".to_string()),
+        );
+        if let EventType::MqttSubscribe(e) = &mut subscribe.event_type {
+            e.captures = vec!["room".to_string(), "device".to_string(), "rest".to_string()];
+        }
+        let events = Events::new(
+            [
+                subscribe,
+                create_mqtt_event(
+                    "test2".to_string(),
+                    None,
+                    "topic2",
+                    MqttBodyMatch::Body("content2".to_string()),
+                ),
+            ]
+            .into_iter()
+            .collect(),
+        );
+        let event = handle_incoming(
+            &events,
+            &Default::default(),
+            "home/kitchen/lamp/state/power",
+            b"on",
+            None,
+        )
+        .unwrap();
+        let metadata = serde_json::to_value(&event.metadata).unwrap();
+        assert_eq!(
+            metadata["test1"]["captures"],
+            json!({"room": "kitchen", "device": "lamp", "rest": "state/power"})
+        );
+        assert!(
+            handle_incoming(&events, &Default::default(), "home/kitchen", b"on", None).is_none()
+        );
+    }
+
+    #[test]
+    fn test_handle_incoming_encoded() {
+        let mut subscribe = create_mqtt_event(
+            "test1".to_string(),
+            Some("test2".to_string()),
+            "sensors/garage",
+            MqttBodyMatch::BodyContains("temperature".to_string()),
+        );
+        if let EventType::MqttSubscribe(e) = &mut subscribe.event_type {
+            e.encoding = Some(PayloadEncoding::Msgpack);
+        }
+        let events = Events::new(
+            [
+                subscribe,
+                create_mqtt_event(
+                    "test2".to_string(),
+                    None,
+                    "topic2",
+                    MqttBodyMatch::Body("content2".to_string()),
+                ),
+            ]
+            .into_iter()
+            .collect(),
+        );
+        let payload = PayloadEncoding::Msgpack
+            .encode(&json!({"temperature": 21.5}).into())
+            .unwrap();
+        let event = handle_incoming(
+            &events,
+            &Default::default(),
+            "sensors/garage",
+            &payload,
+            None,
+        )
+        .unwrap();
+        assert_eq!(event.data, json!({"temperature": 21.5}));
+        // payloads which can not be decoded are ignored
+        let event = handle_incoming(
+            &events,
+            &Default::default(),
+            "sensors/garage",
+            b"\xc1temperature",
+            None,
+        );
+        assert!(event.is_none());
+    }
+
+    #[test]
+    fn test_handle_incoming_v5_properties() {
+        let events = Events::new(
+            [
+                create_mqtt_event(
+                    "test1".to_string(),
+                    Some("test2".to_string()),
+                    "topic1",
+                    MqttBodyMatch::Body("content1".to_string()),
+                ),
+                create_mqtt_event(
+                    "test2".to_string(),
+                    None,
+                    "topic2",
+                    MqttBodyMatch::Body("content2".to_string()),
+                ),
+            ]
+            .into_iter()
+            .collect(),
+        );
+        let properties = PublishProperties {
+            user_properties: vec![("room".to_string(), "hall".to_string())],
+            response_topic: "response/topic".to_string().into(),
+            ..Default::default()
+        };
+        let event = handle_incoming(
+            &events,
+            &Default::default(),
+            "topic1",
+            b"content1",
+            properties_to_json(&properties).into(),
+        )
+        .unwrap();
+        let metadata = serde_json::to_value(&event.metadata).unwrap();
+        assert_eq!(
+            metadata["test1"]["properties"]["user_properties"],
+            json!({"room": "hall"})
+        );
+        assert_eq!(
+            metadata["test1"]["properties"]["response_topic"],
+            json!("response/topic")
+        );
+    }
+
+    #[test]
+    fn test_connection_events() {
+        let events = Events::new(
+            [
+                ReferencingEvent {
+                    name: CONNECTED_EVENT.to_string(),
+                    ..Default::default()
+                },
+                ReferencingEvent {
+                    name: format!("{DISCONNECTED_EVENT}_b"),
+                    ..Default::default()
+                },
+            ]
+            .into_iter()
+            .collect(),
+        );
+        let result = connection_events(&events, CONNECTED_EVENT, "a");
+        assert_eq!(result.len(), 1);
+        let metadata = serde_json::to_value(&result[0].metadata).unwrap();
+        assert_eq!(metadata[CONNECTED_EVENT]["pool_id"], json!("a"));
+
+        assert!(connection_events(&events, DISCONNECTED_EVENT, "a").is_empty());
+        let result = connection_events(&events, DISCONNECTED_EVENT, "b");
+        assert_eq!(result[0].name, "__mqtt_disconnected_b");
+    }
+
+    #[test]
+    fn test_resubscribe_after_reconnect() {
+        let (client, mut connection) =
+            rumqttc::Client::new(rumqttc::MqttOptions::new("test", "localhost", 1883), 10);
+        let client = MqttClient::V4(client);
+        let subscriptions = MqttSubscriptions::default();
+        subscriptions.lock().unwrap().extend([
+            (
+                "test1".to_string(),
+                MqttSubscription {
+                    topic: "devices/lamp/status".to_string(),
+                    qos: QoS::AtLeastOnce,
+                },
+            ),
+            (
+                "test2".to_string(),
+                MqttSubscription {
+                    topic: "devices/lamp/status".to_string(),
+                    qos: QoS::AtLeastOnce,
+                },
+            ),
+        ]);
+        let mut state = ConnectionState {
+            pool_id: "a",
+            client: &client,
+            birth: None,
+            reconnect: Default::default(),
+            delay: Duration::ZERO,
+            connected: false,
+            resubscribe: false,
+            show_error: true,
+            subscriptions: &subscriptions,
+        };
+        let events = Events::new(Default::default());
+
+        // subscriptions are sent by the queue before the first connection
+        state.on_connected(&events);
+        connection.eventloop.clean();
+        assert!(connection.eventloop.pending.is_empty());
+
+        state.on_error(&events, "disconnected");
+        state.on_connected(&events);
+        connection.eventloop.clean();
+        let subscribed: Vec<_> = connection
+            .eventloop
+            .pending
+            .drain(..)
+            .map(|r| match r {
+                rumqttc::Request::Subscribe(s) => s.filters,
+                r => panic!("unexpected request {r:?}"),
+            })
+            .collect();
+        assert_eq!(
+            subscribed,
+            vec![vec![rumqttc::SubscribeFilter::new(
+                "devices/lamp/status".to_string(),
+                QoS::AtLeastOnce
+            )]]
+        );
+    }
+
     fn create_mqtt_event(
         name: String,
         event: Option<EventName>,
@@ -131,6 +633,10 @@ mod tests {
                 topic: topic.to_string(),
                 body: body.into(),
                 pool_id: Default::default(),
+                qos: None,
+                captures: Vec::new(),
+                encoding: None,
+                protobuf: None,
             }),
             next_event: event.map(NextEvent::Name),
             ..Default::default()
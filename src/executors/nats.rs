@@ -0,0 +1,169 @@
+use core::time::Duration;
+use std::{sync::mpsc::Sender, thread::sleep};
+
+use indexmap::IndexSet;
+use log::{debug, info, warn};
+use serde_json::json;
+
+use crate::{
+    events::{EventType, Events, ReferencingEvent},
+    pools::nats::{NatsClient, NatsMessage},
+};
+
+/// seconds to wait before reconnecting to the nats server
+const RECONNECT_DELAY: u64 = 5;
+/// seconds without messages or server pings before reconnecting, servers ping every 2 minutes
+const PING_TIMEOUT: u64 = 5 * 60;
+
+/// subscribes to subjects of nats_subscribe events for the pool,
+/// events without a pool_id are subscribed on the first pool
+pub fn nats_executor(
+    pool_id: &str,
+    url: &str,
+    first_pool: bool,
+    events: &Events,
+    queue_tx: Sender<ReferencingEvent>,
+) -> anyhow::Result<()> {
+    let subscriptions: IndexSet<(&str, Option<&str>)> = events
+        .iter()
+        .filter_map(|e| match &e.event_type {
+            EventType::NatsSubscribe(e)
+                if e.pool_id == pool_id || (e.pool_id.is_empty() && first_pool) =>
+            {
+                Some((e.subject.as_str(), e.queue_group.as_deref()))
+            }
+            _ => None,
+        })
+        .collect();
+    if subscriptions.is_empty() {
+        return Ok(());
+    }
+    let client = NatsClient::new(url)?;
+    loop {
+        match subscribe(pool_id, &client, &subscriptions, events, &queue_tx) {
+            Ok(()) => return Ok(()),
+            Err(e) => warn!("Nats subscription for {pool_id} failed {e}"),
+        }
+        sleep(Duration::from_secs(RECONNECT_DELAY));
+    }
+}
+
+/// returns once the queue is closed
+fn subscribe(
+    pool_id: &str,
+    client: &NatsClient,
+    subscriptions: &IndexSet<(&str, Option<&str>)>,
+    events: &Events,
+    queue_tx: &Sender<ReferencingEvent>,
+) -> anyhow::Result<()> {
+    let mut connection = client.connect()?;
+    // subscription ids start from 1 in the order of subscriptions
+    for (subject, queue_group) in subscriptions {
+        connection.subscribe(subject, *queue_group)?;
+    }
+    connection.ping()?;
+    info!("Subscribed to nats subjects {subscriptions:?} for {pool_id}");
+    connection.set_read_timeout(Some(Duration::from_secs(PING_TIMEOUT)))?;
+    loop {
+        let message = connection.next_message()?;
+        debug!("Nats message received on {}", message.subject);
+        let Some(subscription) = subscriptions.get_index(message.sid as usize - 1) else {
+            continue;
+        };
+        if let Some(e) = handle_incoming(events, pool_id, *subscription, &message) {
+            if queue_tx.send(e).is_err() {
+                return Ok(());
+            }
+        }
+    }
+}
+
+fn handle_incoming(
+    events: &Events,
+    pool_id: &str,
+    (subscribed, queue_group): (&str, Option<&str>),
+    message: &NatsMessage,
+) -> Option<ReferencingEvent> {
+    let ref_event = events.iter().find(|ref_event| {
+        matches!(&ref_event.event_type, EventType::NatsSubscribe(e) if e.matches(subscribed, queue_group, &message.payload))
+    })?;
+
+    if let Some(mut event) = events.get_next_event(ref_event) {
+        event.try_merge_bytes(&message.payload);
+        let mut metadata = ref_event.metadata.clone();
+        metadata.merge(
+            json!({ ref_event.name.as_str(): {"pool_id": pool_id, "subject": message.subject, "reply": message.reply }})
+                .into(),
+        );
+        event.metadata.merge(metadata);
+        Some(event)
+    } else {
+        debug!(
+            "Received nats message {} without further handler",
+            ref_event.name
+        );
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::events::{
+        data::Data, mqtt_subscribe::MqttBodyMatch, nats_subscribe::NatsSubscribeEvent,
+        time::TimeEvent, NextEvent,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_handle_incoming() {
+        let events = Events::new(
+            [
+                ReferencingEvent {
+                    event_type: EventType::NatsSubscribe(NatsSubscribeEvent {
+                        subject: "sensors.>".to_string(),
+                        body: Some(MqttBodyMatch::BodyContains("temperature".to_string())),
+                        ..Default::default()
+                    }),
+                    next_event: NextEvent::Name("test1".to_string()).into(),
+                    name: "sensor_reading".to_string(),
+                    ..Default::default()
+                },
+                ReferencingEvent {
+                    event_type: EventType::Time(TimeEvent {
+                        execute_time: "now".parse().unwrap(),
+                        event_id: None,
+                    }),
+                    name: "test1".to_string(),
+                    ..Default::default()
+                },
+            ]
+            .into_iter()
+            .collect(),
+        );
+        let message = NatsMessage {
+            subject: "sensors.kitchen".to_string(),
+            sid: 1,
+            reply: Some("_INBOX.1".to_string()),
+            payload: br#"{"humidity": 40}"#.to_vec(),
+        };
+        assert!(handle_incoming(&events, "default", ("sensors.>", None), &message).is_none());
+        let message = NatsMessage {
+            payload: br#"{"temperature": 21}"#.to_vec(),
+            ..message
+        };
+        assert!(
+            handle_incoming(&events, "default", ("sensors.>", Some("workers")), &message).is_none()
+        );
+
+        let event = handle_incoming(&events, "default", ("sensors.>", None), &message).unwrap();
+        assert_eq!(event.name, "test1");
+        assert_eq!(event.data, Data::Json(json!({"temperature": 21})));
+        let metadata = serde_json::to_value(&event.metadata).unwrap();
+        assert_eq!(
+            metadata["sensor_reading"]["subject"],
+            json!("sensors.kitchen")
+        );
+        assert_eq!(metadata["sensor_reading"]["reply"], json!("_INBOX.1"));
+    }
+}
@@ -0,0 +1,213 @@
+use core::time::Duration;
+use std::{
+    io::{self, ErrorKind},
+    net::{IpAddr, SocketAddr, ToSocketAddrs, UdpSocket},
+    process,
+    sync::mpsc::Sender,
+    thread::sleep,
+    time::Instant,
+};
+
+use log::{debug, info};
+use serde_json::json;
+use socket2::{Domain, Protocol, Socket, Type};
+
+use crate::{
+    config::PingConfiguration,
+    events::{data::Data, ping::PingState, EventType, Events, ReferencingEvent},
+};
+
+pub fn ping_executor(
+    pool_id: &str,
+    config: &PingConfiguration,
+    events: &Events,
+    queue_tx: Sender<ReferencingEvent>,
+) -> anyhow::Result<()> {
+    let mut presence = Presence::new(config.down_after);
+    let mut sequence: u16 = 0;
+    loop {
+        let result = resolve(&config.host)
+            .and_then(|address| ping(address, sequence, Duration::from_secs(config.timeout)));
+        sequence = sequence.wrapping_add(1);
+        if let Err(e) = &result {
+            debug!("Ping {} failed {e}", config.host);
+        }
+        if let Some(state) = presence.update(result.is_ok()) {
+            info!("Host {} is {state}", config.host);
+            let latency = result.ok().map(|l| l.as_millis() as u64);
+            if let Some(e) = handle_transition(events, pool_id, &config.host, state, latency) {
+                queue_tx.send(e)?;
+            }
+        }
+        sleep(Duration::from_secs(config.interval));
+    }
+}
+
+fn handle_transition(
+    events: &Events,
+    pool_id: &str,
+    host: &str,
+    state: PingState,
+    latency: Option<u64>,
+) -> Option<ReferencingEvent> {
+    let ref_event = events.iter().find(|ref_event| {
+        matches!(&ref_event.event_type, EventType::Ping(e) if e.matches(pool_id, state))
+    })?;
+
+    if let Some(mut event) = events.get_next_event(ref_event) {
+        event.merge(Data::Json(
+            json!({"host": host, "state": state.to_string(), "latency": latency}),
+        ));
+        let mut metadata = ref_event.metadata.clone();
+        metadata.merge(json!({ ref_event.name.as_str(): {"pool_id": pool_id }}).into());
+        event.metadata.merge(metadata);
+        Some(event)
+    } else {
+        debug!(
+            "Received ping event {} without further handler",
+            ref_event.name
+        );
+        None
+    }
+}
+
+/// host state after consecutive ping results
+struct Presence {
+    down_after: u32,
+    failures: u32,
+    state: Option<PingState>,
+}
+
+impl Presence {
+    fn new(down_after: u32) -> Self {
+        Self {
+            down_after: down_after.max(1),
+            failures: 0,
+            state: None,
+        }
+    }
+
+    /// returns the new state on a transition
+    fn update(&mut self, up: bool) -> Option<PingState> {
+        let state = if up {
+            self.failures = 0;
+            PingState::Up
+        } else {
+            self.failures = self.failures.saturating_add(1);
+            if self.failures < self.down_after {
+                return None;
+            }
+            PingState::Down
+        };
+        if self.state == Some(state) {
+            return None;
+        }
+        self.state = Some(state);
+        Some(state)
+    }
+}
+
+fn resolve(host: &str) -> io::Result<IpAddr> {
+    (host, 0)
+        .to_socket_addrs()?
+        .next()
+        .map(|a| a.ip())
+        .ok_or_else(|| io::Error::new(ErrorKind::NotFound, format!("No address found for {host}")))
+}
+
+/// send an icmp echo request returning the time until the reply
+fn ping(address: IpAddr, sequence: u16, timeout: Duration) -> io::Result<Duration> {
+    let (domain, protocol, request, reply) = match address {
+        IpAddr::V4(_) => (Domain::IPV4, Protocol::ICMPV4, 8, 0),
+        IpAddr::V6(_) => (Domain::IPV6, Protocol::ICMPV6, 128, 129),
+    };
+    // unprivileged ping sockets are allowed by net.ipv4.ping_group_range,
+    // raw sockets require CAP_NET_RAW
+    let (socket, raw) = match Socket::new(domain, Type::DGRAM, Some(protocol)) {
+        Ok(s) => (s, false),
+        Err(_) => (Socket::new(domain, Type::RAW, Some(protocol))?, true),
+    };
+    let socket: UdpSocket = socket.into();
+    socket.connect(SocketAddr::new(address, 0))?;
+
+    // the identifier is replaced by the kernel for ping sockets
+    let identifier = process::id() as u16;
+    let mut packet = [0; 16];
+    packet[0] = request;
+    packet[4..6].copy_from_slice(&identifier.to_be_bytes());
+    packet[6..8].copy_from_slice(&sequence.to_be_bytes());
+    packet[8..].copy_from_slice(b"hvents\0\0");
+    // icmpv6 checksum is calculated by the kernel
+    if address.is_ipv4() {
+        let checksum = checksum(&packet);
+        packet[2..4].copy_from_slice(&checksum.to_be_bytes());
+    }
+
+    let started = Instant::now();
+    socket.send(&packet)?;
+    let mut buffer = [0; 1024];
+    loop {
+        let remaining = timeout
+            .checked_sub(started.elapsed())
+            .filter(|r| !r.is_zero())
+            .ok_or_else(|| io::Error::from(ErrorKind::TimedOut))?;
+        socket.set_read_timeout(Some(remaining))?;
+        let size = socket.recv(&mut buffer)?;
+        // raw ipv4 sockets receive the ip header
+        let offset = if raw && address.is_ipv4() {
+            (buffer[0] & 0x0f) as usize * 4
+        } else {
+            0
+        };
+        let Some(icmp) = buffer[..size].get(offset..offset + 8) else {
+            continue;
+        };
+        if icmp[0] == reply
+            && icmp[6..8] == sequence.to_be_bytes()
+            && (!raw || icmp[4..6] == identifier.to_be_bytes())
+        {
+            return Ok(started.elapsed());
+        }
+    }
+}
+
+fn checksum(packet: &[u8]) -> u16 {
+    let mut sum = packet
+        .chunks(2)
+        .map(|c| u32::from(u16::from_be_bytes([c[0], *c.get(1).unwrap_or(&0)])))
+        .sum::<u32>();
+    while sum >> 16 != 0 {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    !(sum as u16)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_presence() {
+        let mut presence = Presence::new(2);
+        assert_eq!(presence.update(false), None);
+        assert_eq!(presence.update(false), Some(PingState::Down));
+        assert_eq!(presence.update(false), None);
+        assert_eq!(presence.update(true), Some(PingState::Up));
+        assert_eq!(presence.update(true), None);
+        assert_eq!(presence.update(false), None);
+        assert_eq!(presence.update(true), None);
+    }
+
+    #[test]
+    fn test_checksum() {
+        let packet = [8, 0, 0, 0, 0, 1, 0, 1];
+        assert_eq!(checksum(&packet), 0xf7fd);
+    }
+
+    #[test]
+    #[ignore = "requires ping sockets or CAP_NET_RAW"]
+    fn test_ping() {
+        let address = resolve("127.0.0.1").unwrap();
+        assert!(ping(address, 1, Duration::from_secs(1)).is_ok());
+    }
+}
@@ -1,28 +1,52 @@
 use std::{
-    sync::mpsc::{Receiver, Sender},
+    collections::HashSet,
+    path::Path,
+    sync::{
+        mpsc::{Receiver, Sender},
+        Mutex,
+    },
     thread::{scope, Builder},
 };
 
+use handlebars::Handlebars;
 use indexmap::IndexMap;
 use log::{debug, error, info, warn};
 use notify::{RecommendedWatcher, RecursiveMode, Watcher};
 use rumqttc::QoS;
+use serde_json::json;
 
 use crate::{
     config::now,
     events::{
+        api_call::ApiCallEvent,
         api_listen::ApiListenAction,
+        coap_call::CoapMessage,
         data::{Data, Metadata},
         file_watch::WatchAction,
+        modbus_read::parse_values,
+        mqtt_subscribe::MqttSubscription,
+        push::PushMessage,
         EventType, Events, NextEvent, ReferencingEvent,
     },
-    pools::{api::ClientPool, http::HttpQueuePool, mqtt::MqttPool},
-    renderer::{load_handlebars, TemplateData},
+    pools::{
+        api::{ApiClient, ClientPool, CIRCUIT_OPEN_EVENT},
+        http::HttpQueuePool,
+        modbus::ModbusPool,
+        mqtt::MqttPool,
+        nats::NatsPool,
+        websocket::WebSocketPool,
+    },
+    renderer::{load_handlebars, load_templates, render_to_write, TemplateData},
 };
 
+/// seconds to wait before observing a coap resource again
+const COAP_OBSERVE_DELAY: u64 = 30;
+
 #[allow(clippy::too_many_arguments)]
 pub fn event_executor(
     events: &Events,
+    templates_dir: Option<&Path>,
+    template_env: &IndexMap<String, String>,
     queue_rx: Receiver<ReferencingEvent>,
     queue_tx: Sender<ReferencingEvent>,
     timer_tx: Sender<ReferencingEvent>,
@@ -30,22 +54,39 @@ pub fn event_executor(
     mqtt_pool: MqttPool,
     client_pool: ClientPool,
     http_queue_pool: HttpQueuePool,
+    websocket_pool: WebSocketPool,
+    #[cfg(feature = "serial")] serial_pool: crate::pools::serial::SerialPool,
+    modbus_pool: ModbusPool,
+    #[cfg(feature = "email")] smtp_pool: crate::pools::smtp::SmtpPool,
+    #[cfg(feature = "redis")] redis_pool: crate::pools::redis::RedisPool,
+    nats_pool: NatsPool,
+    #[cfg(feature = "amqp")] amqp_pool: crate::pools::amqp::AmqpPool,
+    #[cfg(feature = "sql")] sql_pool: crate::pools::sql::SqlPool,
+    #[cfg(feature = "zmq")] zmq_pool: crate::pools::zmq::ZmqPool,
 ) -> Result<(), anyhow::Error> {
-    let handlebars = load_handlebars();
+    let mut handlebars = load_handlebars();
+    load_templates(&mut handlebars, events, templates_dir)?;
     let mut state: IndexMap<String, String> = IndexMap::new();
+    #[cfg(feature = "gpio")]
+    let mut gpio_pool = crate::pools::gpio::GpioPool::default();
+    // returns false once the queue is closed
     let send_next_event = |data: Data, metadata: Metadata, next_event_name: Option<String>| {
         let Some(ref_event) = next_event_name else {
-            return;
+            return true;
         };
-        if let Some(mut event_to_execute) = events.get_event_by_name(&ref_event) {
-            event_to_execute.merge(data);
-            event_to_execute.metadata.merge(metadata);
-            debug!("Queue next event={}", event_to_execute.name);
-            queue_tx.send(event_to_execute).expect("event queue");
-        }
+        let Some(mut event_to_execute) = events.get_event_by_name(&ref_event) else {
+            return true;
+        };
+        event_to_execute.merge(data);
+        event_to_execute.metadata.merge(metadata);
+        debug!("Queue next event={}", event_to_execute.name);
+        queue_tx.send(event_to_execute).is_ok()
     };
+    // one observer per event and url
+    let coap_observers: Mutex<HashSet<(String, String)>> = Mutex::default();
     scope(|thread_scope| {
         'main: for mut received in queue_rx {
+            http_queue_pool.stream(&received);
             if let Some(key) = received.state.as_ref().and_then(|s| s.count.as_deref()) {
                 state
                     .entry(key.to_string())
@@ -55,13 +96,30 @@ pub fn event_executor(
             if let Some(map) = received.state.as_ref().map(|s| &s.replace) {
                 state.extend(map.clone());
             }
+            if received.state.is_some() {
+                http_queue_pool.update_state(&state);
+            }
 
             let template_data = TemplateData {
                 data: &received.data,
                 metadata: &received.metadata,
                 state: &state,
+                env: template_env,
             };
 
+            // pool_id may be a template to select the client from event data
+            let render_pool_id =
+                |pool_id: &str| match handlebars.render_template(pool_id, &template_data) {
+                    Ok(p) => Some(p),
+                    Err(e) => {
+                        error!(
+                            "Failed to render pool_id template event={} {e}",
+                            received.name
+                        );
+                        None
+                    }
+                };
+
             let next_event_name = match &received.next_event {
                 Some(NextEvent::Template(s)) => {
                     match handlebars.render_template(s, &template_data) {
@@ -86,11 +144,42 @@ pub fn event_executor(
 
             match received.event_type {
                 EventType::MqttSubscribe(e) => {
-                    if let Some(c) = mqtt_pool.get(&e.pool_id) {
-                        if let Err(e) = c.try_subscribe(&e.topic, QoS::AtMostOnce) {
+                    let Some(pool_id) = render_pool_id(&e.pool_id) else {
+                        continue;
+                    };
+                    if let (Some(c), Some(subscriptions)) =
+                        (mqtt_pool.get(&pool_id), mqtt_pool.subscriptions(&pool_id))
+                    {
+                        let topic = match handlebars.render_template(&e.topic, &template_data) {
+                            Ok(t) if !t.trim().is_empty() => t,
+                            Ok(_) => {
+                                info!("Empty topic provided for event={}. Ignoring", received.name);
+                                continue;
+                            }
+                            Err(e) => {
+                                error!("Failed to render template event={} {e}", received.name);
+                                continue;
+                            }
+                        };
+                        let qos = e.qos.map(Into::into).unwrap_or(QoS::AtMostOnce);
+                        if let Err(e) = c.try_subscribe(&topic, qos) {
                             error!("Failed to subscribe {e}")
                         } else {
-                            info!("Subscribed to {}", e.topic);
+                            info!("Subscribed to {topic}");
+                            let mut subscriptions =
+                                subscriptions.lock().expect("subscriptions lock");
+                            let previous = subscriptions
+                                .insert(received.name.clone(), MqttSubscription { topic, qos })
+                                .map(|s| s.topic);
+                            // a previously rendered topic is kept while used by other events
+                            if let Some(previous) =
+                                previous.filter(|p| !subscriptions.values().any(|s| &s.topic == p))
+                            {
+                                match c.try_unsubscribe(&previous) {
+                                    Ok(()) => info!("Unsubscribed from {previous}"),
+                                    Err(e) => error!("Failed to unsubscribe {previous} {e}"),
+                                }
+                            }
                         }
                     } else {
                         warn!(
@@ -102,9 +191,24 @@ pub fn event_executor(
                     continue;
                 }
                 EventType::MqttUnsubscribe(e) => {
-                    if let Some(c) = mqtt_pool.get(&e.pool_id) {
-                        if let Err(e) = c.try_unsubscribe(&e.topic) {
-                            error!("Failed to subscribe {e}")
+                    let Some(pool_id) = render_pool_id(&e.pool_id) else {
+                        continue;
+                    };
+                    if let Some(c) = mqtt_pool.get(&pool_id) {
+                        let topic = match handlebars.render_template(&e.topic, &template_data) {
+                            Ok(t) => t,
+                            Err(e) => {
+                                error!("Failed to render template event={} {e}", received.name);
+                                continue;
+                            }
+                        };
+                        if let Err(e) = c.try_unsubscribe(&topic) {
+                            error!("Failed to unsubscribe {e}")
+                        } else if let Some(subscriptions) = mqtt_pool.subscriptions(&pool_id) {
+                            subscriptions
+                                .lock()
+                                .expect("subscriptions lock")
+                                .retain(|_, s| s.topic != topic);
                         }
                     } else {
                         warn!(
@@ -114,7 +218,10 @@ pub fn event_executor(
                     }
                 }
                 EventType::MqttPublish(ref e) => {
-                    if let Some(c) = mqtt_pool.get(&e.pool_id) {
+                    let Some(pool_id) = render_pool_id(&e.pool_id) else {
+                        continue;
+                    };
+                    if let Some(c) = mqtt_pool.get(&pool_id) {
                         let topic = match handlebars.render_template(&e.topic, &template_data) {
                             Ok(t) if !t.trim().is_empty() => t,
                             Ok(_) => {
@@ -126,10 +233,14 @@ pub fn event_executor(
                                 continue;
                             }
                         };
-                        let payload = if let Some(template) = &e.body {
+                        let payload = if e.clear_retained {
+                            Vec::new().into()
+                        } else if e.body.is_some() || e.template_file.is_some() {
                             let mut payload = Vec::default();
-                            if let Err(e) = handlebars.render_template_to_write(
-                                template,
+                            if let Some(Err(e)) = render_to_write(
+                                &handlebars,
+                                e.template_file.as_deref(),
+                                e.body.as_deref(),
                                 &template_data,
                                 &mut payload,
                             ) {
@@ -146,12 +257,30 @@ pub fn event_executor(
                                 }
                             }
                         };
-                        if payload.is_empty() {
+                        if payload.is_empty() && !e.clear_retained {
                             info!("Empty body provided for topic={}. Ignoring", topic);
                             continue;
                         }
+                        let payload = match e.encoding {
+                            Some(encoding) if !e.clear_retained => {
+                                match encoding.encode(&Data::from_bytes(&payload)) {
+                                    Ok(p) => p.into(),
+                                    Err(err) => {
+                                        error!("Failed to encode payload topic={topic} {err}");
+                                        continue;
+                                    }
+                                }
+                            }
+                            _ => payload,
+                        };
                         debug!("Publish to topic={} body={payload:?}", topic);
-                        if let Err(e) = c.try_publish(&topic, QoS::AtLeastOnce, e.retain, payload) {
+                        if let Err(e) = c.try_publish(
+                            &topic,
+                            e.qos.map(Into::into).unwrap_or(QoS::AtLeastOnce),
+                            e.retain || e.clear_retained,
+                            payload,
+                            &e.properties,
+                        ) {
                             error!("Failed to publish topic={topic} {e}");
                             continue;
                         }
@@ -171,17 +300,49 @@ pub fn event_executor(
                                 continue 'main;
                             }
                         };
+                        for value in e
+                            .headers
+                            .values_mut()
+                            .chain(e.query.values_mut())
+                            .chain(e.files.values_mut())
+                        {
+                            match handlebars.render_template(value, &template_data) {
+                                Ok(v) => *value = v,
+                                Err(e) => {
+                                    error!("Failed to render header, query or file template {e}");
+                                    continue 'main;
+                                }
+                            };
+                        }
+                        for value in e.body.iter_mut().chain(e.response_file.iter_mut()) {
+                            match handlebars.render_template(value, &template_data) {
+                                Ok(v) => *value = v,
+                                Err(e) => {
+                                    error!("Failed to render body or response file template {e}");
+                                    continue 'main;
+                                }
+                            };
+                        }
                         let result = Builder::new()
                             .name(format!("api_call {}", e.url))
                             .spawn_scoped(thread_scope, move || {
-                                match e.call_api(client, &received.data, &received.name) {
-                                    Ok((d, m)) => {
+                                let Some(result) = call_pool_api(
+                                    client,
+                                    &mut e,
+                                    &received.data,
+                                    &received.name,
+                                    send_next_event,
+                                ) else {
+                                    return;
+                                };
+                                match result {
+                                    Ok((d, m, status)) => {
                                         received.data.merge_with_policy(d, received.merge_data);
                                         received.metadata.merge(m);
                                         send_next_event(
                                             received.data,
                                             received.metadata,
-                                            next_event_name,
+                                            e.status_event(status).cloned().or(next_event_name),
                                         );
                                     }
                                     Err(e) => {
@@ -298,24 +459,1373 @@ pub fn event_executor(
                     }
                     let result = Builder::new()
                         .name(format!("command {}", c.command))
-                        .spawn_scoped(thread_scope, move || match c.run(&received.data) {
-                            Ok((d, m)) => {
-                                received.data.merge_with_policy(d, received.merge_data);
-                                received.metadata.merge(m);
-                                send_next_event(received.data, received.metadata, next_event_name);
+                        .spawn_scoped(thread_scope, move || {
+                            let output = match c.run(&received.data) {
+                                Ok(o) => o,
+                                Err(e) => {
+                                    error!("Failed to execute command {} {e}", c.command);
+                                    return;
+                                }
+                            };
+                            // exit code and stderr are available even if stdout can not be parsed
+                            received.metadata.merge(
+                                json!({ received.name.as_str(): {
+                                    "exit_code": output.exit_code,
+                                    "stderr": output.stderr,
+                                }})
+                                .into(),
+                            );
+                            let exit_event = c.exit_event(output.exit_code).cloned();
+                            let stdout = output
+                                .stdout
+                                .map_err(|e| warn!("Command {} output not parsed {e}", c.command))
+                                .ok();
+                            if exit_event.is_none() && (output.exit_code != 0 || stdout.is_none()) {
+                                error!(
+                                    "Command {} exited with {} {}",
+                                    c.command,
+                                    output.exit_code,
+                                    output.stderr.trim_end()
+                                );
+                                return;
                             }
-                            Err(e) => error!("Failed to execute command {} {e}", c.command),
+                            if let Some(stdout) = stdout {
+                                received.data.merge_with_policy(stdout, received.merge_data);
+                            }
+                            send_next_event(
+                                received.data,
+                                received.metadata,
+                                exit_event.or(next_event_name),
+                            );
                         });
                     if let Err(e) = result {
                         error!("Unable to run command {e}");
                     }
                     continue;
                 }
-                EventType::Print(e) => e.run(&received.data),
+                #[cfg(feature = "email")]
+                EventType::EmailSend(e) => {
+                    let Some(client) = smtp_pool.get(&e.pool_id).cloned() else {
+                        warn!("No smtp server found for {}", e.pool_id);
+                        continue;
+                    };
+                    let rendered =
+                        e.to.iter()
+                            .map(|t| handlebars.render_template(t, &template_data))
+                            .collect::<Result<Vec<_>, _>>()
+                            .and_then(|to| {
+                                Ok((to, handlebars.render_template(&e.subject, &template_data)?))
+                            });
+                    let (to, subject) = match rendered {
+                        Ok(r) => r,
+                        Err(e) => {
+                            error!("Failed to render template event={} {e}", received.name);
+                            continue;
+                        }
+                    };
+                    let Some(body) = render_payload(
+                        &handlebars,
+                        e.body.as_deref(),
+                        &template_data,
+                        &received.name,
+                    )
+                    .map(|p| String::from_utf8_lossy(&p).into_owned()) else {
+                        continue;
+                    };
+                    let result = Builder::new()
+                        .name(format!("email_send {}", received.name))
+                        .spawn_scoped(thread_scope, move || {
+                            match client.send(&to, &subject, body, e.html) {
+                                Ok(_) => {
+                                    received.metadata.merge(
+                                        json!({ received.name.as_str(): {
+                                            "to": to,
+                                            "subject": subject,
+                                        }})
+                                        .into(),
+                                    );
+                                    send_next_event(
+                                        received.data,
+                                        received.metadata,
+                                        next_event_name,
+                                    );
+                                }
+                                Err(err) => error!("Email send to {to:?} failed {err}"),
+                            }
+                        });
+                    if let Err(e) = result {
+                        error!("Unable to send email {e}");
+                    }
+                    continue;
+                }
+                EventType::Notify(e) => {
+                    let Some(client) = client_pool.get(&e.pool_id) else {
+                        warn!("No client found for {}", e.pool_id);
+                        continue;
+                    };
+                    let url = match handlebars.render_template(&e.url, &template_data) {
+                        Ok(u) => u,
+                        Err(e) => {
+                            error!("Failed to render template event={} {e}", received.name);
+                            continue;
+                        }
+                    };
+                    let Some(text) = render_payload(
+                        &handlebars,
+                        e.text.as_deref(),
+                        &template_data,
+                        &received.name,
+                    )
+                    .map(|p| String::from_utf8_lossy(&p).into_owned()) else {
+                        continue;
+                    };
+                    let mut api_call = e.api_call(url, &text);
+                    let result = Builder::new()
+                        .name(format!("notify {}", received.name))
+                        .spawn_scoped(thread_scope, move || {
+                            let Some(result) = call_pool_api(
+                                client,
+                                &mut api_call,
+                                &received.data,
+                                &received.name,
+                                send_next_event,
+                            ) else {
+                                return;
+                            };
+                            match result {
+                                Ok((_, _, status)) if status < 300 => {
+                                    received.metadata.merge(
+                                        json!({ received.name.as_str(): { "status": status }})
+                                            .into(),
+                                    );
+                                    send_next_event(
+                                        received.data,
+                                        received.metadata,
+                                        next_event_name,
+                                    );
+                                }
+                                Ok((data, _, status)) => error!(
+                                    "Notify event={} failed with status {status} {data:?}",
+                                    received.name
+                                ),
+                                Err(err) => {
+                                    error!("Notify event={} failed {err}", received.name)
+                                }
+                            }
+                        });
+                    if let Err(e) = result {
+                        error!("Unable to notify {e}");
+                    }
+                    continue;
+                }
+                EventType::Push(e) => {
+                    let Some(client) = client_pool.get(&e.pool_id) else {
+                        warn!("No client found for {}", e.pool_id);
+                        continue;
+                    };
+                    let render = |template: &Option<String>| {
+                        template
+                            .as_ref()
+                            .map(|t| handlebars.render_template(t, &template_data))
+                            .transpose()
+                    };
+                    let rendered = render(&e.title)
+                        .and_then(|title| Ok((title, render(&e.priority)?, render(&e.click)?)));
+                    let (title, priority, click) = match rendered {
+                        Ok(r) => r,
+                        Err(e) => {
+                            error!("Failed to render template event={} {e}", received.name);
+                            continue;
+                        }
+                    };
+                    let priority = match priority.map(|p| p.trim().parse::<i64>()).transpose() {
+                        Ok(p) => p,
+                        Err(e) => {
+                            error!("Push priority must be a number event={} {e}", received.name);
+                            continue;
+                        }
+                    };
+                    let Some(message) = render_payload(
+                        &handlebars,
+                        e.message.as_deref(),
+                        &template_data,
+                        &received.name,
+                    )
+                    .map(|p| String::from_utf8_lossy(&p).into_owned()) else {
+                        continue;
+                    };
+                    let mut api_call = e.api_call(&PushMessage {
+                        title,
+                        message,
+                        priority,
+                        click,
+                    });
+                    let result = Builder::new()
+                        .name(format!("push {}", received.name))
+                        .spawn_scoped(thread_scope, move || {
+                            let Some(result) = call_pool_api(
+                                client,
+                                &mut api_call,
+                                &received.data,
+                                &received.name,
+                                send_next_event,
+                            ) else {
+                                return;
+                            };
+                            match result {
+                                Ok((_, _, status)) if status < 300 => {
+                                    received.metadata.merge(
+                                        json!({ received.name.as_str(): { "status": status }})
+                                            .into(),
+                                    );
+                                    send_next_event(
+                                        received.data,
+                                        received.metadata,
+                                        next_event_name,
+                                    );
+                                }
+                                Ok((data, _, status)) => error!(
+                                    "Push event={} failed with status {status} {data:?}",
+                                    received.name
+                                ),
+                                Err(err) => error!("Push event={} failed {err}", received.name),
+                            }
+                        });
+                    if let Err(e) = result {
+                        error!("Unable to push notification {e}");
+                    }
+                    continue;
+                }
+                EventType::SmsSend(e) => {
+                    let Some(client) = client_pool.get(&e.pool_id) else {
+                        warn!("No client found for {}", e.pool_id);
+                        continue;
+                    };
+                    let to = match e
+                        .to
+                        .iter()
+                        .map(|t| handlebars.render_template(t, &template_data))
+                        .collect::<Result<Vec<_>, _>>()
+                    {
+                        Ok(to) => to,
+                        Err(e) => {
+                            error!("Failed to render template event={} {e}", received.name);
+                            continue;
+                        }
+                    };
+                    let Some(body) = render_payload(
+                        &handlebars,
+                        e.body.as_deref(),
+                        &template_data,
+                        &received.name,
+                    )
+                    .map(|p| String::from_utf8_lossy(&p).into_owned()) else {
+                        continue;
+                    };
+                    let result = Builder::new()
+                        .name(format!("sms_send {}", received.name))
+                        .spawn_scoped(thread_scope, move || {
+                            let mut sent = Vec::new();
+                            for recipient in &to {
+                                let Some(result) = call_pool_api(
+                                    client,
+                                    &mut e.api_call(recipient, &body),
+                                    &received.data,
+                                    &received.name,
+                                    send_next_event,
+                                ) else {
+                                    break;
+                                };
+                                match result {
+                                    Ok((_, _, status)) if status < 300 => sent.push(recipient),
+                                    Ok((data, _, status)) => error!(
+                                        "Sms send to {recipient} failed with status {status} {data:?}"
+                                    ),
+                                    Err(err) => error!("Sms send to {recipient} failed {err}"),
+                                }
+                            }
+                            if sent.is_empty() {
+                                return;
+                            }
+                            received
+                                .metadata
+                                .merge(json!({ received.name.as_str(): { "to": sent }}).into());
+                            send_next_event(received.data, received.metadata, next_event_name);
+                        });
+                    if let Err(e) = result {
+                        error!("Unable to send sms {e}");
+                    }
+                    continue;
+                }
+                EventType::Speak(e) => {
+                    let Some(text) = render_payload(
+                        &handlebars,
+                        e.text.as_deref(),
+                        &template_data,
+                        &received.name,
+                    )
+                    .map(|p| String::from_utf8_lossy(&p).into_owned()) else {
+                        continue;
+                    };
+                    let result = Builder::new()
+                        .name(format!("speak {}", received.name))
+                        .spawn_scoped(thread_scope, move || match e.speak(&text) {
+                            Ok(()) => {
+                                received.metadata.merge(
+                                    json!({ received.name.as_str(): { "text": text }}).into(),
+                                );
+                                send_next_event(received.data, received.metadata, next_event_name);
+                            }
+                            Err(err) => error!("Speak event={} failed {err}", received.name),
+                        });
+                    if let Err(e) = result {
+                        error!("Unable to speak {e}");
+                    }
+                    continue;
+                }
+                EventType::InfluxWrite(e) => {
+                    let render = |template| handlebars.render_template(template, &template_data);
+                    let rendered = render(&e.measurement).and_then(|measurement| {
+                        let tags = e
+                            .tags
+                            .iter()
+                            .map(|(k, v)| render(v).map(|v| (k.clone(), v)))
+                            .collect::<Result<IndexMap<_, _>, _>>()?;
+                        Ok((measurement, tags))
+                    });
+                    let (measurement, tags) = match rendered {
+                        Ok(r) => r,
+                        Err(e) => {
+                            error!("Failed to render template event={} {e}", received.name);
+                            continue;
+                        }
+                    };
+                    let line = match e.line(&measurement, &tags, &received.data) {
+                        Ok(l) => l,
+                        Err(err) => {
+                            error!("Influx write event={} failed {err}", received.name);
+                            continue;
+                        }
+                    };
+                    let Some(mut api_call) = e.api_call(line.clone()) else {
+                        if let Err(err) = e.send_udp(&line) {
+                            error!("Influx write event={} failed {err}", received.name);
+                            continue;
+                        }
+                        send_next_event(received.data, received.metadata, next_event_name);
+                        continue;
+                    };
+                    let Some(client) = client_pool.get(&e.pool_id) else {
+                        warn!("No client found for {}", e.pool_id);
+                        continue;
+                    };
+                    let result = Builder::new()
+                        .name(format!("influx_write {}", received.name))
+                        .spawn_scoped(thread_scope, move || {
+                            let Some(result) = call_pool_api(
+                                client,
+                                &mut api_call,
+                                &received.data,
+                                &received.name,
+                                send_next_event,
+                            ) else {
+                                return;
+                            };
+                            match result {
+                                Ok((_, _, status)) if status < 300 => {
+                                    send_next_event(
+                                        received.data,
+                                        received.metadata,
+                                        next_event_name,
+                                    );
+                                }
+                                Ok((data, _, status)) => error!(
+                                    "Influx write event={} failed with status {status} {data:?}",
+                                    received.name
+                                ),
+                                Err(err) => {
+                                    error!("Influx write event={} failed {err}", received.name)
+                                }
+                            }
+                        });
+                    if let Err(e) = result {
+                        error!("Unable to write to influx {e}");
+                    }
+                    continue;
+                }
+                // messages begin in websocket executor
+                EventType::WsListen(_) => continue,
+                // messages begin in tcp executor
+                EventType::TcpListen(_) => continue,
+                // datagrams begin in udp executor
+                EventType::UdpListen(_) => continue,
+                // messages begin in redis executor
+                #[cfg(feature = "redis")]
+                EventType::RedisSubscribe(_) => continue,
+                // messages begin in nats executor
+                EventType::NatsSubscribe(_) => continue,
+                // messages begin in amqp executor
+                #[cfg(feature = "amqp")]
+                EventType::AmqpConsume(_) => continue,
+                // messages begin in zmq executor
+                #[cfg(feature = "zmq")]
+                EventType::ZmqSubscribe(_) => continue,
+                // messages begin in syslog executor
+                EventType::SyslogListen(_) => continue,
+                // messages begin in imap executor
+                #[cfg(feature = "email")]
+                EventType::EmailReceive(_) => continue,
+                // transitions begin in ping executor
+                EventType::Ping(_) => continue,
+                // services begin in mdns executor
+                #[cfg(feature = "mdns")]
+                EventType::Mdns(_) => continue,
+                // devices begin in ssdp executor
+                EventType::Ssdp(_) => continue,
+                // messages begin in serial executor
+                #[cfg(feature = "serial")]
+                EventType::SerialRead(_) => continue,
+                #[cfg(feature = "serial")]
+                EventType::SerialWrite(ref e) => {
+                    let Some(port) = serial_pool.get(&e.pool_id) else {
+                        warn!("No serial port found for {}", e.pool_id);
+                        continue;
+                    };
+                    let Some(payload) = render_payload(
+                        &handlebars,
+                        e.body.as_deref(),
+                        &template_data,
+                        &received.name,
+                    ) else {
+                        continue;
+                    };
+                    if let Err(err) = e.write(port, &payload) {
+                        error!("Serial write to {} failed {err}", e.pool_id);
+                        continue;
+                    }
+                }
+                EventType::WsSend(ref e) => {
+                    let Some(clients) = websocket_pool.get(&e.pool_id) else {
+                        warn!("No websocket server found for {}", e.pool_id);
+                        continue;
+                    };
+                    let Some(payload) = render_payload(
+                        &handlebars,
+                        e.body.as_deref(),
+                        &template_data,
+                        &received.name,
+                    ) else {
+                        continue;
+                    };
+                    let sent = e.send(clients, &payload);
+                    debug!(
+                        "Websocket message sent to {sent} clients event={}",
+                        received.name
+                    );
+                }
+                EventType::TcpSend(e) => {
+                    let host = match handlebars.render_template(&e.host, &template_data) {
+                        Ok(h) => h,
+                        Err(e) => {
+                            error!("Failed to render host template event={} {e}", received.name);
+                            continue;
+                        }
+                    };
+                    let Some(payload) = render_payload(
+                        &handlebars,
+                        e.body.as_deref(),
+                        &template_data,
+                        &received.name,
+                    ) else {
+                        continue;
+                    };
+                    let result = Builder::new()
+                        .name(format!("tcp_send {host}:{}", e.port))
+                        .spawn_scoped(thread_scope, move || match e.send(&host, &payload) {
+                            Ok(response) => {
+                                if let Some(response) = response {
+                                    let data = match String::from_utf8(response) {
+                                        Ok(s) => Data::String(s),
+                                        Err(e) => Data::Bytes(e.into_bytes()),
+                                    };
+                                    received.data.merge_with_policy(data, received.merge_data);
+                                }
+                                send_next_event(received.data, received.metadata, next_event_name);
+                            }
+                            Err(err) => error!("Tcp send to {host}:{} failed {err}", e.port),
+                        });
+                    if let Err(e) = result {
+                        error!("Unable to send tcp message {e}");
+                    }
+                    continue;
+                }
+                EventType::UdpSend(ref e) => {
+                    let address = match handlebars.render_template(&e.address, &template_data) {
+                        Ok(a) => a,
+                        Err(e) => {
+                            error!(
+                                "Failed to render address template event={} {e}",
+                                received.name
+                            );
+                            continue;
+                        }
+                    };
+                    let Some(payload) = render_payload(
+                        &handlebars,
+                        e.body.as_deref(),
+                        &template_data,
+                        &received.name,
+                    ) else {
+                        continue;
+                    };
+                    if let Err(err) = e.send(&address, &payload) {
+                        error!("Udp send to {address} failed {err}");
+                        continue;
+                    }
+                }
+                #[cfg(feature = "redis")]
+                EventType::RedisPublish(e) => {
+                    let Some(client) = redis_pool.get(&e.pool_id) else {
+                        warn!("No redis server found for {}", e.pool_id);
+                        continue;
+                    };
+                    let channel = match handlebars.render_template(&e.channel, &template_data) {
+                        Ok(c) => c,
+                        Err(e) => {
+                            error!("Failed to render template event={} {e}", received.name);
+                            continue;
+                        }
+                    };
+                    let Some(payload) = render_payload(
+                        &handlebars,
+                        e.body.as_deref(),
+                        &template_data,
+                        &received.name,
+                    ) else {
+                        continue;
+                    };
+                    let result = Builder::new()
+                        .name(format!("redis_publish {channel}"))
+                        .spawn_scoped(thread_scope, move || {
+                            debug!("Redis publish to channel={channel} body={payload:?}");
+                            match client.publish(&channel, &payload) {
+                                Ok(_) => {
+                                    send_next_event(
+                                        received.data,
+                                        received.metadata,
+                                        next_event_name,
+                                    );
+                                }
+                                Err(err) => error!("Redis publish to {channel} failed {err}"),
+                            }
+                        });
+                    if let Err(e) = result {
+                        error!("Unable to publish to redis {e}");
+                    }
+                    continue;
+                }
+                #[cfg(feature = "redis")]
+                EventType::RedisCommand(e) => {
+                    let Some(client) = redis_pool.get(&e.pool_id) else {
+                        warn!("No redis server found for {}", e.pool_id);
+                        continue;
+                    };
+                    let rendered =
+                        handlebars
+                            .render_template(&e.key, &template_data)
+                            .and_then(|key| {
+                                let value = e
+                                    .value
+                                    .as_ref()
+                                    .map(|v| handlebars.render_template(v, &template_data))
+                                    .transpose()?;
+                                Ok((key, value))
+                            });
+                    let (key, value) = match rendered {
+                        Ok(r) => r,
+                        Err(e) => {
+                            error!("Failed to render template event={} {e}", received.name);
+                            continue;
+                        }
+                    };
+                    let result = Builder::new()
+                        .name(format!("redis_command {key}"))
+                        .spawn_scoped(thread_scope, move || {
+                            let result = client.with_connection(e.command.is_idempotent(), |c| {
+                                e.run(c, &key, value.as_deref())
+                            });
+                            match result {
+                                Ok(v) => {
+                                    received
+                                        .data
+                                        .merge_with_policy(Data::Json(v), received.merge_data);
+                                    received.metadata.merge(
+                                        json!({ received.name.as_str(): { "key": key }}).into(),
+                                    );
+                                    send_next_event(
+                                        received.data,
+                                        received.metadata,
+                                        next_event_name,
+                                    );
+                                }
+                                Err(err) => error!("Redis command on {key} failed {err}"),
+                            }
+                        });
+                    if let Err(e) = result {
+                        error!("Unable to run redis command {e}");
+                    }
+                    continue;
+                }
+                EventType::NatsPublish(e) => {
+                    let Some(client) = nats_pool.get(&e.pool_id) else {
+                        warn!("No nats server found for {}", e.pool_id);
+                        continue;
+                    };
+                    let subject = match handlebars.render_template(&e.subject, &template_data) {
+                        Ok(s) => s,
+                        Err(e) => {
+                            error!("Failed to render template event={} {e}", received.name);
+                            continue;
+                        }
+                    };
+                    let Some(payload) = render_payload(
+                        &handlebars,
+                        e.body.as_deref(),
+                        &template_data,
+                        &received.name,
+                    ) else {
+                        continue;
+                    };
+                    let result = Builder::new()
+                        .name(format!("nats_publish {subject}"))
+                        .spawn_scoped(thread_scope, move || {
+                            debug!("Nats publish to subject={subject} body={payload:?}");
+                            match client.publish(&subject, &payload) {
+                                Ok(()) => {
+                                    send_next_event(
+                                        received.data,
+                                        received.metadata,
+                                        next_event_name,
+                                    );
+                                }
+                                Err(err) => error!("Nats publish to {subject} failed {err}"),
+                            }
+                        });
+                    if let Err(e) = result {
+                        error!("Unable to publish to nats {e}");
+                    }
+                    continue;
+                }
+                EventType::NatsRequest(e) => {
+                    let Some(client) = nats_pool.get(&e.pool_id) else {
+                        warn!("No nats server found for {}", e.pool_id);
+                        continue;
+                    };
+                    let subject = match handlebars.render_template(&e.subject, &template_data) {
+                        Ok(s) => s,
+                        Err(e) => {
+                            error!("Failed to render template event={} {e}", received.name);
+                            continue;
+                        }
+                    };
+                    let Some(payload) = render_payload(
+                        &handlebars,
+                        e.body.as_deref(),
+                        &template_data,
+                        &received.name,
+                    ) else {
+                        continue;
+                    };
+                    let timeout = core::time::Duration::from_secs(e.timeout);
+                    let result = Builder::new()
+                        .name(format!("nats request {}", received.name))
+                        .spawn_scoped(thread_scope, move || {
+                            match client.request(&subject, &payload, timeout) {
+                                Ok(response) => {
+                                    let mut data = Data::default();
+                                    data.try_merge_bytes(&response);
+                                    received.metadata.merge(
+                                        json!({ received.name.as_str(): { "subject": subject }})
+                                            .into(),
+                                    );
+                                    send_next_event(data, received.metadata, next_event_name);
+                                }
+                                Err(err) => {
+                                    error!("Nats request to {subject} failed {err}")
+                                }
+                            }
+                        });
+                    if let Err(e) = result {
+                        error!("Unable to send nats request {e}");
+                    }
+                    continue;
+                }
+                #[cfg(feature = "amqp")]
+                EventType::AmqpPublish(ref e) => {
+                    let Some(client) = amqp_pool.get(&e.pool_id) else {
+                        warn!("No amqp broker found for {}", e.pool_id);
+                        continue;
+                    };
+                    let routing_key =
+                        match handlebars.render_template(&e.routing_key, &template_data) {
+                            Ok(r) => r,
+                            Err(e) => {
+                                error!("Failed to render template event={} {e}", received.name);
+                                continue;
+                            }
+                        };
+                    let Some(payload) = render_payload(
+                        &handlebars,
+                        e.body.as_deref(),
+                        &template_data,
+                        &received.name,
+                    ) else {
+                        continue;
+                    };
+                    debug!(
+                        "Amqp publish to exchange={} routing_key={routing_key} body={payload:?}",
+                        e.exchange
+                    );
+                    if let Err(err) =
+                        client.publish(&e.exchange, &routing_key, &payload, e.persistent)
+                    {
+                        error!("Amqp publish to {routing_key} failed {err}");
+                        continue;
+                    }
+                }
+                #[cfg(feature = "amqp")]
+                EventType::AmqpAck(ref e) => {
+                    let Some(client) = amqp_pool.get(&e.pool_id) else {
+                        warn!("No amqp broker found for {}", e.pool_id);
+                        continue;
+                    };
+                    let delivery_id = match handlebars
+                        .render_template(&e.delivery_id, &template_data)
+                        .map_err(anyhow::Error::from)
+                        .and_then(|id| Ok(id.trim().parse::<u64>()?))
+                    {
+                        Ok(id) => id,
+                        Err(e) => {
+                            error!("Invalid amqp delivery id event={} {e}", received.name);
+                            continue;
+                        }
+                    };
+                    if let Err(err) = client.acknowledge(delivery_id, e.reject, e.requeue) {
+                        error!("Amqp acknowledgement of {delivery_id} failed {err}");
+                        continue;
+                    }
+                }
+                #[cfg(feature = "zmq")]
+                EventType::ZmqPublish(ref e) => {
+                    let Some(endpoint) = zmq_pool.get(&e.pool_id) else {
+                        warn!("No zmq socket found for {}", e.pool_id);
+                        continue;
+                    };
+                    let topic = match e
+                        .topic
+                        .as_ref()
+                        .map(|t| handlebars.render_template(t, &template_data))
+                        .transpose()
+                    {
+                        Ok(t) => t,
+                        Err(e) => {
+                            error!("Failed to render template event={} {e}", received.name);
+                            continue;
+                        }
+                    };
+                    let Some(payload) = render_payload(
+                        &handlebars,
+                        e.body.as_deref(),
+                        &template_data,
+                        &received.name,
+                    ) else {
+                        continue;
+                    };
+                    debug!("Zmq publish topic={topic:?} body={payload:?}");
+                    if let Err(err) = endpoint.send(topic.as_deref(), &payload) {
+                        error!("Zmq publish event={} failed {err}", received.name);
+                        continue;
+                    }
+                }
+                #[cfg(feature = "zmq")]
+                EventType::ZmqRequest(e) => {
+                    let Some(endpoint) = zmq_pool.get(&e.pool_id) else {
+                        warn!("No zmq socket found for {}", e.pool_id);
+                        continue;
+                    };
+                    let Some(payload) = render_payload(
+                        &handlebars,
+                        e.body.as_deref(),
+                        &template_data,
+                        &received.name,
+                    ) else {
+                        continue;
+                    };
+                    let timeout = core::time::Duration::from_secs(e.timeout);
+                    let result = Builder::new()
+                        .name(format!("zmq request {}", received.name))
+                        .spawn_scoped(thread_scope, move || {
+                            match endpoint.request(&payload, timeout) {
+                                Ok(response) => {
+                                    let mut data = Data::default();
+                                    data.try_merge_bytes(&response);
+                                    send_next_event(data, received.metadata, next_event_name);
+                                }
+                                Err(err) => {
+                                    error!("Zmq request event={} failed {err}", received.name)
+                                }
+                            }
+                        });
+                    if let Err(e) = result {
+                        error!("Unable to send zmq request {e}");
+                    }
+                    continue;
+                }
+                EventType::CoapCall(e) => {
+                    let url = match handlebars.render_template(&e.url, &template_data) {
+                        Ok(u) => u,
+                        Err(e) => {
+                            error!("Failed to render template event={} {e}", received.name);
+                            continue;
+                        }
+                    };
+                    let Some(payload) = render_payload(
+                        &handlebars,
+                        e.body.as_deref(),
+                        &template_data,
+                        &received.name,
+                    ) else {
+                        continue;
+                    };
+                    let observer = (received.name.clone(), url.clone());
+                    if e.observe
+                        && !coap_observers
+                            .lock()
+                            .expect("coap observers")
+                            .insert(observer.clone())
+                    {
+                        debug!("Coap {url} is already observed by event={}", received.name);
+                        continue;
+                    }
+                    let coap_observers = &coap_observers;
+                    let result = Builder::new()
+                        .name(format!("coap {}", received.name))
+                        .spawn_scoped(thread_scope, move || {
+                            let forward = |message: CoapMessage| {
+                                if !message.is_success() {
+                                    error!(
+                                        "Coap call event={} failed with code {} {}",
+                                        received.name,
+                                        message.status(),
+                                        String::from_utf8_lossy(&message.payload)
+                                    );
+                                    return true;
+                                }
+                                let mut data = Data::default();
+                                data.try_merge_bytes(&message.payload);
+                                let mut metadata = received.metadata.clone();
+                                metadata.merge(
+                                    json!({ received.name.as_str(): message.metadata() }).into(),
+                                );
+                                send_next_event(data, metadata, next_event_name.clone())
+                            };
+                            if !e.observe {
+                                match e.call(&url, &payload) {
+                                    Ok(message) => {
+                                        forward(message);
+                                    }
+                                    Err(err) => {
+                                        error!("Coap call event={} failed {err}", received.name)
+                                    }
+                                }
+                                return;
+                            }
+                            loop {
+                                match e.observe(&url, forward) {
+                                    Ok(()) => break,
+                                    Err(err) => warn!("Coap observe {url} failed {err}"),
+                                }
+                                std::thread::sleep(core::time::Duration::from_secs(
+                                    COAP_OBSERVE_DELAY,
+                                ));
+                            }
+                            debug!("Coap observe {url} stopped");
+                            coap_observers
+                                .lock()
+                                .expect("coap observers")
+                                .remove(&observer);
+                        });
+                    if let Err(e) = result {
+                        error!("Unable to call coap {e}");
+                    }
+                    continue;
+                }
+                EventType::WakeOnLan(ref e) => {
+                    let mac = match handlebars.render_template(&e.mac, &template_data) {
+                        Ok(m) => m,
+                        Err(e) => {
+                            error!("Failed to render mac template event={} {e}", received.name);
+                            continue;
+                        }
+                    };
+                    if let Err(err) = e.send(&mac) {
+                        error!("Wake on lan {mac} failed {err}");
+                        continue;
+                    }
+                }
+                EventType::PortCheck(e) => {
+                    let host = match handlebars.render_template(&e.host, &template_data) {
+                        Ok(h) => h,
+                        Err(e) => {
+                            error!("Failed to render host template event={} {e}", received.name);
+                            continue;
+                        }
+                    };
+                    let result = Builder::new()
+                        .name(format!("port_check {host}:{}", e.port))
+                        .spawn_scoped(thread_scope, move || {
+                            let latency = e.check(&host);
+                            let open = latency.is_some();
+                            debug!("Port check {host}:{} open={open}", e.port);
+                            let latency = latency.map(|l| l.as_millis() as u64);
+                            received.metadata.merge(
+                                json!({ received.name.as_str(): {
+                                    "host": host,
+                                    "port": e.port,
+                                    "open": open,
+                                    "latency": latency,
+                                }})
+                                .into(),
+                            );
+                            send_next_event(
+                                received.data,
+                                received.metadata,
+                                e.state_event(open).cloned().or(next_event_name),
+                            );
+                        });
+                    if let Err(e) = result {
+                        error!("Unable to check port {e}");
+                    }
+                    continue;
+                }
+                #[cfg(feature = "snmp")]
+                EventType::SnmpGet(e) => {
+                    let host = match handlebars.render_template(&e.host, &template_data) {
+                        Ok(h) => h,
+                        Err(e) => {
+                            error!("Failed to render host template event={} {e}", received.name);
+                            continue;
+                        }
+                    };
+                    let result = Builder::new()
+                        .name(format!("snmp_get {host}"))
+                        .spawn_scoped(thread_scope, move || match e.get(&host) {
+                            Ok(values) => {
+                                received
+                                    .data
+                                    .merge_with_policy(Data::Json(values), received.merge_data);
+                                received.metadata.merge(
+                                    json!({ received.name.as_str(): { "host": host }}).into(),
+                                );
+                                send_next_event(received.data, received.metadata, next_event_name);
+                            }
+                            Err(err) => error!("Snmp get from {host} failed {err}"),
+                        });
+                    if let Err(e) = result {
+                        error!("Unable to get snmp values {e}");
+                    }
+                    continue;
+                }
+                #[cfg(feature = "sql")]
+                EventType::SqlQuery(e) => {
+                    let Some(client) = sql_pool.get(&e.pool_id).cloned() else {
+                        warn!("No database found for {}", e.pool_id);
+                        continue;
+                    };
+                    let params = match e.params(|t| handlebars.render_template(t, &template_data)) {
+                        Ok(p) => p,
+                        Err(e) => {
+                            error!("Failed to render template event={} {e}", received.name);
+                            continue;
+                        }
+                    };
+                    let result = Builder::new()
+                        .name(format!("sql_query {}", received.name))
+                        .spawn_scoped(thread_scope, move || {
+                            use crate::pools::sql::SqlResult;
+                            match client.query(&e.query, params) {
+                                Ok(SqlResult::Rows(rows)) => {
+                                    received.metadata.merge(
+                                        json!({ received.name.as_str(): { "rows": rows.len() }})
+                                            .into(),
+                                    );
+                                    received.data.merge_with_policy(
+                                        Data::Json(rows.into()),
+                                        received.merge_data,
+                                    );
+                                    send_next_event(
+                                        received.data,
+                                        received.metadata,
+                                        next_event_name,
+                                    );
+                                }
+                                Ok(SqlResult::Affected(rows_affected)) => {
+                                    received.metadata.merge(
+                                        json!({ received.name.as_str(): {
+                                            "rows_affected": rows_affected
+                                        }})
+                                        .into(),
+                                    );
+                                    send_next_event(
+                                        received.data,
+                                        received.metadata,
+                                        next_event_name,
+                                    );
+                                }
+                                Err(err) => {
+                                    error!("Sql query event={} failed {err}", received.name)
+                                }
+                            }
+                        });
+                    if let Err(e) = result {
+                        error!("Unable to run sql query {e}");
+                    }
+                    continue;
+                }
+                #[cfg(feature = "ssh")]
+                EventType::SshExecute(e) => {
+                    let (host, command) = match handlebars
+                        .render_template(&e.connection.host, &template_data)
+                        .and_then(|h| {
+                            Ok((h, handlebars.render_template(&e.command, &template_data)?))
+                        }) {
+                        Ok(r) => r,
+                        Err(e) => {
+                            error!("Failed to render template event={} {e}", received.name);
+                            continue;
+                        }
+                    };
+                    let result = Builder::new()
+                        .name(format!("ssh_execute {host}"))
+                        .spawn_scoped(thread_scope, move || match e.run(&host, &command) {
+                            Ok(output) => {
+                                received
+                                    .data
+                                    .merge_with_policy(output.stdout, received.merge_data);
+                                received.metadata.merge(
+                                    json!({ received.name.as_str(): {
+                                        "host": host,
+                                        "exit_code": output.exit_code,
+                                        "stderr": output.stderr,
+                                    }})
+                                    .into(),
+                                );
+                                send_next_event(received.data, received.metadata, next_event_name);
+                            }
+                            Err(err) => error!("Ssh command on {host} failed {err}"),
+                        });
+                    if let Err(e) = result {
+                        error!("Unable to run ssh command {e}");
+                    }
+                    continue;
+                }
+                #[cfg(feature = "ssh")]
+                EventType::SftpTransfer(e) => {
+                    let render = |template| handlebars.render_template(template, &template_data);
+                    let (host, local, remote) = match render(&e.connection.host)
+                        .and_then(|h| Ok((h, render(&e.local)?, render(&e.remote)?)))
+                    {
+                        Ok(r) => r,
+                        Err(e) => {
+                            error!("Failed to render template event={} {e}", received.name);
+                            continue;
+                        }
+                    };
+                    let result = Builder::new()
+                        .name(format!("sftp_transfer {host}"))
+                        .spawn_scoped(thread_scope, move || {
+                            match e.transfer(&host, local.as_ref(), remote.as_ref()) {
+                                Ok(size) => {
+                                    received.metadata.merge(
+                                        json!({ received.name.as_str(): {
+                                            "host": host,
+                                            "local": local,
+                                            "remote": remote,
+                                            "size": size,
+                                        }})
+                                        .into(),
+                                    );
+                                    send_next_event(
+                                        received.data,
+                                        received.metadata,
+                                        next_event_name,
+                                    );
+                                }
+                                Err(err) => error!("Sftp transfer {remote} on {host} failed {err}"),
+                            }
+                        });
+                    if let Err(e) = result {
+                        error!("Unable to transfer file {e}");
+                    }
+                    continue;
+                }
+                // lines begin in stdin executor
+                EventType::StdinRead(_) => continue,
+                // lines begin in fifo executor
+                #[cfg(unix)]
+                EventType::FifoRead(_) => continue,
+                // messages begin in unix executor
+                #[cfg(unix)]
+                EventType::UnixListen(_) => continue,
+                #[cfg(unix)]
+                EventType::UnixSend(e) => {
+                    let path = match handlebars.render_template(&e.path, &template_data) {
+                        Ok(p) => p,
+                        Err(e) => {
+                            error!("Failed to render path template event={} {e}", received.name);
+                            continue;
+                        }
+                    };
+                    let Some(payload) = render_payload(
+                        &handlebars,
+                        e.body.as_deref(),
+                        &template_data,
+                        &received.name,
+                    ) else {
+                        continue;
+                    };
+                    let result = Builder::new()
+                        .name(format!("unix_send {path}"))
+                        .spawn_scoped(thread_scope, move || match e.send(&path, &payload) {
+                            Ok(response) => {
+                                if let Some(response) = response {
+                                    let data = match String::from_utf8(response) {
+                                        Ok(s) => Data::String(s),
+                                        Err(e) => Data::Bytes(e.into_bytes()),
+                                    };
+                                    received.data.merge_with_policy(data, received.merge_data);
+                                }
+                                send_next_event(received.data, received.metadata, next_event_name);
+                            }
+                            Err(err) => error!("Unix send to {path} failed {err}"),
+                        });
+                    if let Err(e) = result {
+                        error!("Unable to send unix socket message {e}");
+                    }
+                    continue;
+                }
+                // container events begin in docker executor
+                #[cfg(unix)]
+                EventType::DockerEvents(_) => continue,
+                #[cfg(unix)]
+                EventType::DockerControl(e) => {
+                    let container = match handlebars.render_template(&e.container, &template_data) {
+                        Ok(c) => c,
+                        Err(e) => {
+                            error!(
+                                "Failed to render container template event={} {e}",
+                                received.name
+                            );
+                            continue;
+                        }
+                    };
+                    let result = Builder::new()
+                        .name(format!("docker_control {container}"))
+                        .spawn_scoped(thread_scope, move || match e.run(&container) {
+                            Ok(changed) => {
+                                received.metadata.merge(
+                                    json!({ received.name.as_str(): {
+                                        "container": container,
+                                        "action": e.action.to_string(),
+                                        "changed": changed,
+                                    }})
+                                    .into(),
+                                );
+                                send_next_event(received.data, received.metadata, next_event_name);
+                            }
+                            Err(err) => error!("Docker {} {container} failed {err}", e.action),
+                        });
+                    if let Err(e) = result {
+                        error!("Unable to control docker container {e}");
+                    }
+                    continue;
+                }
+                EventType::ModbusRead(e) => {
+                    let Some(connection) = modbus_pool.get(&e.pool_id).cloned() else {
+                        warn!("No modbus connection found for {}", e.pool_id);
+                        continue;
+                    };
+                    let result = Builder::new()
+                        .name(format!("modbus_read {}", e.address))
+                        .spawn_scoped(thread_scope, move || {
+                            let registers = connection.lock().expect("modbus lock").read(
+                                e.unit,
+                                e.register,
+                                e.address,
+                                e.register_count(),
+                            );
+                            match registers {
+                                Ok(registers) => {
+                                    received.data.merge_with_policy(
+                                        Data::Json(e.values(&registers)),
+                                        received.merge_data,
+                                    );
+                                    received.metadata.merge(
+                                        json!({ received.name.as_str(): {
+                                            "unit": e.unit,
+                                            "address": e.address,
+                                        }})
+                                        .into(),
+                                    );
+                                    send_next_event(
+                                        received.data,
+                                        received.metadata,
+                                        next_event_name,
+                                    );
+                                }
+                                Err(err) => {
+                                    error!("Modbus read of {} failed {err}", e.address)
+                                }
+                            }
+                        });
+                    if let Err(e) = result {
+                        error!("Unable to read modbus {e}");
+                    }
+                    continue;
+                }
+                EventType::ModbusWrite(e) => {
+                    let Some(connection) = modbus_pool.get(&e.pool_id).cloned() else {
+                        warn!("No modbus connection found for {}", e.pool_id);
+                        continue;
+                    };
+                    let Some(value) = render_payload(
+                        &handlebars,
+                        e.value.as_deref(),
+                        &template_data,
+                        &received.name,
+                    )
+                    .map(|p| String::from_utf8_lossy(&p).into_owned()) else {
+                        continue;
+                    };
+                    let values = match parse_values(&value, e.register, e.data_type, e.swap_words) {
+                        Ok(v) => v,
+                        Err(err) => {
+                            error!("Modbus write of {} invalid value {err}", e.address);
+                            continue;
+                        }
+                    };
+                    let result = Builder::new()
+                        .name(format!("modbus_write {}", e.address))
+                        .spawn_scoped(thread_scope, move || {
+                            let result = connection
+                                .lock()
+                                .expect("modbus lock")
+                                .write(e.unit, e.register, e.address, &values);
+                            match result {
+                                Ok(_) => {
+                                    send_next_event(
+                                        received.data,
+                                        received.metadata,
+                                        next_event_name,
+                                    );
+                                }
+                                Err(err) => {
+                                    error!("Modbus write of {} failed {err}", e.address)
+                                }
+                            }
+                        });
+                    if let Err(e) = result {
+                        error!("Unable to write modbus {e}");
+                    }
+                    continue;
+                }
+                EventType::Print(e) => {
+                    let Some(text) = render_payload(
+                        &handlebars,
+                        e.template.as_deref(),
+                        &template_data,
+                        &received.name,
+                    )
+                    .map(|p| String::from_utf8_lossy(&p).into_owned()) else {
+                        continue;
+                    };
+                    if let Err(err) = e.run(&text) {
+                        error!("Unable to print event={} {err}", received.name);
+                    }
+                }
                 EventType::Pass => (),
                 // events begin in evdev executor
                 #[cfg(target_os = "linux")]
                 EventType::ScanCodeRead(_) => continue,
+                // advertisements begin in ble executor
+                #[cfg(feature = "ble")]
+                EventType::Ble(_) => continue,
+                // entries begin in journal executor
+                #[cfg(target_os = "linux")]
+                EventType::JournalWatch(_) => continue,
+                // edges begin in gpio executor
+                #[cfg(feature = "gpio")]
+                EventType::GpioWatch(_) => continue,
+                #[cfg(all(target_os = "linux", feature = "sensors"))]
+                EventType::SensorRead(e) => {
+                    let result = Builder::new()
+                        .name(format!("sensor_read {}", received.name))
+                        .spawn_scoped(thread_scope, move || match e.read() {
+                            Ok(values) => {
+                                received
+                                    .data
+                                    .merge_with_policy(Data::Json(values), received.merge_data);
+                                send_next_event(received.data, received.metadata, next_event_name);
+                            }
+                            Err(err) => error!("Sensor read failed event={} {err}", received.name),
+                        });
+                    if let Err(e) = result {
+                        error!("Unable to read sensor {e}");
+                    }
+                    continue;
+                }
+                // signals begin in dbus executor
+                #[cfg(feature = "dbus")]
+                EventType::DbusSubscribe(_) => continue,
+                #[cfg(feature = "dbus")]
+                EventType::DbusCall(e) => {
+                    let args = match e
+                        .args
+                        .iter()
+                        .map(|a| handlebars.render_template(a, &template_data))
+                        .collect::<Result<Vec<_>, _>>()
+                    {
+                        Ok(a) => a,
+                        Err(e) => {
+                            error!("Failed to render template event={} {e}", received.name);
+                            continue;
+                        }
+                    };
+                    let result = Builder::new()
+                        .name(format!("dbus_call {}", e.method))
+                        .spawn_scoped(thread_scope, move || match e.call(&args) {
+                            Ok(reply) => {
+                                received
+                                    .data
+                                    .merge_with_policy(Data::Json(reply), received.merge_data);
+                                send_next_event(received.data, received.metadata, next_event_name);
+                            }
+                            Err(err) => {
+                                error!("Dbus call {}.{} failed {err}", e.interface, e.method)
+                            }
+                        });
+                    if let Err(e) = result {
+                        error!("Unable to call dbus {e}");
+                    }
+                    continue;
+                }
+                #[cfg(feature = "gpio")]
+                EventType::GpioSet(ref e) => {
+                    if let Err(err) = gpio_pool.set(e.pin, e.output) {
+                        error!("Gpio set pin {} failed {err}", e.pin);
+                        continue;
+                    }
+                }
             }
 
             send_next_event(received.data, received.metadata, next_event_name);
@@ -325,12 +1835,75 @@ pub fn event_executor(
     Ok(())
 }
 
+/// calls the api with the pool rate limit, circuit breaker and oauth2 authorization applied,
+/// None is returned without calling while the circuit is open
+fn call_pool_api(
+    client: &ApiClient,
+    api_call: &mut ApiCallEvent,
+    data: &Data,
+    event_name: &str,
+    send_next_event: impl Fn(Data, Metadata, Option<String>) -> bool,
+) -> Option<anyhow::Result<(Data, Metadata, u16)>> {
+    if !client.allow() {
+        warn!(
+            "Api circuit is open for pool={}, skipping event={event_name}",
+            client.pool_id
+        );
+        return None;
+    }
+    client.wait();
+    let result = client.authorization().and_then(|authorization| {
+        if let Some(authorization) = authorization {
+            api_call
+                .headers
+                .entry("Authorization".to_string())
+                .or_insert(authorization);
+        }
+        let api_client = client.redirect_client(api_call.redirect)?;
+        api_call.call_api(&api_client, data, event_name)
+    });
+    let success = matches!(result, Ok((_, _, status)) if status < 500);
+    if client.record(success) {
+        warn!("Api circuit opened for pool={}", client.pool_id);
+        // events named __api_circuit_open and __api_circuit_open_{pool_id}
+        for name in [
+            CIRCUIT_OPEN_EVENT.to_string(),
+            format!("{CIRCUIT_OPEN_EVENT}_{}", client.pool_id),
+        ] {
+            let metadata = json!({ name.as_str(): { "pool_id": client.pool_id }});
+            send_next_event(Data::Empty, metadata.into(), Some(name));
+        }
+    }
+    Some(result)
+}
+
+/// body rendered from the template or the event data if the template is not defined
+fn render_payload(
+    handlebars: &Handlebars,
+    body: Option<&str>,
+    template_data: &TemplateData,
+    event_name: &str,
+) -> Option<Vec<u8>> {
+    let Some(template) = body else {
+        return template_data
+            .data
+            .to_bytes()
+            .map_err(|e| error!("Unable to obtain bytes from data event={event_name} {e}"))
+            .ok();
+    };
+    handlebars
+        .render_template(template, template_data)
+        .map(String::into_bytes)
+        .map_err(|e| error!("Failed to render template event={event_name} {e}"))
+        .ok()
+}
+
 #[cfg(test)]
 mod tests {
     use core::time::Duration;
     use std::{sync::mpsc::channel, thread::spawn};
 
-    use serde_json::{json, Value};
+    use serde_json::Value;
 
     use crate::events::{
         data::Data,
@@ -380,7 +1953,12 @@ mod tests {
                     topic: "1".to_string(),
                     pool_id: Default::default(),
                     body: Default::default(),
+                    template_file: Default::default(),
                     retain: false,
+                    clear_retained: false,
+                    qos: None,
+                    properties: Default::default(),
+                    encoding: None,
                 }),
                 next_event: Some("test1".into()),
                 data: Data::Json(json!({ "test1": "new_text", "test5": "text" })),
@@ -396,6 +1974,8 @@ mod tests {
             let events = Events::new(events.into_iter().collect());
             event_executor(
                 &events,
+                None,
+                &Default::default(),
                 queue_rx,
                 queue_tx.clone(),
                 timer_tx,
@@ -403,6 +1983,21 @@ mod tests {
                 MqttPool::default(),
                 ClientPool::default(),
                 HttpQueuePool::default(),
+                WebSocketPool::default(),
+                #[cfg(feature = "serial")]
+                crate::pools::serial::SerialPool::default(),
+                ModbusPool::default(),
+                #[cfg(feature = "email")]
+                crate::pools::smtp::SmtpPool::default(),
+                #[cfg(feature = "redis")]
+                crate::pools::redis::RedisPool::default(),
+                NatsPool::default(),
+                #[cfg(feature = "amqp")]
+                crate::pools::amqp::AmqpPool::default(),
+                #[cfg(feature = "sql")]
+                crate::pools::sql::SqlPool::default(),
+                #[cfg(feature = "zmq")]
+                crate::pools::zmq::ZmqPool::default(),
             )
             .unwrap();
         });
@@ -471,6 +2066,8 @@ mod tests {
             let events = Events::new(events.into_iter().collect());
             event_executor(
                 &events,
+                None,
+                &Default::default(),
                 queue_rx,
                 queue_tx.clone(),
                 timer_tx,
@@ -478,6 +2075,21 @@ mod tests {
                 MqttPool::default(),
                 ClientPool::default(),
                 HttpQueuePool::default(),
+                WebSocketPool::default(),
+                #[cfg(feature = "serial")]
+                crate::pools::serial::SerialPool::default(),
+                ModbusPool::default(),
+                #[cfg(feature = "email")]
+                crate::pools::smtp::SmtpPool::default(),
+                #[cfg(feature = "redis")]
+                crate::pools::redis::RedisPool::default(),
+                NatsPool::default(),
+                #[cfg(feature = "amqp")]
+                crate::pools::amqp::AmqpPool::default(),
+                #[cfg(feature = "sql")]
+                crate::pools::sql::SqlPool::default(),
+                #[cfg(feature = "zmq")]
+                crate::pools::zmq::ZmqPool::default(),
             )
             .unwrap();
         });
@@ -492,6 +2104,95 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_call_pool_api() {
+        let server = tiny_http::Server::http("127.0.0.1:13359").unwrap();
+        let (header_tx, header_rx) = channel();
+        spawn(move || {
+            for request in server.incoming_requests() {
+                let response = if request.url() == "/token" {
+                    tiny_http::Response::from_string(r#"{"access_token":"access"}"#)
+                } else {
+                    let authorization = request
+                        .headers()
+                        .iter()
+                        .find(|h| h.field.equiv("Authorization"))
+                        .map(|h| h.value.to_string());
+                    header_tx.send(authorization).unwrap();
+                    tiny_http::Response::from_string("unavailable").with_status_code(500)
+                };
+                request.respond(response).unwrap();
+            }
+        });
+
+        let client = ApiClient::new(
+            "notify".to_string(),
+            &crate::config::ClientConfiguration {
+                oauth2: Some(crate::config::OAuth2Configuration {
+                    token_url: "http://127.0.0.1:13359/token".to_string(),
+                    client_id: "id".to_string(),
+                    client_secret: crate::config::SecretValue::Value("secret".to_string()),
+                    refresh_token: None,
+                    scope: None,
+                }),
+                circuit_breaker: Some(crate::config::CircuitBreakerConfiguration {
+                    failures: 1,
+                    cooldown: 60,
+                }),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let sent = Mutex::new(Vec::new());
+        let send_next_event = |_: Data, _: Metadata, name: Option<String>| {
+            sent.lock().unwrap().extend(name);
+            true
+        };
+        let mut api_call = ApiCallEvent {
+            url: "http://127.0.0.1:13359/notify".to_string(),
+            ..Default::default()
+        };
+
+        let result = call_pool_api(
+            &client,
+            &mut api_call,
+            &Data::Empty,
+            "test1",
+            send_next_event,
+        );
+        assert_eq!(result.unwrap().unwrap().2, 500);
+        assert_eq!(header_rx.recv().unwrap().as_deref(), Some("Bearer access"));
+        assert_eq!(
+            *sent.lock().unwrap(),
+            ["__api_circuit_open", "__api_circuit_open_notify"]
+        );
+        // the open circuit skips further calls
+        assert!(call_pool_api(
+            &client,
+            &mut api_call,
+            &Data::Empty,
+            "test1",
+            send_next_event
+        )
+        .is_none());
+    }
+
+    #[test]
+    fn test_render_payload() {
+        let handlebars = load_handlebars();
+        let data = Data::Json(json!({ "light": "on" }));
+        let template_data = TemplateData {
+            data: &data,
+            metadata: &Default::default(),
+            state: &Default::default(),
+            env: &Default::default(),
+        };
+        let render = |body| render_payload(&handlebars, body, &template_data, "test1");
+        assert_eq!(render(Some("{{data.light}}")).unwrap(), b"on");
+        assert_eq!(render(None).unwrap(), br#"{"light":"on"}"#);
+        assert!(render(Some("{{#if}}")).is_none());
+    }
+
     fn create_event(
         name: String,
         next_event: Option<String>,
@@ -0,0 +1,174 @@
+use core::time::Duration;
+use std::{sync::mpsc::Sender, thread::sleep};
+
+use indexmap::IndexSet;
+use log::{debug, info, warn};
+use serde_json::json;
+
+use crate::{
+    events::{EventType, Events, ReferencingEvent},
+    pools::redis::RedisClient,
+};
+
+/// seconds to wait before reconnecting to the redis server
+const RECONNECT_DELAY: u64 = 5;
+
+/// subscribes to channels of redis_subscribe events for the pool,
+/// events without a pool_id are subscribed on the first pool
+pub fn redis_executor(
+    pool_id: &str,
+    url: &str,
+    first_pool: bool,
+    events: &Events,
+    queue_tx: Sender<ReferencingEvent>,
+) -> anyhow::Result<()> {
+    let patterns: IndexSet<&str> = events
+        .iter()
+        .filter_map(|e| match &e.event_type {
+            EventType::RedisSubscribe(e)
+                if e.pool_id == pool_id || (e.pool_id.is_empty() && first_pool) =>
+            {
+                Some(e.channel.as_str())
+            }
+            _ => None,
+        })
+        .collect();
+    if patterns.is_empty() {
+        return Ok(());
+    }
+    let client = RedisClient::new(url)?;
+    loop {
+        match subscribe(pool_id, &client, &patterns, events, &queue_tx) {
+            Ok(()) => return Ok(()),
+            Err(e) => warn!("Redis subscription for {pool_id} failed {e}"),
+        }
+        sleep(Duration::from_secs(RECONNECT_DELAY));
+    }
+}
+
+/// returns once the queue is closed
+fn subscribe(
+    pool_id: &str,
+    client: &RedisClient,
+    patterns: &IndexSet<&str>,
+    events: &Events,
+    queue_tx: &Sender<ReferencingEvent>,
+) -> anyhow::Result<()> {
+    let mut connection = client.connection()?;
+    let mut pubsub = connection.as_pubsub();
+    for pattern in patterns {
+        pubsub.psubscribe(pattern)?;
+    }
+    info!("Subscribed to redis channels {patterns:?} for {pool_id}");
+    loop {
+        let message = pubsub.get_message()?;
+        let pattern: String = message.get_pattern()?;
+        let channel = message.get_channel_name();
+        debug!("Redis message received on {channel}");
+        if let Some(e) = handle_incoming(
+            events,
+            pool_id,
+            &pattern,
+            channel,
+            message.get_payload_bytes(),
+        ) {
+            if queue_tx.send(e).is_err() {
+                return Ok(());
+            }
+        }
+    }
+}
+
+fn handle_incoming(
+    events: &Events,
+    pool_id: &str,
+    pattern: &str,
+    channel: &str,
+    payload: &[u8],
+) -> Option<ReferencingEvent> {
+    let ref_event = events.iter().find(|ref_event| {
+        matches!(&ref_event.event_type, EventType::RedisSubscribe(e) if e.matches(pattern, payload))
+    })?;
+
+    if let Some(mut event) = events.get_next_event(ref_event) {
+        event.try_merge_bytes(payload);
+        let mut metadata = ref_event.metadata.clone();
+        metadata.merge(
+            json!({ ref_event.name.as_str(): {"pool_id": pool_id, "channel": channel, "pattern": pattern }})
+                .into(),
+        );
+        event.metadata.merge(metadata);
+        Some(event)
+    } else {
+        debug!(
+            "Received redis message {} without further handler",
+            ref_event.name
+        );
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::events::{
+        data::Data, mqtt_subscribe::MqttBodyMatch, redis_subscribe::RedisSubscribeEvent,
+        time::TimeEvent, NextEvent,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_handle_incoming() {
+        let events = Events::new(
+            [
+                ReferencingEvent {
+                    event_type: EventType::RedisSubscribe(RedisSubscribeEvent {
+                        channel: "sensors.*".to_string(),
+                        body: Some(MqttBodyMatch::BodyContains("temperature".to_string())),
+                        ..Default::default()
+                    }),
+                    next_event: NextEvent::Name("test1".to_string()).into(),
+                    name: "sensor_reading".to_string(),
+                    ..Default::default()
+                },
+                ReferencingEvent {
+                    event_type: EventType::Time(TimeEvent {
+                        execute_time: "now".parse().unwrap(),
+                        event_id: None,
+                    }),
+                    name: "test1".to_string(),
+                    ..Default::default()
+                },
+            ]
+            .into_iter()
+            .collect(),
+        );
+        assert!(handle_incoming(
+            &events,
+            "default",
+            "sensors.*",
+            "sensors.kitchen",
+            br#"{"humidity": 40}"#
+        )
+        .is_none());
+        assert!(handle_incoming(
+            &events,
+            "default",
+            "lights.*",
+            "lights.kitchen",
+            br#"{"temperature": 21}"#
+        )
+        .is_none());
+
+        let event = handle_incoming(
+            &events,
+            "default",
+            "sensors.*",
+            "sensors.kitchen",
+            br#"{"temperature": 21}"#,
+        )
+        .unwrap();
+        assert_eq!(event.name, "test1");
+        assert_eq!(event.data, Data::Json(json!({"temperature": 21})));
+    }
+}
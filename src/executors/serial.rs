@@ -0,0 +1,143 @@
+use std::{
+    io::{ErrorKind, Read},
+    sync::mpsc::Sender,
+};
+
+use log::debug;
+use serde_json::json;
+
+use crate::events::{EventType, Events, ReferencingEvent};
+
+/// messages without a delimiter are sent when the buffer reaches the size
+const MAX_MESSAGE_SIZE: usize = 65536;
+
+pub fn serial_executor(
+    pool_id: &str,
+    delimiter: &[u8],
+    mut port: impl Read,
+    events: &Events,
+    queue_tx: Sender<ReferencingEvent>,
+) -> anyhow::Result<()> {
+    let mut buffer = Vec::new();
+    let mut read = [0; 1024];
+    loop {
+        let size = match port.read(&mut read) {
+            Ok(0) => return Ok(()),
+            Ok(s) => s,
+            Err(e) if matches!(e.kind(), ErrorKind::TimedOut | ErrorKind::Interrupted) => continue,
+            Err(e) => return Err(e.into()),
+        };
+        buffer.extend_from_slice(&read[..size]);
+        for message in take_messages(&mut buffer, delimiter) {
+            if let Some(e) = handle_incoming(events, pool_id, &message) {
+                queue_tx.send(e)?;
+            }
+        }
+    }
+}
+
+/// split complete messages from the buffer leaving the remainder
+fn take_messages(buffer: &mut Vec<u8>, delimiter: &[u8]) -> Vec<Vec<u8>> {
+    let mut messages = Vec::new();
+    if delimiter.is_empty() {
+        messages.push(std::mem::take(buffer));
+        return messages;
+    }
+    while let Some(position) = buffer.windows(delimiter.len()).position(|w| w == delimiter) {
+        let message: Vec<u8> = buffer.drain(..position + delimiter.len()).collect();
+        let message = &message[..position];
+        // line endings may include a carriage return
+        let message = message.strip_suffix(b"\r").unwrap_or(message);
+        if !message.is_empty() {
+            messages.push(message.to_vec());
+        }
+    }
+    if buffer.len() >= MAX_MESSAGE_SIZE {
+        messages.push(std::mem::take(buffer));
+    }
+    messages
+}
+
+fn handle_incoming(events: &Events, pool_id: &str, payload: &[u8]) -> Option<ReferencingEvent> {
+    let ref_event = events.iter().find(|ref_event| {
+        matches!(&ref_event.event_type, EventType::SerialRead(e) if e.matches(pool_id, payload))
+    })?;
+
+    if let Some(mut event) = events.get_next_event(ref_event) {
+        event.try_merge_bytes(payload);
+        let mut metadata = ref_event.metadata.clone();
+        metadata.merge(json!({ ref_event.name.as_str(): {"pool_id": pool_id }}).into());
+        event.metadata.merge(metadata);
+        Some(event)
+    } else {
+        debug!(
+            "Received serial event {} without further handler",
+            ref_event.name
+        );
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc::channel;
+
+    use crate::events::{
+        data::Data, mqtt_subscribe::MqttBodyMatch, serial_read::SerialReadEvent, time::TimeEvent,
+        NextEvent,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_take_messages() {
+        let mut buffer = b"T=21.5\r\nH=40\r\nT=2".to_vec();
+        assert_eq!(
+            take_messages(&mut buffer, b"\n"),
+            vec![b"T=21.5".to_vec(), b"H=40".to_vec()]
+        );
+        assert_eq!(buffer, b"T=2");
+
+        let mut buffer = b"\x01\x02;;\x03;;".to_vec();
+        assert_eq!(
+            take_messages(&mut buffer, b";;"),
+            vec![b"\x01\x02".to_vec(), b"\x03".to_vec()]
+        );
+        assert!(buffer.is_empty());
+    }
+
+    #[test]
+    fn test_executor() {
+        let (queue_tx, queue_rx) = channel();
+        let events = Events::new(
+            [
+                ReferencingEvent {
+                    event_type: EventType::SerialRead(SerialReadEvent {
+                        pool_id: Default::default(),
+                        body: Some(MqttBodyMatch::BodyContains("T=".to_string())),
+                    }),
+                    next_event: NextEvent::Name("test1".to_string()).into(),
+                    name: "read1".to_string(),
+                    ..Default::default()
+                },
+                ReferencingEvent {
+                    event_type: EventType::Time(TimeEvent {
+                        execute_time: "now".parse().unwrap(),
+                        event_id: None,
+                    }),
+                    name: "test1".to_string(),
+                    ..Default::default()
+                },
+            ]
+            .into_iter()
+            .collect(),
+        );
+        let port: &[u8] = b"H=40\nT=21.5\n";
+        serial_executor("default", b"\n", port, &events, queue_tx).unwrap();
+
+        let event = queue_rx.try_recv().unwrap();
+        assert_eq!(event.name, "test1");
+        assert_eq!(event.data, Data::String("T=21.5".to_string()));
+        assert!(queue_rx.try_recv().is_err());
+    }
+}
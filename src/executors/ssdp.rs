@@ -0,0 +1,240 @@
+use core::time::Duration;
+use std::{
+    collections::HashSet,
+    io::ErrorKind,
+    net::{Ipv4Addr, SocketAddr, SocketAddrV4, UdpSocket},
+    sync::mpsc::Sender,
+    time::Instant,
+};
+
+use anyhow::Context;
+use indexmap::{IndexMap, IndexSet};
+use log::{debug, info, warn};
+use serde_json::json;
+use socket2::{Domain, Protocol, Socket, Type};
+
+use crate::events::{data::Data, ssdp::SsdpState, EventType, Events, ReferencingEvent};
+
+const MULTICAST_ADDRESS: Ipv4Addr = Ipv4Addr::new(239, 255, 255, 250);
+const PORT: u16 = 1900;
+/// devices are searched for again to find the ones missed
+const SEARCH_INTERVAL: Duration = Duration::from_secs(300);
+
+/// search for and listen to announcements of targets referenced by ssdp events
+pub fn ssdp_executor(events: &Events, queue_tx: Sender<ReferencingEvent>) -> anyhow::Result<()> {
+    let targets: IndexSet<String> = events
+        .iter()
+        .filter_map(|e| match &e.event_type {
+            EventType::Ssdp(s) => Some(s.search_target.clone()),
+            _ => None,
+        })
+        .collect();
+    if targets.is_empty() {
+        return Ok(());
+    }
+    let socket = bind().context("Ssdp failed to listen to multicast announcements")?;
+    socket.set_read_timeout(Some(Duration::from_secs(1)))?;
+
+    let mut known = HashSet::new();
+    let mut searched: Option<Instant> = None;
+    let mut buffer = [0; 8192];
+    loop {
+        if searched.is_none_or(|s| s.elapsed() >= SEARCH_INTERVAL) {
+            for target in &targets {
+                if let Err(e) = socket.send_to(
+                    search_message(target).as_bytes(),
+                    SocketAddrV4::new(MULTICAST_ADDRESS, PORT),
+                ) {
+                    warn!("Ssdp search for {target} failed {e}");
+                }
+            }
+            searched = Some(Instant::now());
+        }
+        let (size, remote_address) = match socket.recv_from(&mut buffer) {
+            Ok(r) => r,
+            Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => continue,
+            Err(e) => {
+                warn!("Ssdp receive failed {e}");
+                continue;
+            }
+        };
+        let Some((state, headers)) = parse_message(&String::from_utf8_lossy(&buffer[..size]))
+        else {
+            continue;
+        };
+        let Some(usn) = headers.get("usn") else {
+            continue;
+        };
+        // devices announce themselves repeatedly
+        let changed = match state {
+            SsdpState::Alive => known.insert(usn.clone()),
+            SsdpState::Byebye => known.remove(usn),
+        };
+        if !changed {
+            continue;
+        }
+        info!("Ssdp device {usn} {state}");
+        if let Some(e) = handle_incoming(events, state, &headers, remote_address) {
+            queue_tx.send(e)?;
+        }
+    }
+}
+
+fn bind() -> anyhow::Result<UdpSocket> {
+    let socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))?;
+    // other upnp software may listen on the same port
+    socket.set_reuse_address(true)?;
+    socket.bind(&SocketAddr::from((Ipv4Addr::UNSPECIFIED, PORT)).into())?;
+    socket.join_multicast_v4(&MULTICAST_ADDRESS, &Ipv4Addr::UNSPECIFIED)?;
+    Ok(socket.into())
+}
+
+fn search_message(target: &str) -> String {
+    format!(
+        "M-SEARCH * HTTP/1.1\r\nHOST: {MULTICAST_ADDRESS}:{PORT}\r\nMAN: \"ssdp:discover\"\r\nMX: 2\r\nST: {target}\r\n\r\n"
+    )
+}
+
+/// state and lowercase headers of announcements and search responses
+fn parse_message(message: &str) -> Option<(SsdpState, IndexMap<String, String>)> {
+    let mut lines = message.lines();
+    let start = lines.next()?;
+    let headers: IndexMap<String, String> = lines
+        .filter_map(|l| {
+            let (key, value) = l.split_once(':')?;
+            Some((key.trim().to_lowercase(), value.trim().to_string()))
+        })
+        .collect();
+    let state = if start.starts_with("NOTIFY") {
+        match headers.get("nts").map(String::as_str) {
+            Some("ssdp:alive") => SsdpState::Alive,
+            Some("ssdp:byebye") => SsdpState::Byebye,
+            _ => return None,
+        }
+    } else if start.starts_with("HTTP/1.1 200") {
+        SsdpState::Alive
+    } else {
+        // searches from other clients
+        return None;
+    };
+    Some((state, headers))
+}
+
+fn handle_incoming(
+    events: &Events,
+    state: SsdpState,
+    headers: &IndexMap<String, String>,
+    remote_address: SocketAddr,
+) -> Option<ReferencingEvent> {
+    // notifications specify the type as nt, search responses as st
+    let target = headers.get("nt").or(headers.get("st"))?;
+    let ref_event = events.iter().find(
+        |ref_event| matches!(&ref_event.event_type, EventType::Ssdp(e) if e.matches(target, state)),
+    )?;
+
+    if let Some(mut event) = events.get_next_event(ref_event) {
+        event.merge(Data::Json(json!({
+            "usn": headers.get("usn"),
+            "target": target,
+            "location": headers.get("location"),
+            "server": headers.get("server"),
+        })));
+        let mut metadata = ref_event.metadata.clone();
+        metadata.merge(
+            json!({ ref_event.name.as_str(): {"state": state.to_string(), "remote_address": remote_address }})
+                .into(),
+        );
+        event.metadata.merge(metadata);
+        Some(event)
+    } else {
+        debug!(
+            "Received ssdp event {} without further handler",
+            ref_event.name
+        );
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+
+    use crate::events::{ssdp::SsdpEvent, time::TimeEvent, NextEvent};
+
+    use super::*;
+
+    #[test]
+    fn test_parse_message() {
+        let (state, headers) = parse_message(
+            "NOTIFY * HTTP/1.1\r\nHOST: 239.255.255.250:1900\r\nNT: urn:schemas-upnp-org:device:ZonePlayer:1\r\nNTS: ssdp:alive\r\nLocation: http://192.168.1.40:1400/xml/device_description.xml\r\nUSN: uuid:RINCON_1::urn:schemas-upnp-org:device:ZonePlayer:1\r\n\r\n",
+        )
+        .unwrap();
+        assert_eq!(state, SsdpState::Alive);
+        assert_eq!(
+            headers["location"],
+            "http://192.168.1.40:1400/xml/device_description.xml"
+        );
+        assert_eq!(headers["nt"], "urn:schemas-upnp-org:device:ZonePlayer:1");
+
+        let (state, _) =
+            parse_message("NOTIFY * HTTP/1.1\r\nNTS: ssdp:byebye\r\nUSN: uuid:1\r\n\r\n").unwrap();
+        assert_eq!(state, SsdpState::Byebye);
+
+        let (state, headers) =
+            parse_message("HTTP/1.1 200 OK\r\nST: upnp:rootdevice\r\nUSN: uuid:1\r\n\r\n").unwrap();
+        assert_eq!(state, SsdpState::Alive);
+        assert_eq!(headers["st"], "upnp:rootdevice");
+
+        assert!(parse_message(&search_message("ssdp:all")).is_none());
+    }
+
+    #[test]
+    fn test_handle_incoming() {
+        let events = Events::new(
+            [
+                ReferencingEvent {
+                    event_type: EventType::Ssdp(SsdpEvent {
+                        search_target: "urn:schemas-upnp-org:device:ZonePlayer:1".to_string(),
+                        when: Some(SsdpState::Alive),
+                    }),
+                    next_event: NextEvent::Name("test1".to_string()).into(),
+                    name: "sonos_found".to_string(),
+                    ..Default::default()
+                },
+                ReferencingEvent {
+                    event_type: EventType::Time(TimeEvent {
+                        execute_time: "now".parse().unwrap(),
+                        event_id: None,
+                    }),
+                    name: "test1".to_string(),
+                    ..Default::default()
+                },
+            ]
+            .into_iter()
+            .collect(),
+        );
+        let remote_address = "192.168.1.40:1900".parse().unwrap();
+        let headers: IndexMap<String, String> = [
+            ("st", "urn:schemas-upnp-org:device:ZonePlayer:1"),
+            ("usn", "uuid:RINCON_1"),
+            (
+                "location",
+                "http://192.168.1.40:1400/xml/device_description.xml",
+            ),
+        ]
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+        let event = handle_incoming(&events, SsdpState::Alive, &headers, remote_address).unwrap();
+        assert_eq!(event.name, "test1");
+        let data = serde_json::to_value(&event.data).unwrap();
+        assert_eq!(
+            data["location"],
+            "http://192.168.1.40:1400/xml/device_description.xml"
+        );
+        let metadata: Value = serde_json::to_value(&event.metadata).unwrap();
+        assert_eq!(metadata["sonos_found"]["state"], "alive");
+
+        assert!(handle_incoming(&events, SsdpState::Byebye, &headers, remote_address).is_none());
+    }
+}
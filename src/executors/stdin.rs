@@ -0,0 +1,135 @@
+use std::{
+    io::{stdin, BufRead},
+    sync::mpsc::Sender,
+};
+
+use log::{debug, info};
+use serde_json::json;
+
+use crate::events::{data::Data, stdin_read::StdinFraming, EventType, Events, ReferencingEvent};
+
+/// reads lines from stdin until it is closed
+pub fn stdin_executor(events: &Events, queue_tx: Sender<ReferencingEvent>) -> anyhow::Result<()> {
+    info!("Reading lines from stdin");
+    read_lines(stdin().lock(), events, &queue_tx)?;
+    info!("Stdin closed");
+    Ok(())
+}
+
+fn read_lines(
+    reader: impl BufRead,
+    events: &Events,
+    queue_tx: &Sender<ReferencingEvent>,
+) -> anyhow::Result<()> {
+    for (index, line) in reader.split(b'\n').enumerate() {
+        let line = line?;
+        let line = line.strip_suffix(b"\r").unwrap_or(&line);
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(e) = handle_incoming(events, index + 1, line) {
+            queue_tx.send(e)?;
+        }
+    }
+    Ok(())
+}
+
+fn handle_incoming(events: &Events, line_number: usize, line: &[u8]) -> Option<ReferencingEvent> {
+    let (ref_event, framing) = events
+        .iter()
+        .find_map(|ref_event| match &ref_event.event_type {
+            EventType::StdinRead(e) if e.matches(line) => Some((ref_event, e.framing)),
+            _ => None,
+        })?;
+
+    if let Some(mut event) = events.get_next_event(ref_event) {
+        match framing {
+            StdinFraming::Line => event
+                .data
+                .merge(Data::String(String::from_utf8_lossy(line).into_owned())),
+            StdinFraming::Json => event.data.try_merge_bytes(line),
+        }
+        let mut metadata = ref_event.metadata.clone();
+        metadata.merge(json!({ ref_event.name.as_str(): {"line": line_number }}).into());
+        event.metadata.merge(metadata);
+        Some(event)
+    } else {
+        debug!(
+            "Received stdin line {} without further handler",
+            ref_event.name
+        );
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc::channel;
+
+    use serde_json::Value;
+
+    use crate::events::{
+        mqtt_subscribe::MqttBodyMatch, stdin_read::StdinReadEvent, time::TimeEvent, NextEvent,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_read_lines() {
+        let (queue_tx, queue_rx) = channel();
+        let next_event = |name: &str| ReferencingEvent {
+            event_type: EventType::Time(TimeEvent {
+                execute_time: "now".parse().unwrap(),
+                event_id: None,
+            }),
+            name: name.to_string(),
+            ..Default::default()
+        };
+        let events = Events::new(
+            [
+                ReferencingEvent {
+                    event_type: EventType::StdinRead(StdinReadEvent {
+                        framing: StdinFraming::Json,
+                        body: None,
+                    }),
+                    next_event: NextEvent::Name("json".to_string()).into(),
+                    name: "stdin_json".to_string(),
+                    ..Default::default()
+                },
+                ReferencingEvent {
+                    event_type: EventType::StdinRead(StdinReadEvent {
+                        framing: StdinFraming::Line,
+                        body: Some(MqttBodyMatch::BodyContains("error".to_string())),
+                    }),
+                    next_event: NextEvent::Name("line".to_string()).into(),
+                    name: "stdin_line".to_string(),
+                    ..Default::default()
+                },
+                next_event("json"),
+                next_event("line"),
+            ]
+            .into_iter()
+            .collect(),
+        );
+
+        read_lines(
+            b"{\"level\": 3}\nstarting\n\n42 errors\r\n".as_slice(),
+            &events,
+            &queue_tx,
+        )
+        .unwrap();
+
+        let event = queue_rx.try_recv().unwrap();
+        assert_eq!(event.name, "json");
+        assert_eq!(event.data, Data::Json(json!({"level": 3})));
+
+        // numbers are kept as strings with line framing
+        let event = queue_rx.try_recv().unwrap();
+        assert_eq!(event.name, "line");
+        assert_eq!(event.data, Data::String("42 errors".to_string()));
+        let metadata: Value = serde_json::to_value(&event.metadata).unwrap();
+        assert_eq!(metadata["stdin_line"]["line"], 4);
+
+        assert!(queue_rx.try_recv().is_err());
+    }
+}
@@ -0,0 +1,253 @@
+use std::{
+    io::{BufRead, BufReader, Read},
+    net::{SocketAddr, TcpListener, TcpStream, UdpSocket},
+    sync::mpsc::Sender,
+    thread::{scope, Builder},
+};
+
+use anyhow::{bail, Context};
+use log::{debug, error, warn};
+use serde_json::json;
+
+use crate::{
+    config::{SyslogConfiguration, SyslogProtocol},
+    events::{data::Data, syslog_listen::SyslogMessage, EventType, Events, ReferencingEvent},
+};
+
+/// longer messages are truncated
+const MAX_MESSAGE_SIZE: usize = 65536;
+
+pub fn syslog_executor(
+    pool_id: &str,
+    config: &SyslogConfiguration,
+    events: &Events,
+    queue_tx: Sender<ReferencingEvent>,
+) -> anyhow::Result<()> {
+    match config.protocol {
+        SyslogProtocol::Udp => listen_udp(pool_id, &config.listen, events, queue_tx),
+        SyslogProtocol::Tcp => listen_tcp(pool_id, &config.listen, events, queue_tx),
+    }
+}
+
+fn listen_udp(
+    pool_id: &str,
+    listen: &str,
+    events: &Events,
+    queue_tx: Sender<ReferencingEvent>,
+) -> anyhow::Result<()> {
+    let socket = UdpSocket::bind(listen)
+        .with_context(|| format!("Syslog server failed to listen to {listen}"))?;
+
+    let mut buffer = vec![0; MAX_MESSAGE_SIZE];
+    loop {
+        let (size, remote_address) = match socket.recv_from(&mut buffer) {
+            Ok(r) => r,
+            Err(e) => {
+                warn!("Syslog receive failed {e}");
+                continue;
+            }
+        };
+        if let Some(e) = handle_incoming(events, pool_id, Some(remote_address), &buffer[..size]) {
+            queue_tx.send(e)?;
+        }
+    }
+}
+
+fn listen_tcp(
+    pool_id: &str,
+    listen: &str,
+    events: &Events,
+    queue_tx: Sender<ReferencingEvent>,
+) -> anyhow::Result<()> {
+    let listener = TcpListener::bind(listen)
+        .with_context(|| format!("Syslog server failed to listen to {listen}"))?;
+
+    scope(|s| {
+        for stream in listener.incoming() {
+            let stream = match stream {
+                Ok(s) => s,
+                Err(e) => {
+                    warn!("Syslog connection failed {e}");
+                    continue;
+                }
+            };
+            let queue_tx = queue_tx.clone();
+            let result = Builder::new()
+                .name(format!("syslog {listen}"))
+                .spawn_scoped(s, move || {
+                    if let Err(e) = handle_connection(pool_id, stream, events, queue_tx) {
+                        debug!("Syslog connection closed {e}");
+                    }
+                });
+            if let Err(e) = result {
+                error!("Unable to handle syslog connection {e}");
+            }
+        }
+    });
+    Ok(())
+}
+
+fn handle_connection(
+    pool_id: &str,
+    stream: TcpStream,
+    events: &Events,
+    queue_tx: Sender<ReferencingEvent>,
+) -> anyhow::Result<()> {
+    let remote_address = stream.peer_addr().ok();
+    let mut reader = BufReader::new(stream);
+    loop {
+        let Some(frame) = read_frame(&mut reader)? else {
+            return Ok(());
+        };
+        if frame.is_empty() {
+            continue;
+        }
+        if let Some(e) = handle_incoming(events, pool_id, remote_address, &frame) {
+            queue_tx.send(e)?;
+        }
+    }
+}
+
+/// octet counting frames start with the message length, otherwise frames end with a newline
+fn read_frame(reader: &mut impl BufRead) -> anyhow::Result<Option<Vec<u8>>> {
+    let first = match reader.fill_buf()?.first() {
+        Some(b) => *b,
+        None => return Ok(None),
+    };
+    let mut frame = Vec::new();
+    if first.is_ascii_digit() {
+        reader.take(8).read_until(b' ', &mut frame)?;
+        let Some(size) = frame
+            .strip_suffix(b" ")
+            .and_then(|s| core::str::from_utf8(s).ok())
+            .and_then(|s| s.parse::<usize>().ok())
+        else {
+            bail!("Invalid syslog frame length");
+        };
+        frame = vec![0; size.min(MAX_MESSAGE_SIZE)];
+        reader.read_exact(&mut frame)?;
+    } else {
+        reader
+            .take(MAX_MESSAGE_SIZE as u64)
+            .read_until(b'\n', &mut frame)?;
+    }
+    Ok(Some(frame))
+}
+
+fn handle_incoming(
+    events: &Events,
+    pool_id: &str,
+    remote_address: Option<SocketAddr>,
+    payload: &[u8],
+) -> Option<ReferencingEvent> {
+    let message = SyslogMessage::parse(&String::from_utf8_lossy(payload));
+    let ref_event = events.iter().find(|ref_event| {
+        matches!(&ref_event.event_type, EventType::SyslogListen(e) if e.matches(pool_id, &message))
+    })?;
+
+    if let Some(mut event) = events.get_next_event(ref_event) {
+        event.merge(Data::Json(message.to_json()));
+        let mut metadata = ref_event.metadata.clone();
+        metadata.merge(
+            json!({ ref_event.name.as_str(): {"pool_id": pool_id, "remote_address": remote_address }})
+                .into(),
+        );
+        event.metadata.merge(metadata);
+        Some(event)
+    } else {
+        debug!(
+            "Received syslog event {} without further handler",
+            ref_event.name
+        );
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::time::Duration;
+    use std::{
+        io::Write,
+        sync::mpsc::channel,
+        thread::{sleep, spawn},
+    };
+
+    use serde_json::Value;
+
+    use crate::events::{syslog_listen::SyslogListenEvent, time::TimeEvent, NextEvent};
+
+    use super::*;
+
+    #[test]
+    fn test_read_frame() {
+        let mut reader = &b"<13>first\n11 <13>second\n<13>third"[..];
+        assert_eq!(read_frame(&mut reader).unwrap().unwrap(), b"<13>first\n");
+        assert_eq!(read_frame(&mut reader).unwrap().unwrap(), b"<13>second\n");
+        assert_eq!(read_frame(&mut reader).unwrap().unwrap(), b"<13>third");
+        assert!(read_frame(&mut reader).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_executor() {
+        let (queue_tx, queue_rx) = channel();
+
+        spawn(move || {
+            let events = Events::new(
+                [
+                    ReferencingEvent {
+                        event_type: EventType::SyslogListen(SyslogListenEvent {
+                            app_name: Some("hostapd".to_string()),
+                            ..Default::default()
+                        }),
+                        next_event: NextEvent::Name("test1".to_string()).into(),
+                        name: "listen1".to_string(),
+                        ..Default::default()
+                    },
+                    ReferencingEvent {
+                        event_type: EventType::Time(TimeEvent {
+                            execute_time: "now".parse().unwrap(),
+                            event_id: None,
+                        }),
+                        name: "test1".to_string(),
+                        ..Default::default()
+                    },
+                ]
+                .into_iter()
+                .collect(),
+            );
+            syslog_executor(
+                "router",
+                &SyslogConfiguration {
+                    listen: "127.0.0.1:13351".to_string(),
+                    protocol: SyslogProtocol::Tcp,
+                },
+                &events,
+                queue_tx,
+            )
+            .unwrap();
+        });
+
+        // wait for the server to start
+        let mut stream = (0..20)
+            .find_map(|_| {
+                TcpStream::connect("127.0.0.1:13351")
+                    .map_err(|_| sleep(Duration::from_millis(50)))
+                    .ok()
+            })
+            .unwrap();
+        stream
+            .write_all(b"<30>Oct 11 22:14:15 ap dnsmasq: query\n<30>Oct 11 22:14:16 ap hostapd: wlan0: connected\n")
+            .unwrap();
+
+        let event = queue_rx.recv_timeout(Duration::from_secs(1)).unwrap();
+        assert_eq!(event.name, "test1");
+        let Data::Json(data) = event.data else {
+            panic!("Expected json data");
+        };
+        assert_eq!(data["hostname"], "ap");
+        assert_eq!(data["severity"], "info");
+        assert_eq!(data["message"], "wlan0: connected");
+        let metadata: Value = serde_json::to_value(&event.metadata).unwrap();
+        assert_eq!(metadata["listen1"]["pool_id"], "router");
+    }
+}
@@ -0,0 +1,204 @@
+use std::{
+    io::{BufRead, BufReader, Read},
+    net::{SocketAddr, TcpListener, TcpStream},
+    sync::mpsc::Sender,
+    thread::{scope, Builder},
+};
+
+use anyhow::Context;
+use log::{debug, error, warn};
+use serde_json::json;
+
+use crate::{
+    config::{TcpConfiguration, TcpFraming},
+    events::{EventType, Events, ReferencingEvent},
+};
+
+/// longer lines and reads are split into multiple messages
+const MAX_MESSAGE_SIZE: usize = 65536;
+
+pub fn tcp_executor(
+    pool_id: &str,
+    config: &TcpConfiguration,
+    events: &Events,
+    queue_tx: Sender<ReferencingEvent>,
+) -> anyhow::Result<()> {
+    let listener = TcpListener::bind(&config.listen)
+        .with_context(|| format!("Tcp server failed to listen to {}", config.listen))?;
+
+    scope(|s| {
+        for stream in listener.incoming() {
+            let stream = match stream {
+                Ok(s) => s,
+                Err(e) => {
+                    warn!("Tcp connection failed {e}");
+                    continue;
+                }
+            };
+            let queue_tx = queue_tx.clone();
+            let result = Builder::new()
+                .name(format!("tcp {}", config.listen))
+                .spawn_scoped(s, move || {
+                    if let Err(e) =
+                        handle_connection(pool_id, config.framing, stream, events, queue_tx)
+                    {
+                        debug!("Tcp connection closed {e}");
+                    }
+                });
+            if let Err(e) = result {
+                error!("Unable to handle tcp connection {e}");
+            }
+        }
+    });
+    Ok(())
+}
+
+fn handle_connection(
+    pool_id: &str,
+    framing: TcpFraming,
+    stream: TcpStream,
+    events: &Events,
+    queue_tx: Sender<ReferencingEvent>,
+) -> anyhow::Result<()> {
+    let remote_address = stream.peer_addr().ok();
+    debug!("Tcp client connected remote_address={remote_address:?}");
+    let mut reader = BufReader::new(stream);
+    let mut buffer = Vec::new();
+    loop {
+        buffer.clear();
+        let size = match framing {
+            TcpFraming::Line => reader
+                .by_ref()
+                .take(MAX_MESSAGE_SIZE as u64)
+                .read_until(b'\n', &mut buffer)?,
+            TcpFraming::Raw => {
+                buffer.resize(MAX_MESSAGE_SIZE, 0);
+                let size = reader.read(&mut buffer)?;
+                buffer.truncate(size);
+                size
+            }
+        };
+        if size == 0 {
+            return Ok(());
+        }
+        let payload = match framing {
+            TcpFraming::Line => buffer
+                .strip_suffix(b"\n")
+                .map(|b| b.strip_suffix(b"\r").unwrap_or(b))
+                .unwrap_or(&buffer),
+            TcpFraming::Raw => &buffer,
+        };
+        if framing == TcpFraming::Line && payload.is_empty() {
+            continue;
+        }
+        if let Some(e) = handle_incoming(events, pool_id, remote_address, payload) {
+            queue_tx.send(e)?;
+        }
+    }
+}
+
+fn handle_incoming(
+    events: &Events,
+    pool_id: &str,
+    remote_address: Option<SocketAddr>,
+    payload: &[u8],
+) -> Option<ReferencingEvent> {
+    let ref_event = events.iter().find(|ref_event| {
+        matches!(&ref_event.event_type, EventType::TcpListen(e) if e.matches(pool_id, payload))
+    })?;
+
+    if let Some(mut event) = events.get_next_event(ref_event) {
+        event.try_merge_bytes(payload);
+        let mut metadata = ref_event.metadata.clone();
+        metadata.merge(
+            json!({ ref_event.name.as_str(): {"pool_id": pool_id, "remote_address": remote_address }})
+                .into(),
+        );
+        event.metadata.merge(metadata);
+        Some(event)
+    } else {
+        debug!(
+            "Received tcp event {} without further handler",
+            ref_event.name
+        );
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::time::Duration;
+    use std::{
+        io::Write,
+        sync::mpsc::channel,
+        thread::{sleep, spawn},
+    };
+
+    use serde_json::Value;
+
+    use crate::events::{
+        data::Data, mqtt_subscribe::MqttBodyMatch, tcp_listen::TcpListenEvent, time::TimeEvent,
+        NextEvent,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_executor() {
+        let (queue_tx, queue_rx) = channel();
+
+        spawn(move || {
+            let events = Events::new(
+                [
+                    ReferencingEvent {
+                        event_type: EventType::TcpListen(TcpListenEvent {
+                            pool_id: Default::default(),
+                            body: Some(MqttBodyMatch::BodyContains("ZONE".to_string())),
+                        }),
+                        next_event: NextEvent::Name("test1".to_string()).into(),
+                        name: "listen1".to_string(),
+                        ..Default::default()
+                    },
+                    ReferencingEvent {
+                        event_type: EventType::Time(TimeEvent {
+                            execute_time: "now".parse().unwrap(),
+                            event_id: None,
+                        }),
+                        name: "test1".to_string(),
+                        ..Default::default()
+                    },
+                ]
+                .into_iter()
+                .collect(),
+            );
+            tcp_executor(
+                "alarm",
+                &TcpConfiguration::new("127.0.0.1:13347".to_string()),
+                &events,
+                queue_tx,
+            )
+            .unwrap();
+        });
+
+        // wait for the server to start
+        let mut stream = (0..20)
+            .find_map(|_| {
+                TcpStream::connect("127.0.0.1:13347")
+                    .map_err(|_| sleep(Duration::from_millis(50)))
+                    .ok()
+            })
+            .unwrap();
+        stream
+            .write_all(b"READY\r\n\r\nALARM ZONE 1\r\nALARM ZONE 2\n")
+            .unwrap();
+
+        let event = queue_rx.recv_timeout(Duration::from_secs(1)).unwrap();
+        assert_eq!(event.name, "test1");
+        assert_eq!(event.data, Data::String("ALARM ZONE 1".to_string()));
+        let metadata: Value = serde_json::to_value(&event.metadata).unwrap();
+        assert_eq!(metadata["listen1"]["pool_id"], "alarm");
+
+        let event = queue_rx.recv_timeout(Duration::from_secs(1)).unwrap();
+        assert_eq!(event.data, Data::String("ALARM ZONE 2".to_string()));
+    }
+}
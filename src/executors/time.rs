@@ -14,6 +14,8 @@ use crate::{
     events::{time::COOL_DOWN_DURATION, EventType, Events, ReferencingEvent},
 };
 
+pub const PRUNE_INTERVAL: Duration = Duration::from_secs(3600);
+
 pub fn timed_executor<'a>(
     events: &'a Events,
     mut events_to_execute: IndexMap<&'a str, ReferencingEvent>,
@@ -22,7 +24,18 @@ pub fn timed_executor<'a>(
     database: impl KeyValueStore,
 ) -> Result<(), anyhow::Error> {
     let mut delay_events: HashMap<&str, Instant> = HashMap::new();
+    let mut last_prune: Option<Instant> = None;
     loop {
+        if last_prune
+            .map(|p| p.elapsed() > PRUNE_INTERVAL)
+            .unwrap_or(true)
+        {
+            let removed = database.prune();
+            if removed > 0 {
+                info!("Removed {removed} stale events from store");
+            }
+            last_prune = Instant::now().into();
+        }
         delay_events.retain(|_, d| d.elapsed() <= COOL_DOWN_DURATION);
         for time_event in timer_rx.try_iter() {
             let event_id = events
@@ -38,7 +51,11 @@ pub fn timed_executor<'a>(
                     .map(|t| t.execute_time.to_string())
                     .unwrap_or_else(|| "instant".to_string())
             );
-            database.insert(event_id, &time_event)?;
+            database.insert(
+                event_id,
+                &time_event,
+                time_event.time_event().and_then(|t| t.stale_at()),
+            )?;
             if let Some(e) = events_to_execute.insert(event_id, time_event) {
                 debug!("Previous event {} with the same id removed", e.name);
             }
@@ -0,0 +1,136 @@
+use std::{
+    net::{SocketAddr, UdpSocket},
+    sync::mpsc::Sender,
+};
+
+use anyhow::Context;
+use log::{debug, warn};
+use serde_json::json;
+
+use crate::events::{EventType, Events, ReferencingEvent};
+
+/// maximum udp payload size
+const MAX_DATAGRAM_SIZE: usize = 65536;
+
+pub fn udp_executor(
+    pool_id: &str,
+    listen: &str,
+    events: &Events,
+    queue_tx: Sender<ReferencingEvent>,
+) -> anyhow::Result<()> {
+    let socket = UdpSocket::bind(listen)
+        .with_context(|| format!("Udp server failed to listen to {listen}"))?;
+
+    let mut buffer = vec![0; MAX_DATAGRAM_SIZE];
+    loop {
+        let (size, remote_address) = match socket.recv_from(&mut buffer) {
+            Ok(r) => r,
+            Err(e) => {
+                warn!("Udp receive failed {e}");
+                continue;
+            }
+        };
+        if let Some(e) = handle_incoming(events, pool_id, remote_address, &buffer[..size]) {
+            queue_tx.send(e)?;
+        }
+    }
+}
+
+fn handle_incoming(
+    events: &Events,
+    pool_id: &str,
+    remote_address: SocketAddr,
+    payload: &[u8],
+) -> Option<ReferencingEvent> {
+    let ref_event = events.iter().find(|ref_event| {
+        matches!(&ref_event.event_type, EventType::UdpListen(e) if e.matches(pool_id, payload))
+    })?;
+
+    if let Some(mut event) = events.get_next_event(ref_event) {
+        event.try_merge_bytes(payload);
+        let mut metadata = ref_event.metadata.clone();
+        metadata.merge(
+            json!({ ref_event.name.as_str(): {"pool_id": pool_id, "remote_address": remote_address }})
+                .into(),
+        );
+        event.metadata.merge(metadata);
+        Some(event)
+    } else {
+        debug!(
+            "Received udp event {} without further handler",
+            ref_event.name
+        );
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::time::Duration;
+    use std::{
+        sync::mpsc::channel,
+        thread::{sleep, spawn},
+    };
+
+    use serde_json::Value;
+
+    use crate::events::{
+        data::Data, mqtt_subscribe::MqttBodyMatch, time::TimeEvent, udp_listen::UdpListenEvent,
+        udp_send::UdpSendEvent, NextEvent,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_executor() {
+        let (queue_tx, queue_rx) = channel();
+
+        spawn(move || {
+            let events = Events::new(
+                [
+                    ReferencingEvent {
+                        event_type: EventType::UdpListen(UdpListenEvent {
+                            pool_id: "default".to_string(),
+                            body: Some(MqttBodyMatch::BodyContains("state".to_string())),
+                        }),
+                        next_event: NextEvent::Name("test1".to_string()).into(),
+                        name: "listen1".to_string(),
+                        ..Default::default()
+                    },
+                    ReferencingEvent {
+                        event_type: EventType::Time(TimeEvent {
+                            execute_time: "now".parse().unwrap(),
+                            event_id: None,
+                        }),
+                        name: "test1".to_string(),
+                        ..Default::default()
+                    },
+                ]
+                .into_iter()
+                .collect(),
+            );
+            udp_executor("default", "127.0.0.1:13349", &events, queue_tx).unwrap();
+        });
+
+        let event = UdpSendEvent::default();
+        // datagrams are lost until the socket is bound
+        let received = (0..20).find_map(|_| {
+            event.send("127.0.0.1:13349", b"ping").unwrap();
+            event.send("127.0.0.1:13349", br#"{"state":"on"}"#).unwrap();
+            queue_rx
+                .recv_timeout(Duration::from_millis(50))
+                .map_err(|_| sleep(Duration::from_millis(50)))
+                .ok()
+        });
+
+        let event = received.unwrap();
+        assert_eq!(event.name, "test1");
+        assert_eq!(event.data, Data::Json(json!({ "state": "on" })));
+        let metadata: Value = serde_json::to_value(&event.metadata).unwrap();
+        assert_eq!(metadata["listen1"]["pool_id"], "default");
+        assert!(metadata["listen1"]["remote_address"]
+            .as_str()
+            .unwrap()
+            .starts_with("127.0.0.1:"));
+    }
+}
@@ -0,0 +1,313 @@
+use std::{
+    fs::remove_file,
+    io::{BufRead, BufReader, ErrorKind, Read},
+    os::{
+        fd::{AsRawFd, RawFd},
+        unix::net::{UnixDatagram, UnixListener, UnixStream},
+    },
+    sync::mpsc::Sender,
+    thread::{scope, Builder},
+};
+
+use anyhow::Context;
+use log::{debug, error, warn};
+use serde_json::{json, Value};
+
+use crate::{
+    config::{TcpFraming, UnixConfiguration, UnixSocketType},
+    events::{EventType, Events, ReferencingEvent},
+};
+
+/// longer lines, reads and datagrams are split or truncated
+const MAX_MESSAGE_SIZE: usize = 65536;
+
+pub fn unix_executor(
+    pool_id: &str,
+    config: &UnixConfiguration,
+    events: &Events,
+    queue_tx: Sender<ReferencingEvent>,
+) -> anyhow::Result<()> {
+    // sockets left behind by a previous run prevent binding
+    match remove_file(&config.listen) {
+        Err(e) if e.kind() != ErrorKind::NotFound => {
+            warn!("Unable to remove {} {e}", config.listen.display())
+        }
+        _ => (),
+    }
+    match config.socket {
+        UnixSocketType::Stream => listen_stream(pool_id, config, events, queue_tx),
+        UnixSocketType::Datagram => listen_datagram(pool_id, config, events, queue_tx),
+    }
+}
+
+fn listen_stream(
+    pool_id: &str,
+    config: &UnixConfiguration,
+    events: &Events,
+    queue_tx: Sender<ReferencingEvent>,
+) -> anyhow::Result<()> {
+    let listener = UnixListener::bind(&config.listen).with_context(|| {
+        format!(
+            "Unix socket server failed to listen to {}",
+            config.listen.display()
+        )
+    })?;
+
+    scope(|s| {
+        for stream in listener.incoming() {
+            let stream = match stream {
+                Ok(s) => s,
+                Err(e) => {
+                    warn!("Unix socket connection failed {e}");
+                    continue;
+                }
+            };
+            let queue_tx = queue_tx.clone();
+            let result = Builder::new()
+                .name(format!("unix {}", config.listen.display()))
+                .spawn_scoped(s, move || {
+                    if let Err(e) =
+                        handle_connection(pool_id, config.framing, stream, events, queue_tx)
+                    {
+                        debug!("Unix socket connection closed {e}");
+                    }
+                });
+            if let Err(e) = result {
+                error!("Unable to handle unix socket connection {e}");
+            }
+        }
+    });
+    Ok(())
+}
+
+fn listen_datagram(
+    pool_id: &str,
+    config: &UnixConfiguration,
+    events: &Events,
+    queue_tx: Sender<ReferencingEvent>,
+) -> anyhow::Result<()> {
+    let socket = UnixDatagram::bind(&config.listen).with_context(|| {
+        format!(
+            "Unix datagram socket failed to bind to {}",
+            config.listen.display()
+        )
+    })?;
+    let mut buffer = vec![0; MAX_MESSAGE_SIZE];
+    loop {
+        let (size, address) = socket.recv_from(&mut buffer)?;
+        let peer = json!({ "remote_path": address.as_pathname() });
+        if let Some(e) = handle_incoming(events, pool_id, peer, &buffer[..size]) {
+            queue_tx.send(e)?;
+        }
+    }
+}
+
+fn handle_connection(
+    pool_id: &str,
+    framing: TcpFraming,
+    stream: UnixStream,
+    events: &Events,
+    queue_tx: Sender<ReferencingEvent>,
+) -> anyhow::Result<()> {
+    let peer = peer_credentials(stream.as_raw_fd());
+    debug!("Unix socket client connected {peer}");
+    let mut reader = BufReader::new(stream);
+    let mut buffer = Vec::new();
+    loop {
+        buffer.clear();
+        let size = match framing {
+            TcpFraming::Line => reader
+                .by_ref()
+                .take(MAX_MESSAGE_SIZE as u64)
+                .read_until(b'\n', &mut buffer)?,
+            TcpFraming::Raw => {
+                buffer.resize(MAX_MESSAGE_SIZE, 0);
+                let size = reader.read(&mut buffer)?;
+                buffer.truncate(size);
+                size
+            }
+        };
+        if size == 0 {
+            return Ok(());
+        }
+        let payload = match framing {
+            TcpFraming::Line => buffer
+                .strip_suffix(b"\n")
+                .map(|b| b.strip_suffix(b"\r").unwrap_or(b))
+                .unwrap_or(&buffer),
+            TcpFraming::Raw => &buffer,
+        };
+        if framing == TcpFraming::Line && payload.is_empty() {
+            continue;
+        }
+        if let Some(e) = handle_incoming(events, pool_id, peer.clone(), payload) {
+            queue_tx.send(e)?;
+        }
+    }
+}
+
+/// pid, uid and gid of the connected process
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn peer_credentials(fd: RawFd) -> Value {
+    let mut credentials = libc::ucred {
+        pid: 0,
+        uid: 0,
+        gid: 0,
+    };
+    let mut length = core::mem::size_of::<libc::ucred>() as libc::socklen_t;
+    let result = unsafe {
+        libc::getsockopt(
+            fd,
+            libc::SOL_SOCKET,
+            libc::SO_PEERCRED,
+            &mut credentials as *mut libc::ucred as *mut libc::c_void,
+            &mut length,
+        )
+    };
+    if result != 0 {
+        return json!({});
+    }
+    json!({ "pid": credentials.pid, "uid": credentials.uid, "gid": credentials.gid })
+}
+
+/// uid and gid of the connected process
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn peer_credentials(fd: RawFd) -> Value {
+    let (mut uid, mut gid) = (0, 0);
+    if unsafe { libc::getpeereid(fd, &mut uid, &mut gid) } != 0 {
+        return json!({});
+    }
+    json!({ "uid": uid, "gid": gid })
+}
+
+fn handle_incoming(
+    events: &Events,
+    pool_id: &str,
+    peer: Value,
+    payload: &[u8],
+) -> Option<ReferencingEvent> {
+    let ref_event = events.iter().find(|ref_event| {
+        matches!(&ref_event.event_type, EventType::UnixListen(e) if e.matches(pool_id, payload))
+    })?;
+
+    if let Some(mut event) = events.get_next_event(ref_event) {
+        event.try_merge_bytes(payload);
+        let mut metadata = ref_event.metadata.clone();
+        let mut info = json!({ "pool_id": pool_id });
+        if let (Some(info), Value::Object(peer)) = (info.as_object_mut(), peer) {
+            info.extend(peer);
+        }
+        metadata.merge(json!({ ref_event.name.as_str(): info }).into());
+        event.metadata.merge(metadata);
+        Some(event)
+    } else {
+        debug!(
+            "Received unix socket event {} without further handler",
+            ref_event.name
+        );
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::time::Duration;
+    use std::{
+        env::temp_dir,
+        io::Write,
+        sync::mpsc::channel,
+        thread::{sleep, spawn},
+    };
+
+    use crate::events::{
+        data::Data, mqtt_subscribe::MqttBodyMatch, time::TimeEvent, unix_listen::UnixListenEvent,
+        unix_send::UnixSendEvent, NextEvent,
+    };
+
+    use super::*;
+
+    fn events() -> Events {
+        Events::new(
+            [
+                ReferencingEvent {
+                    event_type: EventType::UnixListen(UnixListenEvent {
+                        pool_id: Default::default(),
+                        body: Some(MqttBodyMatch::BodyContains("backup".to_string())),
+                    }),
+                    next_event: NextEvent::Name("test1".to_string()).into(),
+                    name: "listen1".to_string(),
+                    ..Default::default()
+                },
+                ReferencingEvent {
+                    event_type: EventType::Time(TimeEvent {
+                        execute_time: "now".parse().unwrap(),
+                        event_id: None,
+                    }),
+                    name: "test1".to_string(),
+                    ..Default::default()
+                },
+            ]
+            .into_iter()
+            .collect(),
+        )
+    }
+
+    #[test]
+    fn test_stream() {
+        let (queue_tx, queue_rx) = channel();
+        let path = temp_dir().join(format!("hvents_test_stream_{}.sock", std::process::id()));
+        let config = UnixConfiguration::new(path.clone());
+        spawn(move || unix_executor("scripts", &config, &events(), queue_tx).unwrap());
+
+        // wait for the server to start
+        let mut stream = (0..20)
+            .find_map(|_| {
+                UnixStream::connect(&path)
+                    .map_err(|_| sleep(Duration::from_millis(50)))
+                    .ok()
+            })
+            .unwrap();
+        stream.write_all(b"hello\n\nbackup done\n").unwrap();
+
+        let event = queue_rx.recv_timeout(Duration::from_secs(1)).unwrap();
+        assert_eq!(event.name, "test1");
+        assert_eq!(event.data, Data::String("backup done".to_string()));
+        let metadata: Value = serde_json::to_value(&event.metadata).unwrap();
+        assert_eq!(metadata["listen1"]["pool_id"], "scripts");
+        assert_eq!(metadata["listen1"]["pid"], std::process::id());
+    }
+
+    #[test]
+    fn test_datagram() {
+        let (queue_tx, queue_rx) = channel();
+        let path = temp_dir().join(format!("hvents_test_datagram_{}.sock", std::process::id()));
+        let config = UnixConfiguration {
+            socket: UnixSocketType::Datagram,
+            ..UnixConfiguration::new(path.clone())
+        };
+        spawn(move || unix_executor("scripts", &config, &events(), queue_tx).unwrap());
+
+        let event = UnixSendEvent {
+            path: path.display().to_string(),
+            socket: UnixSocketType::Datagram,
+            ..Default::default()
+        };
+        // wait for the server to start
+        (0..20)
+            .find_map(|_| {
+                event
+                    .send(&event.path, b"backup failed")
+                    .map_err(|_| sleep(Duration::from_millis(50)))
+                    .ok()
+            })
+            .unwrap();
+        let received = queue_rx.recv_timeout(Duration::from_secs(1)).unwrap();
+        assert_eq!(received.data, Data::String("backup failed".to_string()));
+
+        let event = UnixSendEvent {
+            response: true,
+            ..event
+        };
+        assert!(event.send(&event.path, b"backup").is_err());
+    }
+}
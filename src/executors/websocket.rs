@@ -0,0 +1,341 @@
+use core::time::Duration;
+use std::{
+    io::ErrorKind,
+    net::{SocketAddr, TcpListener, TcpStream},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc::{channel, Receiver, Sender, TryRecvError},
+    },
+    thread::{scope, Builder},
+};
+
+use anyhow::Context;
+use log::{debug, error, warn};
+use serde_json::json;
+use tungstenite::{
+    accept_hdr,
+    handshake::server::{ErrorResponse, Request, Response},
+    http::StatusCode,
+    Message, WebSocket,
+};
+
+use crate::{
+    config::HttpConfiguration,
+    events::{
+        ws_listen::{WebSocketClient, WebSocketClients},
+        EventType, Events, ReferencingEvent,
+    },
+    executors::http::allowed_origin,
+    rate_limit::RateLimiter,
+};
+
+/// time to wait for client messages before sending queued messages
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// accepts websocket connections for the http pool, sharing its cors origins, rate limit and max_requests
+pub fn websocket_executor(
+    pool_id: &str,
+    listen: &str,
+    config: &HttpConfiguration,
+    clients: WebSocketClients,
+    events: &Events,
+    queue_tx: Sender<ReferencingEvent>,
+) -> anyhow::Result<()> {
+    let listener = TcpListener::bind(listen)
+        .with_context(|| format!("Websocket server failed to listen to {listen}"))?;
+    let rate_limiter = config.rate_limit.as_ref().map(RateLimiter::new);
+
+    let active = AtomicUsize::new(0);
+    scope(|s| {
+        for stream in listener.incoming() {
+            let stream = match stream {
+                Ok(s) => s,
+                Err(e) => {
+                    warn!("Websocket connection failed {e}");
+                    continue;
+                }
+            };
+            let remote_address = stream.peer_addr().ok();
+            if let Some(limiter) = &rate_limiter {
+                let ip = remote_address.map(|a| a.ip());
+                if !limiter.allow(ip) {
+                    debug!("Websocket rate limit reached for {ip:?}");
+                    continue;
+                }
+            }
+            if active.fetch_add(1, Ordering::SeqCst) >= config.max_requests {
+                active.fetch_sub(1, Ordering::SeqCst);
+                debug!("Websocket connection limit {} reached", config.max_requests);
+                continue;
+            }
+            let (active, clients, queue_tx) = (&active, &clients, &queue_tx);
+            let result = Builder::new()
+                .name(format!("websocket {listen}"))
+                .spawn_scoped(s, move || {
+                    handle_connection(
+                        pool_id,
+                        stream,
+                        remote_address,
+                        config,
+                        clients,
+                        events,
+                        queue_tx,
+                    );
+                    active.fetch_sub(1, Ordering::SeqCst);
+                });
+            if let Err(e) = result {
+                active.fetch_sub(1, Ordering::SeqCst);
+                error!("Unable to handle websocket connection {e}");
+            }
+        }
+    });
+    Ok(())
+}
+
+/// reads client messages and sends queued messages from a single thread until the client disconnects
+fn handle_connection(
+    pool_id: &str,
+    stream: TcpStream,
+    remote_address: Option<SocketAddr>,
+    config: &HttpConfiguration,
+    clients: &WebSocketClients,
+    events: &Events,
+    queue_tx: &Sender<ReferencingEvent>,
+) {
+    // the handshake must be received within the read timeout
+    let timeout = Duration::from_secs(config.read_timeout.max(1));
+    if let Err(e) = stream.set_read_timeout(Some(timeout)) {
+        warn!("Websocket connection failed {e}");
+        return;
+    }
+    let mut path = String::new();
+    #[allow(clippy::result_large_err)]
+    let callback = |request: &Request, response: Response| {
+        path = request.uri().path().to_string();
+        // websocket connections are not preflighted, the origin is checked on upgrade
+        let origin = request
+            .headers()
+            .get("Origin")
+            .and_then(|o| o.to_str().ok());
+        match &config.cors {
+            Some(cors) if allowed_origin(cors, origin).is_none() => {
+                let mut response = ErrorResponse::new(None);
+                *response.status_mut() = StatusCode::FORBIDDEN;
+                Err(response)
+            }
+            _ => Ok(response),
+        }
+    };
+    let mut socket = match accept_hdr(stream, callback) {
+        Ok(s) => s,
+        Err(e) => {
+            debug!("Websocket handshake failed {e}");
+            return;
+        }
+    };
+    if let Err(e) = socket.get_ref().set_read_timeout(Some(POLL_INTERVAL)) {
+        warn!("Websocket connection failed {e}");
+        return;
+    }
+
+    let (sender, receiver) = channel::<Vec<u8>>();
+    clients
+        .lock()
+        .expect("websocket clients lock")
+        .push(WebSocketClient {
+            path: path.clone(),
+            sender,
+        });
+    debug!("Websocket client connected path={path}");
+
+    loop {
+        let payload = match socket.read() {
+            Ok(Message::Text(t)) => Some(t.as_bytes().to_vec()),
+            Ok(Message::Binary(b)) => Some(b.to_vec()),
+            // pings and close frames are answered by tungstenite
+            Ok(_) => None,
+            Err(tungstenite::Error::Io(e))
+                if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) =>
+            {
+                None
+            }
+            Err(e) => {
+                debug!("Websocket connection closed {e}");
+                break;
+            }
+        };
+        if let Some(e) =
+            payload.and_then(|p| handle_incoming(events, pool_id, &path, remote_address, &p))
+        {
+            if let Err(e) = queue_tx.send(e) {
+                error!("Unable to queue event={} {e}", e.0.name);
+            }
+        }
+        if let Err(e) = send_queued(&mut socket, &receiver) {
+            debug!("Websocket send failed {e}");
+            break;
+        }
+    }
+    // the receiver is dropped so that the client is removed on the next ws_send
+    debug!("Websocket client disconnected path={path}");
+}
+
+fn send_queued(
+    socket: &mut WebSocket<TcpStream>,
+    receiver: &Receiver<Vec<u8>>,
+) -> Result<(), tungstenite::Error> {
+    loop {
+        let payload = match receiver.try_recv() {
+            Ok(p) => p,
+            Err(TryRecvError::Empty | TryRecvError::Disconnected) => return Ok(()),
+        };
+        let message = match String::from_utf8(payload) {
+            Ok(t) => Message::text(t),
+            Err(e) => Message::binary(e.into_bytes()),
+        };
+        socket.send(message)?;
+    }
+}
+
+fn handle_incoming(
+    events: &Events,
+    pool_id: &str,
+    path: &str,
+    remote_address: Option<SocketAddr>,
+    payload: &[u8],
+) -> Option<ReferencingEvent> {
+    let ref_event = events.iter().find(|ref_event| {
+        matches!(&ref_event.event_type, EventType::WsListen(e) if e.matches(pool_id, path))
+    })?;
+
+    if let Some(mut event) = events.get_next_event(ref_event) {
+        event.try_merge_bytes(payload);
+        let mut metadata = ref_event.metadata.clone();
+        metadata.merge(
+            json!({ ref_event.name.as_str(): {"path": path, "remote_address": remote_address }})
+                .into(),
+        );
+        event.metadata.merge(metadata);
+        Some(event)
+    } else {
+        debug!(
+            "Received websocket event {} without further handler",
+            ref_event.name
+        );
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::mpsc::channel, thread::sleep, thread::spawn, time::Instant};
+
+    use serde_json::{json, Value};
+
+    use crate::{
+        config::CorsConfiguration,
+        events::{data::Data, time::TimeEvent, ws_listen::WsListenEvent, NextEvent},
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_websocket() {
+        let (queue_tx, queue_rx) = channel();
+        let clients = WebSocketClients::default();
+        let executor_clients = clients.clone();
+
+        spawn(move || {
+            let events = Events::new(
+                [
+                    ReferencingEvent {
+                        event_type: EventType::WsListen(WsListenEvent {
+                            path: "/dashboard".to_string(),
+                            pool_id: Default::default(),
+                        }),
+                        next_event: NextEvent::Name("test1".to_string()).into(),
+                        name: "listen1".to_string(),
+                        ..Default::default()
+                    },
+                    create_time_event("test1", json!({})),
+                ]
+                .into(),
+            );
+            let config = HttpConfiguration {
+                cors: Some(CorsConfiguration {
+                    origins: vec!["http://dashboard".to_string()],
+                    methods: Default::default(),
+                    headers: Default::default(),
+                    max_age: None,
+                }),
+                ..HttpConfiguration::new("127.0.0.1:13357".to_string())
+            };
+            websocket_executor(
+                "default",
+                "127.0.0.1:13358",
+                &config,
+                executor_clients,
+                &events,
+                queue_tx,
+            )
+            .unwrap();
+        });
+
+        wait_for_server("127.0.0.1:13358");
+        let request = |origin: &str| {
+            let mut request = tungstenite::client::IntoClientRequest::into_client_request(
+                "ws://127.0.0.1:13358/dashboard",
+            )
+            .unwrap();
+            request
+                .headers_mut()
+                .insert("Origin", origin.parse().unwrap());
+            request
+        };
+        assert!(tungstenite::connect(request("http://other")).is_err());
+
+        let (mut socket, _) = tungstenite::connect(request("http://dashboard")).unwrap();
+        socket
+            .send(tungstenite::Message::text(r#"{"light":"on"}"#))
+            .unwrap();
+
+        let event = queue_rx.recv_timeout(Duration::from_secs(1)).unwrap();
+        assert_eq!(event.name, "test1");
+        assert_eq!(event.data, json!({ "light": "on" }));
+        let metadata = serde_json::to_value(&event.metadata).unwrap();
+        assert_eq!(metadata["listen1"]["path"], "/dashboard");
+
+        // messages are sent while the client is idle
+        let sender = clients.lock().unwrap()[0].sender.clone();
+        sender.send(b"hello".to_vec()).unwrap();
+        assert_eq!(socket.read().unwrap(), tungstenite::Message::text("hello"));
+        socket.close(None).unwrap();
+        let closed = Instant::now();
+        while sender.send(b"closed".to_vec()).is_ok() {
+            assert!(closed.elapsed() < Duration::from_secs(1));
+            sleep(POLL_INTERVAL);
+        }
+    }
+
+    fn wait_for_server(address: &str) {
+        (0..20)
+            .find_map(|_| {
+                TcpStream::connect(address)
+                    .map_err(|_| sleep(Duration::from_millis(50)))
+                    .ok()
+            })
+            .unwrap();
+    }
+
+    fn create_time_event(name: &str, data: Value) -> ReferencingEvent {
+        ReferencingEvent {
+            event_type: EventType::Time(TimeEvent {
+                execute_time: "now".parse().unwrap(),
+                event_id: None,
+            }),
+            data: Data::Json(data),
+            name: name.to_string(),
+            ..Default::default()
+        }
+    }
+}
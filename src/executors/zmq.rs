@@ -0,0 +1,160 @@
+use core::time::Duration;
+use std::{sync::mpsc::Sender, thread::sleep};
+
+use log::{debug, info, warn};
+use serde_json::json;
+use zmq::Context;
+
+use crate::{
+    config::{ZmqConfiguration, ZmqSocketType},
+    events::{EventType, Events, ReferencingEvent},
+    pools::zmq::open_socket,
+};
+
+/// seconds to wait before opening the socket again
+const RECONNECT_DELAY: u64 = 5;
+
+/// receives messages for zmq_subscribe events on sub and pull sockets,
+/// events without a pool_id are received on the first pool
+pub fn zmq_executor(
+    pool_id: &str,
+    context: &Context,
+    config: &ZmqConfiguration,
+    first_pool: bool,
+    events: &Events,
+    queue_tx: Sender<ReferencingEvent>,
+) -> anyhow::Result<()> {
+    if !matches!(config.socket, ZmqSocketType::Sub | ZmqSocketType::Pull) {
+        return Ok(());
+    }
+    let topics: Vec<&str> = events
+        .iter()
+        .filter_map(|e| match &e.event_type {
+            EventType::ZmqSubscribe(e)
+                if e.pool_id == pool_id || (e.pool_id.is_empty() && first_pool) =>
+            {
+                Some(e.topic.as_str())
+            }
+            _ => None,
+        })
+        .collect();
+    if topics.is_empty() {
+        return Ok(());
+    }
+    loop {
+        match receive(
+            pool_id, context, config, first_pool, &topics, events, &queue_tx,
+        ) {
+            Ok(()) => return Ok(()),
+            Err(e) => warn!("Zmq socket {} failed {e}", config.endpoint),
+        }
+        sleep(Duration::from_secs(RECONNECT_DELAY));
+    }
+}
+
+/// returns once the queue is closed
+fn receive(
+    pool_id: &str,
+    context: &Context,
+    config: &ZmqConfiguration,
+    first_pool: bool,
+    topics: &[&str],
+    events: &Events,
+    queue_tx: &Sender<ReferencingEvent>,
+) -> anyhow::Result<()> {
+    let socket = open_socket(context, config)?;
+    if config.socket == ZmqSocketType::Sub {
+        for topic in topics {
+            socket.set_subscribe(topic.as_bytes())?;
+        }
+    }
+    info!(
+        "Receiving zmq messages on {} for {pool_id}",
+        config.endpoint
+    );
+    loop {
+        let frames = socket.recv_multipart(0)?;
+        debug!("Zmq message received on {}", config.endpoint);
+        if let Some(e) = handle_incoming(events, pool_id, first_pool, &frames) {
+            if queue_tx.send(e).is_err() {
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// the first frame is used as the topic of multipart messages, the last frame as the body
+fn handle_incoming(
+    events: &Events,
+    pool_id: &str,
+    first_pool: bool,
+    frames: &[Vec<u8>],
+) -> Option<ReferencingEvent> {
+    let (first, body) = (frames.first()?, frames.last()?);
+    let ref_event = events.iter().find(|ref_event| {
+        matches!(&ref_event.event_type, EventType::ZmqSubscribe(e)
+            if (e.pool_id == pool_id || (e.pool_id.is_empty() && first_pool)) && e.matches(first, body))
+    })?;
+
+    if let Some(mut event) = events.get_next_event(ref_event) {
+        event.try_merge_bytes(body);
+        let topic = (frames.len() > 1).then(|| String::from_utf8_lossy(first));
+        let mut metadata = ref_event.metadata.clone();
+        metadata
+            .merge(json!({ ref_event.name.as_str(): {"pool_id": pool_id, "topic": topic }}).into());
+        event.metadata.merge(metadata);
+        Some(event)
+    } else {
+        debug!(
+            "Received zmq message {} without further handler",
+            ref_event.name
+        );
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::events::{data::Data, time::TimeEvent, zmq_subscribe::ZmqSubscribeEvent, NextEvent};
+
+    use super::*;
+
+    #[test]
+    fn test_handle_incoming() {
+        let events = Events::new(
+            [
+                ReferencingEvent {
+                    event_type: EventType::ZmqSubscribe(ZmqSubscribeEvent {
+                        topic: "lab.".to_string(),
+                        ..Default::default()
+                    }),
+                    next_event: NextEvent::Name("test1".to_string()).into(),
+                    name: "lab_reading".to_string(),
+                    ..Default::default()
+                },
+                ReferencingEvent {
+                    event_type: EventType::Time(TimeEvent {
+                        execute_time: "now".parse().unwrap(),
+                        event_id: None,
+                    }),
+                    name: "test1".to_string(),
+                    ..Default::default()
+                },
+            ]
+            .into_iter()
+            .collect(),
+        );
+        let frames = [b"lab.scale".to_vec(), br#"{"grams": 12}"#.to_vec()];
+        assert!(handle_incoming(&events, "default", false, &frames).is_none());
+        assert!(handle_incoming(&events, "default", true, &[b"office".to_vec()]).is_none());
+
+        let event = handle_incoming(&events, "default", true, &frames).unwrap();
+        assert_eq!(event.name, "test1");
+        assert_eq!(event.data, Data::Json(json!({"grams": 12})));
+        let metadata = serde_json::to_value(&event.metadata).unwrap();
+        assert_eq!(metadata["lab_reading"]["topic"], json!("lab.scale"));
+
+        let event = handle_incoming(&events, "default", true, &[b"lab.on".to_vec()]).unwrap();
+        assert_eq!(event.data, Data::String("lab.on".to_string()));
+    }
+}
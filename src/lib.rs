@@ -1,6 +1,12 @@
 pub mod config;
 pub mod database;
+#[cfg(unix)]
+mod docker;
 pub mod events;
 pub mod executors;
+mod multipart;
 pub mod pools;
-mod renderer;
+mod rate_limit;
+pub mod renderer;
+#[cfg(feature = "helpers")]
+mod template_helpers;
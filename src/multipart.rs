@@ -0,0 +1,92 @@
+/// part of a multipart/form-data body
+pub struct Part<'a> {
+    pub name: String,
+    pub filename: Option<String>,
+    pub content: &'a [u8],
+}
+
+/// boundary from a content type e.g. multipart/form-data; boundary=abc
+pub fn boundary(content_type: &str) -> Option<&str> {
+    let (mime, parameters) = content_type.split_once(';')?;
+    if !mime.trim().eq_ignore_ascii_case("multipart/form-data") {
+        return None;
+    }
+    parameters.split(';').find_map(|p| {
+        let (key, value) = p.split_once('=')?;
+        key.trim()
+            .eq_ignore_ascii_case("boundary")
+            .then(|| value.trim().trim_matches('"'))
+    })
+}
+
+pub fn parse<'a>(body: &'a [u8], boundary: &str) -> Vec<Part<'a>> {
+    let delimiter = format!("--{boundary}");
+    let mut parts = Vec::new();
+    let Some(start) = find(body, delimiter.as_bytes()) else {
+        return parts;
+    };
+    let mut rest = &body[start + delimiter.len()..];
+    // the last delimiter ends with --
+    while !rest.starts_with(b"--") {
+        let Some(end) = find(rest, delimiter.as_bytes()) else {
+            break;
+        };
+        let section = rest[..end].strip_prefix(b"\r\n").unwrap_or(&rest[..end]);
+        let section = section.strip_suffix(b"\r\n").unwrap_or(section);
+        if let Some(part) = parse_part(section) {
+            parts.push(part);
+        }
+        rest = &rest[end + delimiter.len()..];
+    }
+    parts
+}
+
+fn parse_part(section: &[u8]) -> Option<Part<'_>> {
+    let header_end = find(section, b"\r\n\r\n")?;
+    let headers = String::from_utf8_lossy(&section[..header_end]);
+    let disposition = headers.lines().find_map(|l| {
+        let (key, value) = l.split_once(':')?;
+        key.trim()
+            .eq_ignore_ascii_case("content-disposition")
+            .then_some(value)
+    })?;
+    let parameter = |name: &str| {
+        disposition.split(';').find_map(|p| {
+            let (key, value) = p.split_once('=')?;
+            (key.trim() == name).then(|| value.trim().trim_matches('"').to_string())
+        })
+    };
+    Some(Part {
+        name: parameter("name")?,
+        filename: parameter("filename"),
+        content: &section[header_end + 4..],
+    })
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            boundary("multipart/form-data; boundary=\"xyz\""),
+            Some("xyz")
+        );
+        assert_eq!(boundary("application/json"), None);
+
+        let body = b"preamble\r\n--xyz\r\nContent-Disposition: form-data; name=\"camera\"\r\n\r\nhall\r\n--xyz\r\nContent-Disposition: form-data; name=\"snapshot\"; filename=\"image.jpg\"\r\nContent-Type: image/jpeg\r\n\r\n\x01\x02\r\n\x03\r\n--xyz--\r\n";
+        let parts = parse(body, "xyz");
+        assert_eq!(parts.len(), 2);
+        assert_eq!(parts[0].name, "camera");
+        assert_eq!(parts[0].filename, None);
+        assert_eq!(parts[0].content, b"hall");
+        assert_eq!(parts[1].name, "snapshot");
+        assert_eq!(parts[1].filename.as_deref(), Some("image.jpg"));
+        assert_eq!(parts[1].content, b"\x01\x02\r\n\x03");
+    }
+}
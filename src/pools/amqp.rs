@@ -0,0 +1,145 @@
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc, Mutex,
+};
+
+use anyhow::{anyhow, Context, Result};
+use futures_lite::future::block_on;
+use indexmap::IndexMap;
+use lapin::{
+    acker::Acker,
+    options::{BasicAckOptions, BasicPublishOptions, BasicRejectOptions},
+    BasicProperties, Channel, Connection, ConnectionProperties,
+};
+
+use crate::config::PoolId;
+
+/// delivery mode of messages surviving a broker restart
+const PERSISTENT: u8 = 2;
+
+#[derive(Clone)]
+pub struct AmqpClient {
+    url: String,
+    /// connection and channel reused for publishing
+    channel: Arc<Mutex<Option<(Connection, Channel)>>>,
+    /// deliveries waiting for amqp_ack events
+    pending: Arc<Mutex<IndexMap<u64, Acker>>>,
+    delivery_id: Arc<AtomicU64>,
+}
+
+impl AmqpClient {
+    pub fn new(url: &str) -> Self {
+        Self {
+            url: url.to_string(),
+            channel: Default::default(),
+            pending: Default::default(),
+            delivery_id: Default::default(),
+        }
+    }
+
+    pub fn connect(&self) -> Result<Connection> {
+        block_on(Connection::connect(
+            &self.url,
+            ConnectionProperties::default(),
+        ))
+        .context("Can not connect to amqp broker")
+    }
+
+    pub fn publish(
+        &self,
+        exchange: &str,
+        routing_key: &str,
+        payload: &[u8],
+        persistent: bool,
+    ) -> Result<()> {
+        let mut channel = self.channel.lock().expect("amqp channel lock");
+        let publish_channel = match channel.as_ref() {
+            Some((_, c)) if c.status().connected() => c.clone(),
+            _ => {
+                let connection = self.connect()?;
+                let c = block_on(connection.create_channel())?;
+                channel.replace((connection, c.clone()));
+                c
+            }
+        };
+        let properties = if persistent {
+            BasicProperties::default().with_delivery_mode(PERSISTENT)
+        } else {
+            BasicProperties::default()
+        };
+        block_on(async {
+            publish_channel
+                .basic_publish(
+                    exchange,
+                    routing_key,
+                    BasicPublishOptions::default(),
+                    payload,
+                    properties,
+                )
+                .await?
+                .await
+        })?;
+        Ok(())
+    }
+
+    /// returns the id used by amqp_ack events to acknowledge the delivery
+    pub fn track(&self, acker: Acker) -> u64 {
+        let id = self.delivery_id.fetch_add(1, Ordering::Relaxed) + 1;
+        self.pending
+            .lock()
+            .expect("amqp pending lock")
+            .insert(id, acker);
+        id
+    }
+
+    /// rejected deliveries are requeued or dead lettered depending on the queue
+    pub fn acknowledge(&self, delivery_id: u64, reject: bool, requeue: bool) -> Result<()> {
+        let acker = self
+            .pending
+            .lock()
+            .expect("amqp pending lock")
+            .shift_remove(&delivery_id)
+            .ok_or_else(|| anyhow!("Unknown amqp delivery {delivery_id}"))?;
+        if reject {
+            block_on(acker.reject(BasicRejectOptions { requeue }))?;
+        } else {
+            block_on(acker.ack(BasicAckOptions::default()))?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+pub struct AmqpPool {
+    map: IndexMap<PoolId, AmqpClient>,
+}
+
+impl AmqpPool {
+    pub fn configure(&mut self, pool_id: PoolId, url: &str) -> AmqpClient {
+        let client = AmqpClient::new(url);
+        self.map.insert(pool_id, client.clone());
+        client
+    }
+
+    pub fn get(&self, pool_id: &str) -> Option<&AmqpClient> {
+        // return the first configuration when the pool id is empty
+        if pool_id.is_empty() {
+            return self.map.values().next();
+        }
+        self.map.get(pool_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_acknowledge_unknown_delivery() {
+        let mut pool = AmqpPool::default();
+        pool.configure("default".to_string(), "amqp://127.0.0.1:5672/%2f");
+        let client = pool.get("").unwrap();
+        assert!(client.acknowledge(1, false, false).is_err());
+        assert!(pool.get("other").is_none());
+    }
+}
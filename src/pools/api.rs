@@ -1,26 +1,42 @@
+use core::time::Duration;
+use std::{collections::HashMap, fs::read, path::Path, sync::Mutex, time::Instant};
+
 use indexmap::IndexMap;
-use reqwest::blocking::Client;
+use reqwest::{
+    blocking::{Client, ClientBuilder},
+    header::{HeaderMap, HeaderValue, AUTHORIZATION, CONTENT_TYPE},
+    redirect::Policy,
+    Certificate, Identity, NoProxy, Proxy,
+};
+use serde::Deserialize;
 
-use crate::config::{ClientConfiguration, PoolId};
-use anyhow::anyhow;
+use crate::config::{
+    CircuitBreakerConfiguration, ClientConfiguration, ClientTlsConfiguration, OAuth2Configuration,
+    PoolId,
+};
+use crate::rate_limit::RateLimiter;
 use anyhow::Result;
+use anyhow::{anyhow, bail, Context};
+use log::warn;
+
+/// tokens are refreshed before they expire
+const EXPIRY_MARGIN: Duration = Duration::from_secs(30);
+
+pub const CIRCUIT_OPEN_EVENT: &str = "__api_circuit_open";
 
 #[derive(Default)]
 pub struct ClientPool {
-    clients: IndexMap<PoolId, Client>,
+    clients: IndexMap<PoolId, ApiClient>,
 }
 
 impl ClientPool {
     pub fn configure(&mut self, pool_id: PoolId, config: &ClientConfiguration) -> Result<()> {
-        let headers = (&config.default_headers)
-            .try_into()
-            .map_err(|e| anyhow!("Failed to set default headers {e}"))?;
-        let client = Client::builder().default_headers(headers).build()?;
+        let client = ApiClient::new(pool_id.clone(), config)?;
         self.clients.insert(pool_id, client);
         Ok(())
     }
 
-    pub fn get(&self, pool_id: &str) -> Option<&Client> {
+    pub fn get(&self, pool_id: &str) -> Option<&ApiClient> {
         // return the first configuration when the pool id is empty
         if pool_id.is_empty() {
             return self.clients.values().next();
@@ -28,3 +44,634 @@ impl ClientPool {
         self.clients.get(pool_id)
     }
 }
+
+fn build_client(config: &ClientConfiguration, redirect: Option<usize>) -> Result<Client> {
+    let mut headers: HeaderMap = (&config.default_headers)
+        .try_into()
+        .map_err(|e| anyhow!("Failed to set default headers {e}"))?;
+    if let Some(auth) = &config.auth {
+        let mut value = HeaderValue::try_from(auth.header_value())
+            .map_err(|e| anyhow!("Invalid auth provided {e}"))?;
+        value.set_sensitive(true);
+        headers.insert(AUTHORIZATION, value);
+    }
+    let mut builder = client_builder(config)?.default_headers(headers);
+    match redirect {
+        // redirect responses are returned as is
+        Some(0) => builder = builder.redirect(Policy::none()),
+        Some(max) => builder = builder.redirect(Policy::limited(max)),
+        None => (),
+    }
+    Ok(builder.build()?)
+}
+
+/// timeouts, tls and proxy settings without the default headers and auth
+fn client_builder(config: &ClientConfiguration) -> Result<ClientBuilder> {
+    let mut builder = Client::builder();
+    if let Some(timeout) = config.connect_timeout {
+        builder = builder.connect_timeout(Duration::from_secs(timeout));
+    }
+    if let Some(timeout) = config.timeout {
+        builder = builder.timeout(Duration::from_secs(timeout));
+    }
+    if let Some(tls) = &config.tls {
+        builder = tls_configuration(builder, tls)?;
+    }
+    if let Some(proxy) = &config.proxy {
+        let no_proxy = NoProxy::from_string(&proxy.no_proxy.join(","));
+        builder = builder.proxy(
+            Proxy::all(&proxy.url)
+                .map_err(|e| anyhow!("Invalid proxy {} {e}", proxy.url))?
+                .no_proxy(no_proxy),
+        );
+    }
+    Ok(builder)
+}
+
+fn tls_configuration(
+    mut builder: ClientBuilder,
+    config: &ClientTlsConfiguration,
+) -> Result<ClientBuilder> {
+    if let Some(ca) = &config.ca {
+        for cert in Certificate::from_pem_bundle(&read_file(ca)?)
+            .with_context(|| format!("Invalid certificate {}", ca.to_string_lossy()))?
+        {
+            builder = builder.add_root_certificate(cert);
+        }
+    }
+    match (&config.cert, &config.key) {
+        (Some(cert), Some(key)) => {
+            let mut pem = read_file(cert)?;
+            pem.extend(read_file(key)?);
+            builder = builder.identity(Identity::from_pem(&pem).with_context(|| {
+                format!("Invalid client certificate {}", cert.to_string_lossy())
+            })?);
+        }
+        (None, None) => (),
+        _ => bail!("Both tls cert and key must be provided for client authentication"),
+    }
+    if config.insecure {
+        warn!("Api server certificate verification is disabled");
+        builder = builder.danger_accept_invalid_certs(true);
+    }
+    Ok(builder)
+}
+
+fn read_file(path: &Path) -> Result<Vec<u8>> {
+    read(path).with_context(|| format!("Unable to read {}", path.to_string_lossy()))
+}
+
+pub struct ApiClient {
+    pub pool_id: PoolId,
+    pub client: Client,
+    oauth2: Option<OAuth2>,
+    circuit: Option<CircuitBreaker>,
+    rate_limiter: Option<RateLimiter<()>>,
+    config: ClientConfiguration,
+    /// clients built for events overriding the redirect limit
+    redirect_clients: Mutex<HashMap<usize, Client>>,
+}
+
+impl ApiClient {
+    pub fn new(pool_id: PoolId, config: &ClientConfiguration) -> Result<Self> {
+        Ok(Self {
+            pool_id,
+            client: build_client(config, config.redirect)?,
+            oauth2: config
+                .oauth2
+                .as_ref()
+                .map(|o| OAuth2::new(o, config))
+                .transpose()?,
+            circuit: config.circuit_breaker.map(CircuitBreaker::new),
+            rate_limiter: config.rate_limit.as_ref().map(RateLimiter::new),
+            config: config.clone(),
+            redirect_clients: Default::default(),
+        })
+    }
+
+    /// client following up to the redirect limit, the pool client is used if not specified
+    pub fn redirect_client(&self, redirect: Option<usize>) -> Result<Client> {
+        let Some(max) = redirect.filter(|r| Some(*r) != self.config.redirect) else {
+            return Ok(self.client.clone());
+        };
+        let mut clients = self.redirect_clients.lock().expect("redirect lock");
+        if let Some(client) = clients.get(&max) {
+            return Ok(client.clone());
+        }
+        let client = build_client(&self.config, Some(max))?;
+        clients.insert(max, client.clone());
+        Ok(client)
+    }
+
+    /// wait until the rate limit allows another call
+    pub fn wait(&self) {
+        if let Some(limiter) = &self.rate_limiter {
+            limiter.wait(());
+        }
+    }
+
+    /// false while the circuit is open
+    pub fn allow(&self) -> bool {
+        self.circuit
+            .as_ref()
+            .is_none_or(|c| c.allow(Instant::now()))
+    }
+
+    /// record the call result, returns true if the circuit opened
+    pub fn record(&self, success: bool) -> bool {
+        self.circuit
+            .as_ref()
+            .is_some_and(|c| c.record(success, Instant::now()))
+    }
+
+    /// authorization header value obtained with oauth2 if configured
+    pub fn authorization(&self) -> Result<Option<String>> {
+        let Some(oauth2) = &self.oauth2 else {
+            return Ok(None);
+        };
+        let token = oauth2.access_token()?;
+        Ok(Some(format!("Bearer {token}")))
+    }
+}
+
+struct CircuitBreaker {
+    failures: u32,
+    cooldown: Duration,
+    state: Mutex<CircuitState>,
+}
+
+#[derive(Default)]
+struct CircuitState {
+    failures: u32,
+    open_until: Option<Instant>,
+    /// a probe call is in flight after the cooldown
+    half_open: bool,
+}
+
+impl CircuitBreaker {
+    fn new(config: CircuitBreakerConfiguration) -> Self {
+        Self {
+            failures: config.failures.max(1),
+            cooldown: Duration::from_secs(config.cooldown),
+            state: Default::default(),
+        }
+    }
+
+    fn allow(&self, now: Instant) -> bool {
+        let mut state = self.state.lock().expect("circuit lock");
+        match state.open_until {
+            None => true,
+            // a single probe is allowed after the cooldown, other calls are rejected until it is recorded
+            // or another cooldown passes without a result
+            Some(until) if now >= until => {
+                state.half_open = true;
+                state.open_until = Some(now + self.cooldown);
+                true
+            }
+            Some(_) => false,
+        }
+    }
+
+    fn record(&self, success: bool, now: Instant) -> bool {
+        let mut state = self.state.lock().expect("circuit lock");
+        if success {
+            *state = CircuitState::default();
+            return false;
+        }
+        state.failures += 1;
+        // a failed probe opens the circuit again
+        if state.failures >= self.failures {
+            let opened = state.half_open || state.open_until.is_none();
+            state.half_open = false;
+            state.open_until = Some(now + self.cooldown);
+            return opened;
+        }
+        false
+    }
+}
+
+struct OAuth2 {
+    /// the token endpoint does not receive the pool headers and credentials
+    client: Client,
+    token_url: String,
+    client_id: String,
+    client_secret: String,
+    scope: Option<String>,
+    state: Mutex<OAuth2State>,
+}
+
+struct OAuth2State {
+    access_token: Option<(String, Option<Instant>)>,
+    refresh_token: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    /// seconds
+    expires_in: Option<u64>,
+    refresh_token: Option<String>,
+}
+
+impl OAuth2 {
+    fn new(config: &OAuth2Configuration, client_config: &ClientConfiguration) -> Result<Self> {
+        Ok(Self {
+            client: client_builder(client_config)?.build()?,
+            token_url: config.token_url.clone(),
+            client_id: config.client_id.clone(),
+            client_secret: config.client_secret.read()?,
+            scope: config.scope.clone(),
+            state: Mutex::new(OAuth2State {
+                access_token: None,
+                refresh_token: config.refresh_token.clone(),
+            }),
+        })
+    }
+
+    fn access_token(&self) -> Result<String> {
+        // the lock is held while requesting so that concurrent calls share a single token
+        let mut state = self.state.lock().expect("oauth2 lock");
+        if let Some((token, expires)) = &state.access_token {
+            if expires.is_none_or(|e| Instant::now() + EXPIRY_MARGIN < e) {
+                return Ok(token.clone());
+            }
+        }
+
+        let token = match state.refresh_token.clone() {
+            Some(refresh_token) => match self.request_token(Some(&refresh_token)) {
+                Ok(token) => token,
+                // an expired or revoked refresh token is dropped in favour of client credentials
+                Err(e) => {
+                    warn!("Oauth2 refresh token rejected, requesting a new token {e}");
+                    state.refresh_token = None;
+                    self.request_token(None)?
+                }
+            },
+            None => self.request_token(None)?,
+        };
+
+        let expires = token
+            .expires_in
+            .map(|s| Instant::now() + Duration::from_secs(s));
+        if token.refresh_token.is_some() {
+            state.refresh_token = token.refresh_token;
+        }
+        state.access_token = Some((token.access_token.clone(), expires));
+        Ok(token.access_token)
+    }
+
+    fn request_token(&self, refresh_token: Option<&str>) -> Result<TokenResponse> {
+        let mut form = form_urlencoded::Serializer::new(String::new());
+        match refresh_token {
+            Some(token) => form
+                .append_pair("grant_type", "refresh_token")
+                .append_pair("refresh_token", token),
+            None => form.append_pair("grant_type", "client_credentials"),
+        };
+        form.append_pair("client_id", &self.client_id)
+            .append_pair("client_secret", &self.client_secret);
+        if let Some(scope) = &self.scope {
+            form.append_pair("scope", scope);
+        }
+        let response = self
+            .client
+            .post(&self.token_url)
+            .header(
+                CONTENT_TYPE,
+                HeaderValue::from_static("application/x-www-form-urlencoded"),
+            )
+            .body(form.finish())
+            .send()?
+            .error_for_status()
+            .map_err(|e| anyhow!("Failed to obtain oauth2 token {e}"))?;
+        Ok(serde_json::from_slice(&response.bytes()?)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::mpsc::channel, thread::spawn};
+
+    use crate::config::{ClientAuth, ClientTlsConfiguration, ProxyConfiguration, SecretValue};
+
+    use super::*;
+
+    #[test]
+    fn test_oauth2_refresh_token() {
+        let server = tiny_http::Server::http("127.0.0.1:13341").unwrap();
+        let (body_tx, body_rx) = channel();
+        spawn(move || {
+            for (i, mut request) in server.incoming_requests().enumerate() {
+                let mut body = String::new();
+                request.as_reader().read_to_string(&mut body).unwrap();
+                body_tx.send(body).unwrap();
+                let response = format!(
+                    r#"{{"access_token":"access{i}","expires_in":10,"refresh_token":"refresh{i}"}}"#
+                );
+                request
+                    .respond(tiny_http::Response::from_string(response))
+                    .unwrap();
+            }
+        });
+
+        let client = ApiClient::new(
+            Default::default(),
+            &ClientConfiguration {
+                oauth2: Some(OAuth2Configuration {
+                    token_url: "http://127.0.0.1:13341/token".to_string(),
+                    client_id: "id".to_string(),
+                    client_secret: SecretValue::Value("secret".to_string()),
+                    refresh_token: Some("initial".to_string()),
+                    scope: None,
+                }),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        assert_eq!(
+            client.authorization().unwrap(),
+            Some("Bearer access0".to_string())
+        );
+        assert_eq!(
+            body_rx.recv().unwrap(),
+            "grant_type=refresh_token&refresh_token=initial&client_id=id&client_secret=secret"
+        );
+
+        // expires within the margin, refreshed with the new refresh token
+        assert_eq!(
+            client.authorization().unwrap(),
+            Some("Bearer access1".to_string())
+        );
+        assert!(body_rx.recv().unwrap().contains("refresh_token=refresh0"));
+    }
+
+    #[test]
+    fn test_oauth2_token_headers() {
+        let server = tiny_http::Server::http("127.0.0.1:13360").unwrap();
+        let (headers_tx, headers_rx) = channel();
+        spawn(move || {
+            for request in server.incoming_requests() {
+                let headers: Vec<String> = request
+                    .headers()
+                    .iter()
+                    .map(|h| h.field.as_str().as_str().to_lowercase())
+                    .collect();
+                headers_tx.send(headers).unwrap();
+                request
+                    .respond(tiny_http::Response::from_string(
+                        r#"{"access_token":"access"}"#,
+                    ))
+                    .unwrap();
+            }
+        });
+
+        let client = ApiClient::new(
+            Default::default(),
+            &ClientConfiguration {
+                default_headers: [("X-Api-Key".to_string(), "key".to_string())].into(),
+                auth: Some(ClientAuth::Bearer {
+                    token: "pool".to_string(),
+                }),
+                oauth2: Some(OAuth2Configuration {
+                    token_url: "http://127.0.0.1:13360/token".to_string(),
+                    client_id: "id".to_string(),
+                    client_secret: SecretValue::Value("secret".to_string()),
+                    refresh_token: None,
+                    scope: None,
+                }),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        client.authorization().unwrap();
+        // pool headers and credentials are not sent to the token endpoint
+        let headers = headers_rx.recv().unwrap();
+        assert!(!headers.contains(&"x-api-key".to_string()));
+        assert!(!headers.contains(&"authorization".to_string()));
+    }
+
+    #[test]
+    fn test_oauth2_rejected_refresh_token() {
+        let server = tiny_http::Server::http("127.0.0.1:13353").unwrap();
+        let (body_tx, body_rx) = channel();
+        spawn(move || {
+            for mut request in server.incoming_requests() {
+                let mut body = String::new();
+                request.as_reader().read_to_string(&mut body).unwrap();
+                let response = if body.contains("grant_type=refresh_token") {
+                    tiny_http::Response::from_string(r#"{"error":"invalid_grant"}"#)
+                        .with_status_code(400)
+                } else {
+                    tiny_http::Response::from_string(r#"{"access_token":"access","expires_in":10}"#)
+                };
+                body_tx.send(body).unwrap();
+                request.respond(response).unwrap();
+            }
+        });
+
+        let client = ApiClient::new(
+            Default::default(),
+            &ClientConfiguration {
+                oauth2: Some(OAuth2Configuration {
+                    token_url: "http://127.0.0.1:13353/token".to_string(),
+                    client_id: "id".to_string(),
+                    client_secret: SecretValue::Value("secret".to_string()),
+                    refresh_token: Some("revoked".to_string()),
+                    scope: None,
+                }),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        assert_eq!(
+            client.authorization().unwrap(),
+            Some("Bearer access".to_string())
+        );
+        assert!(body_rx.recv().unwrap().contains("refresh_token=revoked"));
+        assert!(body_rx
+            .recv()
+            .unwrap()
+            .starts_with("grant_type=client_credentials"));
+
+        // the rejected refresh token is not tried again
+        assert_eq!(
+            client.authorization().unwrap(),
+            Some("Bearer access".to_string())
+        );
+        assert!(body_rx
+            .recv()
+            .unwrap()
+            .starts_with("grant_type=client_credentials"));
+    }
+
+    #[test]
+    fn test_proxy() {
+        let server = tiny_http::Server::http("127.0.0.1:13342").unwrap();
+        let (url_tx, url_rx) = channel();
+        spawn(move || {
+            for request in server.incoming_requests() {
+                url_tx.send(request.url().to_string()).unwrap();
+                request.respond(tiny_http::Response::empty(200)).unwrap();
+            }
+        });
+
+        let mut pool = ClientPool::default();
+        pool.configure(
+            "default".to_string(),
+            &ClientConfiguration {
+                proxy: Some(ProxyConfiguration {
+                    url: "http://127.0.0.1:13342".to_string(),
+                    no_proxy: vec!["localhost".to_string()],
+                }),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let client = &pool.get("").unwrap().client;
+        client.get("http://device.invalid/status").send().unwrap();
+        assert_eq!(url_rx.recv().unwrap(), "http://device.invalid/status");
+    }
+
+    #[test]
+    fn test_tls_configuration() {
+        let config = |tls: ClientTlsConfiguration| ClientConfiguration {
+            tls: Some(tls),
+            ..Default::default()
+        };
+        let mut pool = ClientPool::default();
+        assert!(pool
+            .configure(
+                "insecure".to_string(),
+                &config(ClientTlsConfiguration {
+                    ca: None,
+                    cert: None,
+                    key: None,
+                    insecure: true,
+                })
+            )
+            .is_ok());
+        assert!(pool
+            .configure(
+                "missing_key".to_string(),
+                &config(ClientTlsConfiguration {
+                    ca: None,
+                    cert: Some("client.pem".into()),
+                    key: None,
+                    insecure: false,
+                })
+            )
+            .is_err());
+        assert!(pool
+            .configure(
+                "missing_ca".to_string(),
+                &config(ClientTlsConfiguration {
+                    ca: Some("missing_ca.pem".into()),
+                    cert: None,
+                    key: None,
+                    insecure: false,
+                })
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn test_circuit_breaker() {
+        let circuit = CircuitBreaker::new(CircuitBreakerConfiguration {
+            failures: 2,
+            cooldown: 60,
+        });
+        let now = Instant::now();
+        assert!(!circuit.record(false, now));
+        assert!(circuit.allow(now));
+        assert!(circuit.record(false, now));
+        assert!(!circuit.allow(now));
+
+        // a probe is allowed after the cooldown, a failure opens the circuit again
+        let later = now + Duration::from_secs(60);
+        assert!(circuit.allow(later));
+        assert!(circuit.record(false, later));
+        assert!(!circuit.allow(later));
+
+        let later = later + Duration::from_secs(60);
+        assert!(!circuit.record(true, later));
+        assert!(!circuit.record(false, later));
+        assert!(circuit.allow(later));
+    }
+
+    #[test]
+    fn test_circuit_breaker_single_probe() {
+        let circuit = CircuitBreaker::new(CircuitBreakerConfiguration {
+            failures: 1,
+            cooldown: 60,
+        });
+        let now = Instant::now();
+        assert!(circuit.record(false, now));
+
+        let later = now + Duration::from_secs(60);
+        let allowed = std::thread::scope(|s| {
+            let calls: Vec<_> = (0..2).map(|_| s.spawn(|| circuit.allow(later))).collect();
+            calls
+                .into_iter()
+                .map(|c| c.join().unwrap())
+                .filter(|allowed| *allowed)
+                .count()
+        });
+        assert_eq!(allowed, 1);
+        assert!(!circuit.allow(later));
+
+        // the probe succeeded, the circuit is closed
+        assert!(!circuit.record(true, later));
+        assert!(circuit.allow(later));
+        assert!(circuit.allow(later));
+    }
+
+    #[test]
+    fn test_no_oauth2() {
+        let client = ApiClient::new(Default::default(), &Default::default()).unwrap();
+        assert_eq!(client.authorization().unwrap(), None);
+    }
+
+    #[test]
+    fn test_redirect_client() {
+        let server = tiny_http::Server::http("127.0.0.1:13346").unwrap();
+        spawn(move || {
+            for request in server.incoming_requests() {
+                let response = match request.url() {
+                    "/old" => tiny_http::Response::empty(302)
+                        .with_header(tiny_http::Header::from_bytes("Location", "/new").unwrap()),
+                    _ => tiny_http::Response::empty(200),
+                };
+                request.respond(response).unwrap();
+            }
+        });
+
+        let client = ApiClient::new(
+            Default::default(),
+            &ClientConfiguration {
+                redirect: Some(0),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let url = "http://127.0.0.1:13346/old";
+        let response = client
+            .redirect_client(None)
+            .unwrap()
+            .get(url)
+            .send()
+            .unwrap();
+        assert_eq!(response.status().as_u16(), 302);
+
+        let response = client
+            .redirect_client(Some(2))
+            .unwrap()
+            .get(url)
+            .send()
+            .unwrap();
+        assert_eq!(response.status().as_u16(), 200);
+        assert_eq!(response.url().path(), "/new");
+        assert_eq!(client.redirect_clients.lock().unwrap().len(), 1);
+    }
+}
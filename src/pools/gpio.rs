@@ -0,0 +1,44 @@
+use std::collections::{hash_map::Entry, HashMap};
+
+use anyhow::Result;
+use rppal::gpio::{Gpio, OutputPin};
+
+use crate::events::gpio_set::GpioOutput;
+
+/// output pins keep their state until changed
+#[derive(Default)]
+pub struct GpioPool {
+    pins: HashMap<u8, OutputPin>,
+}
+
+impl GpioPool {
+    pub fn set(&mut self, pin: u8, output: GpioOutput) -> Result<()> {
+        let output_pin = match self.pins.entry(pin) {
+            Entry::Occupied(e) => e.into_mut(),
+            Entry::Vacant(e) => {
+                let mut output_pin = Gpio::new()?.get(pin)?.into_output();
+                output_pin.set_reset_on_drop(false);
+                e.insert(output_pin)
+            }
+        };
+        match output {
+            GpioOutput::High => {
+                output_pin.clear_pwm()?;
+                output_pin.set_high();
+            }
+            GpioOutput::Low => {
+                output_pin.clear_pwm()?;
+                output_pin.set_low();
+            }
+            GpioOutput::Toggle => {
+                output_pin.clear_pwm()?;
+                output_pin.toggle();
+            }
+            GpioOutput::Pwm {
+                frequency,
+                duty_cycle,
+            } => output_pin.set_pwm_frequency(frequency, duty_cycle)?,
+        }
+        Ok(())
+    }
+}
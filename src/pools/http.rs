@@ -1,12 +1,18 @@
 use indexmap::IndexMap;
 
 use crate::config::PoolId;
-use crate::events::api_listen::HttpQueue;
+use crate::events::{
+    api_listen::{HttpQueue, HttpState, HttpStreams},
+    ReferencingEvent,
+};
 use anyhow::Result;
+use log::{debug, error};
 
 #[derive(Default)]
 pub struct HttpQueuePool {
     map: IndexMap<PoolId, HttpQueue>,
+    streams: HttpStreams,
+    state: HttpState,
 }
 
 impl HttpQueuePool {
@@ -22,4 +28,45 @@ impl HttpQueuePool {
         }
         self.map.get(pool_id)
     }
+
+    /// server-sent event connections shared by all servers
+    pub fn streams(&self) -> &HttpStreams {
+        &self.streams
+    }
+
+    /// state shared by all servers
+    pub fn state(&self) -> &HttpState {
+        &self.state
+    }
+
+    pub fn update_state(&self, state: &IndexMap<String, String>) {
+        state.clone_into(&mut self.state.write().expect("http state lock"));
+    }
+
+    /// send the event to server-sent event connections and drop the closed ones
+    pub fn stream(&self, event: &ReferencingEvent) {
+        let mut streams = self.streams.lock().expect("http streams lock");
+        if !streams.iter().any(|s| s.matches(&event.name)) {
+            return;
+        }
+        let data = match serde_json::to_string(&event.data) {
+            Ok(d) => d,
+            Err(e) => {
+                error!("Failed to serialize stream data event={} {e}", event.name);
+                return;
+            }
+        };
+        let message = format!("event: {}\ndata: {data}\n\n", event.name);
+        streams.retain(|s| {
+            if !s.matches(&event.name) {
+                return true;
+            }
+            // closed streams and clients not reading fast enough are removed
+            let sent = s.sender.try_send(message.as_bytes().to_vec());
+            if let Err(e) = &sent {
+                debug!("Http stream removed {e}");
+            }
+            sent.is_ok()
+        });
+    }
 }
@@ -1,3 +1,20 @@
+#[cfg(feature = "amqp")]
+pub mod amqp;
 pub mod api;
-pub mod mqtt;
+#[cfg(feature = "gpio")]
+pub mod gpio;
 pub mod http;
+pub mod modbus;
+pub mod mqtt;
+pub mod nats;
+#[cfg(feature = "redis")]
+pub mod redis;
+#[cfg(feature = "serial")]
+pub mod serial;
+#[cfg(feature = "email")]
+pub mod smtp;
+#[cfg(feature = "sql")]
+pub mod sql;
+pub mod websocket;
+#[cfg(feature = "zmq")]
+pub mod zmq;
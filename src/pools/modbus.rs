@@ -0,0 +1,394 @@
+use core::time::Duration;
+use std::{
+    io::{Read, Write},
+    net::{TcpStream, ToSocketAddrs},
+    sync::{Arc, Mutex},
+};
+
+use anyhow::{anyhow, bail, Context, Result};
+use indexmap::IndexMap;
+#[cfg(feature = "serial")]
+use serialport::{ClearBuffer, SerialPort};
+
+use crate::{
+    config::{ModbusConfiguration, PoolId},
+    events::modbus_read::ModbusRegister,
+};
+
+/// registers or bits allowed in a single request
+const MAX_COUNT: u16 = 125;
+
+pub type ModbusConnection = Arc<Mutex<ModbusClient>>;
+
+#[derive(Default)]
+pub struct ModbusPool {
+    map: IndexMap<PoolId, ModbusConnection>,
+}
+
+impl ModbusPool {
+    pub fn configure(&mut self, pool_id: PoolId, config: ModbusConfiguration) -> Result<()> {
+        if config.address.is_none() && config.device.is_none() {
+            bail!("Please provide address or device for modbus {pool_id}");
+        }
+        #[cfg(not(feature = "serial"))]
+        if config.address.is_none() {
+            bail!("Modbus device requires the serial feature for modbus {pool_id}");
+        }
+        self.map
+            .insert(pool_id, Arc::new(Mutex::new(ModbusClient::new(config))));
+        Ok(())
+    }
+
+    pub fn get(&self, pool_id: &str) -> Option<&ModbusConnection> {
+        // return the first configuration when the pool id is empty
+        if pool_id.is_empty() {
+            return self.map.values().next();
+        }
+        self.map.get(pool_id)
+    }
+}
+
+enum Transport {
+    Tcp(TcpStream),
+    #[cfg(feature = "serial")]
+    Rtu(Box<dyn SerialPort>),
+}
+
+/// connects on the first request and reconnects after failures
+pub struct ModbusClient {
+    config: ModbusConfiguration,
+    transport: Option<Transport>,
+    transaction_id: u16,
+}
+
+impl ModbusClient {
+    pub fn new(config: ModbusConfiguration) -> Self {
+        Self {
+            config,
+            transport: None,
+            transaction_id: 0,
+        }
+    }
+
+    /// register values or bits as 0 and 1
+    pub fn read(
+        &mut self,
+        unit: u8,
+        register: ModbusRegister,
+        address: u16,
+        count: u16,
+    ) -> Result<Vec<u16>> {
+        if count == 0 || count > MAX_COUNT {
+            bail!("Modbus read count must be between 1 and {MAX_COUNT}");
+        }
+        let function = match register {
+            ModbusRegister::Coil => 0x01,
+            ModbusRegister::Discrete => 0x02,
+            ModbusRegister::Holding => 0x03,
+            ModbusRegister::Input => 0x04,
+        };
+        let mut pdu = vec![function];
+        pdu.extend_from_slice(&address.to_be_bytes());
+        pdu.extend_from_slice(&count.to_be_bytes());
+        let response = self.request(unit, &pdu)?;
+        let data = response
+            .get(2..)
+            .filter(|d| d.len() == usize::from(response[1]))
+            .ok_or_else(|| anyhow!("Invalid modbus response length"))?;
+        let values: Vec<u16> = if register.is_bit() {
+            (0..usize::from(count))
+                .filter_map(|i| data.get(i / 8).map(|b| u16::from((b >> (i % 8)) & 1)))
+                .collect()
+        } else {
+            data.chunks_exact(2)
+                .map(|c| u16::from_be_bytes([c[0], c[1]]))
+                .collect()
+        };
+        if values.len() != usize::from(count) {
+            bail!(
+                "Modbus response contains {} values, expected {count}",
+                values.len()
+            );
+        }
+        Ok(values)
+    }
+
+    pub fn write(
+        &mut self,
+        unit: u8,
+        register: ModbusRegister,
+        address: u16,
+        values: &[u16],
+    ) -> Result<()> {
+        if values.is_empty() || values.len() > usize::from(MAX_COUNT) {
+            bail!("Modbus write must contain between 1 and {MAX_COUNT} values");
+        }
+        let mut pdu = Vec::new();
+        match (register, values) {
+            (ModbusRegister::Coil, [value]) => {
+                pdu.push(0x05);
+                pdu.extend_from_slice(&address.to_be_bytes());
+                pdu.extend_from_slice(if *value != 0 { &[0xff, 0x00] } else { &[0, 0] });
+            }
+            (ModbusRegister::Coil, values) => {
+                pdu.push(0x0f);
+                pdu.extend_from_slice(&address.to_be_bytes());
+                pdu.extend_from_slice(&(values.len() as u16).to_be_bytes());
+                let bytes: Vec<u8> = values
+                    .chunks(8)
+                    .map(|bits| {
+                        bits.iter()
+                            .enumerate()
+                            .fold(0, |b, (i, v)| b | (u8::from(*v != 0) << i))
+                    })
+                    .collect();
+                pdu.push(bytes.len() as u8);
+                pdu.extend(bytes);
+            }
+            (ModbusRegister::Holding, [value]) => {
+                pdu.push(0x06);
+                pdu.extend_from_slice(&address.to_be_bytes());
+                pdu.extend_from_slice(&value.to_be_bytes());
+            }
+            (ModbusRegister::Holding, values) => {
+                pdu.push(0x10);
+                pdu.extend_from_slice(&address.to_be_bytes());
+                pdu.extend_from_slice(&(values.len() as u16).to_be_bytes());
+                pdu.push((values.len() * 2) as u8);
+                pdu.extend(values.iter().flat_map(|v| v.to_be_bytes()));
+            }
+            (register, _) => bail!("Modbus {register:?} registers are read only"),
+        }
+        self.request(unit, &pdu)?;
+        Ok(())
+    }
+
+    /// send the request returning the response pdu
+    fn request(&mut self, unit: u8, pdu: &[u8]) -> Result<Vec<u8>> {
+        let response = self.transact(unit, pdu);
+        if response.is_err() {
+            // responses may arrive late, start with a new connection
+            self.transport = None;
+        }
+        let response = response?;
+        match response.first() {
+            Some(f) if *f == pdu[0] | 0x80 => bail!(
+                "Modbus exception code {}",
+                response.get(1).copied().unwrap_or_default()
+            ),
+            Some(f) if *f == pdu[0] => Ok(response),
+            _ => bail!("Unexpected modbus response function"),
+        }
+    }
+
+    fn transact(&mut self, unit: u8, pdu: &[u8]) -> Result<Vec<u8>> {
+        self.transaction_id = self.transaction_id.wrapping_add(1);
+        let transaction_id = self.transaction_id;
+        match self.connect()? {
+            Transport::Tcp(stream) => {
+                stream.write_all(&tcp_frame(transaction_id, unit, pdu))?;
+                read_tcp_response(stream, transaction_id)
+            }
+            #[cfg(feature = "serial")]
+            Transport::Rtu(port) => {
+                port.clear(ClearBuffer::Input)?;
+                port.write_all(&rtu_frame(unit, pdu))?;
+                read_rtu_response(port, unit)
+            }
+        }
+    }
+
+    fn connect(&mut self) -> Result<&mut Transport> {
+        if self.transport.is_none() {
+            let timeout = Duration::from_secs(self.config.timeout);
+            let transport = if let Some(address) = &self.config.address {
+                let socket_address = address
+                    .to_socket_addrs()?
+                    .next()
+                    .ok_or_else(|| anyhow!("No address found for {address}"))?;
+                let stream = TcpStream::connect_timeout(&socket_address, timeout)
+                    .with_context(|| format!("Unable to connect to modbus {address}"))?;
+                stream.set_read_timeout(Some(timeout))?;
+                stream.set_write_timeout(Some(timeout))?;
+                Transport::Tcp(stream)
+            } else {
+                self.open_device(timeout)?
+            };
+            self.transport = Some(transport);
+        }
+        self.transport
+            .as_mut()
+            .ok_or_else(|| anyhow!("Modbus not connected"))
+    }
+
+    #[cfg(feature = "serial")]
+    fn open_device(&self, timeout: Duration) -> Result<Transport> {
+        let device = self.config.device.as_deref().unwrap_or_default();
+        let port = serialport::new(device, self.config.baud_rate)
+            .timeout(timeout)
+            .open()
+            .with_context(|| format!("Unable to open modbus device {device}"))?;
+        Ok(Transport::Rtu(port))
+    }
+
+    #[cfg(not(feature = "serial"))]
+    fn open_device(&self, _timeout: Duration) -> Result<Transport> {
+        bail!("Modbus device requires the serial feature")
+    }
+}
+
+fn tcp_frame(transaction_id: u16, unit: u8, pdu: &[u8]) -> Vec<u8> {
+    let mut frame = Vec::with_capacity(pdu.len() + 7);
+    frame.extend_from_slice(&transaction_id.to_be_bytes());
+    // protocol identifier
+    frame.extend_from_slice(&[0, 0]);
+    frame.extend_from_slice(&(pdu.len() as u16 + 1).to_be_bytes());
+    frame.push(unit);
+    frame.extend_from_slice(pdu);
+    frame
+}
+
+fn read_tcp_response(mut reader: impl Read, transaction_id: u16) -> Result<Vec<u8>> {
+    let mut header = [0; 7];
+    reader.read_exact(&mut header)?;
+    if header[..2] != transaction_id.to_be_bytes() {
+        bail!("Unexpected modbus transaction id");
+    }
+    let length = usize::from(u16::from_be_bytes([header[4], header[5]]));
+    if !(2..=254).contains(&length) {
+        bail!("Invalid modbus response length {length}");
+    }
+    let mut pdu = vec![0; length - 1];
+    reader.read_exact(&mut pdu)?;
+    Ok(pdu)
+}
+
+#[cfg(feature = "serial")]
+fn rtu_frame(unit: u8, pdu: &[u8]) -> Vec<u8> {
+    let mut frame = Vec::with_capacity(pdu.len() + 3);
+    frame.push(unit);
+    frame.extend_from_slice(pdu);
+    frame.extend_from_slice(&crc16(&frame).to_le_bytes());
+    frame
+}
+
+#[cfg(feature = "serial")]
+fn read_rtu_response(mut reader: impl Read, unit: u8) -> Result<Vec<u8>> {
+    let mut frame = vec![0; 2];
+    reader.read_exact(&mut frame)?;
+    let remaining = match frame[1] {
+        f if f & 0x80 != 0 => 1,
+        0x01..=0x04 => {
+            let mut count = [0];
+            reader.read_exact(&mut count)?;
+            frame.push(count[0]);
+            usize::from(count[0])
+        }
+        _ => 4,
+    };
+    let start = frame.len();
+    // data followed by the crc
+    frame.resize(start + remaining + 2, 0);
+    reader.read_exact(&mut frame[start..])?;
+    let (message, crc) = frame.split_at(frame.len() - 2);
+    if crc16(message).to_le_bytes() != crc {
+        bail!("Invalid modbus response crc");
+    }
+    if message[0] != unit {
+        bail!("Unexpected modbus response unit {}", message[0]);
+    }
+    Ok(message[1..].to_vec())
+}
+
+#[cfg(feature = "serial")]
+fn crc16(data: &[u8]) -> u16 {
+    data.iter().fold(0xffff, |crc, byte| {
+        (0..8).fold(crc ^ u16::from(*byte), |crc, _| {
+            if crc & 1 != 0 {
+                (crc >> 1) ^ 0xa001
+            } else {
+                crc >> 1
+            }
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{net::TcpListener, thread::spawn};
+
+    use super::*;
+
+    #[cfg(feature = "serial")]
+    #[test]
+    fn test_rtu_frame() {
+        // read holding registers 0 and 1 from unit 1
+        let frame = rtu_frame(1, &[0x03, 0x00, 0x00, 0x00, 0x02]);
+        assert_eq!(frame, [0x01, 0x03, 0x00, 0x00, 0x00, 0x02, 0xc4, 0x0b]);
+
+        let response: &[u8] = &[0x01, 0x03, 0x04, 0x00, 0xd7, 0x00, 0x01, 0x8b, 0xcb];
+        let expected = [0x03, 0x04, 0x00, 0xd7, 0x00, 0x01];
+        assert_eq!(read_rtu_response(response, 1).unwrap(), expected);
+        assert!(read_rtu_response(response, 2).is_err());
+
+        let response: &[u8] = &[0x01, 0x03, 0x04, 0x00, 0xd7, 0x00, 0x01, 0x8b, 0xca];
+        assert!(read_rtu_response(response, 1).is_err());
+    }
+
+    #[test]
+    fn test_tcp_client() {
+        let listener = TcpListener::bind("127.0.0.1:13350").unwrap();
+        spawn(move || {
+            let mut registers = [215u16, 1, 0, 0];
+            let (mut stream, _) = listener.accept().unwrap();
+            loop {
+                let mut header = [0; 7];
+                if stream.read_exact(&mut header).is_err() {
+                    return;
+                }
+                let length = u16::from_be_bytes([header[4], header[5]]) as usize;
+                let mut pdu = vec![0; length - 1];
+                stream.read_exact(&mut pdu).unwrap();
+                let address = u16::from_be_bytes([pdu[1], pdu[2]]) as usize;
+                let response = match pdu[0] {
+                    0x03 => {
+                        let count = u16::from_be_bytes([pdu[3], pdu[4]]) as usize;
+                        let mut response = vec![0x03, (count * 2) as u8];
+                        response.extend(
+                            registers[address..address + count]
+                                .iter()
+                                .flat_map(|r| r.to_be_bytes()),
+                        );
+                        response
+                    }
+                    0x06 => {
+                        registers[address] = u16::from_be_bytes([pdu[3], pdu[4]]);
+                        pdu.clone()
+                    }
+                    f => vec![f | 0x80, 0x01],
+                };
+                stream
+                    .write_all(&tcp_frame(
+                        u16::from_be_bytes([header[0], header[1]]),
+                        header[6],
+                        &response,
+                    ))
+                    .unwrap();
+            }
+        });
+
+        let mut client = ModbusClient::new(ModbusConfiguration::new("127.0.0.1:13350".to_string()));
+        assert_eq!(
+            client.read(1, ModbusRegister::Holding, 0, 2).unwrap(),
+            vec![215, 1]
+        );
+        client.write(1, ModbusRegister::Holding, 2, &[30]).unwrap();
+        assert_eq!(
+            client.read(1, ModbusRegister::Holding, 2, 1).unwrap(),
+            vec![30]
+        );
+        let error = client.read(1, ModbusRegister::Input, 0, 1).unwrap_err();
+        assert_eq!(error.to_string(), "Modbus exception code 1");
+        assert!(client.write(1, ModbusRegister::Input, 0, &[1]).is_err());
+    }
+}
@@ -1,44 +1,293 @@
-use std::time::Duration;
+use std::{fs::File, io::BufReader, path::Path, sync::Arc, time::Duration};
 
+use anyhow::{anyhow, bail, Context, Result};
 use indexmap::IndexMap;
-use log::info;
-use rumqttc::{Client, Connection, MqttOptions};
+use log::{info, warn};
+use rumqttc::{
+    tokio_rustls::rustls::{
+        client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier},
+        pki_types::{CertificateDer, PrivateKeyDer, ServerName, UnixTime},
+        ClientConfig, DigitallySignedStruct, Error as TlsError, RootCertStore, SignatureScheme,
+    },
+    v5::{self, mqttbytes::v5::PublishProperties},
+    Client, Connection, LastWill, MqttOptions, QoS, TlsConfiguration, Transport,
+};
 
-use crate::config::{MqttConfiguration, PoolId};
+use crate::{
+    config::{
+        ClientTlsConfiguration, MqttConfiguration, MqttMessage, MqttProtocol, MqttReconnect, PoolId,
+    },
+    events::{mqtt_publish::MqttPublishProperties, mqtt_subscribe::MqttSubscriptions},
+};
 
 #[derive(Default)]
 pub struct MqttPool {
-    clients: IndexMap<PoolId, Client>,
+    clients: IndexMap<PoolId, MqttClient>,
+    /// rendered topics of each pool by event name
+    subscriptions: IndexMap<PoolId, MqttSubscriptions>,
 }
 
 impl MqttPool {
-    pub fn configure(&mut self, pool_id: PoolId, config: MqttConfiguration) -> Connection {
-        let mut mqtt_options = MqttOptions::new(
-            config.client_id.as_ref().unwrap_or(&pool_id),
-            &config.host,
-            config.port,
-        );
-        if let Some(user) = config.user {
-            if let Some(pass) = config.pass {
-                mqtt_options.set_credentials(user, pass);
-            }
-        }
+    pub fn configure(
+        &mut self,
+        pool_id: PoolId,
+        config: MqttConfiguration,
+    ) -> Result<MqttConnection> {
+        let client_id = config.client_id.as_ref().unwrap_or(&pool_id);
+        let transport = config.tls.as_ref().map(tls_configuration).transpose()?;
 
-        mqtt_options.set_keep_alive(Duration::from_secs(5));
-
-        let (client, connection) = Client::new(mqtt_options, 10);
+        let (client, connection) = match config.protocol {
+            MqttProtocol::V4 => {
+                let mut mqtt_options = MqttOptions::new(client_id, &config.host, config.port);
+                if let Some(user) = &config.user {
+                    if let Some(pass) = &config.pass {
+                        mqtt_options.set_credentials(user, pass);
+                    }
+                }
+                mqtt_options.set_keep_alive(Duration::from_secs(5));
+                if let Some(tls) = transport {
+                    mqtt_options.set_transport(Transport::tls_with_config(tls));
+                }
+                if let Some(will) = &config.will {
+                    mqtt_options.set_last_will(LastWill::new(
+                        &will.topic,
+                        will.body.as_bytes(),
+                        will.qos.map(Into::into).unwrap_or(QoS::AtLeastOnce),
+                        will.retain,
+                    ));
+                }
+                let (client, connection) = Client::new(mqtt_options, 10);
+                (MqttClient::V4(client), MqttEventLoop::V4(connection.into()))
+            }
+            MqttProtocol::V5 => {
+                let mut mqtt_options = v5::MqttOptions::new(client_id, &config.host, config.port);
+                if let Some(user) = &config.user {
+                    if let Some(pass) = &config.pass {
+                        mqtt_options.set_credentials(user, pass);
+                    }
+                }
+                mqtt_options.set_keep_alive(Duration::from_secs(5));
+                if let Some(tls) = transport {
+                    mqtt_options.set_transport(Transport::tls_with_config(tls));
+                }
+                if let Some(will) = &config.will {
+                    mqtt_options.set_last_will(v5::mqttbytes::v5::LastWill::new(
+                        &will.topic,
+                        will.body.as_bytes(),
+                        v5_qos(will.qos.map(Into::into).unwrap_or(QoS::AtLeastOnce)),
+                        will.retain,
+                        None,
+                    ));
+                }
+                let (client, connection) = v5::Client::new(mqtt_options, 10);
+                (MqttClient::V5(client), MqttEventLoop::V5(connection.into()))
+            }
+        };
 
         info!("Connected to {}", config.host);
 
-        self.clients.insert(pool_id, client);
-        connection
+        self.clients.insert(pool_id.clone(), client.clone());
+        let subscriptions = MqttSubscriptions::default();
+        self.subscriptions
+            .insert(pool_id.clone(), subscriptions.clone());
+        Ok(MqttConnection {
+            pool_id,
+            client,
+            event_loop: connection,
+            birth: config.birth,
+            reconnect: config.reconnect,
+            subscriptions,
+        })
     }
 
-    pub fn get(&self, pool_id: &str) -> Option<&Client> {
+    pub fn get(&self, pool_id: &str) -> Option<&MqttClient> {
         // return the first configuration when pool id is empty
         if pool_id.is_empty() {
             return self.clients.values().next();
         }
         self.clients.get(pool_id)
     }
+
+    /// topics subscribed with the client returned for the same pool id
+    pub fn subscriptions(&self, pool_id: &str) -> Option<&MqttSubscriptions> {
+        if pool_id.is_empty() {
+            return self.subscriptions.values().next();
+        }
+        self.subscriptions.get(pool_id)
+    }
+}
+
+#[derive(Clone)]
+pub enum MqttClient {
+    V4(Client),
+    V5(v5::Client),
+}
+
+impl MqttClient {
+    pub fn try_subscribe(&self, topic: &str, qos: QoS) -> Result<()> {
+        match self {
+            MqttClient::V4(c) => c.try_subscribe(topic, qos)?,
+            MqttClient::V5(c) => c.try_subscribe(topic, v5_qos(qos))?,
+        }
+        Ok(())
+    }
+
+    pub fn try_unsubscribe(&self, topic: &str) -> Result<()> {
+        match self {
+            MqttClient::V4(c) => c.try_unsubscribe(topic)?,
+            MqttClient::V5(c) => c.try_unsubscribe(topic)?,
+        }
+        Ok(())
+    }
+
+    pub fn try_publish(
+        &self,
+        topic: &str,
+        qos: QoS,
+        retain: bool,
+        payload: impl Into<Vec<u8>>,
+        properties: &MqttPublishProperties,
+    ) -> Result<()> {
+        match self {
+            MqttClient::V4(c) => c.try_publish(topic, qos, retain, payload)?,
+            MqttClient::V5(c) => c.try_publish_with_properties(
+                topic,
+                v5_qos(qos),
+                retain,
+                payload.into(),
+                PublishProperties {
+                    message_expiry_interval: properties.message_expiry,
+                    topic_alias: properties.topic_alias,
+                    response_topic: properties.response_topic.clone(),
+                    user_properties: properties
+                        .user_properties
+                        .iter()
+                        .map(|(k, v)| (k.clone(), v.clone()))
+                        .collect(),
+                    ..Default::default()
+                },
+            )?,
+        }
+        Ok(())
+    }
+}
+
+pub struct MqttConnection {
+    pub pool_id: PoolId,
+    pub client: MqttClient,
+    pub event_loop: MqttEventLoop,
+    /// message published after each successful connection
+    pub birth: Option<MqttMessage>,
+    pub reconnect: MqttReconnect,
+    pub subscriptions: MqttSubscriptions,
+}
+
+pub enum MqttEventLoop {
+    V4(Box<Connection>),
+    V5(Box<v5::Connection>),
+}
+
+fn v5_qos(qos: QoS) -> v5::mqttbytes::QoS {
+    match qos {
+        QoS::AtMostOnce => v5::mqttbytes::QoS::AtMostOnce,
+        QoS::AtLeastOnce => v5::mqttbytes::QoS::AtLeastOnce,
+        QoS::ExactlyOnce => v5::mqttbytes::QoS::ExactlyOnce,
+    }
+}
+
+fn tls_configuration(config: &ClientTlsConfiguration) -> Result<TlsConfiguration> {
+    let mut roots = RootCertStore::empty();
+    if let Some(ca) = &config.ca {
+        for cert in read_certificates(ca)? {
+            roots.add(cert)?;
+        }
+    } else {
+        for cert in rustls_native_certs::load_native_certs()? {
+            if let Err(e) = roots.add(cert) {
+                warn!("Ignoring invalid system certificate {e}");
+            }
+        }
+    }
+
+    let builder = ClientConfig::builder().with_root_certificates(roots);
+    let mut client_config = match (&config.cert, &config.key) {
+        (Some(cert), Some(key)) => {
+            builder.with_client_auth_cert(read_certificates(cert)?, read_private_key(key)?)?
+        }
+        (None, None) => builder.with_no_client_auth(),
+        _ => bail!("Both tls cert and key must be provided for client authentication"),
+    };
+
+    if config.insecure {
+        warn!("Mqtt server certificate verification is disabled");
+        client_config
+            .dangerous()
+            .set_certificate_verifier(Arc::new(NoServerVerification));
+    }
+
+    Ok(TlsConfiguration::Rustls(Arc::new(client_config)))
+}
+
+fn read_certificates(path: &Path) -> Result<Vec<CertificateDer<'static>>> {
+    let file = File::open(path)
+        .with_context(|| format!("Unable to open certificate {}", path.to_string_lossy()))?;
+    rustls_pemfile::certs(&mut BufReader::new(file))
+        .collect::<Result<_, _>>()
+        .with_context(|| format!("Invalid certificate {}", path.to_string_lossy()))
+}
+
+fn read_private_key(path: &Path) -> Result<PrivateKeyDer<'static>> {
+    let file = File::open(path)
+        .with_context(|| format!("Unable to open private key {}", path.to_string_lossy()))?;
+    rustls_pemfile::private_key(&mut BufReader::new(file))?
+        .ok_or_else(|| anyhow!("No private key found in {}", path.to_string_lossy()))
+}
+
+#[derive(Debug)]
+struct NoServerVerification;
+
+impl ServerCertVerifier for NoServerVerification {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, TlsError> {
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        _message: &[u8],
+        _cert: &CertificateDer<'_>,
+        _dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, TlsError> {
+        Ok(HandshakeSignatureValid::assertion())
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        _message: &[u8],
+        _cert: &CertificateDer<'_>,
+        _dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, TlsError> {
+        Ok(HandshakeSignatureValid::assertion())
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        vec![
+            SignatureScheme::RSA_PKCS1_SHA256,
+            SignatureScheme::RSA_PKCS1_SHA384,
+            SignatureScheme::RSA_PKCS1_SHA512,
+            SignatureScheme::ECDSA_NISTP256_SHA256,
+            SignatureScheme::ECDSA_NISTP384_SHA384,
+            SignatureScheme::ECDSA_NISTP521_SHA512,
+            SignatureScheme::RSA_PSS_SHA256,
+            SignatureScheme::RSA_PSS_SHA384,
+            SignatureScheme::RSA_PSS_SHA512,
+            SignatureScheme::ED25519,
+        ]
+    }
 }
@@ -0,0 +1,108 @@
+use core::time::Duration;
+use std::sync::{Arc, Mutex};
+
+use anyhow::{Context, Result};
+use indexmap::IndexMap;
+use redis::{Client, Commands, Connection, ConnectionLike, RedisError};
+
+use crate::config::PoolId;
+
+/// seconds to wait for a connection
+const CONNECT_TIMEOUT: u64 = 2;
+/// seconds to wait for writing a command or reading its reply on the shared connection
+const IO_TIMEOUT: u64 = 5;
+
+#[derive(Clone)]
+pub struct RedisClient {
+    client: Client,
+    /// connection reused for publishing and commands
+    shared: Arc<Mutex<Option<Connection>>>,
+}
+
+impl RedisClient {
+    pub fn new(url: &str) -> Result<Self> {
+        Ok(Self {
+            client: Client::open(url).with_context(|| format!("Invalid redis url {url}"))?,
+            shared: Default::default(),
+        })
+    }
+
+    pub fn connection(&self) -> Result<Connection> {
+        self.client
+            .get_connection_with_timeout(Duration::from_secs(CONNECT_TIMEOUT))
+            .context("Can not connect to redis")
+    }
+
+    /// runs on the shared connection, a dropped connection is replaced,
+    /// only idempotent commands are run again since the command may have been applied before the reply was lost
+    pub fn with_connection<T>(
+        &self,
+        idempotent: bool,
+        mut run: impl FnMut(&mut Connection) -> Result<T>,
+    ) -> Result<T> {
+        let mut shared = self.shared.lock().expect("redis connection lock");
+        if let Some(connection) = shared.as_mut().filter(|c| c.is_open()) {
+            match run(connection) {
+                Err(e) if is_dropped(&e) => {
+                    // the reply of a timed out command could be read by the next command
+                    shared.take();
+                    if !idempotent || is_timeout(&e) {
+                        return Err(e);
+                    }
+                }
+                result => return result,
+            }
+        }
+        shared.take();
+        let connection = shared.insert(self.shared_connection()?);
+        run(connection)
+    }
+
+    /// connection with read and write timeouts so that a slow server does not block callers indefinitely
+    fn shared_connection(&self) -> Result<Connection> {
+        let connection = self.connection()?;
+        let timeout = Some(Duration::from_secs(IO_TIMEOUT));
+        connection.set_read_timeout(timeout)?;
+        connection.set_write_timeout(timeout)?;
+        Ok(connection)
+    }
+
+    /// returns the number of subscribers that received the message
+    pub fn publish(&self, channel: &str, payload: &[u8]) -> Result<u64> {
+        self.with_connection(false, |c| Ok(c.publish(channel, payload)?))
+    }
+}
+
+fn is_dropped(error: &anyhow::Error) -> bool {
+    error
+        .downcast_ref::<RedisError>()
+        .map(|e| e.is_io_error() || e.is_connection_dropped())
+        .unwrap_or_default()
+}
+
+fn is_timeout(error: &anyhow::Error) -> bool {
+    error
+        .downcast_ref::<RedisError>()
+        .map(RedisError::is_timeout)
+        .unwrap_or_default()
+}
+
+#[derive(Default)]
+pub struct RedisPool {
+    map: IndexMap<PoolId, RedisClient>,
+}
+
+impl RedisPool {
+    pub fn configure(&mut self, pool_id: PoolId, url: &str) -> Result<()> {
+        self.map.insert(pool_id, RedisClient::new(url)?);
+        Ok(())
+    }
+
+    pub fn get(&self, pool_id: &str) -> Option<&RedisClient> {
+        // return the first configuration when the pool id is empty
+        if pool_id.is_empty() {
+            return self.map.values().next();
+        }
+        self.map.get(pool_id)
+    }
+}
@@ -0,0 +1,29 @@
+use std::sync::{Arc, Mutex};
+
+use indexmap::IndexMap;
+use serialport::SerialPort;
+
+use crate::config::PoolId;
+use anyhow::Result;
+
+pub type SerialWriter = Arc<Mutex<Box<dyn SerialPort>>>;
+
+#[derive(Default)]
+pub struct SerialPool {
+    map: IndexMap<PoolId, SerialWriter>,
+}
+
+impl SerialPool {
+    pub fn configure(&mut self, pool_id: PoolId, port: Box<dyn SerialPort>) -> Result<()> {
+        self.map.insert(pool_id, Arc::new(Mutex::new(port)));
+        Ok(())
+    }
+
+    pub fn get(&self, pool_id: &str) -> Option<&SerialWriter> {
+        // return the first configuration when the pool id is empty
+        if pool_id.is_empty() {
+            return self.map.values().next();
+        }
+        self.map.get(pool_id)
+    }
+}
@@ -0,0 +1,146 @@
+use anyhow::Result;
+use indexmap::IndexMap;
+use lettre::{
+    message::{header::ContentType, Mailbox},
+    transport::smtp::authentication::Credentials,
+    Message, SmtpTransport, Transport,
+};
+
+use crate::config::{PoolId, SmtpConfiguration, SmtpTls};
+
+#[derive(Clone)]
+pub struct SmtpClient {
+    transport: SmtpTransport,
+    from: Mailbox,
+}
+
+impl SmtpClient {
+    pub fn new(config: &SmtpConfiguration) -> Result<Self> {
+        let mut builder = match config.tls {
+            SmtpTls::None => SmtpTransport::builder_dangerous(&config.host),
+            SmtpTls::Starttls => SmtpTransport::starttls_relay(&config.host)?,
+            SmtpTls::Tls => SmtpTransport::relay(&config.host)?,
+        };
+        if let Some(port) = config.port {
+            builder = builder.port(port);
+        }
+        if let (Some(user), Some(pass)) = (&config.user, &config.pass) {
+            builder = builder.credentials(Credentials::new(user.clone(), pass.clone()));
+        }
+        Ok(Self {
+            transport: builder.build(),
+            from: config.from.parse()?,
+        })
+    }
+
+    pub fn send(&self, to: &[String], subject: &str, body: String, html: bool) -> Result<()> {
+        let mut builder = Message::builder().from(self.from.clone()).subject(subject);
+        for recipient in to {
+            builder = builder.to(recipient.parse()?);
+        }
+        let content_type = if html {
+            ContentType::TEXT_HTML
+        } else {
+            ContentType::TEXT_PLAIN
+        };
+        self.transport
+            .send(&builder.header(content_type).body(body)?)?;
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+pub struct SmtpPool {
+    map: IndexMap<PoolId, SmtpClient>,
+}
+
+impl SmtpPool {
+    pub fn configure(&mut self, pool_id: PoolId, config: &SmtpConfiguration) -> Result<()> {
+        self.map.insert(pool_id, SmtpClient::new(config)?);
+        Ok(())
+    }
+
+    pub fn get(&self, pool_id: &str) -> Option<&SmtpClient> {
+        // return the first configuration when the pool id is empty
+        if pool_id.is_empty() {
+            return self.map.values().next();
+        }
+        self.map.get(pool_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{BufRead, BufReader, Write},
+        net::TcpListener,
+        thread::spawn,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_send() {
+        let listener = TcpListener::bind("127.0.0.1:13352").unwrap();
+        let handle = spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut writer = stream.try_clone().unwrap();
+            let mut reader = BufReader::new(stream);
+            writer.write_all(b"220 localhost ESMTP\r\n").unwrap();
+            let mut received = Vec::new();
+            let mut in_data = false;
+            let mut line = String::new();
+            while reader.read_line(&mut line).unwrap() > 0 {
+                let command = line.trim_end().to_string();
+                line.clear();
+                if in_data {
+                    if command == "." {
+                        in_data = false;
+                        writer.write_all(b"250 queued\r\n").unwrap();
+                    } else {
+                        received.push(command);
+                    }
+                    continue;
+                }
+                let reply: &[u8] = match command.split(' ').next().unwrap_or_default() {
+                    "EHLO" => b"250 localhost\r\n",
+                    "DATA" => {
+                        in_data = true;
+                        b"354 go ahead\r\n"
+                    }
+                    "QUIT" => {
+                        writer.write_all(b"221 bye\r\n").unwrap();
+                        break;
+                    }
+                    _ => b"250 ok\r\n",
+                };
+                writer.write_all(reply).unwrap();
+            }
+            received
+        });
+
+        let client = SmtpClient::new(&SmtpConfiguration {
+            host: "127.0.0.1".to_string(),
+            port: Some(13352),
+            tls: SmtpTls::None,
+            user: None,
+            pass: None,
+            from: "Home <home@example.com>".to_string(),
+        })
+        .unwrap();
+        client
+            .send(
+                &["me@example.com".to_string()],
+                "Water leak",
+                "Kitchen sensor is wet".to_string(),
+                false,
+            )
+            .unwrap();
+        drop(client);
+
+        let received = handle.join().unwrap();
+        assert!(received.contains(&"Subject: Water leak".to_string()));
+        assert!(received.contains(&"To: me@example.com".to_string()));
+        assert!(received.contains(&"Kitchen sensor is wet".to_string()));
+    }
+}
@@ -0,0 +1,175 @@
+use std::sync::{Arc, Once};
+
+use anyhow::Result;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use indexmap::IndexMap;
+use serde_json::{Map, Value};
+use sqlx::{
+    any::{install_default_drivers, AnyArguments, AnyPoolOptions, AnyRow, AnyTypeInfoKind},
+    query::Query,
+    Any, AnyPool, Column, Executor, Row, Statement, ValueRef,
+};
+use tokio::runtime::{Builder, Runtime};
+
+use crate::{
+    config::{PoolId, SqlConfiguration},
+    events::sql_query::SqlParam,
+};
+
+static INSTALL_DRIVERS: Once = Once::new();
+
+/// query result
+#[derive(Debug, PartialEq)]
+pub enum SqlResult {
+    /// json object per row for statements returning columns
+    Rows(Vec<Value>),
+    Affected(u64),
+}
+
+#[derive(Clone)]
+pub struct SqlClient {
+    runtime: Arc<Runtime>,
+    pool: AnyPool,
+}
+
+impl SqlClient {
+    pub fn new(config: &SqlConfiguration) -> Result<Self> {
+        INSTALL_DRIVERS.call_once(install_default_drivers);
+        let runtime = Builder::new_current_thread().enable_all().build()?;
+        // connections are established on the first query
+        let pool = {
+            let _guard = runtime.enter();
+            AnyPoolOptions::new()
+                .max_connections(config.max_connections)
+                .connect_lazy(&config.url)?
+        };
+        Ok(Self {
+            runtime: Arc::new(runtime),
+            pool,
+        })
+    }
+
+    pub fn query(&self, query: &str, params: Vec<SqlParam>) -> Result<SqlResult> {
+        self.runtime.block_on(async {
+            let statement = self.pool.prepare(query).await?;
+            let returns_rows = !statement.columns().is_empty();
+            let query = params.into_iter().fold(statement.query(), bind);
+            if returns_rows {
+                let rows = query.fetch_all(&self.pool).await?;
+                Ok(SqlResult::Rows(
+                    rows.iter().map(row_to_json).collect::<Result<_>>()?,
+                ))
+            } else {
+                Ok(SqlResult::Affected(
+                    query.execute(&self.pool).await?.rows_affected(),
+                ))
+            }
+        })
+    }
+}
+
+fn bind<'q>(
+    query: Query<'q, Any, AnyArguments<'q>>,
+    param: SqlParam,
+) -> Query<'q, Any, AnyArguments<'q>> {
+    match param {
+        SqlParam::Null => query.bind(None::<String>),
+        SqlParam::Bool(b) => query.bind(b),
+        SqlParam::Integer(i) => query.bind(i),
+        SqlParam::Float(f) => query.bind(f),
+        SqlParam::Text(s) => query.bind(s),
+    }
+}
+
+fn row_to_json(row: &AnyRow) -> Result<Value> {
+    let mut object = Map::new();
+    for (index, column) in row.columns().iter().enumerate() {
+        let kind = row.try_get_raw(index)?.type_info().kind();
+        let value = match kind {
+            AnyTypeInfoKind::Null => Value::Null,
+            AnyTypeInfoKind::Bool => row.try_get::<bool, _>(index)?.into(),
+            AnyTypeInfoKind::SmallInt | AnyTypeInfoKind::Integer | AnyTypeInfoKind::BigInt => {
+                row.try_get::<i64, _>(index)?.into()
+            }
+            AnyTypeInfoKind::Real => row.try_get::<f32, _>(index)?.into(),
+            AnyTypeInfoKind::Double => row.try_get::<f64, _>(index)?.into(),
+            AnyTypeInfoKind::Text => row.try_get::<String, _>(index)?.into(),
+            // binary values are base64 encoded
+            AnyTypeInfoKind::Blob => STANDARD.encode(row.try_get::<Vec<u8>, _>(index)?).into(),
+        };
+        object.insert(column.name().to_string(), value);
+    }
+    Ok(Value::Object(object))
+}
+
+#[derive(Default)]
+pub struct SqlPool {
+    map: IndexMap<PoolId, SqlClient>,
+}
+
+impl SqlPool {
+    pub fn configure(&mut self, pool_id: PoolId, config: &SqlConfiguration) -> Result<()> {
+        self.map.insert(pool_id, SqlClient::new(config)?);
+        Ok(())
+    }
+
+    pub fn get(&self, pool_id: &str) -> Option<&SqlClient> {
+        // return the first configuration when the pool id is empty
+        if pool_id.is_empty() {
+            return self.map.values().next();
+        }
+        self.map.get(pool_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_query() {
+        let client = SqlClient::new(&SqlConfiguration {
+            url: "sqlite::memory:".to_string(),
+            // every in memory connection is a separate database
+            max_connections: 1,
+        })
+        .unwrap();
+        assert_eq!(
+            client
+                .query(
+                    "CREATE TABLE readings (sensor TEXT, value REAL, count INTEGER, raw BLOB)",
+                    Vec::new()
+                )
+                .unwrap(),
+            SqlResult::Affected(0)
+        );
+        assert_eq!(
+            client
+                .query(
+                    "INSERT INTO readings VALUES ($1, $2, $3, x'0102'), ('hall', NULL, 1, NULL)",
+                    vec![
+                        SqlParam::Text("kitchen".to_string()),
+                        SqlParam::Float(21.5),
+                        SqlParam::Integer(3),
+                    ]
+                )
+                .unwrap(),
+            SqlResult::Affected(2)
+        );
+        assert_eq!(
+            client
+                .query(
+                    "SELECT * FROM readings WHERE count >= $1 ORDER BY sensor",
+                    vec![SqlParam::Integer(1)]
+                )
+                .unwrap(),
+            SqlResult::Rows(vec![
+                json!({"sensor": "hall", "value": null, "count": 1, "raw": null}),
+                json!({"sensor": "kitchen", "value": 21.5, "count": 3, "raw": "AQI="}),
+            ])
+        );
+        assert!(client.query("SELECT * FROM missing", Vec::new()).is_err());
+    }
+}
@@ -0,0 +1,25 @@
+use indexmap::IndexMap;
+
+use crate::config::PoolId;
+use crate::events::ws_listen::WebSocketClients;
+use anyhow::Result;
+
+#[derive(Default)]
+pub struct WebSocketPool {
+    map: IndexMap<PoolId, WebSocketClients>,
+}
+
+impl WebSocketPool {
+    pub fn configure(&mut self, pool_id: PoolId, clients: WebSocketClients) -> Result<()> {
+        self.map.insert(pool_id, clients);
+        Ok(())
+    }
+
+    pub fn get(&self, pool_id: &str) -> Option<&WebSocketClients> {
+        // return the first configuration when the pool id is empty
+        if pool_id.is_empty() {
+            return self.map.values().next();
+        }
+        self.map.get(pool_id)
+    }
+}
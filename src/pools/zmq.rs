@@ -0,0 +1,150 @@
+use core::time::Duration;
+use std::sync::Mutex;
+
+use anyhow::{bail, Context as _, Result};
+use indexmap::IndexMap;
+use zmq::{Context, Socket, SocketType};
+
+use crate::config::{PoolId, ZmqConfiguration, ZmqSocketType};
+
+/// opens a socket connected or bound to the configured endpoint
+pub fn open_socket(context: &Context, config: &ZmqConfiguration) -> Result<Socket> {
+    let socket = context.socket(match config.socket {
+        ZmqSocketType::Sub => SocketType::SUB,
+        ZmqSocketType::Pull => SocketType::PULL,
+        ZmqSocketType::Pub => SocketType::PUB,
+        ZmqSocketType::Push => SocketType::PUSH,
+        ZmqSocketType::Req => SocketType::REQ,
+    })?;
+    // pending messages are dropped once the socket is closed
+    socket.set_linger(0)?;
+    if config.bind {
+        socket
+            .bind(&config.endpoint)
+            .with_context(|| format!("Unable to bind zmq socket {}", config.endpoint))?;
+    } else {
+        socket
+            .connect(&config.endpoint)
+            .with_context(|| format!("Unable to connect zmq socket {}", config.endpoint))?;
+    }
+    Ok(socket)
+}
+
+pub struct ZmqEndpoint {
+    context: Context,
+    config: ZmqConfiguration,
+    /// long lived socket for pub and push sockets
+    socket: Option<Mutex<Socket>>,
+}
+
+impl ZmqEndpoint {
+    /// topic is sent as the first frame if provided
+    pub fn send(&self, topic: Option<&str>, body: &[u8]) -> Result<()> {
+        let Some(socket) = &self.socket else {
+            bail!(
+                "Zmq {:?} socket {} can not publish messages",
+                self.config.socket,
+                self.config.endpoint
+            );
+        };
+        let socket = socket.lock().expect("zmq socket lock");
+        match topic {
+            Some(topic) => socket.send_multipart([topic.as_bytes(), body], 0)?,
+            None => socket.send(body, 0)?,
+        }
+        Ok(())
+    }
+
+    /// returns the last frame of the reply
+    pub fn request(&self, body: &[u8], timeout: Duration) -> Result<Vec<u8>> {
+        if self.config.socket != ZmqSocketType::Req {
+            bail!(
+                "Zmq {:?} socket {} can not send requests",
+                self.config.socket,
+                self.config.endpoint
+            );
+        }
+        // req sockets can not be reused after a missing reply
+        let socket = open_socket(&self.context, &self.config)?;
+        socket.set_rcvtimeo(timeout.as_millis().try_into().unwrap_or(i32::MAX))?;
+        socket.send(body, 0)?;
+        match socket.recv_multipart(0) {
+            Ok(mut frames) => Ok(frames.pop().unwrap_or_default()),
+            Err(zmq::Error::EAGAIN) => bail!("No reply received from {}", self.config.endpoint),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct ZmqPool {
+    context: Context,
+    map: IndexMap<PoolId, ZmqEndpoint>,
+}
+
+impl ZmqPool {
+    pub fn context(&self) -> &Context {
+        &self.context
+    }
+
+    pub fn configure(&mut self, pool_id: PoolId, config: &ZmqConfiguration) -> Result<()> {
+        let socket = match config.socket {
+            ZmqSocketType::Pub | ZmqSocketType::Push => {
+                Some(Mutex::new(open_socket(&self.context, config)?))
+            }
+            _ => None,
+        };
+        self.map.insert(
+            pool_id,
+            ZmqEndpoint {
+                context: self.context.clone(),
+                config: config.clone(),
+                socket,
+            },
+        );
+        Ok(())
+    }
+
+    pub fn get(&self, pool_id: &str) -> Option<&ZmqEndpoint> {
+        // return the first configuration when the pool id is empty
+        if pool_id.is_empty() {
+            return self.map.values().next();
+        }
+        self.map.get(pool_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_push_pull() {
+        let mut pool = ZmqPool::default();
+        pool.configure(
+            "sender".to_string(),
+            &ZmqConfiguration {
+                endpoint: "inproc://test_push_pull".to_string(),
+                socket: ZmqSocketType::Push,
+                bind: true,
+            },
+        )
+        .unwrap();
+        let receiver = open_socket(
+            pool.context(),
+            &ZmqConfiguration {
+                endpoint: "inproc://test_push_pull".to_string(),
+                socket: ZmqSocketType::Pull,
+                bind: false,
+            },
+        )
+        .unwrap();
+        let sender = pool.get("").unwrap();
+        sender.send(Some("lab"), b"on").unwrap();
+        assert_eq!(
+            receiver.recv_multipart(0).unwrap(),
+            [b"lab".to_vec(), b"on".to_vec()]
+        );
+        assert!(sender.request(b"on", Duration::from_millis(10)).is_err());
+    }
+}
@@ -0,0 +1,126 @@
+use core::hash::Hash;
+use core::time::Duration;
+use std::{collections::HashMap, sync::Mutex, thread::sleep, time::Instant};
+
+use crate::config::RateLimitConfiguration;
+
+/// buckets are cleaned up when the number of keys exceeds this value
+const PRUNE_AFTER: usize = 1024;
+
+/// one request per day
+const MIN_RATE: f64 = 1.0 / 86400.0;
+
+/// token bucket per key
+pub struct RateLimiter<K> {
+    rate: f64,
+    burst: f64,
+    buckets: Mutex<HashMap<K, Bucket>>,
+}
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl<K: Hash + Eq> RateLimiter<K> {
+    pub fn new(config: &RateLimitConfiguration) -> Self {
+        Self {
+            // guards against waiting forever
+            rate: config.rate.max(MIN_RATE),
+            burst: config.burst.max(1) as f64,
+            buckets: Default::default(),
+        }
+    }
+
+    /// take a token for the key, returns false if none are available
+    pub fn allow(&self, key: K) -> bool {
+        self.allow_at(key, Instant::now())
+    }
+
+    /// take a token for the key, waiting until one is available
+    pub fn wait(&self, key: K) {
+        let delay = self.reserve_at(key, Instant::now());
+        if !delay.is_zero() {
+            sleep(delay);
+        }
+    }
+
+    /// reserve a token returning how long to wait for it
+    fn reserve_at(&self, key: K, now: Instant) -> Duration {
+        let mut buckets = self.buckets.lock().expect("rate limit lock");
+        let bucket = buckets.entry(key).or_insert(Bucket {
+            tokens: self.burst,
+            updated: now,
+        });
+        self.refill(bucket, now);
+        bucket.tokens -= 1.0;
+        if bucket.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-bucket.tokens / self.rate)
+        }
+    }
+
+    fn allow_at(&self, key: K, now: Instant) -> bool {
+        let mut buckets = self.buckets.lock().expect("rate limit lock");
+        if buckets.len() > PRUNE_AFTER {
+            buckets.retain(|_, b| self.refill(b, now) < self.burst);
+        }
+        let bucket = buckets.entry(key).or_insert(Bucket {
+            tokens: self.burst,
+            updated: now,
+        });
+        self.refill(bucket, now);
+        if bucket.tokens < 1.0 {
+            return false;
+        }
+        bucket.tokens -= 1.0;
+        true
+    }
+
+    fn refill(&self, bucket: &mut Bucket, now: Instant) -> f64 {
+        let elapsed = now.saturating_duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * self.rate).min(self.burst);
+        bucket.updated = now;
+        bucket.tokens
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_allow() {
+        let limiter = RateLimiter::new(&RateLimitConfiguration {
+            rate: 1.0,
+            burst: 2,
+        });
+        let now = Instant::now();
+        assert!(limiter.allow_at("a", now));
+        assert!(limiter.allow_at("a", now));
+        assert!(!limiter.allow_at("a", now));
+        assert!(limiter.allow_at("b", now));
+
+        assert!(!limiter.allow_at("a", now + Duration::from_millis(500)));
+        assert!(limiter.allow_at("a", now + Duration::from_secs(1)));
+        assert!(!limiter.allow_at("a", now + Duration::from_secs(1)));
+    }
+
+    #[test]
+    fn test_reserve() {
+        let limiter = RateLimiter::new(&RateLimitConfiguration {
+            rate: 1.0,
+            burst: 2,
+        });
+        let now = Instant::now();
+        assert_eq!(limiter.reserve_at((), now), Duration::ZERO);
+        assert_eq!(limiter.reserve_at((), now), Duration::ZERO);
+        assert_eq!(limiter.reserve_at((), now), Duration::from_secs(1));
+        assert_eq!(limiter.reserve_at((), now), Duration::from_secs(2));
+        assert_eq!(
+            limiter.reserve_at((), now + Duration::from_secs(4)),
+            Duration::ZERO
+        );
+    }
+}
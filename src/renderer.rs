@@ -1,24 +1,138 @@
+use anyhow::{bail, Context as _};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chrono::{DateTime, FixedOffset, Local, NaiveDateTime, NaiveTime, TimeDelta, TimeZone};
 use handlebars::{
-    Context, Handlebars, Helper, HelperResult, JsonRender, Output, RenderContext, RenderErrorReason,
+    BlockContext, Context, DirectorySourceOptions, Handlebars, Helper, HelperDef, HelperResult,
+    JsonRender, Output, RenderContext, RenderError, RenderErrorReason, Renderable, ScopedJson,
 };
 use human_date_parser::{from_human_time, ParseResult};
 use indexmap::IndexMap;
+use log::warn;
+use rand::{seq::SliceRandom, Rng};
 use serde::Serialize;
-use std::fmt::Write;
+use serde_json::Value;
+use serde_json_path::JsonPath;
+use std::{fmt::Write, path::Path};
+use uuid::Uuid;
 
-use crate::events::data::{Data, Metadata};
+use crate::events::{
+    data::{Data, Metadata},
+    EventType, Events,
+};
 
 pub fn load_handlebars() -> Handlebars<'static> {
     let mut handlebars = Handlebars::new();
     handlebars.register_helper("date-time-format", Box::new(date_time_helper));
+    handlebars.register_helper("base64", Box::new(base64_helper));
+    handlebars.register_helper("base64dec", Box::new(base64_decode_helper));
+    handlebars.register_helper("hex", Box::new(hex_helper));
+    for operation in [
+        MathOperation::Add,
+        MathOperation::Sub,
+        MathOperation::Mul,
+        MathOperation::Div,
+        MathOperation::Round,
+        MathOperation::Gt,
+        MathOperation::Lt,
+    ] {
+        handlebars.register_helper(operation.name(), Box::new(operation));
+    }
+    handlebars.register_helper("json-get", Box::new(JsonGetHelper));
+    handlebars.register_helper("date-add", Box::new(DateOperation::Add));
+    handlebars.register_helper("date-diff", Box::new(DateOperation::Diff));
+    handlebars.register_helper("random", Box::new(RandomOperation::Integer));
+    handlebars.register_helper("random-float", Box::new(RandomOperation::Float));
+    handlebars.register_helper("random-pick", Box::new(RandomOperation::Pick));
+    handlebars.register_helper("uuid", Box::new(IdOperation::Uuid));
+    handlebars.register_helper("now-ms", Box::new(IdOperation::NowMs));
+    for operation in [
+        StringOperation::Upper,
+        StringOperation::Lower,
+        StringOperation::Trim,
+        StringOperation::Replace,
+        StringOperation::Split,
+        StringOperation::Substr,
+        StringOperation::Default,
+    ] {
+        handlebars.register_helper(operation.name(), Box::new(operation));
+    }
+    #[cfg(feature = "helpers")]
+    crate::template_helpers::register_helpers(&mut handlebars);
     handlebars
 }
 
+/// register template files referenced by events, templates are named by their path
+///
+/// .hbs files from the templates directory are named by their relative path without the extension
+/// and can be used as partials e.g. {{> envelope}} or {{> sensors/reading}}
+pub fn load_templates(
+    handlebars: &mut Handlebars,
+    events: &Events,
+    templates_dir: Option<&Path>,
+) -> anyhow::Result<()> {
+    if let Some(dir) = templates_dir {
+        if !dir.is_dir() {
+            bail!(
+                "Templates directory {} does not exist",
+                dir.to_string_lossy()
+            );
+        }
+        handlebars
+            .register_templates_directory(dir, DirectorySourceOptions::default())
+            .with_context(|| format!("Unable to load templates from {}", dir.to_string_lossy()))?;
+    }
+    let files = events.iter().filter_map(|e| match &e.event_type {
+        EventType::ApiListen(e) => e.template_file.as_deref(),
+        EventType::MqttPublish(e) => e.template_file.as_deref(),
+        _ => None,
+    });
+    for file in files {
+        let name = file.to_string_lossy();
+        if handlebars.has_template(&name) {
+            continue;
+        }
+        handlebars
+            .register_template_file(&name, file)
+            .with_context(|| format!("Unable to load template file {name}"))?;
+    }
+    Ok(())
+}
+
+/// whitelisted environment variables available in templates, missing variables are skipped
+pub fn template_env(names: &[String]) -> IndexMap<String, String> {
+    names
+        .iter()
+        .filter_map(|name| match std::env::var(name) {
+            Ok(value) => Some((name.clone(), value)),
+            Err(e) => {
+                warn!("Environment variable {name} is not available in templates {e}");
+                None
+            }
+        })
+        .collect()
+}
+
+/// render the template file if provided, otherwise the inline template
+pub fn render_to_write(
+    handlebars: &Handlebars,
+    template_file: Option<&Path>,
+    template: Option<&str>,
+    data: &impl Serialize,
+    writer: impl std::io::Write,
+) -> Option<Result<(), RenderError>> {
+    match (template_file, template) {
+        (Some(file), _) => Some(handlebars.render_to_write(&file.to_string_lossy(), data, writer)),
+        (None, Some(template)) => Some(handlebars.render_template_to_write(template, data, writer)),
+        (None, None) => None,
+    }
+}
+
 #[derive(Serialize)]
 pub struct TemplateData<'a> {
     pub data: &'a Data,
     pub metadata: &'a Metadata,
     pub state: &'a IndexMap<String, String>,
+    pub env: &'a IndexMap<String, String>,
 }
 
 fn date_time_helper(
@@ -57,14 +171,570 @@ fn date_time_helper(
     Ok(())
 }
 
+/// e.g. Basic {{base64 "user:password"}}
+fn base64_helper(
+    h: &Helper,
+    _: &Handlebars,
+    _: &Context,
+    _: &mut RenderContext,
+    out: &mut dyn Output,
+) -> HelperResult {
+    let value = render_param(h, "base64", 0)?;
+    out.write(&STANDARD.encode(value))?;
+    Ok(())
+}
+
+/// invalid utf-8 is replaced
+fn base64_decode_helper(
+    h: &Helper,
+    _: &Handlebars,
+    _: &Context,
+    _: &mut RenderContext,
+    out: &mut dyn Output,
+) -> HelperResult {
+    let value = render_param(h, "base64dec", 0)?;
+    let decoded = STANDARD
+        .decode(value.trim())
+        .map_err(|e| RenderErrorReason::Other(format!("Invalid base64 {e}")))?;
+    out.write(&String::from_utf8_lossy(&decoded))?;
+    Ok(())
+}
+
+fn hex_helper(
+    h: &Helper,
+    _: &Handlebars,
+    _: &Context,
+    _: &mut RenderContext,
+    out: &mut dyn Output,
+) -> HelperResult {
+    let value = render_param(h, "hex", 0)?;
+    out.write(&hex::encode(value))?;
+    Ok(())
+}
+
+/// numbers or numeric strings e.g. {{add data.temperature -1.5}} or {{#if (gt data.humidity 60)}}
+#[derive(Clone, Copy)]
+enum MathOperation {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    /// to the number of decimals provided by the second parameter, 0 by default
+    Round,
+    Gt,
+    Lt,
+}
+
+impl MathOperation {
+    fn name(&self) -> &'static str {
+        match self {
+            MathOperation::Add => "add",
+            MathOperation::Sub => "sub",
+            MathOperation::Mul => "mul",
+            MathOperation::Div => "div",
+            MathOperation::Round => "round",
+            MathOperation::Gt => "gt",
+            MathOperation::Lt => "lt",
+        }
+    }
+}
+
+impl HelperDef for MathOperation {
+    fn call_inner<'reg: 'rc, 'rc>(
+        &self,
+        h: &Helper<'rc>,
+        _: &'reg Handlebars<'reg>,
+        _: &'rc Context,
+        _: &mut RenderContext<'reg, 'rc>,
+    ) -> Result<ScopedJson<'rc>, RenderError> {
+        let name = self.name();
+        let a = number_param(h, name, 0)?;
+        let value = match self {
+            MathOperation::Round => {
+                let decimals = match h.param(1) {
+                    Some(_) => number_param(h, name, 1)?,
+                    None => 0.0,
+                };
+                let factor = 10_f64.powi(decimals as i32);
+                number_value((a * factor).round() / factor)
+            }
+            MathOperation::Gt => Value::Bool(a > number_param(h, name, 1)?),
+            MathOperation::Lt => Value::Bool(a < number_param(h, name, 1)?),
+            MathOperation::Add => number_value(a + number_param(h, name, 1)?),
+            MathOperation::Sub => number_value(a - number_param(h, name, 1)?),
+            MathOperation::Mul => number_value(a * number_param(h, name, 1)?),
+            MathOperation::Div => match number_param(h, name, 1)? {
+                0.0 => return Err(RenderErrorReason::Other("Division by zero".to_string()).into()),
+                b => number_value(a / b),
+            },
+        };
+        Ok(ScopedJson::Derived(value))
+    }
+}
+
+/// e.g. {{lower (replace data.device " " "_")}} or {{#each (split data.rooms ",")}}
+#[derive(Clone, Copy)]
+enum StringOperation {
+    Upper,
+    Lower,
+    Trim,
+    /// all occurrences
+    Replace,
+    /// array of parts
+    Split,
+    /// by characters from the start, to the end if length is not provided
+    Substr,
+    /// the second parameter if the first one is missing, null or empty
+    Default,
+}
+
+impl StringOperation {
+    fn name(&self) -> &'static str {
+        match self {
+            StringOperation::Upper => "upper",
+            StringOperation::Lower => "lower",
+            StringOperation::Trim => "trim",
+            StringOperation::Replace => "replace",
+            StringOperation::Split => "split",
+            StringOperation::Substr => "substr",
+            StringOperation::Default => "default",
+        }
+    }
+}
+
+impl HelperDef for StringOperation {
+    fn call_inner<'reg: 'rc, 'rc>(
+        &self,
+        h: &Helper<'rc>,
+        _: &'reg Handlebars<'reg>,
+        _: &'rc Context,
+        _: &mut RenderContext<'reg, 'rc>,
+    ) -> Result<ScopedJson<'rc>, RenderError> {
+        let name = self.name();
+        let value = match self {
+            StringOperation::Upper => render_param(h, name, 0)?.to_uppercase().into(),
+            StringOperation::Lower => render_param(h, name, 0)?.to_lowercase().into(),
+            StringOperation::Trim => render_param(h, name, 0)?.trim().into(),
+            StringOperation::Replace => render_param(h, name, 0)?
+                .replace(&render_param(h, name, 1)?, &render_param(h, name, 2)?)
+                .into(),
+            StringOperation::Split => render_param(h, name, 0)?
+                .split(&render_param(h, name, 1)?)
+                .map(Value::from)
+                .collect(),
+            StringOperation::Substr => {
+                let s = render_param(h, name, 0)?;
+                let chars = s.chars().skip(number_param(h, name, 1)? as usize);
+                match h.param(2) {
+                    Some(_) => chars
+                        .take(number_param(h, name, 2)? as usize)
+                        .collect::<String>(),
+                    None => chars.collect::<String>(),
+                }
+                .into()
+            }
+            StringOperation::Default => match h.param(0).map(|p| p.value()) {
+                None | Some(Value::Null) => None,
+                Some(Value::String(s)) if s.is_empty() => None,
+                Some(v) => Some(v.clone()),
+            }
+            .map(Ok)
+            .unwrap_or_else(|| {
+                h.param(1)
+                    .map(|p| p.value().clone())
+                    .ok_or(RenderErrorReason::ParamNotFoundForIndex(name, 1))
+            })?,
+        };
+        Ok(ScopedJson::Derived(value))
+    }
+}
+
+/// human time or rfc3339 timestamp, rfc3339 timestamps keep their offset
+enum DateOperation {
+    /// e.g. {{date-add "today" "-1d" "%Y-%m-%d"}}, format is rfc3339 if not provided
+    Add,
+    /// the second time minus the first one e.g. {{date-diff "now" data.until "m"}}, in seconds if no unit is provided
+    Diff,
+}
+
+impl HelperDef for DateOperation {
+    fn call_inner<'reg: 'rc, 'rc>(
+        &self,
+        h: &Helper<'rc>,
+        _: &'reg Handlebars<'reg>,
+        _: &'rc Context,
+        _: &mut RenderContext<'reg, 'rc>,
+    ) -> Result<ScopedJson<'rc>, RenderError> {
+        let value = match self {
+            DateOperation::Add => {
+                let time = parse_time(&render_param(h, "date-add", 0)?)?;
+                let offset = parse_offset(&render_param(h, "date-add", 1)?)?;
+                let format = match h.param(2) {
+                    Some(f) => f.value().render(),
+                    None => "%Y-%m-%dT%H:%M:%S%:z".to_string(),
+                };
+                let time = time
+                    .checked_add_signed(offset)
+                    .ok_or_else(|| RenderErrorReason::Other("Date out of range".to_string()))?;
+                let mut formatted = String::new();
+                write!(formatted, "{}", time.format(&format))
+                    .map_err(|e| RenderErrorReason::Other(e.to_string()))?;
+                Value::String(formatted)
+            }
+            DateOperation::Diff => {
+                let from = parse_time(&render_param(h, "date-diff", 0)?)?;
+                let to = parse_time(&render_param(h, "date-diff", 1)?)?;
+                let unit = match h.param(2) {
+                    Some(u) => u.value().render(),
+                    None => "s".to_string(),
+                };
+                let seconds = (to - from).num_milliseconds() as f64 / 1000.0;
+                number_value(seconds / unit_seconds(&unit)? as f64)
+            }
+        };
+        Ok(ScopedJson::Derived(value))
+    }
+}
+
+fn parse_time(s: &str) -> Result<DateTime<FixedOffset>, RenderError> {
+    if let Ok(time) = DateTime::parse_from_rfc3339(s) {
+        return Ok(time);
+    }
+    let invalid = || RenderErrorReason::Other(format!("Invalid time {s}"));
+    let time = match from_human_time(s).map_err(|e| RenderErrorReason::Other(e.to_string()))? {
+        ParseResult::DateTime(d) => d,
+        ParseResult::Date(d) => Local
+            .from_local_datetime(&NaiveDateTime::new(d, NaiveTime::default()))
+            .earliest()
+            .ok_or_else(invalid)?,
+        ParseResult::Time(t) => Local
+            .from_local_datetime(&Local::now().date_naive().and_time(t))
+            .earliest()
+            .ok_or_else(invalid)?,
+    };
+    Ok(time.fixed_offset())
+}
+
+/// e.g. +2h, -1d or 1h30m with units s, m, h, d and w
+fn parse_offset(s: &str) -> Result<TimeDelta, RenderError> {
+    let invalid = || RenderErrorReason::Other(format!("Invalid offset {s}"));
+    let (negative, rest) = match s.trim().split_at_checked(1) {
+        Some(("-", rest)) => (true, rest),
+        Some(("+", rest)) => (false, rest),
+        _ => (false, s.trim()),
+    };
+    let mut seconds: i64 = 0;
+    let mut amount = String::new();
+    for c in rest.chars() {
+        if c.is_ascii_digit() {
+            amount.push(c);
+            continue;
+        }
+        let value: i64 = amount.parse().map_err(|_| invalid())?;
+        seconds = value
+            .checked_mul(unit_seconds(&c.to_string())?)
+            .and_then(|v| seconds.checked_add(v))
+            .ok_or_else(invalid)?;
+        amount.clear();
+    }
+    if !amount.is_empty() || rest.is_empty() {
+        return Err(invalid().into());
+    }
+    let seconds = if negative { -seconds } else { seconds };
+    TimeDelta::try_seconds(seconds).ok_or_else(|| invalid().into())
+}
+
+fn unit_seconds(unit: &str) -> Result<i64, RenderError> {
+    Ok(match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 3600,
+        "d" => 86400,
+        "w" => 604800,
+        _ => return Err(RenderErrorReason::Other(format!("Invalid time unit {unit}")).into()),
+    })
+}
+
+enum RandomOperation {
+    /// between min and max inclusive e.g. {{random 30 90}}
+    Integer,
+    /// between min inclusive and max exclusive e.g. {{random-float 0.5 1.5}}
+    Float,
+    /// one of the parameters or one of the array values e.g. {{random-pick "on" "off"}} or {{random-pick data.messages}}
+    Pick,
+}
+
+impl HelperDef for RandomOperation {
+    fn call_inner<'reg: 'rc, 'rc>(
+        &self,
+        h: &Helper<'rc>,
+        _: &'reg Handlebars<'reg>,
+        _: &'rc Context,
+        _: &mut RenderContext<'reg, 'rc>,
+    ) -> Result<ScopedJson<'rc>, RenderError> {
+        let mut rng = rand::thread_rng();
+        let value = match self {
+            RandomOperation::Integer => {
+                let min = number_param(h, "random", 0)? as i64;
+                let max = number_param(h, "random", 1)? as i64;
+                if min > max {
+                    return Err(RenderErrorReason::Other(format!(
+                        "random min {min} is greater than max {max}"
+                    ))
+                    .into());
+                }
+                Value::from(rng.gen_range(min..=max))
+            }
+            RandomOperation::Float => {
+                let min = number_param(h, "random-float", 0)?;
+                let max = number_param(h, "random-float", 1)?;
+                if min >= max || !(max - min).is_finite() {
+                    return Err(RenderErrorReason::Other(format!(
+                        "random-float min {min} must be less than max {max}"
+                    ))
+                    .into());
+                }
+                Value::from(rng.gen_range(min..max))
+            }
+            RandomOperation::Pick => {
+                let values: Vec<&Value> = match h.params().as_slice() {
+                    [p] if p.value().is_array() => {
+                        p.value().as_array().into_iter().flatten().collect()
+                    }
+                    params => params.iter().map(|p| p.value()).collect(),
+                };
+                values
+                    .choose(&mut rng)
+                    .map(|v| (*v).clone())
+                    .ok_or(RenderErrorReason::Other(
+                        "random-pick expects at least one value".to_string(),
+                    ))?
+            }
+        };
+        Ok(ScopedJson::Derived(value))
+    }
+}
+
+enum IdOperation {
+    /// random v4 or time ordered v7 e.g. {{uuid}} or {{uuid "v7"}}
+    Uuid,
+    /// milliseconds since the unix epoch
+    NowMs,
+}
+
+impl HelperDef for IdOperation {
+    fn call_inner<'reg: 'rc, 'rc>(
+        &self,
+        h: &Helper<'rc>,
+        _: &'reg Handlebars<'reg>,
+        _: &'rc Context,
+        _: &mut RenderContext<'reg, 'rc>,
+    ) -> Result<ScopedJson<'rc>, RenderError> {
+        let value = match self {
+            IdOperation::Uuid => match h.param(0).map(|p| p.value().render()).as_deref() {
+                None | Some("v4") => Uuid::new_v4().to_string().into(),
+                Some("v7") => Uuid::now_v7().to_string().into(),
+                Some(version) => {
+                    return Err(RenderErrorReason::Other(format!(
+                        "Unsupported uuid version {version}, expected v4 or v7"
+                    ))
+                    .into())
+                }
+            },
+            IdOperation::NowMs => Local::now().timestamp_millis().into(),
+        };
+        Ok(ScopedJson::Derived(value))
+    }
+}
+
+/// value by json pointer e.g. /sensors/0 or the first value matched by a json path e.g. $..temperature,
+/// stringify=true renders the value as json, the block form renders with the value as context
+struct JsonGetHelper;
+
+impl JsonGetHelper {
+    fn get(h: &Helper) -> Result<Option<Value>, RenderError> {
+        let value = h
+            .param(0)
+            .ok_or(RenderErrorReason::ParamNotFoundForIndex("json-get", 0))?
+            .value();
+        let path = render_param(h, "json-get", 1)?;
+        let found = if path.starts_with('$') {
+            let path = JsonPath::parse(&path)
+                .map_err(|e| RenderErrorReason::Other(format!("Invalid json path {path} {e}")))?;
+            path.query(value).first().cloned()
+        } else {
+            value.pointer(&path).cloned()
+        };
+        let stringify = h
+            .hash_get("stringify")
+            .map(|v| v.value().as_bool().unwrap_or_default())
+            .unwrap_or_default();
+        match found {
+            Some(v) if stringify => Ok(Some(Value::String(
+                serde_json::to_string(&v).map_err(|e| RenderErrorReason::Other(e.to_string()))?,
+            ))),
+            found => Ok(found),
+        }
+    }
+}
+
+impl HelperDef for JsonGetHelper {
+    fn call_inner<'reg: 'rc, 'rc>(
+        &self,
+        h: &Helper<'rc>,
+        _: &'reg Handlebars<'reg>,
+        _: &'rc Context,
+        _: &mut RenderContext<'reg, 'rc>,
+    ) -> Result<ScopedJson<'rc>, RenderError> {
+        Ok(ScopedJson::Derived(Self::get(h)?.unwrap_or_default()))
+    }
+
+    fn call<'reg: 'rc, 'rc>(
+        &self,
+        h: &Helper<'rc>,
+        r: &'reg Handlebars<'reg>,
+        ctx: &'rc Context,
+        rc: &mut RenderContext<'reg, 'rc>,
+        out: &mut dyn Output,
+    ) -> HelperResult {
+        let value = Self::get(h)?;
+        if !h.is_block() {
+            let content = value.unwrap_or_default().render();
+            let content = if rc.is_disable_escape() {
+                content
+            } else {
+                r.get_escape_fn()(&content)
+            };
+            out.write(&content)?;
+            return Ok(());
+        }
+        match (value, h.template(), h.inverse()) {
+            (Some(value), Some(t), _) => {
+                let mut block = BlockContext::new();
+                block.set_base_value(value);
+                rc.push_block(block);
+                let result = t.render(r, ctx, rc, out);
+                rc.pop_block();
+                result
+            }
+            (None, _, Some(t)) => t.render(r, ctx, rc, out),
+            _ => Ok(()),
+        }
+    }
+}
+
+pub(crate) fn number_param(
+    h: &Helper,
+    name: &'static str,
+    index: usize,
+) -> Result<f64, RenderError> {
+    let value = h
+        .param(index)
+        .ok_or(RenderErrorReason::ParamNotFoundForIndex(name, index))?
+        .value();
+    match value {
+        Value::Number(n) => n.as_f64(),
+        Value::String(s) => s.trim().parse().ok(),
+        _ => None,
+    }
+    .ok_or_else(|| RenderErrorReason::Other(format!("{name} expects a number, got {value}")).into())
+}
+
+/// whole numbers are rendered without a fraction
+pub(crate) fn number_value(n: f64) -> Value {
+    if n.fract() == 0.0 && n.abs() < 9_007_199_254_740_992.0 {
+        Value::from(n as i64)
+    } else {
+        Value::from(n)
+    }
+}
+
+pub(crate) fn render_param(
+    h: &Helper,
+    name: &'static str,
+    index: usize,
+) -> Result<String, RenderError> {
+    Ok(h.param(index)
+        .ok_or(RenderErrorReason::ParamNotFoundForIndex(name, index))?
+        .value()
+        .render())
+}
+
 #[cfg(test)]
 mod tests {
     use serde_json::{json, Value};
 
-    use crate::config::now;
+    use crate::{
+        config::now,
+        events::{mqtt_publish::MqttPublishEvent, ReferencingEvent},
+    };
 
     use super::*;
 
+    #[test]
+    fn test_load_templates() {
+        let dir = std::env::temp_dir().join("hvents_test_templates");
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("status.hbs");
+        std::fs::write(&file, "status {{data.status}}").unwrap();
+
+        let events = Events::new(
+            [ReferencingEvent {
+                event_type: EventType::MqttPublish(MqttPublishEvent {
+                    template_file: file.clone().into(),
+                    ..Default::default()
+                }),
+                name: "publish".to_string(),
+                ..Default::default()
+            }]
+            .into_iter()
+            .collect(),
+        );
+        let mut handlebars = load_handlebars();
+        load_templates(&mut handlebars, &events, None).unwrap();
+
+        let mut content = Vec::new();
+        render_to_write(
+            &handlebars,
+            Some(&file),
+            Some("ignored"),
+            &json!({"data": {"status": "on"}}),
+            &mut content,
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(content, b"status on");
+        assert!(render_to_write(&handlebars, None, None, &json!({}), &mut content).is_none());
+    }
+
+    #[test]
+    fn test_load_templates_dir() {
+        let dir = std::env::temp_dir().join("hvents_test_templates_dir");
+        std::fs::create_dir_all(dir.join("sensors")).unwrap();
+        std::fs::write(
+            dir.join("envelope.hbs"),
+            r#"{"id":"{{data.id}}","body":{{> @partial-block}}}"#,
+        )
+        .unwrap();
+        std::fs::write(dir.join("sensors/reading.hbs"), r#"{"t":{{data.t}}}"#).unwrap();
+        std::fs::write(dir.join("notes.txt"), "ignored").unwrap();
+
+        let events = Events::new(Default::default());
+        let mut handlebars = load_handlebars();
+        load_templates(&mut handlebars, &events, Some(&dir)).unwrap();
+        assert!(!handlebars.has_template("notes"));
+
+        let result = handlebars
+            .render_template(
+                "{{#> envelope}}{{> sensors/reading}}{{/envelope}}",
+                &json!({"data": {"id": "a1", "t": 21}}),
+            )
+            .unwrap();
+        assert_eq!(result, r#"{"id":"a1","body":{"t":21}}"#);
+
+        assert!(load_templates(&mut handlebars, &events, Some(&dir.join("missing"))).is_err());
+    }
+
     #[test]
     fn test_handle_bars() {
         let now = now();
@@ -114,4 +784,232 @@ mod tests {
         let result = handlebars.render_template(template, &data);
         assert!(result.is_err());
     }
+
+    #[test]
+    fn test_encoding_helpers() {
+        let handlebars = load_handlebars();
+        let data =
+            json!({"user": "admin", "password": "secret", "token": "aGVsbG8gd29ybGQ=", "id": 255});
+
+        let template = r#"Basic {{base64 "admin:secret"}}"#;
+        let result = handlebars.render_template(template, &data).unwrap();
+        assert_eq!(result, "Basic YWRtaW46c2VjcmV0");
+
+        let template = r#"{{base64dec token}}"#;
+        let result = handlebars.render_template(template, &data).unwrap();
+        assert_eq!(result, "hello world");
+
+        let template = r#"{{hex user}} {{hex id}}"#;
+        let result = handlebars.render_template(template, &data).unwrap();
+        assert_eq!(result, "61646d696e 323535");
+
+        let template = r#"{{base64dec user}}"#;
+        assert!(handlebars.render_template(template, &data).is_err());
+
+        let template = r#"{{base64}}"#;
+        assert!(handlebars.render_template(template, &data).is_err());
+    }
+
+    #[test]
+    fn test_string_helpers() {
+        let handlebars = load_handlebars();
+        let data = json!({"device": " Living Room Lamp ", "rooms": "hall,kitchen", "empty": "", "id": 12345});
+
+        let template = r#"home/{{lower (replace (trim device) " " "_")}}/{{upper "on"}}"#;
+        let result = handlebars.render_template(template, &data).unwrap();
+        assert_eq!(result, "home/living_room_lamp/ON");
+
+        let template = r#"{{#each (split rooms ",")}}[{{this}}]{{/each}}"#;
+        let result = handlebars.render_template(template, &data).unwrap();
+        assert_eq!(result, "[hall][kitchen]");
+
+        let template = r#"{{substr (trim device) 7 4}} {{substr id 3}} {{substr "ąčę" 1 1}}"#;
+        let result = handlebars.render_template(template, &data).unwrap();
+        assert_eq!(result, "Room 45 č");
+
+        let template = r#"{{default missing "unknown"}} {{default empty "none"}} {{default id 0}}"#;
+        let result = handlebars.render_template(template, &data).unwrap();
+        assert_eq!(result, "unknown none 12345");
+
+        let template = r#"{{replace device "Lamp"}}"#;
+        assert!(handlebars.render_template(template, &data).is_err());
+    }
+
+    #[test]
+    fn test_json_get_helper() {
+        let handlebars = load_handlebars();
+        let data = json!({
+            "path": "/garage/temperature",
+            "rooms": {"garage": {"temperature": 12.5, "doors": ["main", "side"]}},
+            "sensors": [{"id": 1, "battery": 80}, {"id": 2, "battery": 15}]
+        });
+
+        let template = r#"{{json-get rooms path}} {{json-get sensors "$[?@.battery < 20].id"}}"#;
+        let result = handlebars.render_template(template, &data).unwrap();
+        assert_eq!(result, "12.5 2");
+
+        let template = r#"{{{json-get rooms "/garage" stringify=true}}}"#;
+        let result = handlebars.render_template(template, &data).unwrap();
+        assert_eq!(result, r#"{"doors":["main","side"],"temperature":12.5}"#);
+
+        let template = r#"{{#json-get rooms "/garage"}}{{#each doors}}{{this}} {{/each}}{{temperature}}{{else}}missing{{/json-get}}"#;
+        let result = handlebars.render_template(template, &data).unwrap();
+        assert_eq!(result, "main side 12.5");
+
+        let template = r#"{{#json-get rooms "/hall"}}{{temperature}}{{else}}missing{{/json-get}}[{{json-get rooms "/hall"}}]"#;
+        let result = handlebars.render_template(template, &data).unwrap();
+        assert_eq!(result, "missing[]");
+
+        let template = r#"{{#if (gt (json-get sensors "/0/battery") 50)}}charged{{/if}}"#;
+        let result = handlebars.render_template(template, &data).unwrap();
+        assert_eq!(result, "charged");
+
+        let template = r#"{{json-get sensors "$[invalid"}}"#;
+        assert!(handlebars.render_template(template, &data).is_err());
+    }
+
+    #[test]
+    fn test_date_helpers() {
+        let handlebars = load_handlebars();
+        let data =
+            json!({"start": "2024-10-01T22:30:00+03:00", "end": "2024-10-02T01:00:00+03:00"});
+
+        let template = r#"{{date-add start "+2h"}} {{date-add start "-1d" "%Y-%m-%d"}} {{date-add start "1h30m" "%H:%M"}}"#;
+        let result = handlebars.render_template(template, &data).unwrap();
+        assert_eq!(result, "2024-10-02T00:30:00+03:00 2024-09-30 00:00");
+
+        let template =
+            r#"{{date-diff start end}} {{date-diff start end "h"}} {{date-diff end start "m"}}"#;
+        let result = handlebars.render_template(template, &data).unwrap();
+        assert_eq!(result, "9000 2.5 -150");
+
+        let template = r#"{{#if (gt (date-diff "now" (date-add "now" "+1w")) 600000)}}week{{/if}}"#;
+        let result = handlebars.render_template(template, &data).unwrap();
+        assert_eq!(result, "week");
+
+        for template in [
+            r#"{{date-add start "+2x"}}"#,
+            r#"{{date-add start "+"}}"#,
+            r#"{{date-add start "2"}}"#,
+            r#"{{date-add "unknown" "+2h"}}"#,
+            r#"{{date-diff start end "y"}}"#,
+        ] {
+            assert!(
+                handlebars.render_template(template, &data).is_err(),
+                "{template}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_random_helpers() {
+        let handlebars = load_handlebars();
+        let data = json!({"messages": ["one", "two", "three"], "empty": []});
+
+        for _ in 0..20 {
+            let result = handlebars
+                .render_template(r#"{{random 2 4}}"#, &data)
+                .unwrap();
+            assert!(["2", "3", "4"].contains(&result.as_str()), "{result}");
+
+            let result: f64 = handlebars
+                .render_template(r#"{{random-float 0.5 1.5}}"#, &data)
+                .unwrap()
+                .parse()
+                .unwrap();
+            assert!((0.5..1.5).contains(&result));
+
+            let result = handlebars
+                .render_template(r#"{{random-pick messages}}"#, &data)
+                .unwrap();
+            assert!(["one", "two", "three"].contains(&result.as_str()));
+
+            let result = handlebars
+                .render_template(
+                    r#"{{#if (gt (random 5 5) 4)}}{{random-pick "on" "on"}}{{/if}}"#,
+                    &data,
+                )
+                .unwrap();
+            assert_eq!(result, "on");
+        }
+
+        for template in [
+            r#"{{random 4 2}}"#,
+            r#"{{random-float 1 1}}"#,
+            r#"{{random-pick empty}}"#,
+            r#"{{random-pick}}"#,
+        ] {
+            assert!(
+                handlebars.render_template(template, &data).is_err(),
+                "{template}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_template_env() {
+        let env = template_env(&["PATH".to_string(), "HVENTS_TEST_MISSING".to_string()]);
+        assert_eq!(env.len(), 1);
+        assert_eq!(env["PATH"], std::env::var("PATH").unwrap());
+    }
+
+    #[test]
+    fn test_id_helpers() {
+        let handlebars = load_handlebars();
+        let data = json!({});
+
+        let first = handlebars.render_template("{{uuid}}", &data).unwrap();
+        let second = handlebars
+            .render_template(r#"{{uuid "v4"}}"#, &data)
+            .unwrap();
+        assert_ne!(first, second);
+        assert_eq!(Uuid::parse_str(&first).unwrap().get_version_num(), 4);
+        let ordered = handlebars
+            .render_template(r#"{{uuid "v7"}}"#, &data)
+            .unwrap();
+        assert_eq!(Uuid::parse_str(&ordered).unwrap().get_version_num(), 7);
+        assert!(handlebars
+            .render_template(r#"{{uuid "v1"}}"#, &data)
+            .is_err());
+
+        let before = Local::now().timestamp_millis();
+        let now: i64 = handlebars
+            .render_template("{{now-ms}}", &data)
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert!(now >= before && now - before < 1000);
+        let result = handlebars
+            .render_template(r#"{{#if (gt (now-ms) 1700000000000)}}valid{{/if}}"#, &data)
+            .unwrap();
+        assert_eq!(result, "valid");
+    }
+
+    #[test]
+    fn test_math_helpers() {
+        let handlebars = load_handlebars();
+        let data = json!({"temperature": "21.46", "offset": -1.5, "humidity": 64});
+
+        let template =
+            r#"{{add temperature offset}} {{sub humidity 4}} {{mul 2 3}} {{div humidity 8}}"#;
+        let result = handlebars.render_template(template, &data).unwrap();
+        assert_eq!(result, "19.96 60 6 8");
+
+        let template =
+            r#"{{round temperature 1}} {{round (div 10 4)}} {{round (mul temperature 2)}}"#;
+        let result = handlebars.render_template(template, &data).unwrap();
+        assert_eq!(result, "21.5 3 43");
+
+        let template = r#"{{#if (gt humidity 60)}}humid{{/if}}{{#if (lt temperature 20)}} cold{{else}} warm{{/if}}"#;
+        let result = handlebars.render_template(template, &data).unwrap();
+        assert_eq!(result, "humid warm");
+
+        for template in [
+            r#"{{div humidity 0}}"#,
+            r#"{{add humidity "wet"}}"#,
+            r#"{{gt humidity}}"#,
+        ] {
+            assert!(handlebars.render_template(template, &data).is_err());
+        }
+    }
 }
@@ -0,0 +1,492 @@
+use handlebars::{
+    Context, Handlebars, Helper, HelperDef, JsonRender, RenderContext, RenderError,
+    RenderErrorReason, ScopedJson,
+};
+use serde_json::Value;
+
+use crate::renderer::{number_param, number_value, render_param};
+
+/// largest count and output in bytes of repeat and indent, payload values must not exhaust memory
+const MAX_OUTPUT_LENGTH: usize = 1 << 20;
+
+/// sprig style helpers enabled by the helpers feature,
+/// case conversion helpers e.g. snakeCase are provided by handlebars
+pub fn register_helpers(handlebars: &mut Handlebars) {
+    for operation in [
+        TextOperation::Contains,
+        TextOperation::HasPrefix,
+        TextOperation::HasSuffix,
+        TextOperation::Repeat,
+        TextOperation::Trunc,
+        TextOperation::Abbrev,
+        TextOperation::Quote,
+        TextOperation::Nospace,
+        TextOperation::Indent,
+        TextOperation::Plural,
+        TextOperation::ToJson,
+        TextOperation::FromJson,
+    ] {
+        handlebars.register_helper(operation.name(), Box::new(operation));
+    }
+    for operation in [
+        ListOperation::Join,
+        ListOperation::First,
+        ListOperation::Last,
+        ListOperation::Has,
+        ListOperation::Uniq,
+        ListOperation::Sort,
+    ] {
+        handlebars.register_helper(operation.name(), Box::new(operation));
+    }
+    for operation in [
+        NumberOperation::Min,
+        NumberOperation::Max,
+        NumberOperation::Floor,
+        NumberOperation::Ceil,
+        NumberOperation::Abs,
+        NumberOperation::Mod,
+    ] {
+        handlebars.register_helper(operation.name(), Box::new(operation));
+    }
+}
+
+/// text helpers e.g. {{#if (has-prefix data.topic "home/")}}
+#[derive(Clone, Copy)]
+enum TextOperation {
+    Contains,
+    HasPrefix,
+    HasSuffix,
+    Repeat,
+    /// to the number of characters
+    Trunc,
+    /// truncate to the number of characters including ...
+    Abbrev,
+    Quote,
+    /// remove all whitespace
+    Nospace,
+    /// prefix each line with the number of spaces
+    Indent,
+    /// second parameter when the count is 1, third otherwise e.g. {{plural data.count "light" "lights"}}
+    Plural,
+    ToJson,
+    FromJson,
+}
+
+impl TextOperation {
+    fn name(&self) -> &'static str {
+        match self {
+            TextOperation::Contains => "contains",
+            TextOperation::HasPrefix => "has-prefix",
+            TextOperation::HasSuffix => "has-suffix",
+            TextOperation::Repeat => "repeat",
+            TextOperation::Trunc => "trunc",
+            TextOperation::Abbrev => "abbrev",
+            TextOperation::Quote => "quote",
+            TextOperation::Nospace => "nospace",
+            TextOperation::Indent => "indent",
+            TextOperation::Plural => "plural",
+            TextOperation::ToJson => "to-json",
+            TextOperation::FromJson => "from-json",
+        }
+    }
+}
+
+impl HelperDef for TextOperation {
+    fn call_inner<'reg: 'rc, 'rc>(
+        &self,
+        h: &Helper<'rc>,
+        _: &'reg Handlebars<'reg>,
+        _: &'rc Context,
+        _: &mut RenderContext<'reg, 'rc>,
+    ) -> Result<ScopedJson<'rc>, RenderError> {
+        let name = self.name();
+        let text = || render_param(h, name, 0);
+        let value = match self {
+            TextOperation::Contains => Value::Bool(text()?.contains(&render_param(h, name, 1)?)),
+            TextOperation::HasPrefix => {
+                Value::Bool(text()?.starts_with(&render_param(h, name, 1)?))
+            }
+            TextOperation::HasSuffix => Value::Bool(text()?.ends_with(&render_param(h, name, 1)?)),
+            TextOperation::Repeat => {
+                let s = text()?;
+                let count = count_param(h, name, 1)?;
+                output_length(name, s.len(), count)?;
+                Value::String(s.repeat(count))
+            }
+            TextOperation::Trunc => {
+                Value::String(text()?.chars().take(count_param(h, name, 1)?).collect())
+            }
+            TextOperation::Abbrev => {
+                let s = text()?;
+                let width = count_param(h, name, 1)?;
+                if s.chars().count() <= width {
+                    Value::String(s)
+                } else if width <= 3 {
+                    Value::String(s.chars().take(width).collect())
+                } else {
+                    let abbreviated: String = s.chars().take(width - 3).collect();
+                    Value::String(abbreviated + "...")
+                }
+            }
+            TextOperation::Quote => Value::String(Value::String(text()?).to_string()),
+            TextOperation::Nospace => {
+                Value::String(text()?.chars().filter(|c| !c.is_whitespace()).collect())
+            }
+            TextOperation::Indent => {
+                let s = text()?;
+                let count = count_param(h, name, 1)?;
+                output_length(name, s.lines().count().max(1), count)?;
+                let padding = " ".repeat(count);
+                Value::String(
+                    s.lines()
+                        .map(|l| format!("{padding}{l}"))
+                        .collect::<Vec<_>>()
+                        .join("\n"),
+                )
+            }
+            TextOperation::Plural => {
+                let index = if number_param(h, name, 0)? == 1.0 {
+                    1
+                } else {
+                    2
+                };
+                Value::String(render_param(h, name, index)?)
+            }
+            TextOperation::ToJson => {
+                let value = h
+                    .param(0)
+                    .ok_or(RenderErrorReason::ParamNotFoundForIndex(name, 0))?
+                    .value();
+                Value::String(value.to_string())
+            }
+            TextOperation::FromJson => serde_json::from_str(&text()?)
+                .map_err(|e| RenderErrorReason::Other(format!("{name} invalid json {e}")))?,
+        };
+        Ok(ScopedJson::Derived(value))
+    }
+}
+
+/// the first parameter is an array e.g. {{join data.rooms ", "}}
+#[derive(Clone, Copy)]
+enum ListOperation {
+    Join,
+    /// null if the array is empty
+    First,
+    /// null if the array is empty
+    Last,
+    /// array contains the value
+    Has,
+    /// remove duplicates keeping the first occurrence
+    Uniq,
+    /// numbers are compared as numbers, other values by their rendered string
+    Sort,
+}
+
+impl ListOperation {
+    fn name(&self) -> &'static str {
+        match self {
+            ListOperation::Join => "join",
+            ListOperation::First => "first",
+            ListOperation::Last => "last",
+            ListOperation::Has => "has",
+            ListOperation::Uniq => "uniq",
+            ListOperation::Sort => "sort",
+        }
+    }
+}
+
+impl HelperDef for ListOperation {
+    fn call_inner<'reg: 'rc, 'rc>(
+        &self,
+        h: &Helper<'rc>,
+        _: &'reg Handlebars<'reg>,
+        _: &'rc Context,
+        _: &mut RenderContext<'reg, 'rc>,
+    ) -> Result<ScopedJson<'rc>, RenderError> {
+        let name = self.name();
+        let value = h
+            .param(0)
+            .ok_or(RenderErrorReason::ParamNotFoundForIndex(name, 0))?
+            .value();
+        let Value::Array(items) = value else {
+            return Err(
+                RenderErrorReason::Other(format!("{name} expects an array, got {value}")).into(),
+            );
+        };
+        let value = match self {
+            ListOperation::Join => {
+                let separator = match h.param(1) {
+                    Some(_) => render_param(h, name, 1)?,
+                    None => String::new(),
+                };
+                Value::String(
+                    items
+                        .iter()
+                        .map(|v| v.render())
+                        .collect::<Vec<_>>()
+                        .join(&separator),
+                )
+            }
+            ListOperation::First => items.first().cloned().unwrap_or_default(),
+            ListOperation::Last => items.last().cloned().unwrap_or_default(),
+            ListOperation::Has => {
+                let needle = h
+                    .param(1)
+                    .ok_or(RenderErrorReason::ParamNotFoundForIndex(name, 1))?
+                    .value();
+                Value::Bool(items.contains(needle))
+            }
+            ListOperation::Uniq => {
+                let mut unique: Vec<Value> = Vec::with_capacity(items.len());
+                for item in items {
+                    if !unique.contains(item) {
+                        unique.push(item.clone());
+                    }
+                }
+                Value::Array(unique)
+            }
+            ListOperation::Sort => {
+                let mut sorted = items.clone();
+                if sorted.iter().all(Value::is_number) {
+                    sorted.sort_by(|a, b| {
+                        a.as_f64()
+                            .unwrap_or_default()
+                            .total_cmp(&b.as_f64().unwrap_or_default())
+                    });
+                } else {
+                    sorted.sort_by_key(|v| v.render());
+                }
+                Value::Array(sorted)
+            }
+        };
+        Ok(ScopedJson::Derived(value))
+    }
+}
+
+/// numbers or numeric strings e.g. {{max data.outside data.inside}}
+#[derive(Clone, Copy)]
+enum NumberOperation {
+    /// smallest of the parameters or array values
+    Min,
+    /// largest of the parameters or array values
+    Max,
+    Floor,
+    Ceil,
+    Abs,
+    /// remainder of the division
+    Mod,
+}
+
+impl NumberOperation {
+    fn name(&self) -> &'static str {
+        match self {
+            NumberOperation::Min => "min",
+            NumberOperation::Max => "max",
+            NumberOperation::Floor => "floor",
+            NumberOperation::Ceil => "ceil",
+            NumberOperation::Abs => "abs",
+            NumberOperation::Mod => "mod",
+        }
+    }
+}
+
+impl HelperDef for NumberOperation {
+    fn call_inner<'reg: 'rc, 'rc>(
+        &self,
+        h: &Helper<'rc>,
+        _: &'reg Handlebars<'reg>,
+        _: &'rc Context,
+        _: &mut RenderContext<'reg, 'rc>,
+    ) -> Result<ScopedJson<'rc>, RenderError> {
+        let name = self.name();
+        let value = match self {
+            NumberOperation::Min | NumberOperation::Max => {
+                let numbers = match h.param(0).map(|p| p.value()) {
+                    Some(Value::Array(items)) => items
+                        .iter()
+                        .map(|v| match v {
+                            Value::Number(n) => n.as_f64(),
+                            Value::String(s) => s.trim().parse().ok(),
+                            _ => None,
+                        })
+                        .collect::<Option<Vec<f64>>>()
+                        .ok_or_else(|| {
+                            RenderErrorReason::Other(format!("{name} expects numbers"))
+                        })?,
+                    _ => (0..h.params().len())
+                        .map(|i| number_param(h, name, i))
+                        .collect::<Result<Vec<f64>, _>>()?,
+                };
+                let result = match self {
+                    NumberOperation::Min => numbers.into_iter().reduce(f64::min),
+                    _ => numbers.into_iter().reduce(f64::max),
+                };
+                result.map(number_value).unwrap_or_default()
+            }
+            NumberOperation::Floor => number_value(number_param(h, name, 0)?.floor()),
+            NumberOperation::Ceil => number_value(number_param(h, name, 0)?.ceil()),
+            NumberOperation::Abs => number_value(number_param(h, name, 0)?.abs()),
+            NumberOperation::Mod => match number_param(h, name, 1)? {
+                0.0 => return Err(RenderErrorReason::Other("mod by zero".to_string()).into()),
+                b => number_value(number_param(h, name, 0)? % b),
+            },
+        };
+        Ok(ScopedJson::Derived(value))
+    }
+}
+
+fn count_param(h: &Helper, name: &'static str, index: usize) -> Result<usize, RenderError> {
+    let n = number_param(h, name, index)?;
+    if !n.is_finite() || n < 0.0 {
+        return Err(RenderErrorReason::Other(format!("{name} expects a positive number")).into());
+    }
+    if n > MAX_OUTPUT_LENGTH as f64 {
+        return Err(RenderErrorReason::Other(format!(
+            "{name} expects a number up to {MAX_OUTPUT_LENGTH}"
+        ))
+        .into());
+    }
+    Ok(n as usize)
+}
+
+/// fails if the text repeated count times would exceed the maximum output
+fn output_length(name: &'static str, length: usize, count: usize) -> Result<(), RenderError> {
+    match length.checked_mul(count) {
+        Some(total) if total <= MAX_OUTPUT_LENGTH => Ok(()),
+        _ => Err(RenderErrorReason::Other(format!(
+            "{name} output exceeds {MAX_OUTPUT_LENGTH} bytes"
+        ))
+        .into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::renderer::load_handlebars;
+
+    #[test]
+    fn test_text_helpers() {
+        let handlebars = load_handlebars();
+        let data = json!({"topic": "home/hall/light", "name": "living room", "count": 1, "config": {"on": true}, "lines": "a\nb"});
+        let cases = [
+            (r#"{{#if (contains data.topic "hall")}}yes{{/if}}"#, "yes"),
+            (
+                r#"{{#if (has-prefix data.topic "home/")}}yes{{/if}}"#,
+                "yes",
+            ),
+            (
+                r#"{{#if (has-suffix data.topic "fan")}}yes{{else}}no{{/if}}"#,
+                "no",
+            ),
+            (r#"{{repeat "-" 3}}"#, "---"),
+            (r#"{{trunc data.name 6}}"#, "living"),
+            (r#"{{abbrev data.name 8}}"#, "livin..."),
+            (r#"{{abbrev data.name 20}}"#, "living room"),
+            (r#"{{{quote data.name}}}"#, r#""living room""#),
+            (r#"{{nospace data.name}}"#, "livingroom"),
+            ("{{indent data.lines 2}}", "  a\n  b"),
+            (r#"{{plural data.count "light" "lights"}}"#, "light"),
+            (r#"{{plural 3 "light" "lights"}}"#, "lights"),
+            (r#"{{{to-json data.config}}}"#, r#"{"on":true}"#),
+            (r#"{{#with (from-json "{\"a\": 2}")}}{{a}}{{/with}}"#, "2"),
+            // case conversion provided by handlebars
+            (r#"{{snakeCase data.name}}"#, "living_room"),
+        ];
+        for (template, expected) in cases {
+            assert_eq!(
+                handlebars
+                    .render_template(template, &json!({ "data": data }))
+                    .unwrap(),
+                expected,
+                "{template}"
+            );
+        }
+        assert!(handlebars
+            .render_template(r#"{{trunc "abc" -1}}"#, &json!({}))
+            .is_err());
+    }
+
+    #[test]
+    fn test_oversized_count() {
+        let handlebars = load_handlebars();
+        for (template, count) in [
+            (r#"{{repeat "-" data.count}}"#, json!(1e20)),
+            (r#"{{repeat "-" data.count}}"#, json!(1e10)),
+            (r#"{{repeat "abc" data.count}}"#, json!(1 << 19)),
+            (r#"{{indent data.lines data.count}}"#, json!(1 << 20)),
+            (r#"{{trunc "abc" data.count}}"#, json!(f64::MAX)),
+        ] {
+            let data = json!({"count": count, "lines": "a\nb"});
+            assert!(
+                handlebars
+                    .render_template(template, &json!({ "data": data }))
+                    .is_err(),
+                "{template} {count}"
+            );
+        }
+        assert_eq!(
+            handlebars
+                .render_template(r#"{{repeat "-" 3}}"#, &json!({}))
+                .unwrap(),
+            "---"
+        );
+    }
+
+    #[test]
+    fn test_list_helpers() {
+        let handlebars = load_handlebars();
+        let data = json!({"rooms": ["kitchen", "hall", "kitchen"], "temperatures": [21, 3.5, 19], "empty": []});
+        let cases = [
+            (r#"{{join data.rooms ", "}}"#, "kitchen, hall, kitchen"),
+            (r#"{{first data.rooms}}"#, "kitchen"),
+            (r#"{{last data.temperatures}}"#, "19"),
+            (r#"{{first data.empty}}"#, ""),
+            (r#"{{#if (has data.rooms "hall")}}yes{{/if}}"#, "yes"),
+            (r#"{{#if (has data.temperatures 3.5)}}yes{{/if}}"#, "yes"),
+            (r#"{{join (uniq data.rooms) ","}}"#, "kitchen,hall"),
+            (r#"{{join (sort data.rooms) ","}}"#, "hall,kitchen,kitchen"),
+            (r#"{{join (sort data.temperatures) ","}}"#, "3.5,19,21"),
+        ];
+        for (template, expected) in cases {
+            assert_eq!(
+                handlebars
+                    .render_template(template, &json!({ "data": data }))
+                    .unwrap(),
+                expected,
+                "{template}"
+            );
+        }
+        assert!(handlebars
+            .render_template(r#"{{join "a,b" ","}}"#, &json!({}))
+            .is_err());
+    }
+
+    #[test]
+    fn test_number_helpers() {
+        let handlebars = load_handlebars();
+        let data = json!({"temperatures": [21, "3.5", 19]});
+        let cases = [
+            (r#"{{min 3 "1.5" 2}}"#, "1.5"),
+            (r#"{{max data.temperatures}}"#, "21"),
+            (r#"{{min data.temperatures}}"#, "3.5"),
+            (r#"{{floor 2.7}}"#, "2"),
+            (r#"{{ceil "2.1"}}"#, "3"),
+            (r#"{{abs -4}}"#, "4"),
+            (r#"{{mod 7 3}}"#, "1"),
+        ];
+        for (template, expected) in cases {
+            assert_eq!(
+                handlebars
+                    .render_template(template, &json!({ "data": data }))
+                    .unwrap(),
+                expected,
+                "{template}"
+            );
+        }
+        assert!(handlebars
+            .render_template("{{mod 7 0}}", &json!({}))
+            .is_err());
+    }
+}